@@ -44,6 +44,7 @@ const CQL_KEYWORDS: &[&str] = &[
     "DECIMAL",
     "DELETE",
     "DESC",
+    "DESCRIBE",
     "DISTINCT",
     "DOUBLE",
     "DROP",
@@ -204,6 +205,45 @@ fn print_result(result: QueryResult) -> Result<(), IntoRowsResultError> {
     }
 }
 
+/// Handles `DESCRIBE KEYSPACE <keyspace>` and `DESCRIBE TABLE <keyspace>.<table>`
+/// commands, which are implemented client-side using schema metadata.
+/// Returns `None` if the line is not a `DESCRIBE` command.
+async fn describe(session: &Session, line: &str) -> Option<Result<String>> {
+    let mut words = line.trim().trim_end_matches(';').split_whitespace();
+    if !matches!(words.next()?.to_uppercase().as_str(), "DESC" | "DESCRIBE") {
+        return None;
+    }
+    let kind = words.next().map(|word| word.to_uppercase());
+    let name = words.next();
+
+    let result = async {
+        session.refresh_metadata().await?;
+        let cluster_state = session.get_cluster_state();
+        match (kind.as_deref(), name) {
+            (Some("KEYSPACE"), Some(keyspace_name)) => cluster_state
+                .get_keyspace(keyspace_name)
+                .ok_or_else(|| anyhow::anyhow!("Keyspace '{}' not found", keyspace_name))
+                .and_then(|keyspace| Ok(keyspace.describe(keyspace_name)?)),
+            (Some("TABLE"), Some(name)) => {
+                let (keyspace_name, table_name) = name
+                    .split_once('.')
+                    .ok_or_else(|| anyhow::anyhow!("Expected <keyspace>.<table>"))?;
+                cluster_state
+                    .get_keyspace(keyspace_name)
+                    .and_then(|keyspace| keyspace.tables.get(table_name))
+                    .ok_or_else(|| anyhow::anyhow!("Table '{}' not found", name))
+                    .and_then(|table| Ok(table.as_cql_query(keyspace_name, table_name)?))
+            }
+            _ => Err(anyhow::anyhow!(
+                "Usage: DESCRIBE KEYSPACE <keyspace> | DESCRIBE TABLE <keyspace>.<table>"
+            )),
+        }
+    }
+    .await;
+
+    Some(result)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
@@ -230,6 +270,13 @@ async fn main() -> Result<()> {
                     continue;
                 }
                 rl.add_history_entry(line.as_str());
+                if let Some(description) = describe(&session, &line).await {
+                    match description {
+                        Err(err) => println!("Error: {}", err),
+                        Ok(description) => println!("{}", description),
+                    }
+                    continue;
+                }
                 let maybe_res = session.query_unpaged(line, &[]).await;
                 match maybe_res {
                     Err(err) => println!("Error: {}", err),
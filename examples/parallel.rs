@@ -1,10 +1,12 @@
 use anyhow::Result;
 use scylla::client::session::Session;
 use scylla::client::session_builder::SessionBuilder;
+use scylla::client::RequestLimits;
 use std::env;
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 
-use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -12,7 +14,14 @@ async fn main() -> Result<()> {
 
     println!("Connecting to {} ...", uri);
 
-    let session: Session = SessionBuilder::new().known_node(uri).build().await?;
+    // At most `parallelism` requests are in flight at once;
+    // the rest wait in the driver until they can be sent.
+    let parallelism = NonZeroUsize::new(256).unwrap();
+    let session: Session = SessionBuilder::new()
+        .known_node(uri)
+        .request_limits(RequestLimits::new().with_max_concurrent_requests(parallelism))
+        .build()
+        .await?;
     let session = Arc::new(session);
 
     session.query_unpaged("CREATE KEYSPACE IF NOT EXISTS examples_ks WITH REPLICATION = {'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}", &[]).await?;
@@ -24,16 +33,10 @@ async fn main() -> Result<()> {
         )
         .await?;
 
-    let parallelism = 256;
-    let sem = Arc::new(Semaphore::new(parallelism));
-
+    let mut tasks = JoinSet::new();
     for i in 0..100_000usize {
-        if i % 1000 == 0 {
-            println!("{}", i);
-        }
         let session = session.clone();
-        let permit = sem.clone().acquire_owned().await;
-        tokio::task::spawn(async move {
+        tasks.spawn(async move {
             session
                 .query_unpaged(
                     format!(
@@ -45,14 +48,17 @@ async fn main() -> Result<()> {
                 )
                 .await
                 .unwrap();
-
-            let _permit = permit;
         });
     }
 
-    // Wait for all in-flight requests to finish
-    for _ in 0..parallelism {
-        sem.acquire().await.unwrap().forget();
+    // Wait for all requests to finish
+    let mut done = 0usize;
+    while let Some(result) = tasks.join_next().await {
+        result?;
+        done += 1;
+        if done % 1000 == 0 {
+            println!("{}", done);
+        }
     }
 
     println!("Ok.");
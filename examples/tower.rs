@@ -1,46 +1,37 @@
-use scylla::client::session::Session;
 use scylla::client::session_builder::SessionBuilder;
+use scylla::client::tower::SessionService;
+use scylla::statement::unprepared::Statement;
 use scylla::value::Row;
 use std::env;
-use std::future::Future;
-use std::pin::Pin;
+use std::num::NonZeroUsize;
 use std::sync::Arc;
-use std::task::Context;
-use std::task::Poll;
-use tower::Service;
-
-struct SessionService {
-    session: Arc<Session>,
-}
-
-// A trivial service implementation for sending parameterless simple string requests to Scylla.
-impl Service<scylla::statement::unprepared::Statement> for SessionService {
-    type Response = scylla::response::query_result::QueryResult;
-    type Error = scylla::errors::ExecutionError;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
-
-    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        Poll::Ready(Ok(()))
-    }
-
-    fn call(&mut self, req: scylla::statement::unprepared::Statement) -> Self::Future {
-        let session = self.session.clone();
-        Box::pin(async move { session.query_unpaged(req, &[]).await })
-    }
-}
+use std::time::Duration;
+use tower::{BoxError, ServiceBuilder, ServiceExt};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
 
     println!("Connecting to {} ...", uri);
-    let mut session: SessionService = SessionService {
-        session: Arc::new(SessionBuilder::new().known_node(uri).build().await?),
-    };
-
-    let rows_result = session
-        .call("SELECT keyspace_name, table_name FROM system_schema.tables;".into())
-        .await?
+    let session = Arc::new(SessionBuilder::new().known_node(uri).build().await?);
+
+    // At most 128 requests are in flight at once. When all of them are in flight,
+    // new requests are rejected immediately instead of waiting (load shedding).
+    // Each request is given at most 5 seconds to complete.
+    let service = ServiceBuilder::new()
+        .load_shed()
+        .timeout(Duration::from_secs(5))
+        .service(
+            SessionService::new(session)
+                .with_max_concurrent_requests(NonZeroUsize::new(128).unwrap()),
+        );
+
+    let rows_result = service
+        .oneshot(Statement::from(
+            "SELECT keyspace_name, table_name FROM system_schema.tables;",
+        ))
+        .await
+        .map_err(|err: BoxError| anyhow::anyhow!(err))?
         .into_rows_result()?;
 
     let print_text = |t: &Option<scylla::value::CqlValue>| {
@@ -0,0 +1,962 @@
+//! Conversion of query results to [Apache Arrow](https://arrow.apache.org/) record batches.
+//!
+//! [RecordBatchBuilder] deserializes rows directly from the raw frame slices
+//! into Arrow arrays, without going through [CqlValue](crate::value::CqlValue).
+//! The Arrow schema of the batches is derived from the column specs of the result.
+//!
+//! # Type mapping
+//!
+//! | CQL type               | Arrow type                                          |
+//! |------------------------|-----------------------------------------------------|
+//! | `ascii`, `text`        | `Utf8`                                              |
+//! | `boolean`              | `Boolean`                                           |
+//! | `tinyint`              | `Int8`                                              |
+//! | `smallint`             | `Int16`                                             |
+//! | `int`                  | `Int32`                                             |
+//! | `bigint`, `counter`    | `Int64`                                             |
+//! | `varint`               | `Decimal128(38, 0)`                                 |
+//! | `decimal`              | `Utf8` (decimal notation, e.g. `-12.34`)            |
+//! | `float`                | `Float32`                                           |
+//! | `double`               | `Float64`                                           |
+//! | `blob`                 | `Binary`                                            |
+//! | `uuid`, `timeuuid`     | `FixedSizeBinary(16)`                               |
+//! | `inet`                 | `Utf8`                                              |
+//! | `timestamp`            | `Timestamp(Millisecond, "UTC")`                     |
+//! | `date`                 | `Date32`                                            |
+//! | `time`                 | `Time64(Nanosecond)`                                |
+//! | `duration`             | `Interval(MonthDayNano)`                            |
+//! | `list`, `set`          | `List`                                              |
+//! | `map`                  | `Map` (with non-nullable keys)                      |
+//! | `vector`               | `FixedSizeList`                                     |
+//! | user defined type      | `Struct` (with fields named after the UDT fields)   |
+//! | `tuple`                | `Struct` (with fields named `0`, `1`, ...)          |
+//!
+//! All columns, collection elements and fields are nullable. Empty values
+//! of types other than `ascii`, `text` and `blob` are converted to nulls.
+//! Deserialization of a `varint` which does not fit in 38 decimal digits fails.
+//!
+//! This module is available only under the crate feature `arrow-54`.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use arrow_array_54::builder::{
+    BinaryBuilder, BooleanBuilder, Date32Builder, Decimal128Builder, FixedSizeBinaryBuilder,
+    Float32Builder, Float64Builder, Int16Builder, Int32Builder, Int64Builder, Int8Builder,
+    IntervalMonthDayNanoBuilder, StringBuilder, Time64NanosecondBuilder,
+    TimestampMillisecondBuilder,
+};
+use arrow_array_54::{
+    ArrayRef, FixedSizeListArray, ListArray, MapArray, RecordBatch, RecordBatchOptions, StructArray,
+};
+use arrow_buffer_54::{IntervalMonthDayNano, NullBufferBuilder, OffsetBuffer};
+use arrow_schema_54::{
+    DataType, Field, FieldRef, Fields, IntervalUnit, Schema, SchemaRef, TimeUnit,
+    DECIMAL128_MAX_PRECISION,
+};
+use uuid::Uuid;
+
+use super::row::ColumnIterator;
+use super::value::{
+    mk_deser_err, BuiltinDeserializationErrorKind, DeserializeValue, MapDeserializationErrorKind,
+    SetOrListDeserializationErrorKind, TupleDeserializationErrorKind, UdtDeserializationErrorKind,
+    UdtIterator, VectorDeserializationErrorKind, VectorIterator,
+};
+use super::{DeserializationError, FrameSlice, TypeCheckError};
+use crate::frame::frame_errors::LowLevelDeserializationError;
+use crate::frame::response::result::{CollectionType, ColumnSpec, ColumnType, NativeType};
+use crate::frame::types;
+use crate::value::{
+    decimal_to_string, CqlDate, CqlDecimalBorrowed, CqlDuration, CqlTime, CqlTimestamp,
+    CqlVarintBorrowed,
+};
+
+/// The time zone of the Arrow timestamps.
+const TIMESTAMP_TIME_ZONE: &str = "UTC";
+
+/// Returns the Arrow schema of the record batches built from rows with given columns.
+///
+/// See the [module-level docs](self) for the type mapping.
+pub fn arrow_schema(specs: &[ColumnSpec]) -> Schema {
+    Schema::new(
+        specs
+            .iter()
+            .map(|spec| Field::new(spec.name(), arrow_data_type(spec.typ()), true))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Returns the Arrow data type which values of given CQL type are converted to.
+///
+/// See the [module-level docs](self) for the type mapping.
+pub fn arrow_data_type(typ: &ColumnType) -> DataType {
+    match typ {
+        ColumnType::Native(native) => match native {
+            NativeType::Ascii | NativeType::Text | NativeType::Inet | NativeType::Decimal => {
+                DataType::Utf8
+            }
+            NativeType::Boolean => DataType::Boolean,
+            NativeType::Blob => DataType::Binary,
+            NativeType::TinyInt => DataType::Int8,
+            NativeType::SmallInt => DataType::Int16,
+            NativeType::Int => DataType::Int32,
+            NativeType::BigInt | NativeType::Counter => DataType::Int64,
+            NativeType::Varint => DataType::Decimal128(DECIMAL128_MAX_PRECISION, 0),
+            NativeType::Float => DataType::Float32,
+            NativeType::Double => DataType::Float64,
+            NativeType::Uuid | NativeType::Timeuuid => DataType::FixedSizeBinary(16),
+            NativeType::Timestamp => {
+                DataType::Timestamp(TimeUnit::Millisecond, Some(TIMESTAMP_TIME_ZONE.into()))
+            }
+            NativeType::Date => DataType::Date32,
+            NativeType::Time => DataType::Time64(TimeUnit::Nanosecond),
+            NativeType::Duration => DataType::Interval(IntervalUnit::MonthDayNano),
+        },
+        ColumnType::Collection { typ, .. } => match typ {
+            CollectionType::List(elem_typ) | CollectionType::Set(elem_typ) => {
+                DataType::List(list_field(elem_typ))
+            }
+            CollectionType::Map(key_typ, value_typ) => {
+                DataType::Map(map_entries_field(key_typ, value_typ), false)
+            }
+        },
+        ColumnType::Vector { typ, dimensions } => {
+            DataType::FixedSizeList(list_field(typ), *dimensions as i32)
+        }
+        ColumnType::UserDefinedType { definition, .. } => DataType::Struct(
+            definition
+                .field_types
+                .iter()
+                .map(|(name, typ)| Field::new(name.as_ref(), arrow_data_type(typ), true))
+                .collect(),
+        ),
+        ColumnType::Tuple(typs) => DataType::Struct(
+            typs.iter()
+                .enumerate()
+                .map(|(position, typ)| Field::new(position.to_string(), arrow_data_type(typ), true))
+                .collect(),
+        ),
+    }
+}
+
+fn list_field(elem_typ: &ColumnType) -> FieldRef {
+    Arc::new(Field::new_list_field(arrow_data_type(elem_typ), true))
+}
+
+fn map_entries_field(key_typ: &ColumnType, value_typ: &ColumnType) -> FieldRef {
+    let entries = Fields::from(vec![
+        Field::new("key", arrow_data_type(key_typ), false),
+        Field::new("value", arrow_data_type(value_typ), true),
+    ]);
+    Arc::new(Field::new("entries", DataType::Struct(entries), false))
+}
+
+/// Builds Arrow [RecordBatch]es from rows of a query result.
+///
+/// Rows are appended with [append_row](Self::append_row) and deserialized
+/// directly into Arrow arrays. [finish](Self::finish) returns the batch
+/// of the appended rows and resets the builder, so it can be reused,
+/// e.g. for the next page of the result.
+///
+/// ```rust
+/// # use scylla_cql::deserialize::arrow::RecordBatchBuilder;
+/// # use scylla_cql::deserialize::result::RawRowLendingIterator;
+/// # use scylla_cql::deserialize::row::ColumnIterator;
+/// # use scylla_cql::deserialize::DeserializationError;
+/// # use scylla_cql::frame::response::result::ColumnSpec;
+/// # use arrow_array_54::RecordBatch;
+/// fn to_record_batch(
+///     specs: &[ColumnSpec],
+///     rows: &mut RawRowLendingIterator,
+/// ) -> Result<RecordBatch, DeserializationError> {
+///     let mut builder = RecordBatchBuilder::new(specs);
+///     while let Some(row) = rows.next() {
+///         builder.append_row(row?)?;
+///     }
+///     Ok(builder.finish())
+/// }
+/// ```
+pub struct RecordBatchBuilder {
+    schema: SchemaRef,
+    columns: Vec<ColumnBuilder>,
+    num_rows: usize,
+}
+
+impl RecordBatchBuilder {
+    /// Creates a builder for rows with given columns.
+    pub fn new(specs: &[ColumnSpec]) -> Self {
+        Self {
+            schema: Arc::new(arrow_schema(specs)),
+            columns: specs
+                .iter()
+                .map(|spec| ColumnBuilder::new(spec.typ()))
+                .collect(),
+            num_rows: 0,
+        }
+    }
+
+    /// Returns the schema of the built record batches.
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Returns the number of rows appended since the builder was created
+    /// or last finished.
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    /// Deserializes the row and appends it to the batch.
+    ///
+    /// If an error is returned, the row may have been appended partially,
+    /// and the builder should be discarded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the row has different columns than the ones
+    /// the builder was created for.
+    pub fn append_row<'frame, 'metadata>(
+        &mut self,
+        row: ColumnIterator<'frame, 'metadata>,
+    ) -> Result<(), DeserializationError> {
+        assert_eq!(
+            row.columns_remaining(),
+            self.columns.len(),
+            "The row has a different number of columns than the record batch",
+        );
+        for (column, builder) in row.zip(self.columns.iter_mut()) {
+            let column = column?;
+            builder.append(column.spec.typ(), column.slice)?;
+        }
+        self.num_rows += 1;
+        Ok(())
+    }
+
+    /// Returns the record batch of the appended rows and resets the builder.
+    pub fn finish(&mut self) -> RecordBatch {
+        let columns = self.columns.iter_mut().map(ColumnBuilder::finish).collect();
+        let options = RecordBatchOptions::new().with_row_count(Some(self.num_rows));
+        self.num_rows = 0;
+        RecordBatch::try_new_with_options(self.schema.clone(), columns, &options)
+            .expect("Arrays built for the schema should match it")
+    }
+}
+
+/// Builds the Arrow array of a single column (or of a nested value).
+///
+/// The variant always corresponds to the CQL type which is passed
+/// to [append](Self::append), as the builder was created from it.
+enum ColumnBuilder {
+    Boolean(BooleanBuilder),
+    TinyInt(Int8Builder),
+    SmallInt(Int16Builder),
+    Int(Int32Builder),
+    BigInt(Int64Builder),
+    Varint(Decimal128Builder),
+    Decimal(StringBuilder),
+    Float(Float32Builder),
+    Double(Float64Builder),
+    Text(StringBuilder),
+    Blob(BinaryBuilder),
+    Uuid(FixedSizeBinaryBuilder),
+    Inet(StringBuilder),
+    Timestamp(TimestampMillisecondBuilder),
+    Date(Date32Builder),
+    Time(Time64NanosecondBuilder),
+    Duration(IntervalMonthDayNanoBuilder),
+    List {
+        field: FieldRef,
+        offsets: Vec<i32>,
+        nulls: NullBufferBuilder,
+        values: Box<ColumnBuilder>,
+    },
+    Map {
+        field: FieldRef,
+        offsets: Vec<i32>,
+        nulls: NullBufferBuilder,
+        keys: Box<ColumnBuilder>,
+        values: Box<ColumnBuilder>,
+    },
+    Vector {
+        field: FieldRef,
+        dimensions: usize,
+        nulls: NullBufferBuilder,
+        values: Box<ColumnBuilder>,
+    },
+    /// Either a UDT or a tuple.
+    Struct {
+        fields: Fields,
+        nulls: NullBufferBuilder,
+        children: Vec<ColumnBuilder>,
+    },
+}
+
+impl ColumnBuilder {
+    fn new(typ: &ColumnType) -> Self {
+        match typ {
+            ColumnType::Native(native) => match native {
+                NativeType::Ascii | NativeType::Text => Self::Text(StringBuilder::new()),
+                NativeType::Boolean => Self::Boolean(BooleanBuilder::new()),
+                NativeType::Blob => Self::Blob(BinaryBuilder::new()),
+                NativeType::TinyInt => Self::TinyInt(Int8Builder::new()),
+                NativeType::SmallInt => Self::SmallInt(Int16Builder::new()),
+                NativeType::Int => Self::Int(Int32Builder::new()),
+                NativeType::BigInt | NativeType::Counter => Self::BigInt(Int64Builder::new()),
+                NativeType::Varint => {
+                    Self::Varint(Decimal128Builder::new().with_data_type(arrow_data_type(typ)))
+                }
+                NativeType::Decimal => Self::Decimal(StringBuilder::new()),
+                NativeType::Float => Self::Float(Float32Builder::new()),
+                NativeType::Double => Self::Double(Float64Builder::new()),
+                NativeType::Uuid | NativeType::Timeuuid => {
+                    Self::Uuid(FixedSizeBinaryBuilder::new(16))
+                }
+                NativeType::Inet => Self::Inet(StringBuilder::new()),
+                NativeType::Timestamp => Self::Timestamp(
+                    TimestampMillisecondBuilder::new().with_timezone(TIMESTAMP_TIME_ZONE),
+                ),
+                NativeType::Date => Self::Date(Date32Builder::new()),
+                NativeType::Time => Self::Time(Time64NanosecondBuilder::new()),
+                NativeType::Duration => Self::Duration(IntervalMonthDayNanoBuilder::new()),
+            },
+            ColumnType::Collection { typ: coll_typ, .. } => match coll_typ {
+                CollectionType::List(elem_typ) | CollectionType::Set(elem_typ) => Self::List {
+                    field: list_field(elem_typ),
+                    offsets: vec![0],
+                    nulls: NullBufferBuilder::new(0),
+                    values: Box::new(Self::new(elem_typ)),
+                },
+                CollectionType::Map(key_typ, value_typ) => Self::Map {
+                    field: map_entries_field(key_typ, value_typ),
+                    offsets: vec![0],
+                    nulls: NullBufferBuilder::new(0),
+                    keys: Box::new(Self::new(key_typ)),
+                    values: Box::new(Self::new(value_typ)),
+                },
+            },
+            ColumnType::Vector {
+                typ: elem_typ,
+                dimensions,
+            } => Self::Vector {
+                field: list_field(elem_typ),
+                dimensions: *dimensions as usize,
+                nulls: NullBufferBuilder::new(0),
+                values: Box::new(Self::new(elem_typ)),
+            },
+            ColumnType::UserDefinedType { definition, .. } => Self::Struct {
+                fields: struct_fields(typ),
+                nulls: NullBufferBuilder::new(0),
+                children: definition
+                    .field_types
+                    .iter()
+                    .map(|(_, typ)| Self::new(typ))
+                    .collect(),
+            },
+            ColumnType::Tuple(typs) => Self::Struct {
+                fields: struct_fields(typ),
+                nulls: NullBufferBuilder::new(0),
+                children: typs.iter().map(Self::new).collect(),
+            },
+        }
+    }
+
+    fn append<'frame, 'metadata>(
+        &mut self,
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<(), DeserializationError> {
+        let mut slice = match v {
+            Some(slice) if !slice.is_empty() || allows_empty(typ) => slice,
+            _ => {
+                self.append_null();
+                return Ok(());
+            }
+        };
+        let v = Some(slice);
+
+        match self {
+            Self::Boolean(builder) => builder.append_value(bool::deserialize(typ, v)?),
+            Self::TinyInt(builder) => builder.append_value(i8::deserialize(typ, v)?),
+            Self::SmallInt(builder) => builder.append_value(i16::deserialize(typ, v)?),
+            Self::Int(builder) => builder.append_value(i32::deserialize(typ, v)?),
+            Self::BigInt(builder) => builder.append_value(i64::deserialize(typ, v)?),
+            Self::Varint(builder) => {
+                let varint = CqlVarintBorrowed::deserialize(typ, v)?;
+                let value = varint_to_i128(varint.as_signed_bytes_be_slice())
+                    .filter(|value| {
+                        value.unsigned_abs() < 10u128.pow(DECIMAL128_MAX_PRECISION as u32)
+                    })
+                    .ok_or_else(|| {
+                        mk_deser_err::<Self>(typ, BuiltinDeserializationErrorKind::ValueOverflow)
+                    })?;
+                builder.append_value(value);
+            }
+            Self::Decimal(builder) => {
+                let decimal = CqlDecimalBorrowed::deserialize(typ, v)?;
+                let (bytes, scale) = decimal.as_signed_be_bytes_slice_and_exponent();
+                builder.append_value(decimal_to_string(bytes, scale));
+            }
+            Self::Float(builder) => builder.append_value(f32::deserialize(typ, v)?),
+            Self::Double(builder) => builder.append_value(f64::deserialize(typ, v)?),
+            Self::Text(builder) => builder.append_value(<&str>::deserialize(typ, v)?),
+            Self::Blob(builder) => builder.append_value(<&[u8]>::deserialize(typ, v)?),
+            Self::Uuid(builder) => builder
+                .append_value(Uuid::deserialize(typ, v)?.as_bytes())
+                .expect("UUIDs are 16 bytes long"),
+            Self::Inet(builder) => {
+                builder.append_value(IpAddr::deserialize(typ, v)?.to_string());
+            }
+            Self::Timestamp(builder) => {
+                builder.append_value(CqlTimestamp::deserialize(typ, v)?.0);
+            }
+            Self::Date(builder) => {
+                let CqlDate(days) = CqlDate::deserialize(typ, v)?;
+                // CqlDate counts the days from 2^31 days before the unix epoch.
+                builder.append_value((days as i64 - (1 << 31)) as i32);
+            }
+            Self::Time(builder) => builder.append_value(CqlTime::deserialize(typ, v)?.0),
+            Self::Duration(builder) => {
+                let CqlDuration {
+                    months,
+                    days,
+                    nanoseconds,
+                } = CqlDuration::deserialize(typ, v)?;
+                builder.append_value(IntervalMonthDayNano::new(months, days, nanoseconds));
+            }
+            Self::List {
+                offsets,
+                nulls,
+                values,
+                ..
+            } => {
+                let elem_typ = match typ {
+                    ColumnType::Collection {
+                        typ: CollectionType::List(elem_typ) | CollectionType::Set(elem_typ),
+                        ..
+                    } => elem_typ,
+                    _ => unreachable!("The builder was created for a list or set"),
+                };
+                let count = types::read_int_length(slice.as_slice_mut()).map_err(|err| {
+                    mk_deser_err::<Self>(
+                        typ,
+                        SetOrListDeserializationErrorKind::LengthDeserializationFailed(
+                            DeserializationError::new(err),
+                        ),
+                    )
+                })?;
+                for _ in 0..count {
+                    let elem = slice
+                        .read_cql_bytes()
+                        .map_err(|err| raw_bytes_read_err(typ, err))?;
+                    values.append(elem_typ, elem).map_err(|err| {
+                        mk_deser_err::<Self>(
+                            typ,
+                            SetOrListDeserializationErrorKind::ElementDeserializationFailed(err),
+                        )
+                    })?;
+                }
+                push_offset(typ, offsets, count)?;
+                nulls.append_non_null();
+            }
+            Self::Map {
+                offsets,
+                nulls,
+                keys,
+                values,
+                ..
+            } => {
+                let (key_typ, value_typ) = match typ {
+                    ColumnType::Collection {
+                        typ: CollectionType::Map(key_typ, value_typ),
+                        ..
+                    } => (key_typ, value_typ),
+                    _ => unreachable!("The builder was created for a map"),
+                };
+                let count = types::read_int_length(slice.as_slice_mut()).map_err(|err| {
+                    mk_deser_err::<Self>(
+                        typ,
+                        MapDeserializationErrorKind::LengthDeserializationFailed(
+                            DeserializationError::new(err),
+                        ),
+                    )
+                })?;
+                for _ in 0..count {
+                    let key = slice
+                        .read_cql_bytes()
+                        .map_err(|err| raw_bytes_read_err(typ, err))?
+                        .filter(|key| !key.is_empty() || allows_empty(key_typ))
+                        .ok_or_else(|| {
+                            mk_deser_err::<Self>(
+                                typ,
+                                MapDeserializationErrorKind::KeyDeserializationFailed(
+                                    mk_deser_err::<Self>(
+                                        key_typ,
+                                        BuiltinDeserializationErrorKind::ExpectedNonNull,
+                                    ),
+                                ),
+                            )
+                        })?;
+                    keys.append(key_typ, Some(key)).map_err(|err| {
+                        mk_deser_err::<Self>(
+                            typ,
+                            MapDeserializationErrorKind::KeyDeserializationFailed(err),
+                        )
+                    })?;
+                    let value = slice
+                        .read_cql_bytes()
+                        .map_err(|err| raw_bytes_read_err(typ, err))?;
+                    values.append(value_typ, value).map_err(|err| {
+                        mk_deser_err::<Self>(
+                            typ,
+                            MapDeserializationErrorKind::ValueDeserializationFailed(err),
+                        )
+                    })?;
+                }
+                push_offset(typ, offsets, count)?;
+                nulls.append_non_null();
+            }
+            Self::Vector { nulls, values, .. } => {
+                for elem in VectorIterator::<RawValue>::deserialize(typ, v)? {
+                    let RawValue { typ: elem_typ, v } = elem?;
+                    values.append(elem_typ, v).map_err(|err| {
+                        mk_deser_err::<Self>(
+                            typ,
+                            VectorDeserializationErrorKind::ElementDeserializationFailed(err),
+                        )
+                    })?;
+                }
+                nulls.append_non_null();
+            }
+            Self::Struct {
+                nulls, children, ..
+            } => {
+                match typ {
+                    ColumnType::UserDefinedType { .. } => {
+                        let fields = UdtIterator::deserialize(typ, v)?;
+                        for (((field_name, field_typ), field), child) in
+                            fields.zip(children.iter_mut())
+                        {
+                            // Trailing fields may be missing from the serialized form.
+                            child.append(field_typ, field?.flatten()).map_err(|err| {
+                                mk_deser_err::<Self>(
+                                    typ,
+                                    UdtDeserializationErrorKind::FieldDeserializationFailed {
+                                        field_name: field_name.to_string(),
+                                        err,
+                                    },
+                                )
+                            })?;
+                        }
+                    }
+                    ColumnType::Tuple(typs) => {
+                        for (position, (elem_typ, child)) in
+                            typs.iter().zip(children.iter_mut()).enumerate()
+                        {
+                            // Trailing elements may be missing from the serialized form.
+                            let elem = if slice.is_empty() {
+                                None
+                            } else {
+                                slice
+                                    .read_cql_bytes()
+                                    .map_err(|err| raw_bytes_read_err(typ, err))?
+                            };
+                            child.append(elem_typ, elem).map_err(|err| {
+                                mk_deser_err::<Self>(
+                                    typ,
+                                    TupleDeserializationErrorKind::FieldDeserializationFailed {
+                                        position,
+                                        err,
+                                    },
+                                )
+                            })?;
+                        }
+                    }
+                    _ => unreachable!("The builder was created for a UDT or tuple"),
+                }
+                nulls.append_non_null();
+            }
+        }
+        Ok(())
+    }
+
+    fn append_null(&mut self) {
+        match self {
+            Self::Boolean(builder) => builder.append_null(),
+            Self::TinyInt(builder) => builder.append_null(),
+            Self::SmallInt(builder) => builder.append_null(),
+            Self::Int(builder) => builder.append_null(),
+            Self::BigInt(builder) => builder.append_null(),
+            Self::Varint(builder) => builder.append_null(),
+            Self::Float(builder) => builder.append_null(),
+            Self::Double(builder) => builder.append_null(),
+            Self::Decimal(builder) | Self::Text(builder) | Self::Inet(builder) => {
+                builder.append_null()
+            }
+            Self::Blob(builder) => builder.append_null(),
+            Self::Uuid(builder) => builder.append_null(),
+            Self::Timestamp(builder) => builder.append_null(),
+            Self::Date(builder) => builder.append_null(),
+            Self::Time(builder) => builder.append_null(),
+            Self::Duration(builder) => builder.append_null(),
+            Self::List { offsets, nulls, .. } | Self::Map { offsets, nulls, .. } => {
+                offsets.push(*offsets.last().unwrap());
+                nulls.append_null();
+            }
+            Self::Vector {
+                dimensions,
+                nulls,
+                values,
+                ..
+            } => {
+                // The child array must have `dimensions` slots for every element.
+                for _ in 0..*dimensions {
+                    values.append_null();
+                }
+                nulls.append_null();
+            }
+            Self::Struct {
+                nulls, children, ..
+            } => {
+                for child in children {
+                    child.append_null();
+                }
+                nulls.append_null();
+            }
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            Self::Boolean(builder) => Arc::new(builder.finish()),
+            Self::TinyInt(builder) => Arc::new(builder.finish()),
+            Self::SmallInt(builder) => Arc::new(builder.finish()),
+            Self::Int(builder) => Arc::new(builder.finish()),
+            Self::BigInt(builder) => Arc::new(builder.finish()),
+            Self::Varint(builder) => Arc::new(builder.finish()),
+            Self::Float(builder) => Arc::new(builder.finish()),
+            Self::Double(builder) => Arc::new(builder.finish()),
+            Self::Decimal(builder) | Self::Text(builder) | Self::Inet(builder) => {
+                Arc::new(builder.finish())
+            }
+            Self::Blob(builder) => Arc::new(builder.finish()),
+            Self::Uuid(builder) => Arc::new(builder.finish()),
+            Self::Timestamp(builder) => Arc::new(builder.finish()),
+            Self::Date(builder) => Arc::new(builder.finish()),
+            Self::Time(builder) => Arc::new(builder.finish()),
+            Self::Duration(builder) => Arc::new(builder.finish()),
+            Self::List {
+                field,
+                offsets,
+                nulls,
+                values,
+            } => Arc::new(ListArray::new(
+                field.clone(),
+                take_offsets(offsets),
+                values.finish(),
+                nulls.finish(),
+            )),
+            Self::Map {
+                field,
+                offsets,
+                nulls,
+                keys,
+                values,
+            } => {
+                let DataType::Struct(entry_fields) = field.data_type() else {
+                    unreachable!("Map entries are structs");
+                };
+                let entries = StructArray::new(
+                    entry_fields.clone(),
+                    vec![keys.finish(), values.finish()],
+                    None,
+                );
+                Arc::new(MapArray::new(
+                    field.clone(),
+                    take_offsets(offsets),
+                    entries,
+                    nulls.finish(),
+                    false,
+                ))
+            }
+            Self::Vector {
+                field,
+                dimensions,
+                nulls,
+                values,
+            } => Arc::new(FixedSizeListArray::new(
+                field.clone(),
+                *dimensions as i32,
+                values.finish(),
+                nulls.finish(),
+            )),
+            Self::Struct {
+                fields,
+                nulls,
+                children,
+            } => {
+                let len = nulls.len();
+                let nulls = nulls.finish();
+                if children.is_empty() {
+                    Arc::new(StructArray::new_empty_fields(len, nulls))
+                } else {
+                    let arrays = children.iter_mut().map(Self::finish).collect();
+                    Arc::new(StructArray::new(fields.clone(), arrays, nulls))
+                }
+            }
+        }
+    }
+}
+
+fn struct_fields(typ: &ColumnType) -> Fields {
+    match arrow_data_type(typ) {
+        DataType::Struct(fields) => fields,
+        _ => unreachable!("UDTs and tuples are converted to structs"),
+    }
+}
+
+/// Returns whether an empty value of the type is different from null.
+fn allows_empty(typ: &ColumnType) -> bool {
+    matches!(
+        typ,
+        ColumnType::Native(NativeType::Ascii | NativeType::Text | NativeType::Blob)
+    )
+}
+
+fn raw_bytes_read_err(typ: &ColumnType, err: LowLevelDeserializationError) -> DeserializationError {
+    mk_deser_err::<ColumnBuilder>(
+        typ,
+        BuiltinDeserializationErrorKind::RawCqlBytesReadError(err),
+    )
+}
+
+fn push_offset(
+    typ: &ColumnType,
+    offsets: &mut Vec<i32>,
+    count: usize,
+) -> Result<(), DeserializationError> {
+    let offset = i32::try_from(count)
+        .ok()
+        .and_then(|count| offsets.last().unwrap().checked_add(count))
+        .ok_or_else(|| {
+            mk_deser_err::<ColumnBuilder>(typ, BuiltinDeserializationErrorKind::ValueOverflow)
+        })?;
+    offsets.push(offset);
+    Ok(())
+}
+
+fn take_offsets(offsets: &mut Vec<i32>) -> OffsetBuffer<i32> {
+    OffsetBuffer::new(std::mem::replace(offsets, vec![0]).into())
+}
+
+/// Converts the big-endian two's complement number to i128, if it fits.
+fn varint_to_i128(bytes: &[u8]) -> Option<i128> {
+    if bytes.len() > 16 {
+        return None;
+    }
+    let fill = if bytes.first().is_some_and(|byte| byte & 0x80 != 0) {
+        0xff
+    } else {
+        0x00
+    };
+    let mut buf = [fill; 16];
+    buf[16 - bytes.len()..].copy_from_slice(bytes);
+    Some(i128::from_be_bytes(buf))
+}
+
+/// An element of a vector, along with its type, which is yet to be deserialized.
+struct RawValue<'frame, 'metadata> {
+    typ: &'metadata ColumnType<'metadata>,
+    v: Option<FrameSlice<'frame>>,
+}
+
+impl<'frame, 'metadata> DeserializeValue<'frame, 'metadata> for RawValue<'frame, 'metadata> {
+    fn type_check(_typ: &ColumnType) -> Result<(), TypeCheckError> {
+        Ok(())
+    }
+
+    fn deserialize(
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        Ok(Self { typ, v })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array_54::cast::AsArray;
+    use arrow_array_54::types::{
+        Date32Type, Decimal128Type, Float32Type, Int32Type, TimestampMillisecondType,
+    };
+    use arrow_array_54::Array;
+    use bytes::Bytes;
+
+    use super::{arrow_schema, RecordBatchBuilder};
+    use crate::deserialize::row::ColumnIterator;
+    use crate::deserialize::tests::spec;
+    use crate::deserialize::value::tests::udt_def_with_fields;
+    use crate::deserialize::{DeserializationError, FrameSlice};
+    use crate::frame::response::result::{CollectionType, ColumnSpec, ColumnType, NativeType};
+    use crate::serialize::row::tests::do_serialize;
+    use crate::value::{CqlDate, CqlTimestamp, CqlValue, CqlVarint};
+
+    fn append_row(
+        builder: &mut RecordBatchBuilder,
+        specs: &[ColumnSpec],
+        values: Vec<Option<CqlValue>>,
+    ) -> Result<(), DeserializationError> {
+        let bytes = Bytes::from(do_serialize(values, specs));
+        builder.append_row(ColumnIterator::new(specs, FrameSlice::new(&bytes)))
+    }
+
+    fn specs() -> Vec<ColumnSpec<'static>> {
+        vec![
+            spec("id", ColumnType::Native(NativeType::Int)),
+            spec("name", ColumnType::Native(NativeType::Text)),
+            spec("big", ColumnType::Native(NativeType::Varint)),
+            spec("at", ColumnType::Native(NativeType::Timestamp)),
+            spec("day", ColumnType::Native(NativeType::Date)),
+            spec(
+                "tags",
+                ColumnType::Collection {
+                    frozen: false,
+                    typ: CollectionType::List(Box::new(ColumnType::Native(NativeType::Int))),
+                },
+            ),
+            spec(
+                "scores",
+                ColumnType::Collection {
+                    frozen: false,
+                    typ: CollectionType::Map(
+                        Box::new(ColumnType::Native(NativeType::Text)),
+                        Box::new(ColumnType::Native(NativeType::Int)),
+                    ),
+                },
+            ),
+            spec(
+                "address",
+                udt_def_with_fields([
+                    ("street", ColumnType::Native(NativeType::Text)),
+                    ("number", ColumnType::Native(NativeType::Int)),
+                ]),
+            ),
+            spec(
+                "pair",
+                ColumnType::Tuple(vec![
+                    ColumnType::Native(NativeType::Int),
+                    ColumnType::Native(NativeType::Text),
+                ]),
+            ),
+            spec(
+                "embedding",
+                ColumnType::Vector {
+                    typ: Box::new(ColumnType::Native(NativeType::Float)),
+                    dimensions: 2,
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_record_batch() {
+        let specs = specs();
+        let mut builder = RecordBatchBuilder::new(&specs);
+        assert_eq!(*builder.schema(), arrow_schema(&specs));
+
+        append_row(
+            &mut builder,
+            &specs,
+            vec![
+                Some(CqlValue::Int(1)),
+                Some(CqlValue::Text("Alice".to_owned())),
+                Some(CqlValue::Varint(CqlVarint::from_signed_bytes_be(vec![
+                    0xff, 0x00,
+                ]))),
+                Some(CqlValue::Timestamp(CqlTimestamp(1_000))),
+                Some(CqlValue::Date(CqlDate((1 << 31) + 2))),
+                Some(CqlValue::List(vec![CqlValue::Int(1), CqlValue::Int(2)])),
+                Some(CqlValue::Map(vec![(
+                    CqlValue::Text("math".to_owned()),
+                    CqlValue::Int(5),
+                )])),
+                Some(CqlValue::UserDefinedType {
+                    keyspace: "ks".to_owned(),
+                    name: "udt".to_owned(),
+                    fields: vec![
+                        ("street".to_owned(), Some(CqlValue::Text("Main".to_owned()))),
+                        ("number".to_owned(), None),
+                    ],
+                }),
+                Some(CqlValue::Tuple(vec![Some(CqlValue::Int(7)), None])),
+                Some(CqlValue::Vector(vec![
+                    CqlValue::Float(0.5),
+                    CqlValue::Float(1.5),
+                ])),
+            ],
+        )
+        .unwrap();
+        append_row(&mut builder, &specs, vec![None; specs.len()]).unwrap();
+        assert_eq!(builder.num_rows(), 2);
+
+        let batch = builder.finish();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(builder.num_rows(), 0);
+        for column in batch.columns() {
+            assert!(column.is_valid(0));
+            assert!(column.is_null(1));
+        }
+
+        assert_eq!(batch.column(0).as_primitive::<Int32Type>().value(0), 1);
+        assert_eq!(batch.column(1).as_string::<i32>().value(0), "Alice");
+        assert_eq!(
+            batch.column(2).as_primitive::<Decimal128Type>().value(0),
+            -256
+        );
+        assert_eq!(
+            batch
+                .column(3)
+                .as_primitive::<TimestampMillisecondType>()
+                .value(0),
+            1_000
+        );
+        assert_eq!(batch.column(4).as_primitive::<Date32Type>().value(0), 2);
+
+        let tags = batch.column(5).as_list::<i32>();
+        assert_eq!(tags.value(0).as_primitive::<Int32Type>().values(), &[1, 2]);
+        assert!(tags.value(1).is_empty());
+
+        let scores = batch.column(6).as_map();
+        assert_eq!(scores.keys().as_string::<i32>().value(0), "math");
+        assert_eq!(scores.values().as_primitive::<Int32Type>().value(0), 5);
+
+        let address = batch.column(7).as_struct();
+        assert_eq!(address.column(0).as_string::<i32>().value(0), "Main");
+        assert!(address.column(1).is_null(0));
+
+        let pair = batch.column(8).as_struct();
+        assert_eq!(pair.column(0).as_primitive::<Int32Type>().value(0), 7);
+        assert!(pair.column(1).is_null(0));
+
+        let embedding = batch.column(9).as_fixed_size_list();
+        assert_eq!(
+            embedding.value(0).as_primitive::<Float32Type>().values(),
+            &[0.5, 1.5]
+        );
+        assert_eq!(embedding.values().len(), 4);
+
+        // The builder can be reused after finishing.
+        append_row(&mut builder, &specs, vec![None; specs.len()]).unwrap();
+        assert_eq!(builder.finish().num_rows(), 1);
+    }
+
+    #[test]
+    fn test_varint_overflow() {
+        let specs = [spec("big", ColumnType::Native(NativeType::Varint))];
+        let mut builder = RecordBatchBuilder::new(&specs);
+        let too_big = CqlVarint::from_signed_bytes_be(vec![0x7f; 17]);
+        append_row(&mut builder, &specs, vec![Some(CqlValue::Varint(too_big))]).unwrap_err();
+        // 10^38 does not fit in 38 digits, although it fits in i128.
+        let too_big = CqlVarint::from_signed_bytes_be(10i128.pow(38).to_be_bytes().to_vec());
+        append_row(&mut builder, &specs, vec![Some(CqlValue::Varint(too_big))]).unwrap_err();
+    }
+}
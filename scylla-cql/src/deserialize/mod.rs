@@ -215,6 +215,9 @@ pub mod result;
 pub mod row;
 pub mod value;
 
+#[cfg(feature = "arrow-54")]
+pub mod arrow;
+
 pub use frame_slice::FrameSlice;
 
 use std::error::Error;
@@ -178,6 +178,41 @@ impl<'frame, 'metadata> DeserializeRow<'frame, 'metadata> for Row {
     }
 }
 
+#[cfg(feature = "serde_json-1")]
+impl<'frame, 'metadata> DeserializeRow<'frame, 'metadata>
+    for serde_json_1::Map<String, serde_json_1::Value>
+{
+    #[inline]
+    fn type_check(_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        // All CQL types can be converted to JSON.
+        Ok(())
+    }
+
+    fn deserialize(
+        mut row: ColumnIterator<'frame, 'metadata>,
+    ) -> Result<Self, DeserializationError> {
+        let mut columns = serde_json_1::Map::new();
+        while let Some(column) = row
+            .next()
+            .transpose()
+            .map_err(deser_error_replace_rust_name::<Self>)?
+        {
+            let value = <serde_json_1::Value>::deserialize(column.spec.typ(), column.slice)
+                .map_err(|err| {
+                    mk_deser_err::<Self>(
+                        BuiltinDeserializationErrorKind::ColumnDeserializationFailed {
+                            column_index: column.index,
+                            column_name: column.spec.name().to_owned(),
+                            err,
+                        },
+                    )
+                })?;
+            columns.insert(column.spec.name().to_owned(), value);
+        }
+        Ok(columns)
+    }
+}
+
 // tuples
 //
 /// This is the new encouraged way for deserializing a row.
@@ -71,6 +71,25 @@ impl<'frame, 'metadata> DeserializeValue<'frame, 'metadata> for CqlValue {
     }
 }
 
+#[cfg(feature = "serde_json-1")]
+impl<'frame, 'metadata> DeserializeValue<'frame, 'metadata> for serde_json_1::Value {
+    fn type_check(_typ: &ColumnType) -> Result<(), TypeCheckError> {
+        // All CQL types can be converted to JSON.
+        Ok(())
+    }
+
+    fn deserialize(
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        let value = <Option<CqlValue>>::deserialize(typ, v)
+            .map_err(deser_error_replace_rust_name::<Self>)?;
+        Ok(value.map_or(serde_json_1::Value::Null, |value| {
+            crate::value::json::cql_value_to_json(&value)
+        }))
+    }
+}
+
 // Option represents nullability of CQL values:
 // None corresponds to null,
 // Some(val) to non-null values.
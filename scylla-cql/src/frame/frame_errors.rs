@@ -53,6 +53,10 @@ pub enum FrameBodyExtensionsParseError {
     /// Failed to decompress frame body (lz4).
     #[error("Error decompressing lz4 data {0}")]
     Lz4DecompressError(Arc<dyn Error + Sync + Send>),
+
+    /// Failed to decompress frame body (zstd).
+    #[error("Zstd decompression error: {0}")]
+    ZstdDecompressError(Arc<dyn Error + Sync + Send>),
 }
 
 /// An error that occurred during frame header deserialization.
@@ -73,7 +77,7 @@ pub enum FrameHeaderParseError {
     FrameFromServer,
 
     /// Received a frame with unsupported version.
-    #[error("Received a frame from version {0}, but only 4 is supported")]
+    #[error("Received a frame from version {0}, but only 4 and 5 are supported")]
     VersionNotSupported(u8),
 
     /// Received unknown response opcode.
@@ -89,6 +93,43 @@ pub enum FrameHeaderParseError {
     ConnectionClosed(usize, usize),
 }
 
+/// An error that occurred while reading a protocol v5 segment
+/// or reassembling frames from segments.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SegmentParseError {
+    /// Failed to read the segment from the socket.
+    #[error("Failed to read a segment: {0}")]
+    IoError(std::io::Error),
+
+    /// Checksum of the segment header does not match its contents.
+    #[error("Segment header CRC24 mismatch: received {received:#08x}, computed {computed:#08x}")]
+    HeaderChecksumMismatch { received: u32, computed: u32 },
+
+    /// Checksum of the segment payload does not match its contents.
+    #[error(
+        "Segment payload CRC32 mismatch: received {received:#010x}, computed {computed:#010x}"
+    )]
+    PayloadChecksumMismatch { received: u32, computed: u32 },
+
+    /// Failed to decompress segment payload (lz4).
+    #[error("Error decompressing lz4 segment payload: {0}")]
+    Lz4DecompressError(Arc<dyn Error + Sync + Send>),
+
+    /// A self-contained segment ended in the middle of a frame.
+    #[error("Self-contained segment ends with an incomplete frame")]
+    IncompleteFrame,
+
+    /// A self-contained segment arrived while a frame split across
+    /// multiple segments was still being reassembled.
+    #[error("Received a self-contained segment in the middle of a multi-segment frame")]
+    UnexpectedSelfContainedSegment,
+
+    /// A segment carrying a part of a frame contained more data than the frame.
+    #[error("Segment contains more data than the frame being reassembled")]
+    FrameOverflow,
+}
+
 /// An error that occurred during CQL request serialization.
 #[non_exhaustive]
 #[derive(Error, Debug, Clone)]
@@ -121,9 +162,17 @@ pub enum CqlRequestSerializationError {
     #[error("Failed to serialize QUERY request: {0}")]
     QuerySerialization(#[from] QuerySerializationError),
 
+    /// Failed to serialize the custom payload attached to the request.
+    #[error("Failed to serialize request custom payload: {0}")]
+    CustomPayloadSerialization(std::num::TryFromIntError),
+
     /// Request body compression failed.
     #[error("Snap compression error: {0}")]
     SnapCompressError(Arc<dyn Error + Sync + Send>),
+
+    /// Request body compression failed (zstd).
+    #[error("Zstd compression error: {0}")]
+    ZstdCompressError(Arc<dyn Error + Sync + Send>),
 }
 
 /// An error type returned when deserialization of CQL
@@ -297,6 +346,8 @@ pub enum ClusterChangeEventParseError {
 pub enum PreparedParseError {
     #[error("Malformed prepared statement's id length: {0}")]
     IdLengthParseError(LowLevelDeserializationError),
+    #[error("Malformed result metadata id: {0}")]
+    ResultMetadataIdParseError(LowLevelDeserializationError),
     #[error("Invalid result metadata: {0}")]
     ResultMetadataParseError(ResultMetadataParseError),
     #[error("Invalid prepared metadata: {0}")]
@@ -325,6 +376,10 @@ pub enum RawRowsAndPagingStateResponseParseError {
     /// Failed to parse paging state response.
     #[error("Malformed paging state: {0}")]
     PagingStateParseError(LowLevelDeserializationError),
+
+    /// Failed to parse the id of changed result metadata.
+    #[error("Malformed new metadata id: {0}")]
+    NewMetadataIdParseError(LowLevelDeserializationError),
 }
 
 /// An error type returned when deserialization
@@ -388,6 +443,10 @@ pub enum ResultMetadataParseError {
     #[error("Malformed paging state: {0}")]
     PagingStateParseError(LowLevelDeserializationError),
 
+    /// Failed to parse the id of changed result metadata.
+    #[error("Malformed new metadata id: {0}")]
+    NewMetadataIdParseError(LowLevelDeserializationError),
+
     /// Failed to parse global table spec.
     #[error("Invalid global table spec: {0}")]
     GlobalTableSpecParseError(#[from] TableSpecParseError),
@@ -2,6 +2,7 @@ pub mod frame_errors;
 pub mod protocol_features;
 pub mod request;
 pub mod response;
+pub mod segment;
 pub mod server_event_type;
 pub mod types;
 
@@ -18,7 +19,7 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::{collections::HashMap, convert::TryFrom};
 
-use request::SerializableRequest;
+use request::{RequestOpcode, SerializableRequest};
 use response::ResponseOpcode;
 
 const HEADER_SIZE: usize = 9;
@@ -86,8 +87,104 @@ impl Display for Compression {
     }
 }
 
+/// A wire protocol compression algorithm, including the ones which are not part
+/// of [Compression].
+///
+/// [Compression] is kept as is, so that matching on it does not break;
+/// new algorithms are added here instead.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum CompressionAlgorithm {
+    /// LZ4 compression algorithm.
+    Lz4,
+    /// Snappy compression algorithm.
+    Snappy,
+    /// Zstandard compression algorithm.
+    ///
+    /// Not part of the CQL specification, so it is used only if the server
+    /// advertises `zstd` in its SUPPORTED `COMPRESSION` options.
+    ///
+    /// Compressing and decompressing requires the `zstd` feature; without it,
+    /// both fail at runtime.
+    Zstd {
+        /// Compression level, as understood by zstd: higher levels trade speed for ratio,
+        /// negative levels trade ratio for speed and `0` selects zstd's default level.
+        level: i32,
+    },
+}
+
+impl CompressionAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompressionAlgorithm::Lz4 => "lz4",
+            CompressionAlgorithm::Snappy => "snappy",
+            CompressionAlgorithm::Zstd { .. } => "zstd",
+        }
+    }
+}
+
+impl From<Compression> for CompressionAlgorithm {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::Lz4 => CompressionAlgorithm::Lz4,
+            Compression::Snappy => CompressionAlgorithm::Snappy,
+        }
+    }
+}
+
+impl FromStr for CompressionAlgorithm {
+    type Err = CompressionFromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zstd" => Ok(Self::Zstd { level: 0 }),
+            other => other.parse::<Compression>().map(Into::into),
+        }
+    }
+}
+
+impl Display for CompressionAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The version of the CQL binary protocol.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+#[non_exhaustive]
+pub enum ProtocolVersion {
+    /// Protocol v4.
+    #[default]
+    V4,
+    /// Protocol v5. After STARTUP, frames are sent inside checksummed segments
+    /// (see [segment]), which are also the unit of compression.
+    V5,
+}
+
+impl ProtocolVersion {
+    /// The version number, as sent in the frame header.
+    pub fn as_u8(self) -> u8 {
+        match self {
+            ProtocolVersion::V4 => 4,
+            ProtocolVersion::V5 => 5,
+        }
+    }
+
+    /// Whether frames are wrapped in segments after STARTUP.
+    pub fn uses_segments(self) -> bool {
+        self >= ProtocolVersion::V5
+    }
+}
+
+impl Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}", self.as_u8())
+    }
+}
+
 pub struct SerializedRequest {
     data: Vec<u8>,
+    opcode: RequestOpcode,
 }
 
 impl SerializedRequest {
@@ -95,23 +192,72 @@ impl SerializedRequest {
         req: &R,
         compression: Option<Compression>,
         tracing: bool,
+    ) -> Result<SerializedRequest, CqlRequestSerializationError> {
+        Self::make_for_version(
+            req,
+            ProtocolVersion::V4,
+            compression.map(Into::into),
+            tracing,
+        )
+    }
+
+    /// Serializes the request for the given protocol version.
+    ///
+    /// In protocol v5, compression applies to segments rather than to individual
+    /// frames, so `compression` is expected to be `None` there.
+    pub fn make_for_version<R: SerializableRequest>(
+        req: &R,
+        version: ProtocolVersion,
+        compression: Option<CompressionAlgorithm>,
+        tracing: bool,
+    ) -> Result<SerializedRequest, CqlRequestSerializationError> {
+        Self::make_with_custom_payload(req, version, compression, tracing, None)
+    }
+
+    /// Serializes the request for the given protocol version, attaching
+    /// the given custom payload to it.
+    ///
+    /// The payload is written in front of the request body (and compressed
+    /// together with it), and the CUSTOM_PAYLOAD flag is set in the header.
+    /// A missing or empty payload is not sent at all.
+    pub fn make_with_custom_payload<R: SerializableRequest>(
+        req: &R,
+        version: ProtocolVersion,
+        compression: Option<CompressionAlgorithm>,
+        tracing: bool,
+        custom_payload: Option<&HashMap<String, Bytes>>,
     ) -> Result<SerializedRequest, CqlRequestSerializationError> {
         let mut flags = 0;
         let mut data = vec![0; HEADER_SIZE];
 
+        let custom_payload = custom_payload.filter(|payload| !payload.is_empty());
+        let serialize_body = |buf: &mut Vec<u8>| -> Result<(), CqlRequestSerializationError> {
+            if let Some(custom_payload) = custom_payload {
+                types::write_bytes_map(custom_payload, buf)
+                    .map_err(CqlRequestSerializationError::CustomPayloadSerialization)?;
+            }
+            req.serialize_for_version(version, buf)?;
+            Ok(())
+        };
+
+        if custom_payload.is_some() {
+            flags |= flag::CUSTOM_PAYLOAD;
+        }
+
         if let Some(compression) = compression {
             flags |= flag::COMPRESSION;
-            let body = req.to_bytes()?;
-            compress_append(&body, compression, &mut data)?;
+            let mut body = Vec::new();
+            serialize_body(&mut body)?;
+            compress_append_with_algorithm(&body, compression, &mut data)?;
         } else {
-            req.serialize(&mut data)?;
+            serialize_body(&mut data)?;
         }
 
         if tracing {
             flags |= flag::TRACING;
         }
 
-        data[0] = 4; // We only support version 4 for now
+        data[0] = version.as_u8();
         data[1] = flags;
         // Leave space for the stream number
         data[4] = R::OPCODE as u8;
@@ -119,7 +265,10 @@ impl SerializedRequest {
         let req_size = (data.len() - HEADER_SIZE) as u32;
         data[5..9].copy_from_slice(&req_size.to_be_bytes());
 
-        Ok(Self { data })
+        Ok(Self {
+            data,
+            opcode: R::OPCODE,
+        })
     }
 
     pub fn set_stream(&mut self, stream: i16) {
@@ -129,6 +278,10 @@ impl SerializedRequest {
     pub fn get_data(&self) -> &[u8] {
         &self.data[..]
     }
+
+    pub fn opcode(&self) -> RequestOpcode {
+        self.opcode
+    }
 }
 
 // Parts of the frame header which are not determined by the request/response type.
@@ -158,14 +311,59 @@ pub async fn read_response_frame(
         .await
         .map_err(FrameHeaderParseError::HeaderIoError)?;
 
+    let (frame_params, opcode, length) = parse_response_header(&raw_header)?;
+
+    let mut raw_body = Vec::with_capacity(length).limit(length);
+    while raw_body.has_remaining_mut() {
+        let n = reader.read_buf(&mut raw_body).await.map_err(|err| {
+            FrameHeaderParseError::BodyChunkIoError(raw_body.remaining_mut(), err)
+        })?;
+        if n == 0 {
+            // EOF, too early
+            return Err(FrameHeaderParseError::ConnectionClosed(
+                raw_body.remaining_mut(),
+                length,
+            ));
+        }
+    }
+
+    Ok((frame_params, opcode, raw_body.into_inner().into()))
+}
+
+/// Parses a whole response frame (an envelope, in protocol v5 terms)
+/// that has already been read into memory, e.g. from a segment.
+pub fn parse_response_frame(
+    mut frame: Bytes,
+) -> Result<(FrameParams, ResponseOpcode, Bytes), FrameHeaderParseError> {
+    if frame.len() < HEADER_SIZE {
+        return Err(FrameHeaderParseError::ConnectionClosed(
+            HEADER_SIZE - frame.len(),
+            HEADER_SIZE,
+        ));
+    }
+    let raw_header: [u8; HEADER_SIZE] = frame[..HEADER_SIZE].try_into().unwrap();
+    let (frame_params, opcode, length) = parse_response_header(&raw_header)?;
+    frame.advance(HEADER_SIZE);
+    if frame.len() != length {
+        return Err(FrameHeaderParseError::ConnectionClosed(
+            length.saturating_sub(frame.len()),
+            length,
+        ));
+    }
+
+    Ok((frame_params, opcode, frame))
+}
+
+fn parse_response_header(
+    raw_header: &[u8; HEADER_SIZE],
+) -> Result<(FrameParams, ResponseOpcode, usize), FrameHeaderParseError> {
     let mut buf = &raw_header[..];
 
-    // TODO: Validate version
     let version = buf.get_u8();
     if version & 0x80 != 0x80 {
         return Err(FrameHeaderParseError::FrameFromClient);
     }
-    if version & 0x7F != 0x04 {
+    if !matches!(version & 0x7F, 0x04 | 0x05) {
         return Err(FrameHeaderParseError::VersionNotSupported(version & 0x7f));
     }
 
@@ -183,21 +381,7 @@ pub async fn read_response_frame(
     // TODO: Guard from frames that are too large
     let length = buf.get_u32() as usize;
 
-    let mut raw_body = Vec::with_capacity(length).limit(length);
-    while raw_body.has_remaining_mut() {
-        let n = reader.read_buf(&mut raw_body).await.map_err(|err| {
-            FrameHeaderParseError::BodyChunkIoError(raw_body.remaining_mut(), err)
-        })?;
-        if n == 0 {
-            // EOF, too early
-            return Err(FrameHeaderParseError::ConnectionClosed(
-                raw_body.remaining_mut(),
-                length,
-            ));
-        }
-    }
-
-    Ok((frame_params, opcode, raw_body.into_inner().into()))
+    Ok((frame_params, opcode, length))
 }
 
 pub struct ResponseBodyWithExtensions {
@@ -210,11 +394,20 @@ pub struct ResponseBodyWithExtensions {
 pub fn parse_response_body_extensions(
     flags: u8,
     compression: Option<Compression>,
+    body: Bytes,
+) -> Result<ResponseBodyWithExtensions, FrameBodyExtensionsParseError> {
+    parse_response_body_extensions_with_algorithm(flags, compression.map(Into::into), body)
+}
+
+/// Like [parse_response_body_extensions], but accepts any [CompressionAlgorithm].
+pub fn parse_response_body_extensions_with_algorithm(
+    flags: u8,
+    compression: Option<CompressionAlgorithm>,
     mut body: Bytes,
 ) -> Result<ResponseBodyWithExtensions, FrameBodyExtensionsParseError> {
     if flags & flag::COMPRESSION != 0 {
         if let Some(compression) = compression {
-            body = decompress(&body, compression)?.into();
+            body = decompress_with_algorithm(&body, compression)?.into();
         } else {
             return Err(FrameBodyExtensionsParseError::NoCompressionNegotiated);
         }
@@ -266,9 +459,18 @@ pub fn compress_append(
     uncomp_body: &[u8],
     compression: Compression,
     out: &mut Vec<u8>,
+) -> Result<(), CqlRequestSerializationError> {
+    compress_append_with_algorithm(uncomp_body, compression.into(), out)
+}
+
+/// Like [compress_append], but accepts any [CompressionAlgorithm].
+pub fn compress_append_with_algorithm(
+    uncomp_body: &[u8],
+    compression: CompressionAlgorithm,
+    out: &mut Vec<u8>,
 ) -> Result<(), CqlRequestSerializationError> {
     match compression {
-        Compression::Lz4 => {
+        CompressionAlgorithm::Lz4 => {
             let uncomp_len = uncomp_body.len() as u32;
             let tmp = lz4_flex::compress(uncomp_body);
             out.reserve_exact(std::mem::size_of::<u32>() + tmp.len());
@@ -276,7 +478,7 @@ pub fn compress_append(
             out.extend_from_slice(&tmp[..]);
             Ok(())
         }
-        Compression::Snappy => {
+        CompressionAlgorithm::Snappy => {
             let old_size = out.len();
             out.resize(old_size + snap::raw::max_compress_len(uncomp_body.len()), 0);
             let compressed_size = snap::raw::Encoder::new()
@@ -285,26 +487,87 @@ pub fn compress_append(
             out.truncate(old_size + compressed_size);
             Ok(())
         }
+        #[cfg(feature = "zstd")]
+        CompressionAlgorithm::Zstd { level } => {
+            let tmp = zstd::bulk::compress(uncomp_body, level)
+                .map_err(|err| CqlRequestSerializationError::ZstdCompressError(Arc::new(err)))?;
+            out.extend_from_slice(&tmp[..]);
+            Ok(())
+        }
+        #[cfg(not(feature = "zstd"))]
+        CompressionAlgorithm::Zstd { .. } => Err(CqlRequestSerializationError::ZstdCompressError(
+            Arc::new(ZstdNotEnabledError),
+        )),
     }
 }
 
 pub fn decompress(
-    mut comp_body: &[u8],
+    comp_body: &[u8],
     compression: Compression,
+) -> Result<Vec<u8>, FrameBodyExtensionsParseError> {
+    decompress_with_algorithm(comp_body, compression.into())
+}
+
+/// Like [decompress], but accepts any [CompressionAlgorithm].
+pub fn decompress_with_algorithm(
+    mut comp_body: &[u8],
+    compression: CompressionAlgorithm,
 ) -> Result<Vec<u8>, FrameBodyExtensionsParseError> {
     match compression {
-        Compression::Lz4 => {
+        CompressionAlgorithm::Lz4 => {
             let uncomp_len = comp_body.get_u32() as usize;
             let uncomp_body = lz4_flex::decompress(comp_body, uncomp_len)
                 .map_err(|err| FrameBodyExtensionsParseError::Lz4DecompressError(Arc::new(err)))?;
             Ok(uncomp_body)
         }
-        Compression::Snappy => snap::raw::Decoder::new()
+        CompressionAlgorithm::Snappy => snap::raw::Decoder::new()
             .decompress_vec(comp_body)
             .map_err(|err| FrameBodyExtensionsParseError::SnapDecompressError(Arc::new(err))),
+        #[cfg(feature = "zstd")]
+        CompressionAlgorithm::Zstd { .. } => decompress_zstd(comp_body, MAX_BODY_LEN),
+        #[cfg(not(feature = "zstd"))]
+        CompressionAlgorithm::Zstd { .. } => Err(
+            FrameBodyExtensionsParseError::ZstdDecompressError(Arc::new(ZstdNotEnabledError)),
+        ),
+    }
+}
+
+/// The maximum length of a frame body allowed by the protocol (256 MB).
+/// Bounds the output of decompression, so that a small compressed body
+/// cannot expand to an arbitrary amount of memory.
+#[cfg(feature = "zstd")]
+const MAX_BODY_LEN: usize = 256 * 1024 * 1024;
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(
+    comp_body: &[u8],
+    max_len: usize,
+) -> Result<Vec<u8>, FrameBodyExtensionsParseError> {
+    use std::io::Read;
+
+    let to_error = |err| FrameBodyExtensionsParseError::ZstdDecompressError(Arc::new(err));
+    let mut uncomp_body = Vec::new();
+    zstd::stream::read::Decoder::with_buffer(comp_body)
+        .map_err(to_error)?
+        // Reading one byte past the limit tells a body of exactly `max_len` bytes from a longer one.
+        .take(max_len as u64 + 1)
+        .read_to_end(&mut uncomp_body)
+        .map_err(to_error)?;
+    if uncomp_body.len() > max_len {
+        return Err(to_error(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("decompressed body exceeds the limit of {} bytes", max_len),
+        )));
     }
+    Ok(uncomp_body)
 }
 
+/// Zstd compression was negotiated, but scylla-cql was built without the `zstd` feature.
+#[cfg(not(feature = "zstd"))]
+#[derive(Error, Debug)]
+#[error("Zstd compression requires the `zstd` feature of scylla-cql")]
+struct ZstdNotEnabledError;
+
 /// An error type for parsing an enum value from a primitive.
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
 #[error("No discrimant in enum `{enum_name}` matches the value `{primitive:?}`")]
@@ -317,6 +580,8 @@ pub struct TryFromPrimitiveError<T: Copy + std::fmt::Debug> {
 mod test {
     use super::*;
 
+    use assert_matches::assert_matches;
+
     #[test]
     fn test_lz4_compress() {
         let mut out = Vec::from(&b"Hello"[..]);
@@ -340,4 +605,94 @@ mod test {
         assert_eq!(32, comp_body.len());
         assert_eq!(uncomp_body.as_bytes(), result);
     }
+
+    #[test]
+    fn test_custom_payload_serialization() {
+        let prepare = request::prepare::Prepare {
+            query: "SELECT * FROM ks.t",
+        };
+        let plain = SerializedRequest::make(&prepare, None, false).unwrap();
+
+        // An empty payload is not sent.
+        let empty = HashMap::new();
+        let with_empty = SerializedRequest::make_with_custom_payload(
+            &prepare,
+            ProtocolVersion::V4,
+            None,
+            false,
+            Some(&empty),
+        )
+        .unwrap();
+        assert_eq!(plain.get_data(), with_empty.get_data());
+
+        let payload = HashMap::from([("traceparent".to_owned(), Bytes::from_static(b"00-ab"))]);
+        let with_payload = SerializedRequest::make_with_custom_payload(
+            &prepare,
+            ProtocolVersion::V4,
+            None,
+            false,
+            Some(&payload),
+        )
+        .unwrap();
+        let data = with_payload.get_data();
+        assert_eq!(data[1], flag::CUSTOM_PAYLOAD);
+
+        let mut body = &data[HEADER_SIZE..];
+        assert_eq!(
+            u32::from_be_bytes(data[5..9].try_into().unwrap()) as usize,
+            body.len()
+        );
+        assert_eq!(types::read_bytes_map(&mut body).unwrap(), payload);
+        assert_eq!(body, &plain.get_data()[HEADER_SIZE..]);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_compress_decompress() {
+        let uncomp_body = "Hello, World!".repeat(100);
+        for level in [-5, 0, 3, 19] {
+            let mut comp_body = Vec::from(&b"Hello"[..]);
+            let compression = CompressionAlgorithm::Zstd { level };
+            compress_append_with_algorithm(uncomp_body.as_bytes(), compression, &mut comp_body)
+                .unwrap();
+            assert_eq!(b"Hello", &comp_body[..5]);
+            assert!(comp_body.len() < uncomp_body.len());
+            let result = decompress_with_algorithm(&comp_body[5..], compression).unwrap();
+            assert_eq!(uncomp_body.as_bytes(), result);
+        }
+
+        // The level is not negotiated, so the name alone maps to zstd's default level.
+        assert_eq!(
+            "zstd".parse::<CompressionAlgorithm>().unwrap(),
+            CompressionAlgorithm::Zstd { level: 0 }
+        );
+        "zstd".parse::<Compression>().unwrap_err();
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_decompression_is_bounded() {
+        let uncomp_body = vec![0u8; 1024];
+        let comp_body = zstd::bulk::compress(&uncomp_body, 0).unwrap();
+
+        assert_eq!(decompress_zstd(&comp_body, 1024).unwrap(), uncomp_body);
+        assert_matches!(
+            decompress_zstd(&comp_body, 1023),
+            Err(FrameBodyExtensionsParseError::ZstdDecompressError(_))
+        );
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn test_zstd_fails_without_feature() {
+        let compression = "zstd".parse::<CompressionAlgorithm>().unwrap();
+        assert_matches!(
+            compress_append_with_algorithm(b"Hello", compression, &mut Vec::new()),
+            Err(CqlRequestSerializationError::ZstdCompressError(_))
+        );
+        assert_matches!(
+            decompress_with_algorithm(b"Hello", compression),
+            Err(FrameBodyExtensionsParseError::ZstdDecompressError(_))
+        );
+    }
 }
@@ -4,8 +4,9 @@ use thiserror::Error;
 
 use crate::frame::{
     frame_errors::CqlRequestSerializationError,
-    request::{RequestOpcode, SerializableRequest},
+    request::{extensions::RequestExtensions, RequestOpcode, SerializableRequest},
     types::{self, SerialConsistency},
+    ProtocolVersion,
 };
 use crate::serialize::{
     raw_batch::{RawBatchValues, RawBatchValuesIterator},
@@ -20,6 +21,10 @@ const FLAG_WITH_SERIAL_CONSISTENCY: u8 = 0x10;
 const FLAG_WITH_DEFAULT_TIMESTAMP: u8 = 0x20;
 const ALL_FLAGS: u8 = FLAG_WITH_SERIAL_CONSISTENCY | FLAG_WITH_DEFAULT_TIMESTAMP;
 
+// Batch flags introduced in protocol v5, where flags take 4 bytes.
+const FLAG_WITH_KEYSPACE: u32 = 0x80;
+const FLAG_WITH_NOW_IN_SECONDS: u32 = 0x100;
+
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
 pub struct Batch<'b, Statement, Values>
 where
@@ -36,8 +41,7 @@ where
 }
 
 /// The type of a batch.
-#[derive(Clone, Copy)]
-#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BatchType {
     Logged = 0,
     Unlogged = 1,
@@ -75,7 +79,12 @@ where
     Statement: Clone,
     Values: RawBatchValues,
 {
-    fn do_serialize(&self, buf: &mut Vec<u8>) -> Result<(), BatchSerializationError> {
+    fn do_serialize(
+        &self,
+        version: ProtocolVersion,
+        extensions: &RequestExtensions,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), BatchSerializationError> {
         // Serializing type of batch
         buf.put_u8(self.batch_type as u8);
 
@@ -161,7 +170,24 @@ where
             flags |= FLAG_WITH_DEFAULT_TIMESTAMP;
         }
 
-        buf.put_u8(flags);
+        if version >= ProtocolVersion::V5 {
+            let mut flags = flags as u32;
+            if extensions.keyspace.is_some() {
+                flags |= FLAG_WITH_KEYSPACE;
+            }
+            if extensions.now_in_seconds.is_some() {
+                flags |= FLAG_WITH_NOW_IN_SECONDS;
+            }
+            buf.put_u32(flags);
+        } else {
+            if extensions.keyspace.is_some() {
+                return Err(BatchSerializationError::KeyspaceUnsupported(version));
+            }
+            if extensions.now_in_seconds.is_some() {
+                return Err(BatchSerializationError::NowInSecondsUnsupported(version));
+            }
+            buf.put_u8(flags);
+        }
 
         if let Some(serial_consistency) = self.serial_consistency {
             types::write_serial_consistency(serial_consistency, buf);
@@ -169,9 +195,26 @@ where
         if let Some(timestamp) = self.timestamp {
             types::write_long(timestamp, buf);
         }
+        if let Some(keyspace) = &extensions.keyspace {
+            types::write_string(keyspace, buf)
+                .map_err(BatchSerializationError::KeyspaceNameSerialization)?;
+        }
+        if let Some(now_in_seconds) = extensions.now_in_seconds {
+            types::write_int(now_in_seconds, buf);
+        }
 
         Ok(())
     }
+
+    pub(crate) fn serialize_with_extensions(
+        &self,
+        version: ProtocolVersion,
+        extensions: &RequestExtensions,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
+        self.do_serialize(version, extensions, buf)?;
+        Ok(())
+    }
 }
 
 impl<Statement, Values> SerializableRequest for Batch<'_, Statement, Values>
@@ -183,8 +226,15 @@ where
     const OPCODE: RequestOpcode = RequestOpcode::Batch;
 
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
-        self.do_serialize(buf)?;
-        Ok(())
+        self.serialize_for_version(ProtocolVersion::V4, buf)
+    }
+
+    fn serialize_for_version(
+        &self,
+        version: ProtocolVersion,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_with_extensions(version, &RequestExtensions::default(), buf)
     }
 }
 
@@ -325,6 +375,18 @@ pub enum BatchSerializationError {
         n_announced_statements: usize,
         n_serialized_statements: usize,
     },
+
+    /// Failed to serialize the keyspace name.
+    #[error("Failed to serialize keyspace name: {0}")]
+    KeyspaceNameSerialization(TryFromIntError),
+
+    /// Keyspace was specified, but the protocol version does not support it.
+    #[error("Setting keyspace per request requires protocol v5 or newer, but {0} is used")]
+    KeyspaceUnsupported(ProtocolVersion),
+
+    /// `now_in_seconds` was specified, but the protocol version does not support it.
+    #[error("Setting now_in_seconds requires protocol v5 or newer, but {0} is used")]
+    NowInSecondsUnsupported(ProtocolVersion),
 }
 
 /// An error type returned when serialization of one of the
@@ -5,8 +5,9 @@ use bytes::Bytes;
 use thiserror::Error;
 
 use crate::{
-    frame::request::{query, RequestOpcode, SerializableRequest},
+    frame::request::{extensions::RequestExtensions, query, RequestOpcode, SerializableRequest},
     frame::types,
+    frame::ProtocolVersion,
 };
 
 use super::{
@@ -24,13 +25,39 @@ impl SerializableRequest for Execute<'_> {
     const OPCODE: RequestOpcode = RequestOpcode::Execute;
 
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_for_version(ProtocolVersion::V4, buf)
+    }
+
+    fn serialize_for_version(
+        &self,
+        version: ProtocolVersion,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_with_extensions(version, &RequestExtensions::default(), buf)
+    }
+}
+
+impl Execute<'_> {
+    pub(crate) fn serialize_with_extensions(
+        &self,
+        version: ProtocolVersion,
+        extensions: &RequestExtensions,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
         // Serializing statement id
         types::write_short_bytes(&self.id[..], buf)
             .map_err(ExecuteSerializationError::StatementIdSerialization)?;
 
+        // Serializing result metadata id
+        if version >= ProtocolVersion::V5 {
+            let result_metadata_id = extensions.result_metadata_id.as_deref().unwrap_or_default();
+            types::write_short_bytes(result_metadata_id, buf)
+                .map_err(ExecuteSerializationError::ResultMetadataIdSerialization)?;
+        }
+
         // Serializing params
         self.parameters
-            .serialize(buf)
+            .serialize_with_extensions(version, extensions, buf)
             .map_err(ExecuteSerializationError::QueryParametersSerialization)?;
         Ok(())
     }
@@ -56,4 +83,8 @@ pub enum ExecuteSerializationError {
     /// Failed to serialize prepared statement id.
     #[error("Malformed statement id: {0}")]
     StatementIdSerialization(TryFromIntError),
+
+    /// Failed to serialize result metadata id.
+    #[error("Malformed result metadata id: {0}")]
+    ResultMetadataIdSerialization(TryFromIntError),
 }
@@ -0,0 +1,161 @@
+//! Request fields introduced in protocol v5.
+//!
+//! The request structs in this module's parent have public fields and can be
+//! constructed with struct literals, so new fields cannot be added to them
+//! without breaking their users. Fields introduced in protocol v5 are therefore
+//! kept in [RequestExtensions], which is sent together with a request
+//! by wrapping both in an [ExtendedRequest].
+
+use std::borrow::Cow;
+
+use bytes::Bytes;
+
+use crate::frame::frame_errors::CqlRequestSerializationError;
+use crate::frame::request::{
+    batch::{Batch, BatchStatement},
+    execute::Execute,
+    prepare::Prepare,
+    query::Query,
+    RequestOpcode, SerializableRequest,
+};
+use crate::frame::ProtocolVersion;
+use crate::serialize::raw_batch::RawBatchValues;
+
+/// Fields of QUERY, EXECUTE, BATCH and PREPARE requests introduced
+/// in protocol v5.
+///
+/// All of them are unset by default. Fields which are not part of a given
+/// request are ignored when serializing it, e.g. `now_in_seconds` is not
+/// sent with PREPARE. Setting `keyspace` or `now_in_seconds` is an error
+/// when the request is serialized for a protocol version older than v5.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestExtensions<'a> {
+    /// Keyspace in which the request is executed or the statement is prepared,
+    /// overriding the keyspace the connection is bound to.
+    /// Sent with QUERY, BATCH and PREPARE.
+    pub keyspace: Option<Cow<'a, str>>,
+
+    /// Current time (in seconds since the epoch) to be used by the server
+    /// when executing the request, e.g. for TTL calculations.
+    /// Sent with QUERY, EXECUTE and BATCH.
+    pub now_in_seconds: Option<i32>,
+
+    /// Id of the result metadata returned when the statement was prepared.
+    /// Sent with EXECUTE, where an empty id is sent if it is missing.
+    pub result_metadata_id: Option<Bytes>,
+}
+
+impl<'a> RequestExtensions<'a> {
+    /// Sets the keyspace in which the request is executed.
+    pub fn with_keyspace(mut self, keyspace: Option<Cow<'a, str>>) -> Self {
+        self.keyspace = keyspace;
+        self
+    }
+
+    /// Sets the current time used by the server when executing the request.
+    pub fn with_now_in_seconds(mut self, now_in_seconds: Option<i32>) -> Self {
+        self.now_in_seconds = now_in_seconds;
+        self
+    }
+
+    /// Sets the id of the result metadata of the executed statement.
+    pub fn with_result_metadata_id(mut self, result_metadata_id: Option<Bytes>) -> Self {
+        self.result_metadata_id = result_metadata_id;
+        self
+    }
+}
+
+/// A request sent together with its [RequestExtensions].
+///
+/// Implements [SerializableRequest] for [Query], [Execute], [Batch] and [Prepare].
+#[non_exhaustive]
+pub struct ExtendedRequest<'a, R> {
+    /// The wrapped request.
+    pub request: R,
+    /// Fields of the request introduced in protocol v5.
+    pub extensions: RequestExtensions<'a>,
+}
+
+impl<'a, R> ExtendedRequest<'a, R> {
+    /// Wraps the request together with its extensions.
+    pub fn new(request: R, extensions: RequestExtensions<'a>) -> Self {
+        Self {
+            request,
+            extensions,
+        }
+    }
+}
+
+impl SerializableRequest for ExtendedRequest<'_, Query<'_>> {
+    const OPCODE: RequestOpcode = RequestOpcode::Query;
+
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_for_version(ProtocolVersion::V4, buf)
+    }
+
+    fn serialize_for_version(
+        &self,
+        version: ProtocolVersion,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
+        self.request
+            .serialize_with_extensions(version, &self.extensions, buf)
+    }
+}
+
+impl SerializableRequest for ExtendedRequest<'_, Execute<'_>> {
+    const OPCODE: RequestOpcode = RequestOpcode::Execute;
+
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_for_version(ProtocolVersion::V4, buf)
+    }
+
+    fn serialize_for_version(
+        &self,
+        version: ProtocolVersion,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
+        self.request
+            .serialize_with_extensions(version, &self.extensions, buf)
+    }
+}
+
+impl<Statement, Values> SerializableRequest for ExtendedRequest<'_, Batch<'_, Statement, Values>>
+where
+    for<'s> BatchStatement<'s>: From<&'s Statement>,
+    Statement: Clone,
+    Values: RawBatchValues,
+{
+    const OPCODE: RequestOpcode = RequestOpcode::Batch;
+
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_for_version(ProtocolVersion::V4, buf)
+    }
+
+    fn serialize_for_version(
+        &self,
+        version: ProtocolVersion,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
+        self.request
+            .serialize_with_extensions(version, &self.extensions, buf)
+    }
+}
+
+impl SerializableRequest for ExtendedRequest<'_, Prepare<'_>> {
+    const OPCODE: RequestOpcode = RequestOpcode::Prepare;
+
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_for_version(ProtocolVersion::V4, buf)
+    }
+
+    fn serialize_for_version(
+        &self,
+        version: ProtocolVersion,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
+        self.request
+            .serialize_with_extensions(version, &self.extensions, buf)
+    }
+}
@@ -1,6 +1,7 @@
 pub mod auth_response;
 pub mod batch;
 pub mod execute;
+pub mod extensions;
 pub mod options;
 pub mod prepare;
 pub mod query;
@@ -17,6 +18,7 @@ use bytes::Bytes;
 pub use auth_response::AuthResponse;
 pub use batch::Batch;
 pub use execute::Execute;
+pub use extensions::{ExtendedRequest, RequestExtensions};
 pub use options::Options;
 pub use prepare::Prepare;
 pub use query::Query;
@@ -26,7 +28,7 @@ use self::batch::BatchStatement;
 
 use super::frame_errors::{CqlRequestSerializationError, LowLevelDeserializationError};
 use super::types::SerialConsistency;
-use super::TryFromPrimitiveError;
+use super::{ProtocolVersion, TryFromPrimitiveError};
 
 /// Possible requests sent by the client.
 #[derive(Debug, Copy, Clone)]
@@ -98,6 +100,19 @@ pub trait SerializableRequest {
 
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError>;
 
+    /// Serializes the request body in the form defined by the given protocol version.
+    ///
+    /// Most requests look the same in all supported versions, so by default
+    /// this is the same as [SerializableRequest::serialize].
+    fn serialize_for_version(
+        &self,
+        version: ProtocolVersion,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
+        let _ = version;
+        self.serialize(buf)
+    }
+
     fn to_bytes(&self) -> Result<Bytes, CqlRequestSerializationError> {
         let mut v = Vec::new();
         self.serialize(&mut v)?;
@@ -183,6 +198,7 @@ impl Request<'_> {
 mod tests {
     use std::{borrow::Cow, ops::Deref};
 
+    use assert_matches::assert_matches;
     use bytes::Bytes;
 
     use crate::serialize::row::SerializedValues;
@@ -191,16 +207,22 @@ mod tests {
             request::{
                 batch::{Batch, BatchStatement, BatchType},
                 execute::Execute,
-                query::{Query, QueryParameters},
-                DeserializableRequest, SerializableRequest,
+                prepare::Prepare,
+                query::{
+                    Query, QueryParameters, QueryParametersSerializationError,
+                    QuerySerializationError,
+                },
+                DeserializableRequest, ExtendedRequest, RequestExtensions, SerializableRequest,
             },
             response::result::{ColumnType, NativeType},
             types::{self, SerialConsistency},
+            ProtocolVersion,
         },
         Consistency,
     };
 
     use super::query::PagingState;
+    use super::CqlRequestSerializationError;
 
     #[test]
     fn request_ser_de_identity() {
@@ -373,4 +395,85 @@ mod tests {
             let _parse_error = Batch::deserialize(&mut &buf[..]).unwrap_err();
         }
     }
+
+    #[test]
+    fn v5_keyspace_and_now_in_seconds() {
+        let query = ExtendedRequest::new(
+            Query {
+                contents: Cow::Borrowed("SELECT * FROM t"),
+                parameters: QueryParameters::default(),
+            },
+            RequestExtensions::default()
+                .with_keyspace(Some(Cow::Borrowed("ks")))
+                .with_now_in_seconds(Some(1_700_000_000)),
+        );
+
+        let mut buf = Vec::new();
+        query
+            .serialize_for_version(ProtocolVersion::V5, &mut buf)
+            .unwrap();
+        let mut expected = Vec::new();
+        types::write_long_string("SELECT * FROM t", &mut expected).unwrap();
+        types::write_consistency(query.request.parameters.consistency, &mut expected);
+        expected.extend_from_slice(&0x0180_u32.to_be_bytes());
+        types::write_string("ks", &mut expected).unwrap();
+        types::write_int(1_700_000_000, &mut expected);
+        assert_eq!(buf, expected);
+
+        // Neither can be expressed in protocol v4.
+        let mut buf = Vec::new();
+        assert_matches!(
+            query.serialize_for_version(ProtocolVersion::V4, &mut buf),
+            Err(CqlRequestSerializationError::QuerySerialization(
+                QuerySerializationError::QueryParametersSerialization(
+                    QueryParametersSerializationError::KeyspaceUnsupported(ProtocolVersion::V4)
+                )
+            ))
+        );
+        let query = ExtendedRequest::new(
+            query.request,
+            RequestExtensions::default().with_now_in_seconds(Some(0)),
+        );
+        assert_matches!(
+            query.serialize_for_version(ProtocolVersion::V4, &mut buf),
+            Err(CqlRequestSerializationError::QuerySerialization(
+                QuerySerializationError::QueryParametersSerialization(
+                    QueryParametersSerializationError::NowInSecondsUnsupported(ProtocolVersion::V4)
+                )
+            ))
+        );
+
+        // Without extensions, the request looks the same as in v4,
+        // except for the wider flags.
+        let mut v4 = Vec::new();
+        query.request.serialize(&mut v4).unwrap();
+        let mut v5 = Vec::new();
+        query
+            .request
+            .serialize_for_version(ProtocolVersion::V5, &mut v5)
+            .unwrap();
+        assert_eq!(v5.len(), v4.len() + 3);
+
+        // Prepare
+        let prepare = ExtendedRequest::new(
+            Prepare {
+                query: "SELECT * FROM t",
+            },
+            RequestExtensions::default().with_keyspace(Some(Cow::Borrowed("ks"))),
+        );
+        let mut buf = Vec::new();
+        prepare
+            .serialize_for_version(ProtocolVersion::V5, &mut buf)
+            .unwrap();
+        let mut expected = Vec::new();
+        types::write_long_string("SELECT * FROM t", &mut expected).unwrap();
+        expected.extend_from_slice(&0x01_u32.to_be_bytes());
+        types::write_string("ks", &mut expected).unwrap();
+        assert_eq!(buf, expected);
+
+        let mut buf = Vec::new();
+        prepare
+            .serialize_for_version(ProtocolVersion::V4, &mut buf)
+            .unwrap_err();
+    }
 }
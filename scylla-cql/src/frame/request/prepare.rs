@@ -1,14 +1,19 @@
 use std::num::TryFromIntError;
 
+use bytes::BufMut;
 use thiserror::Error;
 
 use crate::frame::frame_errors::CqlRequestSerializationError;
 
 use crate::{
-    frame::request::{RequestOpcode, SerializableRequest},
+    frame::request::{extensions::RequestExtensions, RequestOpcode, SerializableRequest},
     frame::types,
+    frame::ProtocolVersion,
 };
 
+// Prepare flags, present since protocol v5.
+const FLAG_WITH_KEYSPACE: u32 = 0x01;
+
 pub struct Prepare<'a> {
     pub query: &'a str,
 }
@@ -17,8 +22,43 @@ impl SerializableRequest for Prepare<'_> {
     const OPCODE: RequestOpcode = RequestOpcode::Prepare;
 
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_for_version(ProtocolVersion::V4, buf)
+    }
+
+    fn serialize_for_version(
+        &self,
+        version: ProtocolVersion,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_with_extensions(version, &RequestExtensions::default(), buf)
+    }
+}
+
+impl Prepare<'_> {
+    pub(crate) fn serialize_with_extensions(
+        &self,
+        version: ProtocolVersion,
+        extensions: &RequestExtensions,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
         types::write_long_string(self.query, buf)
             .map_err(PrepareSerializationError::StatementStringSerialization)?;
+
+        if version >= ProtocolVersion::V5 {
+            let mut flags = 0;
+            if extensions.keyspace.is_some() {
+                flags |= FLAG_WITH_KEYSPACE;
+            }
+            buf.put_u32(flags);
+
+            if let Some(keyspace) = &extensions.keyspace {
+                types::write_string(keyspace, buf)
+                    .map_err(PrepareSerializationError::KeyspaceNameSerialization)?;
+            }
+        } else if extensions.keyspace.is_some() {
+            return Err(PrepareSerializationError::KeyspaceUnsupported(version).into());
+        }
+
         Ok(())
     }
 }
@@ -30,4 +70,12 @@ pub enum PrepareSerializationError {
     /// Failed to serialize the CQL statement string.
     #[error("Failed to serialize statement contents: {0}")]
     StatementStringSerialization(TryFromIntError),
+
+    /// Failed to serialize the keyspace name.
+    #[error("Failed to serialize keyspace name: {0}")]
+    KeyspaceNameSerialization(TryFromIntError),
+
+    /// Keyspace was specified, but the protocol version does not support it.
+    #[error("Setting keyspace per request requires protocol v5 or newer, but {0} is used")]
+    KeyspaceUnsupported(ProtocolVersion),
 }
@@ -1,17 +1,24 @@
 use std::{borrow::Cow, num::TryFromIntError, ops::ControlFlow, sync::Arc};
 
-use crate::frame::{frame_errors::CqlRequestSerializationError, types::SerialConsistency};
+use crate::frame::{
+    frame_errors::CqlRequestSerializationError, types::SerialConsistency, ProtocolVersion,
+};
 use crate::serialize::row::SerializedValues;
 use bytes::{Buf, BufMut};
 use thiserror::Error;
 
 use crate::{
-    frame::request::{RequestOpcode, SerializableRequest},
+    frame::request::{extensions::RequestExtensions, RequestOpcode, SerializableRequest},
     frame::types,
 };
 
 use super::{DeserializableRequest, RequestDeserializationError};
 
+#[cfg(feature = "paging-cursor")]
+mod cursor;
+#[cfg(feature = "paging-cursor")]
+pub use cursor::{PagingCursor, PagingCursorCodec, PagingCursorError};
+
 // Query flags
 const FLAG_VALUES: u8 = 0x01;
 const FLAG_SKIP_METADATA: u8 = 0x02;
@@ -28,6 +35,10 @@ const ALL_FLAGS: u8 = FLAG_VALUES
     | FLAG_WITH_DEFAULT_TIMESTAMP
     | FLAG_WITH_NAMES_FOR_VALUES;
 
+// Query flags introduced in protocol v5, where flags take 4 bytes.
+const FLAG_WITH_KEYSPACE: u32 = 0x80;
+const FLAG_WITH_NOW_IN_SECONDS: u32 = 0x100;
+
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
 pub struct Query<'q> {
     pub contents: Cow<'q, str>,
@@ -38,10 +49,29 @@ impl SerializableRequest for Query<'_> {
     const OPCODE: RequestOpcode = RequestOpcode::Query;
 
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_for_version(ProtocolVersion::V4, buf)
+    }
+
+    fn serialize_for_version(
+        &self,
+        version: ProtocolVersion,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_with_extensions(version, &RequestExtensions::default(), buf)
+    }
+}
+
+impl Query<'_> {
+    pub(crate) fn serialize_with_extensions(
+        &self,
+        version: ProtocolVersion,
+        extensions: &RequestExtensions,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CqlRequestSerializationError> {
         types::write_long_string(&self.contents, buf)
             .map_err(QuerySerializationError::StatementStringSerialization)?;
         self.parameters
-            .serialize(buf)
+            .serialize_with_extensions(version, extensions, buf)
             .map_err(QuerySerializationError::QueryParametersSerialization)?;
         Ok(())
     }
@@ -88,6 +118,28 @@ impl QueryParameters<'_> {
     pub fn serialize(
         &self,
         buf: &mut impl BufMut,
+    ) -> Result<(), QueryParametersSerializationError> {
+        self.serialize_for_version(ProtocolVersion::V4, buf)
+    }
+
+    /// Serializes the parameters in the form defined by the given protocol version.
+    /// Since v5, flags take 4 bytes instead of 1.
+    pub fn serialize_for_version(
+        &self,
+        version: ProtocolVersion,
+        buf: &mut impl BufMut,
+    ) -> Result<(), QueryParametersSerializationError> {
+        self.serialize_with_extensions(version, &RequestExtensions::default(), buf)
+    }
+
+    /// Serializes the parameters together with the keyspace and `now_in_seconds`
+    /// from the extensions. Specifying them for protocol versions older than v5
+    /// is an error.
+    pub(crate) fn serialize_with_extensions(
+        &self,
+        version: ProtocolVersion,
+        extensions: &RequestExtensions,
+        buf: &mut impl BufMut,
     ) -> Result<(), QueryParametersSerializationError> {
         types::write_consistency(self.consistency, buf);
 
@@ -118,7 +170,28 @@ impl QueryParameters<'_> {
             flags |= FLAG_WITH_DEFAULT_TIMESTAMP;
         }
 
-        buf.put_u8(flags);
+        if version >= ProtocolVersion::V5 {
+            let mut flags = flags as u32;
+            if extensions.keyspace.is_some() {
+                flags |= FLAG_WITH_KEYSPACE;
+            }
+            if extensions.now_in_seconds.is_some() {
+                flags |= FLAG_WITH_NOW_IN_SECONDS;
+            }
+            buf.put_u32(flags);
+        } else {
+            if extensions.keyspace.is_some() {
+                return Err(QueryParametersSerializationError::KeyspaceUnsupported(
+                    version,
+                ));
+            }
+            if extensions.now_in_seconds.is_some() {
+                return Err(QueryParametersSerializationError::NowInSecondsUnsupported(
+                    version,
+                ));
+            }
+            buf.put_u8(flags);
+        }
 
         if !self.values.is_empty() {
             self.values.write_to_request(buf);
@@ -140,6 +213,15 @@ impl QueryParameters<'_> {
             types::write_long(timestamp, buf);
         }
 
+        if let Some(keyspace) = &extensions.keyspace {
+            types::write_string(keyspace, buf)
+                .map_err(QueryParametersSerializationError::KeyspaceNameSerialization)?;
+        }
+
+        if let Some(now_in_seconds) = extensions.now_in_seconds {
+            types::write_int(now_in_seconds, buf);
+        }
+
         Ok(())
     }
 }
@@ -304,4 +386,16 @@ pub enum QueryParametersSerializationError {
     /// Failed to serialize paging state.
     #[error("Malformed paging state: {0}")]
     BadPagingState(#[from] TryFromIntError),
+
+    /// Failed to serialize the keyspace name.
+    #[error("Failed to serialize keyspace name: {0}")]
+    KeyspaceNameSerialization(TryFromIntError),
+
+    /// Keyspace was specified, but the protocol version does not support it.
+    #[error("Setting keyspace per request requires protocol v5 or newer, but {0} is used")]
+    KeyspaceUnsupported(ProtocolVersion),
+
+    /// `now_in_seconds` was specified, but the protocol version does not support it.
+    #[error("Setting now_in_seconds requires protocol v5 or newer, but {0} is used")]
+    NowInSecondsUnsupported(ProtocolVersion),
 }
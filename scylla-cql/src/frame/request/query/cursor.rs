@@ -0,0 +1,347 @@
+//! Encoding of [PagingState] as opaque, URL-safe cursors.
+//!
+//! This module is available only under the crate feature `paging-cursor`.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use byteorder::ReadBytesExt as _;
+use bytes::BufMut;
+use thiserror::Error;
+
+use crate::frame::types;
+
+use super::PagingState;
+
+const CURSOR_VERSION: u8 = 1;
+
+// Cursor flags
+const FLAG_WITH_PAGING_STATE: u8 = 0x01;
+const FLAG_WITH_STATEMENT_ID: u8 = 0x02;
+const FLAG_SIGNED: u8 = 0x04;
+const ALL_FLAGS: u8 = FLAG_WITH_PAGING_STATE | FLAG_WITH_STATEMENT_ID | FLAG_SIGNED;
+
+#[cfg(feature = "hmac-012")]
+type HmacSha256 = hmac_012::Hmac<sha2_010::Sha256>;
+#[cfg(feature = "hmac-012")]
+const SIGNATURE_LEN: usize = 32;
+
+/// Converts [PagingState]s to and from cursors: strings which can be handed out
+/// to the clients of an application (e.g. as pagination tokens in HTTP APIs)
+/// and used later to resume a paged query.
+///
+/// A cursor is an URL-safe base64 string (without padding) containing:
+/// - the version of the cursor format, so that the format can evolve,
+/// - the paging state,
+/// - optionally, the id of the prepared statement the paging state belongs to,
+///   so that the cursor is rejected if it is used with another statement,
+/// - optionally (under the crate feature `hmac-012`), a HMAC-SHA256 signature
+///   of all the above, so that the clients can't forge nor alter the cursors.
+///
+/// The paging state is not encrypted, so the clients can read it. Without
+/// a signature, they can also modify it, and the database may be queried
+/// with the modified paging state.
+#[derive(Clone, Default)]
+pub struct PagingCursorCodec {
+    #[cfg(feature = "hmac-012")]
+    key: Option<Vec<u8>>,
+}
+
+impl PagingCursorCodec {
+    /// Creates a codec of unsigned cursors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a codec of cursors signed with the key.
+    ///
+    /// The codec accepts only cursors signed with the same key.
+    #[cfg(feature = "hmac-012")]
+    pub fn with_hmac_key(key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key: Some(key.into()),
+        }
+    }
+
+    /// Encodes the paging state as a cursor.
+    ///
+    /// If `statement_id` is given (see `PreparedStatement::get_id`), the cursor
+    /// can be decoded only with the same statement id.
+    ///
+    /// Panics if the statement id is longer than 65535 bytes or the paging state
+    /// is longer than 2^31 - 1 bytes, which are never returned by the database.
+    pub fn encode(&self, paging_state: &PagingState, statement_id: Option<&[u8]>) -> String {
+        let mut buf = Vec::new();
+        buf.put_u8(CURSOR_VERSION);
+        let mut flags = 0;
+        if paging_state.as_bytes_slice().is_some() {
+            flags |= FLAG_WITH_PAGING_STATE;
+        }
+        if statement_id.is_some() {
+            flags |= FLAG_WITH_STATEMENT_ID;
+        }
+        if self.signs() {
+            flags |= FLAG_SIGNED;
+        }
+        buf.put_u8(flags);
+        if let Some(statement_id) = statement_id {
+            types::write_short_bytes(statement_id, &mut buf).expect("Statement id too long");
+        }
+        if let Some(raw_paging_state) = paging_state.as_bytes_slice() {
+            types::write_bytes(raw_paging_state, &mut buf).expect("Paging state too long");
+        }
+        #[cfg(feature = "hmac-012")]
+        if let Some(mac) = self.mac() {
+            use hmac_012::Mac as _;
+
+            let signature = mac.chain_update(&buf).finalize().into_bytes();
+            buf.put_slice(&signature);
+        }
+        URL_SAFE_NO_PAD.encode(buf)
+    }
+
+    /// Decodes a cursor created with [encode](Self::encode).
+    ///
+    /// `statement_id` must be the same as the one given when the cursor was encoded.
+    pub fn decode(
+        &self,
+        cursor: &str,
+        statement_id: Option<&[u8]>,
+    ) -> Result<PagingCursor, PagingCursorError> {
+        let raw = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .map_err(|_| PagingCursorError::InvalidEncoding)?;
+        let mut buf = raw.as_slice();
+        let version = buf.read_u8().map_err(|_| PagingCursorError::Malformed)?;
+        if version != CURSOR_VERSION {
+            return Err(PagingCursorError::UnsupportedVersion(version));
+        }
+        let flags = buf.read_u8().map_err(|_| PagingCursorError::Malformed)?;
+        if flags & !ALL_FLAGS != 0 {
+            return Err(PagingCursorError::Malformed);
+        }
+
+        let cursor_statement_id = if flags & FLAG_WITH_STATEMENT_ID != 0 {
+            Some(types::read_short_bytes(&mut buf).map_err(|_| PagingCursorError::Malformed)?)
+        } else {
+            None
+        };
+        let raw_paging_state = if flags & FLAG_WITH_PAGING_STATE != 0 {
+            Some(types::read_bytes(&mut buf).map_err(|_| PagingCursorError::Malformed)?)
+        } else {
+            None
+        };
+
+        if flags & FLAG_SIGNED != 0 {
+            self.verify(&raw[..raw.len() - buf.len()], buf)?;
+        } else if self.signs() {
+            return Err(PagingCursorError::MissingSignature);
+        } else if !buf.is_empty() {
+            return Err(PagingCursorError::Malformed);
+        }
+
+        if cursor_statement_id != statement_id {
+            return Err(PagingCursorError::StatementMismatch);
+        }
+
+        let paging_state = match raw_paging_state {
+            Some(raw_paging_state) => PagingState::new_from_raw_bytes(raw_paging_state),
+            None => PagingState::start(),
+        };
+        Ok(PagingCursor { paging_state })
+    }
+
+    fn signs(&self) -> bool {
+        #[cfg(feature = "hmac-012")]
+        if self.key.is_some() {
+            return true;
+        }
+        false
+    }
+
+    #[cfg(feature = "hmac-012")]
+    fn mac(&self) -> Option<HmacSha256> {
+        use hmac_012::Mac as _;
+
+        let key = self.key.as_deref()?;
+        Some(HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length"))
+    }
+
+    /// Verifies the signature of the signed part of a cursor.
+    fn verify(&self, signed: &[u8], signature: &[u8]) -> Result<(), PagingCursorError> {
+        #[cfg(feature = "hmac-012")]
+        if let Some(mac) = self.mac() {
+            use hmac_012::Mac as _;
+
+            if signature.len() != SIGNATURE_LEN {
+                return Err(PagingCursorError::Malformed);
+            }
+            return mac
+                .chain_update(signed)
+                .verify_slice(signature)
+                .map_err(|_| PagingCursorError::InvalidSignature);
+        }
+        let _ = (signed, signature);
+        Err(PagingCursorError::UnverifiableSignature)
+    }
+}
+
+impl std::fmt::Debug for PagingCursorCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The key is not printed on purpose.
+        f.debug_struct("PagingCursorCodec")
+            .field("signed", &self.signs())
+            .finish()
+    }
+}
+
+/// A paging state decoded from a cursor with [PagingCursorCodec::decode].
+///
+/// It can't be constructed otherwise, so a query resumed from a `PagingCursor`
+/// (e.g. with `Session::execute_iter_from`) uses only a paging state which passed
+/// the checks of the codec, including the verification of the signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PagingCursor {
+    paging_state: PagingState,
+}
+
+impl PagingCursor {
+    /// Returns the decoded paging state.
+    #[inline]
+    pub fn paging_state(&self) -> &PagingState {
+        &self.paging_state
+    }
+
+    /// Converts the cursor into the decoded paging state.
+    #[inline]
+    pub fn into_paging_state(self) -> PagingState {
+        self.paging_state
+    }
+}
+
+/// An error returned when a paging cursor can't be decoded.
+#[non_exhaustive]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PagingCursorError {
+    /// The cursor is not a valid URL-safe base64 string.
+    #[error("Paging cursor is not a valid URL-safe base64 string")]
+    InvalidEncoding,
+
+    /// The cursor was created with an unknown version of the format.
+    #[error("Unsupported paging cursor version: {0}")]
+    UnsupportedVersion(u8),
+
+    /// The contents of the cursor are malformed.
+    #[error("Malformed paging cursor")]
+    Malformed,
+
+    /// The cursor belongs to another statement.
+    #[error("Paging cursor belongs to another statement")]
+    StatementMismatch,
+
+    /// The cursor is not signed, but the codec requires signatures.
+    #[error("Paging cursor is not signed")]
+    MissingSignature,
+
+    /// The signature of the cursor does not match its contents.
+    #[error("Paging cursor has an invalid signature")]
+    InvalidSignature,
+
+    /// The cursor is signed, but the codec has no key to verify the signature.
+    #[error("Paging cursor is signed, but no key to verify it was provided")]
+    UnverifiableSignature,
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine as _;
+
+    use super::{PagingCursorCodec, PagingCursorError};
+    use crate::frame::request::query::PagingState;
+
+    #[test]
+    fn cursor_roundtrip() {
+        let codec = PagingCursorCodec::new();
+        let paging_state = PagingState::new_from_raw_bytes(&[1, 2, 3, 255][..]);
+
+        for paging_state in [PagingState::start(), paging_state] {
+            let cursor = codec.encode(&paging_state, None);
+            assert_eq!(
+                codec.decode(&cursor, None).unwrap().paging_state(),
+                &paging_state
+            );
+
+            let cursor = codec.encode(&paging_state, Some(b"id"));
+            assert!(!cursor.contains(['+', '/', '=']));
+            assert_eq!(
+                codec.decode(&cursor, Some(b"id")).unwrap().paging_state(),
+                &paging_state
+            );
+            assert_matches!(
+                codec.decode(&cursor, Some(b"other")),
+                Err(PagingCursorError::StatementMismatch)
+            );
+            assert_matches!(
+                codec.decode(&cursor, None),
+                Err(PagingCursorError::StatementMismatch)
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_cursors() {
+        let codec = PagingCursorCodec::new();
+        assert_matches!(
+            codec.decode("not base64!", None),
+            Err(PagingCursorError::InvalidEncoding)
+        );
+        assert_matches!(codec.decode("", None), Err(PagingCursorError::Malformed));
+        // Version 2.
+        assert_matches!(
+            codec.decode("AgA", None),
+            Err(PagingCursorError::UnsupportedVersion(2))
+        );
+        // Unknown flag.
+        assert_matches!(codec.decode("AUA", None), Err(PagingCursorError::Malformed));
+        // Truncated paging state.
+        let cursor = codec.encode(&PagingState::new_from_raw_bytes(&[1, 2, 3][..]), None);
+        let mut raw = URL_SAFE_NO_PAD.decode(cursor).unwrap();
+        raw.pop();
+        assert_matches!(
+            codec.decode(&URL_SAFE_NO_PAD.encode(raw), None),
+            Err(PagingCursorError::Malformed)
+        );
+    }
+
+    #[cfg(feature = "hmac-012")]
+    #[test]
+    fn signed_cursors() {
+        let codec = PagingCursorCodec::with_hmac_key("secret");
+        let paging_state = PagingState::new_from_raw_bytes(&[1, 2, 3][..]);
+        let cursor = codec.encode(&paging_state, Some(b"id"));
+        assert_eq!(
+            codec
+                .decode(&cursor, Some(b"id"))
+                .unwrap()
+                .into_paging_state(),
+            paging_state
+        );
+
+        let other_key = PagingCursorCodec::with_hmac_key("other");
+        assert_matches!(
+            other_key.decode(&cursor, Some(b"id")),
+            Err(PagingCursorError::InvalidSignature)
+        );
+        assert_matches!(
+            PagingCursorCodec::new().decode(&cursor, Some(b"id")),
+            Err(PagingCursorError::UnverifiableSignature)
+        );
+
+        let unsigned = PagingCursorCodec::new().encode(&paging_state, Some(b"id"));
+        assert_matches!(
+            codec.decode(&unsigned, Some(b"id")),
+            Err(PagingCursorError::MissingSignature)
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::frame::frame_errors::{CqlErrorParseError, LowLevelDeserializationError};
 use crate::frame::protocol_features::ProtocolFeatures;
-use crate::frame::types;
+use crate::frame::{types, ProtocolVersion};
 use crate::Consistency;
 use byteorder::ReadBytesExt;
 use bytes::Bytes;
@@ -24,6 +24,23 @@ fn make_error_field_err(
     }
 }
 
+// Since protocol v5, READ_FAILURE and WRITE_FAILURE carry a map from the replicas
+// that failed to their failure codes instead of just the number of failures.
+fn read_num_failures(
+    features: &ProtocolFeatures,
+    buf: &mut &[u8],
+) -> Result<i32, LowLevelDeserializationError> {
+    let num_failures = types::read_int(buf)?;
+    if features.protocol_version >= ProtocolVersion::V5 {
+        for _ in 0..num_failures {
+            let addr_len = buf.read_u8()?;
+            types::read_raw_bytes(addr_len as usize, buf)?;
+            types::read_short(buf)?;
+        }
+    }
+    Ok(num_failures)
+}
+
 impl Error {
     pub fn deserialize(
         features: &ProtocolFeatures,
@@ -81,7 +98,7 @@ impl Error {
                     .map_err(|err| make_error_field_err("READ_FAILURE", "RECEIVED", err))?,
                 required: types::read_int(buf)
                     .map_err(|err| make_error_field_err("READ_FAILURE", "REQUIRED", err))?,
-                numfailures: types::read_int(buf)
+                numfailures: read_num_failures(features, buf)
                     .map_err(|err| make_error_field_err("READ_FAILURE", "NUM_FAILURES", err))?,
                 data_present: buf
                     .read_u8()
@@ -105,7 +122,7 @@ impl Error {
                     .map_err(|err| make_error_field_err("WRITE_FAILURE", "RECEIVED", err))?,
                 required: types::read_int(buf)
                     .map_err(|err| make_error_field_err("WRITE_FAILURE", "REQUIRED", err))?,
-                numfailures: types::read_int(buf)
+                numfailures: read_num_failures(features, buf)
                     .map_err(|err| make_error_field_err("WRITE_FAILURE", "NUM_FAILURES", err))?,
                 write_type: WriteType::from(
                     types::read_string(buf)
@@ -514,6 +531,7 @@ impl WriteType {
 mod tests {
     use super::{DbError, Error, OperationType, WriteType};
     use crate::frame::protocol_features::ProtocolFeatures;
+    use crate::frame::ProtocolVersion;
     use crate::Consistency;
     use bytes::Bytes;
     use std::convert::TryInto;
@@ -658,6 +676,41 @@ mod tests {
         assert_eq!(error.reason, "message 2");
     }
 
+    #[test]
+    fn deserialize_read_failure_v5() {
+        let features = ProtocolFeatures {
+            protocol_version: ProtocolVersion::V5,
+            ..Default::default()
+        };
+
+        let mut bytes = make_error_request_bytes(0x1300, "message 2");
+        bytes.extend(0x0003_i16.to_be_bytes());
+        bytes.extend(4_i32.to_be_bytes());
+        bytes.extend(5_i32.to_be_bytes());
+        // Reason map with two entries.
+        bytes.extend(2_i32.to_be_bytes());
+        bytes.push(4);
+        bytes.extend([127, 0, 0, 1]);
+        bytes.extend(0x0001_i16.to_be_bytes());
+        bytes.push(16);
+        bytes.extend([0; 16]);
+        bytes.extend(0x0002_i16.to_be_bytes());
+        bytes.push(0);
+
+        let error: Error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+
+        assert_eq!(
+            error.error,
+            DbError::ReadFailure {
+                consistency: Consistency::Three,
+                received: 4,
+                required: 5,
+                numfailures: 2,
+                data_present: false,
+            }
+        );
+    }
+
     #[test]
     fn deserialize_function_failure() {
         let features = ProtocolFeatures::default();
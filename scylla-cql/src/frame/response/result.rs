@@ -11,7 +11,7 @@ use crate::frame::frame_errors::{
 };
 use crate::frame::request::query::PagingStateResponse;
 use crate::frame::response::event::SchemaChangeEvent;
-use crate::frame::types;
+use crate::frame::{types, ProtocolVersion};
 use bytes::{Buf, Bytes};
 use std::borrow::Cow;
 use std::fmt::Debug;
@@ -324,6 +324,7 @@ impl<'frame> ColumnSpec<'frame> {
 pub struct ResultMetadata<'a> {
     col_count: usize,
     col_specs: Vec<ColumnSpec<'a>>,
+    id: Option<Bytes>,
 }
 
 impl<'a> ResultMetadata<'a> {
@@ -337,6 +338,16 @@ impl<'a> ResultMetadata<'a> {
         &self.col_specs
     }
 
+    /// Returns the id of the metadata, which the server sends together with
+    /// the result metadata of a prepared statement since protocol v5.
+    ///
+    /// The id has to be sent back when executing the statement, so that
+    /// the server can tell whether the client's metadata is up to date.
+    #[inline]
+    pub fn id(&self) -> Option<&Bytes> {
+        self.id.as_ref()
+    }
+
     // Preferred to implementing Default, because users shouldn't be encouraged to create
     // empty ResultMetadata.
     #[inline]
@@ -344,6 +355,7 @@ impl<'a> ResultMetadata<'a> {
         Self {
             col_count: 0,
             col_specs: Vec::new(),
+            id: None,
         }
     }
 }
@@ -413,6 +425,10 @@ pub struct RawMetadataAndRawRows {
 
     /// Metadata cached in PreparedStatement, if present.
     cached_metadata: Option<Arc<ResultMetadata<'static>>>,
+
+    /// New id of the result metadata, sent in protocol v5 if the metadata
+    /// changed since the statement was prepared.
+    new_metadata_id: Option<Bytes>,
 }
 
 impl RawMetadataAndRawRows {
@@ -432,6 +448,7 @@ impl RawMetadataAndRawRows {
             no_metadata: false,
             raw_metadata_and_rows,
             cached_metadata: None,
+            new_metadata_id: None,
         }
     }
 
@@ -440,6 +457,41 @@ impl RawMetadataAndRawRows {
     pub fn metadata_and_rows_bytes_size(&self) -> usize {
         self.raw_metadata_and_rows.len()
     }
+
+    /// Returns the new id of the result metadata, if the server reported
+    /// that the metadata changed since the statement was prepared.
+    ///
+    /// Only sent in protocol v5 and later; the new metadata is then
+    /// contained in this response.
+    #[inline]
+    pub fn new_metadata_id(&self) -> Option<&Bytes> {
+        self.new_metadata_id.as_ref()
+    }
+
+    /// Deserializes the result metadata sent by the server into an owned form,
+    /// leaving `self` intact. Returns `None` if the server did not send metadata.
+    ///
+    /// Useful to update result metadata cached on the client side.
+    pub fn server_metadata_owned(
+        &self,
+    ) -> StdResult<Option<ResultMetadata<'static>>, ResultMetadataParseError> {
+        if self.no_metadata {
+            return Ok(None);
+        }
+
+        let buf = &mut &self.raw_metadata_and_rows[..];
+        let global_table_spec = self
+            .global_tables_spec
+            .then(|| deser_table_spec(buf))
+            .transpose()?;
+        let col_specs = deser_col_specs_owned(buf, global_table_spec, self.col_count)?;
+
+        Ok(Some(ResultMetadata {
+            col_count: self.col_count,
+            col_specs,
+            id: None,
+        }))
+    }
 }
 
 mod self_borrowed_metadata {
@@ -850,6 +902,7 @@ fn deser_result_metadata(
     let global_tables_spec = flags & 0x0001 != 0;
     let has_more_pages = flags & 0x0002 != 0;
     let no_metadata = flags & 0x0004 != 0;
+    let metadata_changed = flags & 0x0008 != 0;
 
     let col_count =
         types::read_int_length(buf).map_err(ResultMetadataParseError::ColumnCountParseError)?;
@@ -860,6 +913,10 @@ fn deser_result_metadata(
 
     let paging_state = PagingStateResponse::new_from_raw_bytes(raw_paging_state);
 
+    if metadata_changed {
+        types::read_short_bytes(buf).map_err(ResultMetadataParseError::NewMetadataIdParseError)?;
+    }
+
     let col_specs = if no_metadata {
         vec![]
     } else {
@@ -873,6 +930,7 @@ fn deser_result_metadata(
     let metadata = ResultMetadata {
         col_count,
         col_specs,
+        id: None,
     };
     Ok((metadata, paging_state))
 }
@@ -889,6 +947,7 @@ impl RawMetadataAndRawRows {
         let global_tables_spec = flags & 0x0001 != 0;
         let has_more_pages = flags & 0x0002 != 0;
         let no_metadata = flags & 0x0004 != 0;
+        let metadata_changed = flags & 0x0008 != 0;
 
         let col_count = types::read_int_length(frame.as_slice_mut())
             .map_err(RawRowsAndPagingStateResponseParseError::ColumnCountParseError)?;
@@ -902,12 +961,23 @@ impl RawMetadataAndRawRows {
 
         let paging_state = PagingStateResponse::new_from_raw_bytes(raw_paging_state);
 
+        // Sent in protocol v5 if the result metadata has changed since
+        // the statement was prepared. The new metadata follows.
+        let new_metadata_id = metadata_changed
+            .then(|| {
+                types::read_short_bytes(frame.as_slice_mut())
+                    .map(Bytes::copy_from_slice)
+                    .map_err(RawRowsAndPagingStateResponseParseError::NewMetadataIdParseError)
+            })
+            .transpose()?;
+
         let raw_rows = Self {
             col_count,
             global_tables_spec,
             no_metadata,
             raw_metadata_and_rows: frame.to_bytes(),
             cached_metadata,
+            new_metadata_id,
         };
 
         Ok((raw_rows, paging_state))
@@ -941,6 +1011,7 @@ impl RawMetadataAndRawRows {
                 ResultMetadata {
                     col_count,
                     col_specs,
+                    id: None,
                 }
             };
             Ok(server_metadata)
@@ -1061,16 +1132,27 @@ fn deser_set_keyspace(buf: &mut &[u8]) -> StdResult<SetKeyspace, SetKeyspacePars
     Ok(SetKeyspace { keyspace_name })
 }
 
-fn deser_prepared(buf: &mut &[u8]) -> StdResult<Prepared, PreparedParseError> {
+fn deser_prepared(
+    version: ProtocolVersion,
+    buf: &mut &[u8],
+) -> StdResult<Prepared, PreparedParseError> {
     let id_len = types::read_short(buf)
         .map_err(|err| PreparedParseError::IdLengthParseError(err.into()))?
         as usize;
     let id: Bytes = buf[0..id_len].to_owned().into();
     buf.advance(id_len);
+    let result_metadata_id = (version >= ProtocolVersion::V5)
+        .then(|| {
+            types::read_short_bytes(buf)
+                .map(Bytes::copy_from_slice)
+                .map_err(PreparedParseError::ResultMetadataIdParseError)
+        })
+        .transpose()?;
     let prepared_metadata =
         deser_prepared_metadata(buf).map_err(PreparedParseError::PreparedMetadataParseError)?;
-    let (result_metadata, paging_state_response) =
+    let (mut result_metadata, paging_state_response) =
         deser_result_metadata(buf).map_err(PreparedParseError::ResultMetadataParseError)?;
+    result_metadata.id = result_metadata_id;
     if let PagingStateResponse::HasMorePages { state } = paging_state_response {
         return Err(PreparedParseError::NonZeroPagingState(
             state
@@ -1096,6 +1178,15 @@ fn deser_schema_change(buf: &mut &[u8]) -> StdResult<SchemaChange, SchemaChangeE
 pub fn deserialize(
     buf_bytes: Bytes,
     cached_metadata: Option<&Arc<ResultMetadata<'static>>>,
+) -> StdResult<Result, CqlResultParseError> {
+    deserialize_for_version(ProtocolVersion::V4, buf_bytes, cached_metadata)
+}
+
+/// Deserializes a RESULT response in the form defined by the given protocol version.
+pub fn deserialize_for_version(
+    version: ProtocolVersion,
+    buf_bytes: Bytes,
+    cached_metadata: Option<&Arc<ResultMetadata<'static>>>,
 ) -> StdResult<Result, CqlResultParseError> {
     let buf = &mut &*buf_bytes;
     use self::Result::*;
@@ -1106,7 +1197,7 @@ pub fn deserialize(
             0x0001 => Void,
             0x0002 => Rows(deser_rows(buf_bytes.slice_ref(buf), cached_metadata)?),
             0x0003 => SetKeyspace(deser_set_keyspace(buf)?),
-            0x0004 => Prepared(deser_prepared(buf)?),
+            0x0004 => Prepared(deser_prepared(version, buf)?),
             0x0005 => SchemaChange(deser_schema_change(buf)?),
             id => return Err(CqlResultParseError::UnknownResultId(id)),
         },
@@ -1250,6 +1341,7 @@ mod test_utils {
             Self {
                 col_count,
                 col_specs,
+                id: None,
             }
         }
 
@@ -0,0 +1,397 @@
+//! Segment framing, introduced in protocol v5.
+//!
+//! After STARTUP, frames (called envelopes by the v5 specification) are no longer
+//! written directly to the socket. Instead, they are packed into segments, each
+//! protected by a CRC24 of its header and a CRC32 of its payload.
+//! A segment is either self-contained, in which case it holds one or more whole
+//! frames, or it holds a part of a single frame that is too large to fit
+//! into one segment.
+//!
+//! If compression is negotiated, segment payloads are compressed with LZ4;
+//! frames themselves are never compressed in protocol v5.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use bytes::{Buf, BufMut, Bytes};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::frame_errors::{FrameHeaderParseError, SegmentParseError};
+use super::response::ResponseOpcode;
+use super::{FrameParams, HEADER_SIZE};
+
+/// The maximum length of a segment payload, compressed or not.
+pub const MAX_SEGMENT_PAYLOAD_LEN: usize = (1 << 17) - 1;
+
+const UNCOMPRESSED_HEADER_LEN: usize = 3;
+const COMPRESSED_HEADER_LEN: usize = 5;
+const HEADER_CRC_LEN: usize = 3;
+const PAYLOAD_CRC_LEN: usize = 4;
+
+const CRC24_INIT: u32 = 0x875060;
+const CRC24_POLY: u32 = 0x1974F0B;
+
+// The payload CRC32 is seeded with these bytes, so that a payload of zeros
+// does not have a zero checksum.
+const CRC32_INITIAL_BYTES: [u8; 4] = [0xFA, 0x2D, 0x55, 0xCA];
+
+/// Computes CRC24 of the `len` least significant bytes of `bytes`,
+/// starting from the least significant one.
+fn crc24(mut bytes: u64, len: usize) -> u32 {
+    let mut crc = CRC24_INIT;
+    for _ in 0..len {
+        crc ^= ((bytes & 0xff) as u32) << 16;
+        bytes >>= 8;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc
+}
+
+fn crc32(payload: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&CRC32_INITIAL_BYTES);
+    hasher.update(payload);
+    hasher.finalize()
+}
+
+fn read_le(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |acc, byte| (acc << 8) | *byte as u64)
+}
+
+fn write_le(mut value: u64, len: usize, out: &mut Vec<u8>) {
+    for _ in 0..len {
+        out.put_u8(value as u8);
+        value >>= 8;
+    }
+}
+
+/// A single segment read from the socket, after verification and decompression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub payload: Bytes,
+    pub self_contained: bool,
+}
+
+/// Appends a segment with the given payload to `out`.
+///
+/// The payload must not be longer than [MAX_SEGMENT_PAYLOAD_LEN].
+/// If `compressed` is set, the payload is compressed with LZ4, unless that
+/// would not make it any smaller.
+pub fn write_segment(payload: &[u8], self_contained: bool, compressed: bool, out: &mut Vec<u8>) {
+    assert!(
+        payload.len() <= MAX_SEGMENT_PAYLOAD_LEN,
+        "segment payload of {} bytes exceeds the limit of {} bytes",
+        payload.len(),
+        MAX_SEGMENT_PAYLOAD_LEN
+    );
+    let self_contained = self_contained as u64;
+
+    if compressed {
+        let compressed_payload = lz4_flex::block::compress(payload);
+        // If compression does not help, the payload is sent as is,
+        // which is signalled by an uncompressed length of 0.
+        let (payload, uncompressed_len) = if compressed_payload.len() < payload.len() {
+            (&compressed_payload[..], payload.len())
+        } else {
+            (payload, 0)
+        };
+        let header = payload.len() as u64 | (uncompressed_len as u64) << 17 | self_contained << 34;
+        write_le(header, COMPRESSED_HEADER_LEN, out);
+        write_le(
+            crc24(header, COMPRESSED_HEADER_LEN) as u64,
+            HEADER_CRC_LEN,
+            out,
+        );
+        out.extend_from_slice(payload);
+        out.put_u32_le(crc32(payload));
+    } else {
+        let header = payload.len() as u64 | self_contained << 17;
+        write_le(header, UNCOMPRESSED_HEADER_LEN, out);
+        write_le(
+            crc24(header, UNCOMPRESSED_HEADER_LEN) as u64,
+            HEADER_CRC_LEN,
+            out,
+        );
+        out.extend_from_slice(payload);
+        out.put_u32_le(crc32(payload));
+    }
+}
+
+/// Reads a single segment and verifies its checksums.
+///
+/// `compressed` must match the compression negotiated for the connection.
+pub async fn read_segment(
+    reader: &mut (impl AsyncRead + Unpin),
+    compressed: bool,
+) -> Result<Segment, SegmentParseError> {
+    let header_len = if compressed {
+        COMPRESSED_HEADER_LEN
+    } else {
+        UNCOMPRESSED_HEADER_LEN
+    };
+    let mut raw_header = [0u8; COMPRESSED_HEADER_LEN + HEADER_CRC_LEN];
+    let raw_header = &mut raw_header[..header_len + HEADER_CRC_LEN];
+    reader
+        .read_exact(raw_header)
+        .await
+        .map_err(SegmentParseError::IoError)?;
+
+    let header = read_le(&raw_header[..header_len]);
+    let received_crc = read_le(&raw_header[header_len..]) as u32;
+    let computed_crc = crc24(header, header_len);
+    if received_crc != computed_crc {
+        return Err(SegmentParseError::HeaderChecksumMismatch {
+            received: received_crc,
+            computed: computed_crc,
+        });
+    }
+
+    let payload_len = (header & MAX_SEGMENT_PAYLOAD_LEN as u64) as usize;
+    let (uncompressed_len, self_contained) = if compressed {
+        (
+            ((header >> 17) & MAX_SEGMENT_PAYLOAD_LEN as u64) as usize,
+            header & (1 << 34) != 0,
+        )
+    } else {
+        (0, header & (1 << 17) != 0)
+    };
+
+    let mut payload = vec![0u8; payload_len + PAYLOAD_CRC_LEN];
+    reader
+        .read_exact(&mut payload)
+        .await
+        .map_err(SegmentParseError::IoError)?;
+    let received_crc = (&payload[payload_len..]).get_u32_le();
+    payload.truncate(payload_len);
+    let computed_crc = crc32(&payload);
+    if received_crc != computed_crc {
+        return Err(SegmentParseError::PayloadChecksumMismatch {
+            received: received_crc,
+            computed: computed_crc,
+        });
+    }
+
+    let payload = if uncompressed_len > 0 {
+        lz4_flex::block::decompress(&payload, uncompressed_len)
+            .map_err(|err| SegmentParseError::Lz4DecompressError(Arc::new(err)))?
+    } else {
+        payload
+    };
+
+    Ok(Segment {
+        payload: payload.into(),
+        self_contained,
+    })
+}
+
+/// Packs serialized frames into segments.
+///
+/// Consecutive frames are coalesced into self-contained segments as long as
+/// they fit; frames larger than [MAX_SEGMENT_PAYLOAD_LEN] are split
+/// across multiple segments.
+#[derive(Debug)]
+pub struct SegmentEncoder {
+    compressed: bool,
+    pending: Vec<u8>,
+}
+
+impl SegmentEncoder {
+    pub fn new(compressed: bool) -> Self {
+        Self {
+            compressed,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Adds a frame to be sent, appending any segments that are complete to `out`.
+    pub fn push_frame(&mut self, frame: &[u8], out: &mut Vec<u8>) {
+        if self.pending.len() + frame.len() > MAX_SEGMENT_PAYLOAD_LEN {
+            self.flush(out);
+        }
+
+        if frame.len() > MAX_SEGMENT_PAYLOAD_LEN {
+            for part in frame.chunks(MAX_SEGMENT_PAYLOAD_LEN) {
+                write_segment(part, false, self.compressed, out);
+            }
+        } else {
+            self.pending.extend_from_slice(frame);
+        }
+    }
+
+    /// Appends a self-contained segment with all pending frames to `out`.
+    pub fn flush(&mut self, out: &mut Vec<u8>) {
+        if !self.pending.is_empty() {
+            write_segment(&self.pending, true, self.compressed, out);
+            self.pending.clear();
+        }
+    }
+}
+
+/// Reassembles response frames from consecutive segments.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    partial: Vec<u8>,
+    frames: VecDeque<Bytes>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consumes the payload of a segment read from the socket.
+    pub fn push_segment(&mut self, segment: Segment) -> Result<(), SegmentParseError> {
+        if segment.self_contained {
+            if !self.partial.is_empty() {
+                return Err(SegmentParseError::UnexpectedSelfContainedSegment);
+            }
+            let mut payload = segment.payload;
+            while !payload.is_empty() {
+                let frame_len =
+                    Self::frame_len(&payload).ok_or(SegmentParseError::IncompleteFrame)?;
+                if frame_len > payload.len() {
+                    return Err(SegmentParseError::IncompleteFrame);
+                }
+                self.frames.push_back(payload.split_to(frame_len));
+            }
+        } else {
+            self.partial.extend_from_slice(&segment.payload);
+            if let Some(frame_len) = Self::frame_len(&self.partial) {
+                match self.partial.len().cmp(&frame_len) {
+                    std::cmp::Ordering::Less => {}
+                    std::cmp::Ordering::Equal => {
+                        let frame = std::mem::take(&mut self.partial);
+                        self.frames.push_back(frame.into());
+                    }
+                    std::cmp::Ordering::Greater => return Err(SegmentParseError::FrameOverflow),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the next complete frame, if any.
+    pub fn next_frame(
+        &mut self,
+    ) -> Option<Result<(FrameParams, ResponseOpcode, Bytes), FrameHeaderParseError>> {
+        self.frames.pop_front().map(super::parse_response_frame)
+    }
+
+    // Length of the whole frame, including the header, if the header is complete.
+    fn frame_len(buf: &[u8]) -> Option<usize> {
+        let body_len = buf.get(5..HEADER_SIZE)?;
+        Some(HEADER_SIZE + (&body_len[..]).get_u32() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response_frame(stream: i16, body: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x85, 0x00];
+        frame.put_i16(stream);
+        frame.put_u8(ResponseOpcode::Result as u8);
+        frame.put_u32(body.len() as u32);
+        frame.extend_from_slice(body);
+        frame
+    }
+
+    async fn decode_all(mut buf: &[u8], compressed: bool) -> Vec<(i16, Bytes)> {
+        let mut decoder = FrameDecoder::new();
+        let mut frames = Vec::new();
+        while !buf.is_empty() {
+            let segment = read_segment(&mut buf, compressed).await.unwrap();
+            decoder.push_segment(segment).unwrap();
+            while let Some(frame) = decoder.next_frame() {
+                let (params, _, body) = frame.unwrap();
+                frames.push((params.stream, body));
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn test_segment_header_layout() {
+        let mut out = Vec::new();
+        write_segment(b"abc", true, false, &mut out);
+        assert_eq!(&out[..3], &[3, 0, 2]);
+        assert_eq!(read_le(&out[3..6]) as u32, crc24(0x20003, 3));
+        assert_eq!(&out[6..9], b"abc");
+        assert_eq!((&out[9..]).get_u32_le(), crc32(b"abc"));
+    }
+
+    #[tokio::test]
+    async fn test_segments_roundtrip() {
+        for compressed in [false, true] {
+            let small_body = b"Hello, World!".repeat(10);
+            let large_body = b"Hello, World!".repeat(20000);
+            let frames = [
+                response_frame(1, &small_body),
+                response_frame(2, &[]),
+                response_frame(3, &large_body),
+                response_frame(4, &small_body),
+            ];
+
+            let mut encoder = SegmentEncoder::new(compressed);
+            let mut out = Vec::new();
+            for frame in &frames {
+                encoder.push_frame(frame, &mut out);
+            }
+            encoder.flush(&mut out);
+
+            let decoded = decode_all(&out, compressed).await;
+            assert_eq!(
+                decoded,
+                vec![
+                    (1, Bytes::from(small_body.clone())),
+                    (2, Bytes::new()),
+                    (3, Bytes::from(large_body)),
+                    (4, Bytes::from(small_body)),
+                ]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_segment_is_rejected() {
+        let mut out = Vec::new();
+        write_segment(&response_frame(1, b"body"), true, false, &mut out);
+
+        let mut corrupted_header = out.clone();
+        corrupted_header[0] ^= 0x01;
+        assert!(matches!(
+            read_segment(&mut &corrupted_header[..], false).await,
+            Err(SegmentParseError::HeaderChecksumMismatch { .. })
+        ));
+
+        let mut corrupted_payload = out.clone();
+        corrupted_payload[8] ^= 0x01;
+        assert!(matches!(
+            read_segment(&mut &corrupted_payload[..], false).await,
+            Err(SegmentParseError::PayloadChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_incomplete_self_contained_frame_is_rejected() {
+        let frame = response_frame(1, b"body");
+        let mut decoder = FrameDecoder::new();
+        assert!(matches!(
+            decoder.push_segment(Segment {
+                payload: Bytes::copy_from_slice(&frame[..frame.len() - 1]),
+                self_contained: true,
+            }),
+            Err(SegmentParseError::IncompleteFrame)
+        ));
+    }
+}
@@ -14,6 +14,9 @@ pub mod value;
 
 pub mod utils;
 
+#[cfg(feature = "serde")]
+pub mod serde_bridge;
+
 pub use crate::frame::types::Consistency;
 
 #[doc(hidden)]
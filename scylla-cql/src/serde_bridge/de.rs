@@ -0,0 +1,397 @@
+//! Deserialization of CQL rows and values into types implementing [serde::Deserialize].
+
+use chrono_04::{DateTime, NaiveDate, NaiveTime};
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeOwned, Error as _, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+use super::{SerdeBridgeError, SerdeRow, SerdeValue};
+use crate::deserialize::row::{self, ColumnIterator, DeserializeRow};
+use crate::deserialize::value::DeserializeValue;
+use crate::deserialize::{DeserializationError, FrameSlice, TypeCheckError};
+use crate::frame::response::result::{CollectionType, ColumnSpec, ColumnType, NativeType};
+use crate::value::{CqlDate, CqlTime, CqlTimestamp, CqlValue};
+
+impl<'frame, 'metadata, T> DeserializeValue<'frame, 'metadata> for SerdeValue<T>
+where
+    T: DeserializeOwned,
+{
+    fn type_check(typ: &ColumnType) -> Result<(), TypeCheckError> {
+        // The shape of `T` is known only when it is deserialized, so only
+        // the CQL types which can't be represented in serde are rejected here.
+        check_supported(typ)
+    }
+
+    fn deserialize(
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        let value = Option::<CqlValue>::deserialize(typ, v)?;
+        T::deserialize(ValueDeserializer(value.as_ref()))
+            .map(SerdeValue)
+            .map_err(DeserializationError::new)
+    }
+}
+
+impl<'frame, 'metadata, T> DeserializeRow<'frame, 'metadata> for SerdeRow<T>
+where
+    T: DeserializeOwned,
+{
+    fn type_check(specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        // See the comment in `SerdeValue::type_check`.
+        for (column_index, spec) in specs.iter().enumerate() {
+            check_supported(spec.typ()).map_err(|err| {
+                row::mk_typck_err::<Self>(
+                    specs.iter().map(|spec| spec.typ().clone().into_owned()),
+                    row::BuiltinTypeCheckErrorKind::ColumnTypeCheckFailed {
+                        column_index,
+                        column_name: spec.name().to_owned(),
+                        err,
+                    },
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    fn deserialize(row: ColumnIterator<'frame, 'metadata>) -> Result<Self, DeserializationError> {
+        let columns = row
+            .map(|column| {
+                let column = column?;
+                let value = Option::<CqlValue>::deserialize(column.spec.typ(), column.slice)?;
+                Ok((column.spec.name(), value))
+            })
+            .collect::<Result<Vec<_>, DeserializationError>>()?;
+        T::deserialize(RowDeserializer(&columns))
+            .map(SerdeRow)
+            .map_err(DeserializationError::new)
+    }
+}
+
+/// Checks that the CQL type, including the types nested in it, can be deserialized
+/// with serde. See the [module-level docs](super) for the supported types.
+fn check_supported(typ: &ColumnType) -> Result<(), TypeCheckError> {
+    match typ {
+        ColumnType::Native(NativeType::Decimal | NativeType::Duration) => {
+            Err(TypeCheckError::new(SerdeBridgeError::custom(format_args!(
+                "CQL type {typ:?} is not supported by the serde bridge"
+            ))))
+        }
+        ColumnType::Native(_) => Ok(()),
+        ColumnType::Collection { typ, .. } => match typ {
+            CollectionType::List(elem) | CollectionType::Set(elem) => check_supported(elem),
+            CollectionType::Map(key, value) => {
+                check_supported(key)?;
+                check_supported(value)
+            }
+        },
+        ColumnType::Vector { typ, .. } => check_supported(typ),
+        ColumnType::UserDefinedType { definition, .. } => definition
+            .field_types
+            .iter()
+            .try_for_each(|(_, typ)| check_supported(typ)),
+        ColumnType::Tuple(types) => types.iter().try_for_each(check_supported),
+    }
+}
+
+/// Deserializes a row by column names (into a struct or a map),
+/// or by column positions (into a tuple or a sequence).
+struct RowDeserializer<'a>(&'a [(&'a str, Option<CqlValue>)]);
+
+impl<'de> serde::Deserializer<'de> for RowDeserializer<'_> {
+    type Error = SerdeBridgeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let mut map = MapDeserializer::new(
+            self.0
+                .iter()
+                .map(|(name, value)| (*name, ValueDeserializer(value.as_ref()))),
+        );
+        let result = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(result)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let mut seq = SeqDeserializer::new(
+            self.0
+                .iter()
+                .map(|(_, value)| ValueDeserializer(value.as_ref())),
+        );
+        let result = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(result)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct enum identifier ignored_any
+    }
+}
+
+/// Deserializes a single CQL value, `None` meaning null.
+#[derive(Clone, Copy)]
+struct ValueDeserializer<'a>(Option<&'a CqlValue>);
+
+impl<'a> ValueDeserializer<'a> {
+    fn unsupported(value: &CqlValue) -> SerdeBridgeError {
+        SerdeBridgeError::custom(format_args!(
+            "deserializing {value:?} with serde is not supported"
+        ))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, SerdeBridgeError> for ValueDeserializer<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> serde::Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = SerdeBridgeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let Some(value) = self.0 else {
+            return visitor.visit_none();
+        };
+        match value {
+            CqlValue::Ascii(s) | CqlValue::Text(s) => visitor.visit_str(s),
+            CqlValue::Boolean(b) => visitor.visit_bool(*b),
+            CqlValue::Blob(b) => visitor.visit_bytes(b),
+            CqlValue::TinyInt(i) => visitor.visit_i8(*i),
+            CqlValue::SmallInt(i) => visitor.visit_i16(*i),
+            CqlValue::Int(i) => visitor.visit_i32(*i),
+            CqlValue::BigInt(i) => visitor.visit_i64(*i),
+            CqlValue::Counter(c) => visitor.visit_i64(c.0),
+            CqlValue::Varint(v) => {
+                let bytes = v.as_signed_bytes_be_slice();
+                if bytes.len() > 16 {
+                    return Err(SerdeBridgeError::custom("varint out of range of i128"));
+                }
+                // Sign-extend to 16 bytes.
+                let fill = if bytes.first().is_some_and(|b| b & 0x80 != 0) {
+                    0xff
+                } else {
+                    0
+                };
+                let mut buf = [fill; 16];
+                buf[16 - bytes.len()..].copy_from_slice(bytes);
+                visitor.visit_i128(i128::from_be_bytes(buf))
+            }
+            CqlValue::Float(f) => visitor.visit_f32(*f),
+            CqlValue::Double(f) => visitor.visit_f64(*f),
+            CqlValue::Uuid(u) => visitor.visit_str(u.hyphenated().encode_lower(&mut [0; 36])),
+            CqlValue::Timeuuid(u) => {
+                visitor.visit_str(u.as_ref().hyphenated().encode_lower(&mut [0; 36]))
+            }
+            CqlValue::Inet(addr) => visitor.visit_string(addr.to_string()),
+            CqlValue::Timestamp(CqlTimestamp(millis)) => visitor.visit_i64(*millis),
+            CqlValue::Time(CqlTime(nanos)) => visitor.visit_i64(*nanos),
+            CqlValue::Date(date) => visitor.visit_string(date_to_naive(*date)?.to_string()),
+            CqlValue::List(elems) | CqlValue::Set(elems) | CqlValue::Vector(elems) => {
+                visit_seq(elems.iter().map(Some), visitor)
+            }
+            CqlValue::Tuple(elems) => visit_seq(elems.iter().map(Option::as_ref), visitor),
+            CqlValue::Map(entries) => {
+                let mut map = MapDeserializer::new(entries.iter().map(|(key, value)| {
+                    (ValueDeserializer(Some(key)), ValueDeserializer(Some(value)))
+                }));
+                let result = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(result)
+            }
+            CqlValue::UserDefinedType { fields, .. } => {
+                let mut map = MapDeserializer::new(
+                    fields
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), ValueDeserializer(value.as_ref()))),
+                );
+                let result = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(result)
+            }
+            CqlValue::Empty => visitor.visit_none(),
+            CqlValue::Decimal(_) | CqlValue::Duration(_) => Err(Self::unsupported(value)),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Some(CqlValue::Timestamp(timestamp)) => {
+                visitor.visit_string(timestamp_to_datetime(*timestamp)?.to_rfc3339())
+            }
+            Some(CqlValue::Time(time)) => visitor.visit_string(time_to_naive(*time)?.to_string()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Some(CqlValue::Uuid(u)) => visitor.visit_bytes(u.as_bytes()),
+            Some(CqlValue::Timeuuid(u)) => visitor.visit_bytes(u.as_bytes()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            None | Some(CqlValue::Empty) => visitor.visit_none(),
+            Some(_) => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            None => visitor.visit_unit(),
+            Some(_) => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            // `Vec<u8>` is deserialized as a sequence.
+            Some(CqlValue::Blob(bytes)) => {
+                let mut seq = SeqDeserializer::new(bytes.iter().copied());
+                let result = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(result)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Some(CqlValue::Ascii(s) | CqlValue::Text(s)) => {
+                visitor.visit_enum(s.as_str().into_deserializer())
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
+        map struct identifier
+    }
+}
+
+fn visit_seq<'a, 'de, V: Visitor<'de>>(
+    elems: impl Iterator<Item = Option<&'a CqlValue>>,
+    visitor: V,
+) -> Result<V::Value, SerdeBridgeError> {
+    let mut seq = SeqDeserializer::new(elems.map(ValueDeserializer));
+    let result = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(result)
+}
+
+fn date_to_naive(date: CqlDate) -> Result<NaiveDate, SerdeBridgeError> {
+    date.try_to_chrono_04_naive_date()
+        .map_err(|_| SerdeBridgeError::custom("date out of range"))
+}
+
+fn timestamp_to_datetime(
+    timestamp: CqlTimestamp,
+) -> Result<DateTime<chrono_04::Utc>, SerdeBridgeError> {
+    DateTime::from_timestamp_millis(timestamp.0)
+        .ok_or_else(|| SerdeBridgeError::custom("timestamp out of range"))
+}
+
+fn time_to_naive(time: CqlTime) -> Result<NaiveTime, SerdeBridgeError> {
+    let secs = time.0.div_euclid(1_000_000_000);
+    let nanos = time.0.rem_euclid(1_000_000_000);
+    u32::try_from(secs)
+        .ok()
+        .and_then(|secs| NaiveTime::from_num_seconds_from_midnight_opt(secs, nanos as u32))
+        .ok_or_else(|| SerdeBridgeError::custom("time out of range"))
+}
@@ -0,0 +1,119 @@
+//! Bridge between [serde] and the CQL (de)serialization traits.
+//!
+//! Types which implement [serde::Serialize] and/or [serde::Deserialize] can be
+//! used as rows or values by wrapping them in [SerdeRow] or [SerdeValue],
+//! without deriving [SerializeRow](crate::SerializeRow), [DeserializeRow](crate::DeserializeRow),
+//! [SerializeValue](crate::SerializeValue) or [DeserializeValue](crate::DeserializeValue).
+//!
+//! ```rust
+//! # use scylla_cql::serde_bridge::{SerdeRow, SerdeValue};
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Address {
+//!     street: String,
+//!     number: i32,
+//! }
+//!
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct User {
+//!     id: i64,
+//!     name: String,
+//!     emails: Vec<String>,
+//!     address: Option<Address>,
+//! }
+//!
+//! // Binds `id`, `name`, `emails` and `address` markers, or the columns of the same names
+//! // when used as the result of a query. `address` is a UDT.
+//! # fn check(user: User) {
+//! let values = SerdeRow(user);
+//! # }
+//! ```
+//!
+//! The values are converted through [CqlValue](crate::value::CqlValue), so the bridge
+//! is slower than the derived implementations, and it can't borrow from the response frame.
+//!
+//! # Type mapping
+//!
+//! Values are type checked against the CQL types of the columns (or bind markers)
+//! when they are (de)serialized. The CQL types are mapped to the serde data model as follows:
+//!
+//! | CQL type                               | serde type                                                  |
+//! |----------------------------------------|-------------------------------------------------------------|
+//! | `ascii`, `text`                        | string (or unit enum variant)                               |
+//! | `boolean`                              | bool                                                        |
+//! | `tinyint`, `smallint`, `int`, `bigint`, `counter`, `varint` | integer (any integer type the value fits in) |
+//! | `float`, `double`                      | f32, f64                                                    |
+//! | `blob`                                 | bytes or sequence of u8                                     |
+//! | `uuid`, `timeuuid`                     | string (hyphenated) or 16 bytes                             |
+//! | `inet`                                 | string                                                      |
+//! | `timestamp`                            | i64 (milliseconds since unix epoch) or RFC 3339 string      |
+//! | `date`                                 | string (`YYYY-MM-DD`)                                       |
+//! | `time`                                 | i64 (nanoseconds since midnight) or string (`HH:MM:SS.fff`) |
+//! | `list`, `set`, `vector`                | sequence                                                    |
+//! | `map`                                  | map                                                         |
+//! | `tuple`                                | tuple or sequence                                           |
+//! | user defined type                      | struct or map                                               |
+//! | null                                   | none (or unit)                                              |
+//!
+//! `decimal` and `duration` are not supported: columns of these types (also nested in
+//! collections, tuples and UDTs) fail the type check when deserialized.
+//!
+//! A row is mapped to a struct or a map (by column names), or to a tuple or a sequence
+//! (by column positions).
+//!
+//! This module is available only under the crate feature `serde`.
+
+use thiserror::Error;
+
+mod de;
+mod ser;
+
+/// Wrapper which (de)serializes a row using the [serde] implementation of `T`.
+///
+/// Implements [DeserializeRow](crate::deserialize::row::DeserializeRow) for `T: serde::de::DeserializeOwned`
+/// and [SerializeRow](crate::serialize::row::SerializeRow) for `T: serde::Serialize`.
+/// See the [module-level docs](self) for the details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SerdeRow<T>(pub T);
+
+impl<T> SerdeRow<T> {
+    /// Unwraps the inner value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// Wrapper which (de)serializes a CQL value using the [serde] implementation of `T`.
+///
+/// Implements [DeserializeValue](crate::deserialize::value::DeserializeValue) for `T: serde::de::DeserializeOwned`
+/// and [SerializeValue](crate::serialize::value::SerializeValue) for `T: serde::Serialize`.
+/// See the [module-level docs](self) for the details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SerdeValue<T>(pub T);
+
+impl<T> SerdeValue<T> {
+    /// Unwraps the inner value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// An error returned when a value can't be converted between
+/// the serde data model and the CQL type.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("{0}")]
+pub struct SerdeBridgeError(String);
+
+impl serde::de::Error for SerdeBridgeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        SerdeBridgeError(msg.to_string())
+    }
+}
+
+impl serde::ser::Error for SerdeBridgeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        SerdeBridgeError(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests;
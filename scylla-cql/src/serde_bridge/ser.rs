@@ -0,0 +1,980 @@
+//! Serialization of types implementing [serde::Serialize] into CQL rows and values.
+
+use std::net::IpAddr;
+
+use chrono_04::{DateTime, NaiveDate, NaiveTime, Timelike};
+use serde::ser::{
+    Error as _, Impossible, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple,
+    SerializeTupleStruct,
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::{SerdeBridgeError, SerdeRow, SerdeValue};
+use crate::frame::response::result::{CollectionType, ColumnSpec, ColumnType, NativeType};
+use crate::serialize::row::{
+    mk_ser_err, mk_typck_err, BuiltinSerializationErrorKind, BuiltinTypeCheckErrorKind,
+    RowSerializationContext, SerializeRow,
+};
+use crate::serialize::value::SerializeValue;
+use crate::serialize::writers::WrittenCellProof;
+use crate::serialize::{CellWriter, RowWriter, SerializationError};
+use crate::value::{Counter, CqlDate, CqlTime, CqlTimestamp, CqlTimeuuid, CqlValue, CqlVarint};
+
+impl<T: Serialize> SerializeValue for SerdeValue<T> {
+    fn serialize<'b>(
+        &self,
+        typ: &ColumnType,
+        writer: CellWriter<'b>,
+    ) -> Result<WrittenCellProof<'b>, SerializationError> {
+        let value = self
+            .0
+            .serialize(ValueSerializer { typ })
+            .map_err(SerializationError::new)?;
+        SerializeValue::serialize(&value, typ, writer)
+    }
+}
+
+impl<T: Serialize> SerializeRow for SerdeRow<T> {
+    fn serialize(
+        &self,
+        ctx: &RowSerializationContext<'_>,
+        writer: &mut RowWriter,
+    ) -> Result<(), SerializationError> {
+        let columns = ctx.columns();
+        let values = self
+            .0
+            .serialize(RowSerializer { columns })
+            .map_err(|err| match err {
+                RowSerializerError::TypeCheck(kind) => mk_typck_err::<Self>(kind),
+                RowSerializerError::Column { name, err } => {
+                    mk_ser_err::<Self>(BuiltinSerializationErrorKind::ColumnSerializationFailed {
+                        name,
+                        err: SerializationError::new(err),
+                    })
+                }
+                RowSerializerError::Other(err) => SerializationError::new(err),
+            })?;
+
+        for (value, spec) in values.iter().zip(columns) {
+            crate::_macro_internal::ser::row::serialize_column::<Self>(value, spec, writer)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        // A row is empty iff it can be serialized as a row with no columns.
+        // No values are serialized then: the first field or element fails the check.
+        self.0.serialize(RowSerializer { columns: &[] }).is_ok()
+    }
+}
+
+/// An error of serializing a row, converted to a [SerializationError]
+/// once the name of the Rust type is known.
+#[derive(Debug, Error)]
+enum RowSerializerError {
+    #[error("{0}")]
+    TypeCheck(BuiltinTypeCheckErrorKind),
+    #[error("failed to serialize column {name}: {err}")]
+    Column { name: String, err: SerdeBridgeError },
+    #[error(transparent)]
+    Other(#[from] SerdeBridgeError),
+}
+
+impl serde::ser::Error for RowSerializerError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        RowSerializerError::Other(SerdeBridgeError::custom(msg))
+    }
+}
+
+/// Serializes a row, producing the values of the columns in the order of the columns.
+struct RowSerializer<'a> {
+    columns: &'a [ColumnSpec<'a>],
+}
+
+impl<'a> RowSerializer<'a> {
+    fn unsupported(what: &str) -> RowSerializerError {
+        RowSerializerError::Other(SerdeBridgeError::custom(format_args!(
+            "cannot serialize {what} as a row"
+        )))
+    }
+
+    fn named(self) -> NamedRowSerializer<'a> {
+        NamedRowSerializer {
+            columns: self.columns,
+            values: vec![None; self.columns.len()],
+            pending_key: None,
+        }
+    }
+
+    fn positional(self) -> PositionalRowSerializer<'a> {
+        PositionalRowSerializer {
+            columns: self.columns,
+            values: Vec::with_capacity(self.columns.len()),
+            count: 0,
+        }
+    }
+
+    fn empty(self) -> Result<Vec<Option<CqlValue>>, RowSerializerError> {
+        if !self.columns.is_empty() {
+            return Err(RowSerializerError::TypeCheck(
+                BuiltinTypeCheckErrorKind::WrongColumnCount {
+                    rust_cols: 0,
+                    cql_cols: self.columns.len(),
+                },
+            ));
+        }
+        Ok(Vec::new())
+    }
+}
+
+macro_rules! unsupported_in_row {
+    ($($method:ident($($arg:ty),*) => $what:literal;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<Self::Ok, Self::Error> {
+                Err(Self::unsupported($what))
+            }
+        )*
+    };
+}
+
+impl<'a> serde::Serializer for RowSerializer<'a> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowSerializerError;
+
+    type SerializeSeq = PositionalRowSerializer<'a>;
+    type SerializeTuple = PositionalRowSerializer<'a>;
+    type SerializeTupleStruct = PositionalRowSerializer<'a>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = NamedRowSerializer<'a>;
+    type SerializeStruct = NamedRowSerializer<'a>;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    unsupported_in_row! {
+        serialize_bool(bool) => "bool";
+        serialize_i8(i8) => "integer";
+        serialize_i16(i16) => "integer";
+        serialize_i32(i32) => "integer";
+        serialize_i64(i64) => "integer";
+        serialize_i128(i128) => "integer";
+        serialize_u8(u8) => "integer";
+        serialize_u16(u16) => "integer";
+        serialize_u32(u32) => "integer";
+        serialize_u64(u64) => "integer";
+        serialize_u128(u128) => "integer";
+        serialize_f32(f32) => "float";
+        serialize_f64(f64) => "float";
+        serialize_char(char) => "char";
+        serialize_str(&str) => "string";
+        serialize_bytes(&[u8]) => "bytes";
+        serialize_unit_variant(&'static str, u32, &'static str) => "enum";
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.empty()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.empty()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.empty()
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(Self::unsupported("enum"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(self.positional())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Ok(self.positional())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Ok(self.positional())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(Self::unsupported("enum"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(self.named())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(self.named())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(Self::unsupported("enum"))
+    }
+}
+
+/// Serializes a row from a struct or a map, matching the fields to the columns by name.
+struct NamedRowSerializer<'a> {
+    columns: &'a [ColumnSpec<'a>],
+    values: Vec<Option<Option<CqlValue>>>,
+    pending_key: Option<String>,
+}
+
+impl NamedRowSerializer<'_> {
+    fn serialize_column<T: Serialize + ?Sized>(
+        &mut self,
+        name: &str,
+        value: &T,
+    ) -> Result<(), RowSerializerError> {
+        let Some(index) = self.columns.iter().position(|spec| spec.name() == name) else {
+            return Err(RowSerializerError::TypeCheck(
+                BuiltinTypeCheckErrorKind::NoColumnWithName {
+                    name: name.to_owned(),
+                },
+            ));
+        };
+        let value = value
+            .serialize(ValueSerializer {
+                typ: self.columns[index].typ(),
+            })
+            .map_err(|err| RowSerializerError::Column {
+                name: name.to_owned(),
+                err,
+            })?;
+        self.values[index] = Some(value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<Option<CqlValue>>, RowSerializerError> {
+        self.values
+            .into_iter()
+            .zip(self.columns)
+            .map(|(value, spec)| {
+                value.ok_or_else(|| {
+                    RowSerializerError::TypeCheck(
+                        BuiltinTypeCheckErrorKind::ValueMissingForColumn {
+                            name: spec.name().to_owned(),
+                        },
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+impl SerializeMap for NamedRowSerializer<'_> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowSerializerError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        self.pending_key = Some(serialize_name(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let name = self.pending_key.take().ok_or_else(|| {
+            RowSerializerError::custom("serialize_value called before serialize_key")
+        })?;
+        self.serialize_column(&name, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl SerializeStruct for NamedRowSerializer<'_> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowSerializerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.serialize_column(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+/// Serializes a row from a tuple or a sequence, matching the elements to the columns by position.
+struct PositionalRowSerializer<'a> {
+    columns: &'a [ColumnSpec<'a>],
+    values: Vec<Option<CqlValue>>,
+    count: usize,
+}
+
+impl PositionalRowSerializer<'_> {
+    fn serialize_column<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), RowSerializerError> {
+        // Excess elements are only counted, so that the column count mismatch is reported.
+        if let Some(spec) = self.columns.get(self.count) {
+            let value = value
+                .serialize(ValueSerializer { typ: spec.typ() })
+                .map_err(|err| RowSerializerError::Column {
+                    name: spec.name().to_owned(),
+                    err,
+                })?;
+            self.values.push(value);
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<Option<CqlValue>>, RowSerializerError> {
+        if self.count != self.columns.len() {
+            return Err(RowSerializerError::TypeCheck(
+                BuiltinTypeCheckErrorKind::WrongColumnCount {
+                    rust_cols: self.count,
+                    cql_cols: self.columns.len(),
+                },
+            ));
+        }
+        Ok(self.values)
+    }
+}
+
+impl SerializeSeq for PositionalRowSerializer<'_> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowSerializerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.serialize_column(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl SerializeTuple for PositionalRowSerializer<'_> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowSerializerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.serialize_column(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl SerializeTupleStruct for PositionalRowSerializer<'_> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowSerializerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.serialize_column(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+/// The type used to serialize names of columns and UDT fields.
+const TEXT: ColumnType<'static> = ColumnType::Native(NativeType::Text);
+
+/// The type used to serialize elements of a `blob` serialized as a sequence.
+/// `smallint` accepts all values of `u8`, the range is checked afterwards.
+const BLOB_ELEMENT: ColumnType<'static> = ColumnType::Native(NativeType::SmallInt);
+
+fn serialize_name<T: Serialize + ?Sized>(key: &T) -> Result<String, SerdeBridgeError> {
+    match key.serialize(ValueSerializer { typ: &TEXT })? {
+        Some(CqlValue::Text(name)) => Ok(name),
+        _ => Err(SerdeBridgeError::custom("expected a string as the name")),
+    }
+}
+
+/// Serializes a single value into a [CqlValue] of the given type, `None` meaning null.
+struct ValueSerializer<'a> {
+    typ: &'a ColumnType<'a>,
+}
+
+impl<'a> ValueSerializer<'a> {
+    fn mismatch(&self, what: &str) -> SerdeBridgeError {
+        SerdeBridgeError::custom(format_args!(
+            "cannot serialize {what} as a value of type {:?}",
+            self.typ
+        ))
+    }
+
+    fn serialize_integer(self, value: i128) -> Result<Option<CqlValue>, SerdeBridgeError> {
+        let out_of_range = || {
+            SerdeBridgeError::custom(format_args!(
+                "integer {value} out of range of type {:?}",
+                self.typ
+            ))
+        };
+        let value = match self.typ {
+            ColumnType::Native(NativeType::TinyInt) => {
+                CqlValue::TinyInt(value.try_into().map_err(|_| out_of_range())?)
+            }
+            ColumnType::Native(NativeType::SmallInt) => {
+                CqlValue::SmallInt(value.try_into().map_err(|_| out_of_range())?)
+            }
+            ColumnType::Native(NativeType::Int) => {
+                CqlValue::Int(value.try_into().map_err(|_| out_of_range())?)
+            }
+            ColumnType::Native(NativeType::BigInt) => {
+                CqlValue::BigInt(value.try_into().map_err(|_| out_of_range())?)
+            }
+            ColumnType::Native(NativeType::Counter) => {
+                CqlValue::Counter(Counter(value.try_into().map_err(|_| out_of_range())?))
+            }
+            ColumnType::Native(NativeType::Timestamp) => {
+                CqlValue::Timestamp(CqlTimestamp(value.try_into().map_err(|_| out_of_range())?))
+            }
+            ColumnType::Native(NativeType::Time) => {
+                CqlValue::Time(CqlTime(value.try_into().map_err(|_| out_of_range())?))
+            }
+            ColumnType::Native(NativeType::Varint) => {
+                let bytes = value.to_be_bytes();
+                // Strip the redundant leading bytes, keeping the sign bit.
+                let start = (0..15)
+                    .find(|&i| {
+                        !(bytes[i] == 0 && bytes[i + 1] & 0x80 == 0
+                            || bytes[i] == 0xff && bytes[i + 1] & 0x80 != 0)
+                    })
+                    .unwrap_or(15);
+                CqlValue::Varint(CqlVarint::from_signed_bytes_be_slice(&bytes[start..]))
+            }
+            _ => return Err(self.mismatch("integer")),
+        };
+        Ok(Some(value))
+    }
+
+    fn parse_str(&self, v: &str) -> Result<CqlValue, SerdeBridgeError> {
+        let invalid = |err: &dyn std::fmt::Display| {
+            SerdeBridgeError::custom(format_args!(
+                "cannot parse {v:?} as a value of type {:?}: {err}",
+                self.typ
+            ))
+        };
+        let value = match self.typ {
+            ColumnType::Native(NativeType::Text) => CqlValue::Text(v.to_owned()),
+            ColumnType::Native(NativeType::Ascii) => {
+                if !v.is_ascii() {
+                    return Err(invalid(&"non-ascii characters"));
+                }
+                CqlValue::Ascii(v.to_owned())
+            }
+            ColumnType::Native(NativeType::Uuid) => {
+                CqlValue::Uuid(Uuid::parse_str(v).map_err(|err| invalid(&err))?)
+            }
+            ColumnType::Native(NativeType::Timeuuid) => CqlValue::Timeuuid(CqlTimeuuid::from(
+                Uuid::parse_str(v).map_err(|err| invalid(&err))?,
+            )),
+            ColumnType::Native(NativeType::Inet) => {
+                CqlValue::Inet(v.parse::<IpAddr>().map_err(|err| invalid(&err))?)
+            }
+            ColumnType::Native(NativeType::Timestamp) => {
+                let datetime = DateTime::parse_from_rfc3339(v).map_err(|err| invalid(&err))?;
+                CqlValue::Timestamp(CqlTimestamp(datetime.timestamp_millis()))
+            }
+            ColumnType::Native(NativeType::Date) => {
+                let date = v.parse::<NaiveDate>().map_err(|err| invalid(&err))?;
+                let unix_epoch = NaiveDate::from_yo_opt(1970, 1).unwrap();
+                let days = date.signed_duration_since(unix_epoch).num_days();
+                // CqlDate counts days from 2^31 days before the unix epoch.
+                CqlValue::Date(CqlDate((days + (1 << 31)) as u32))
+            }
+            ColumnType::Native(NativeType::Time) => {
+                let time = v.parse::<NaiveTime>().map_err(|err| invalid(&err))?;
+                let nanos = time.num_seconds_from_midnight() as i64 * 1_000_000_000
+                    + time.nanosecond() as i64;
+                CqlValue::Time(CqlTime(nanos))
+            }
+            _ => return Err(self.mismatch("string")),
+        };
+        Ok(value)
+    }
+}
+
+impl<'a> serde::Serializer for ValueSerializer<'a> {
+    type Ok = Option<CqlValue>;
+    type Error = SerdeBridgeError;
+
+    type SerializeSeq = SeqSerializer<'a>;
+    type SerializeTuple = SeqSerializer<'a>;
+    type SerializeTupleStruct = SeqSerializer<'a>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = MapSerializer<'a>;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        match self.typ {
+            ColumnType::Native(NativeType::Boolean) => Ok(Some(CqlValue::Boolean(v))),
+            _ => Err(self.mismatch("bool")),
+        }
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        let v = i128::try_from(v)
+            .map_err(|_| SerdeBridgeError::custom(format_args!("integer {v} out of range")))?;
+        self.serialize_integer(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        match self.typ {
+            ColumnType::Native(NativeType::Float) => Ok(Some(CqlValue::Float(v))),
+            ColumnType::Native(NativeType::Double) => Ok(Some(CqlValue::Double(v.into()))),
+            _ => Err(self.mismatch("float")),
+        }
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        match self.typ {
+            // Some formats (e.g. JSON) represent all floats as f64.
+            ColumnType::Native(NativeType::Float) => Ok(Some(CqlValue::Float(v as f32))),
+            ColumnType::Native(NativeType::Double) => Ok(Some(CqlValue::Double(v))),
+            _ => Err(self.mismatch("float")),
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.parse_str(v).map(Some)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        let value = match self.typ {
+            ColumnType::Native(NativeType::Blob) => CqlValue::Blob(v.to_vec()),
+            ColumnType::Native(NativeType::Uuid) => {
+                CqlValue::Uuid(Uuid::from_slice(v).map_err(SerdeBridgeError::custom)?)
+            }
+            ColumnType::Native(NativeType::Timeuuid) => CqlValue::Timeuuid(CqlTimeuuid::from(
+                Uuid::from_slice(v).map_err(SerdeBridgeError::custom)?,
+            )),
+            _ => return Err(self.mismatch("bytes")),
+        };
+        Ok(Some(value))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(self.mismatch("enum"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        match self.typ {
+            ColumnType::Collection {
+                typ: CollectionType::List(_) | CollectionType::Set(_),
+                ..
+            }
+            | ColumnType::Vector { .. }
+            | ColumnType::Tuple(_)
+            | ColumnType::Native(NativeType::Blob) => Ok(SeqSerializer {
+                typ: self.typ,
+                values: Vec::with_capacity(len.unwrap_or(0)),
+            }),
+            _ => Err(self.mismatch("sequence")),
+        }
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(self.mismatch("enum"))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        match self.typ {
+            ColumnType::Collection {
+                typ: CollectionType::Map(_, _),
+                ..
+            }
+            | ColumnType::UserDefinedType { .. } => Ok(MapSerializer {
+                typ: self.typ,
+                entries: Vec::with_capacity(len.unwrap_or(0)),
+                fields: Vec::new(),
+                pending_key: None,
+            }),
+            _ => Err(self.mismatch("map")),
+        }
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(self.mismatch("enum"))
+    }
+}
+
+/// Serializes a `list`, `set`, `vector`, `tuple` or `blob` from a sequence.
+struct SeqSerializer<'a> {
+    typ: &'a ColumnType<'a>,
+    values: Vec<Option<CqlValue>>,
+}
+
+impl SeqSerializer<'_> {
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerdeBridgeError> {
+        let typ = match self.typ {
+            ColumnType::Collection {
+                typ: CollectionType::List(typ) | CollectionType::Set(typ),
+                ..
+            }
+            | ColumnType::Vector { typ, .. } => typ,
+            ColumnType::Tuple(types) => types.get(self.values.len()).ok_or_else(|| {
+                SerdeBridgeError::custom(format_args!("too many elements for type {:?}", self.typ))
+            })?,
+            _ => &BLOB_ELEMENT,
+        };
+        self.values.push(value.serialize(ValueSerializer { typ })?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Option<CqlValue>, SerdeBridgeError> {
+        let non_null = |values: Vec<Option<CqlValue>>| {
+            values
+                .into_iter()
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| {
+                    SerdeBridgeError::custom("null elements are not allowed in collections")
+                })
+        };
+        let value = match self.typ {
+            ColumnType::Collection {
+                typ: CollectionType::List(_),
+                ..
+            } => CqlValue::List(non_null(self.values)?),
+            ColumnType::Collection {
+                typ: CollectionType::Set(_),
+                ..
+            } => CqlValue::Set(non_null(self.values)?),
+            ColumnType::Vector { .. } => CqlValue::Vector(non_null(self.values)?),
+            ColumnType::Tuple(_) => CqlValue::Tuple(self.values),
+            _ => CqlValue::Blob(
+                non_null(self.values)?
+                    .into_iter()
+                    .map(|value| match value {
+                        CqlValue::SmallInt(byte) => u8::try_from(byte).ok(),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| SerdeBridgeError::custom("blob element out of range of u8"))?,
+            ),
+        };
+        Ok(Some(value))
+    }
+}
+
+impl SerializeSeq for SeqSerializer<'_> {
+    type Ok = Option<CqlValue>;
+    type Error = SerdeBridgeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SeqSerializer::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl SerializeTuple for SeqSerializer<'_> {
+    type Ok = Option<CqlValue>;
+    type Error = SerdeBridgeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SeqSerializer::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl SerializeTupleStruct for SeqSerializer<'_> {
+    type Ok = Option<CqlValue>;
+    type Error = SerdeBridgeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SeqSerializer::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+/// Serializes a `map` or a UDT from a map or a struct.
+struct MapSerializer<'a> {
+    typ: &'a ColumnType<'a>,
+    entries: Vec<(CqlValue, CqlValue)>,
+    fields: Vec<(String, Option<CqlValue>)>,
+    pending_key: Option<CqlValue>,
+}
+
+impl MapSerializer<'_> {
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeBridgeError> {
+        let key = match self.typ {
+            ColumnType::Collection {
+                typ: CollectionType::Map(key_type, _),
+                ..
+            } => key
+                .serialize(ValueSerializer { typ: key_type })?
+                .ok_or_else(|| SerdeBridgeError::custom("null map keys are not allowed"))?,
+            _ => CqlValue::Text(serialize_name(key)?),
+        };
+        self.pending_key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerdeBridgeError> {
+        let key = self.pending_key.take().ok_or_else(|| {
+            SerdeBridgeError::custom("serialize_value called before serialize_key")
+        })?;
+        match self.typ {
+            ColumnType::Collection {
+                typ: CollectionType::Map(_, value_type),
+                ..
+            } => {
+                let value = value
+                    .serialize(ValueSerializer { typ: value_type })?
+                    .ok_or_else(|| SerdeBridgeError::custom("null map values are not allowed"))?;
+                self.entries.push((key, value));
+            }
+            ColumnType::UserDefinedType { definition, .. } => {
+                let CqlValue::Text(name) = key else {
+                    unreachable!("UDT field names are serialized as text")
+                };
+                let Some((_, field_type)) = definition
+                    .field_types
+                    .iter()
+                    .find(|(field_name, _)| *field_name == name)
+                else {
+                    return Err(SerdeBridgeError::custom(format_args!(
+                        "no field named {name} in UDT {}.{}",
+                        definition.keyspace, definition.name
+                    )));
+                };
+                let value = value.serialize(ValueSerializer { typ: field_type })?;
+                self.fields.push((name, value));
+            }
+            _ => unreachable!("MapSerializer is created only for maps and UDTs"),
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Option<CqlValue>, SerdeBridgeError> {
+        let value = match self.typ {
+            ColumnType::UserDefinedType { definition, .. } => {
+                // The fields are ordered as in the definition, missing fields are null.
+                let fields = definition
+                    .field_types
+                    .iter()
+                    .map(|(name, _)| {
+                        let value = self
+                            .fields
+                            .iter()
+                            .position(|(field_name, _)| field_name == name)
+                            .and_then(|index| self.fields.swap_remove(index).1);
+                        (name.to_string(), value)
+                    })
+                    .collect();
+                CqlValue::UserDefinedType {
+                    keyspace: definition.keyspace.to_string(),
+                    name: definition.name.to_string(),
+                    fields,
+                }
+            }
+            _ => CqlValue::Map(self.entries),
+        };
+        Ok(Some(value))
+    }
+}
+
+impl SerializeMap for MapSerializer<'_> {
+    type Ok = Option<CqlValue>;
+    type Error = SerdeBridgeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        MapSerializer::serialize_key(self, key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        MapSerializer::serialize_value(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl SerializeStruct for MapSerializer<'_> {
+    type Ok = Option<CqlValue>;
+    type Error = SerdeBridgeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        MapSerializer::serialize_key(self, key)?;
+        MapSerializer::serialize_value(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::BTreeMap;
+
+use assert_matches::assert_matches;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use super::{SerdeRow, SerdeValue};
+use crate::deserialize::row::tests::deserialize as deserialize_row;
+use crate::deserialize::tests::spec;
+use crate::deserialize::value::tests::{deserialize as deserialize_value, udt_def_with_fields};
+use crate::frame::response::result::{CollectionType, ColumnSpec, ColumnType, NativeType};
+use crate::serialize::row::tests::do_serialize as serialize_row;
+use crate::serialize::row::{
+    BuiltinSerializationError, BuiltinSerializationErrorKind, BuiltinTypeCheckError,
+    BuiltinTypeCheckErrorKind, RowSerializationContext, SerializeRow,
+};
+use crate::serialize::value::tests::do_serialize as serialize_value;
+use crate::serialize::value::SerializeValue;
+use crate::serialize::{CellWriter, RowWriter, SerializationError};
+use crate::value::{Counter, CqlTimestamp, CqlValue};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Address {
+    street: String,
+    number: i32,
+    flat: Option<i16>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    id: i64,
+    name: String,
+    emails: Vec<String>,
+    scores: BTreeMap<String, f64>,
+    address: Option<Address>,
+}
+
+fn address_type() -> ColumnType<'static> {
+    udt_def_with_fields([
+        ("street", ColumnType::Native(NativeType::Text)),
+        ("number", ColumnType::Native(NativeType::Int)),
+        ("flat", ColumnType::Native(NativeType::SmallInt)),
+    ])
+}
+
+fn user_specs() -> Vec<ColumnSpec<'static>> {
+    vec![
+        spec("address", address_type()),
+        spec("id", ColumnType::Native(NativeType::BigInt)),
+        spec(
+            "emails",
+            ColumnType::Collection {
+                frozen: false,
+                typ: CollectionType::List(Box::new(ColumnType::Native(NativeType::Text))),
+            },
+        ),
+        spec("name", ColumnType::Native(NativeType::Text)),
+        spec(
+            "scores",
+            ColumnType::Collection {
+                frozen: false,
+                typ: CollectionType::Map(
+                    Box::new(ColumnType::Native(NativeType::Ascii)),
+                    Box::new(ColumnType::Native(NativeType::Double)),
+                ),
+            },
+        ),
+    ]
+}
+
+fn serialize_row_err<T: SerializeRow>(t: T, columns: &[ColumnSpec]) -> SerializationError {
+    let ctx = RowSerializationContext { columns };
+    let mut ret = Vec::new();
+    let mut writer = RowWriter::new(&mut ret);
+    t.serialize(&ctx, &mut writer).unwrap_err()
+}
+
+fn round_trip<T>(value: T, typ: &ColumnType) -> T
+where
+    T: Serialize + serde::de::DeserializeOwned,
+{
+    let bytes = Bytes::from(serialize_value(SerdeValue(value), typ));
+    deserialize_value::<SerdeValue<T>>(typ, &bytes)
+        .unwrap()
+        .into_inner()
+}
+
+#[test]
+fn test_row_round_trip() {
+    let specs = user_specs();
+    let user = User {
+        id: 42,
+        name: "Alice".to_owned(),
+        emails: vec!["alice@example.com".to_owned()],
+        scores: BTreeMap::from([("math".to_owned(), 4.5), ("art".to_owned(), 5.0)]),
+        address: Some(Address {
+            street: "Main".to_owned(),
+            number: 7,
+            flat: None,
+        }),
+    };
+
+    let bytes = Bytes::from(serialize_row(SerdeRow(&user), &specs));
+
+    // The columns are serialized in the order of the specs, as with the derived implementation.
+    let CqlValue::UserDefinedType { fields, .. } =
+        deserialize_value::<CqlValue>(specs[0].typ(), &bytes).unwrap()
+    else {
+        panic!("expected a UDT");
+    };
+    assert_eq!(
+        fields,
+        vec![
+            ("street".to_owned(), Some(CqlValue::Text("Main".to_owned()))),
+            ("number".to_owned(), Some(CqlValue::Int(7))),
+            ("flat".to_owned(), None),
+        ]
+    );
+
+    let SerdeRow(deserialized) = deserialize_row::<SerdeRow<User>>(&specs, &bytes).unwrap();
+    assert_eq!(deserialized, user);
+
+    // Rows can also be (de)serialized by position.
+    let specs = [
+        spec("a", ColumnType::Native(NativeType::Int)),
+        spec("b", ColumnType::Native(NativeType::Text)),
+    ];
+    let bytes = Bytes::from(serialize_row(SerdeRow((1, "x")), &specs));
+    let SerdeRow(deserialized) =
+        deserialize_row::<SerdeRow<(i32, String)>>(&specs, &bytes).unwrap();
+    assert_eq!(deserialized, (1, "x".to_owned()));
+}
+
+#[test]
+fn test_row_serialization_errors() {
+    let specs = [
+        spec("a", ColumnType::Native(NativeType::Int)),
+        spec("b", ColumnType::Native(NativeType::Text)),
+    ];
+
+    #[derive(Serialize)]
+    struct Missing {
+        a: i32,
+    }
+    let err = serialize_row_err(SerdeRow(Missing { a: 1 }), &specs);
+    let err = err.downcast_ref::<BuiltinTypeCheckError>().unwrap();
+    assert_matches!(
+        &err.kind,
+        BuiltinTypeCheckErrorKind::ValueMissingForColumn { name } if name == "b"
+    );
+
+    #[derive(Serialize)]
+    struct Unknown {
+        a: i32,
+        b: String,
+        c: bool,
+    }
+    let err = serialize_row_err(
+        SerdeRow(Unknown {
+            a: 1,
+            b: String::new(),
+            c: true,
+        }),
+        &specs,
+    );
+    let err = err.downcast_ref::<BuiltinTypeCheckError>().unwrap();
+    assert_matches!(
+        &err.kind,
+        BuiltinTypeCheckErrorKind::NoColumnWithName { name } if name == "c"
+    );
+
+    let err = serialize_row_err(SerdeRow((1, "x", 2)), &specs);
+    let err = err.downcast_ref::<BuiltinTypeCheckError>().unwrap();
+    assert_matches!(
+        err.kind,
+        BuiltinTypeCheckErrorKind::WrongColumnCount {
+            rust_cols: 3,
+            cql_cols: 2
+        }
+    );
+
+    // Type mismatch is reported for the column.
+    let err = serialize_row_err(SerdeRow(("x", "y")), &specs);
+    let err = err.downcast_ref::<BuiltinSerializationError>().unwrap();
+    assert_matches!(
+        &err.kind,
+        BuiltinSerializationErrorKind::ColumnSerializationFailed { name, .. } if name == "a"
+    );
+}
+
+#[test]
+fn test_value_round_trip() {
+    let int = ColumnType::Native(NativeType::Int);
+    assert_eq!(round_trip(123u16, &int), 123);
+    assert_eq!(round_trip(Some(-5i64), &int), Some(-5));
+    assert_eq!(round_trip(None::<i32>, &int), None);
+
+    let varint = ColumnType::Native(NativeType::Varint);
+    for value in [
+        0i128,
+        1,
+        -1,
+        127,
+        128,
+        -128,
+        -129,
+        i64::MAX as i128 * 4,
+        i128::MIN,
+    ] {
+        assert_eq!(round_trip(value, &varint), value);
+    }
+
+    let blob = ColumnType::Native(NativeType::Blob);
+    assert_eq!(round_trip(vec![1u8, 2, 255], &blob), vec![1, 2, 255]);
+
+    let uuid = ColumnType::Native(NativeType::Uuid);
+    let uuid_str = "8e14e760-7fa8-11eb-bc66-000000000001";
+    assert_eq!(round_trip(uuid_str.to_owned(), &uuid), uuid_str);
+
+    let date = ColumnType::Native(NativeType::Date);
+    for value in ["1970-01-01", "2024-02-29", "1969-12-31"] {
+        assert_eq!(round_trip(value.to_owned(), &date), value);
+    }
+
+    let time = ColumnType::Native(NativeType::Time);
+    assert_eq!(round_trip("12:34:56.789".to_owned(), &time), "12:34:56.789");
+
+    let timestamp = ColumnType::Native(NativeType::Timestamp);
+    assert_eq!(round_trip(1_000i64, &timestamp), 1_000);
+    assert_eq!(
+        round_trip("2024-01-02T03:04:05.006+00:00".to_owned(), &timestamp),
+        "2024-01-02T03:04:05.006+00:00"
+    );
+    let bytes = Bytes::from(serialize_value(
+        SerdeValue("1970-01-01T00:00:01Z"),
+        &timestamp,
+    ));
+    assert_eq!(
+        deserialize_value::<CqlTimestamp>(&timestamp, &bytes).unwrap(),
+        CqlTimestamp(1_000)
+    );
+
+    let counter = ColumnType::Native(NativeType::Counter);
+    let bytes = Bytes::from(serialize_value(SerdeValue(7u8), &counter));
+    assert_eq!(
+        deserialize_value::<Counter>(&counter, &bytes).unwrap(),
+        Counter(7)
+    );
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Color {
+        Red,
+        Green,
+    }
+    let text = ColumnType::Native(NativeType::Text);
+    assert_eq!(round_trip(Color::Green, &text), Color::Green);
+
+    let tuple = ColumnType::Tuple(vec![
+        ColumnType::Native(NativeType::Int),
+        ColumnType::Native(NativeType::Text),
+    ]);
+    assert_eq!(
+        round_trip((1, Some("a".to_owned())), &tuple),
+        (1, Some("a".to_owned()))
+    );
+    assert_eq!(round_trip((1, None::<String>), &tuple), (1, None));
+
+    let address = Address {
+        street: "Main".to_owned(),
+        number: 7,
+        flat: Some(3),
+    };
+    assert_eq!(
+        round_trip(address, &address_type()),
+        Address {
+            street: "Main".to_owned(),
+            number: 7,
+            flat: Some(3),
+        }
+    );
+}
+
+#[test]
+fn test_value_errors() {
+    let tinyint = ColumnType::Native(NativeType::TinyInt);
+    let err = serialize_value_err(SerdeValue(1000), &tinyint);
+    assert!(err.to_string().contains("out of range"), "{err}");
+
+    let int = ColumnType::Native(NativeType::Int);
+    serialize_value_err(SerdeValue("x"), &int);
+
+    let ascii = ColumnType::Native(NativeType::Ascii);
+    serialize_value_err(SerdeValue("zażółć"), &ascii);
+
+    #[derive(Serialize)]
+    struct Unknown {
+        street: String,
+        zip: String,
+    }
+    serialize_value_err(
+        SerdeValue(Unknown {
+            street: String::new(),
+            zip: String::new(),
+        }),
+        &address_type(),
+    );
+
+    // Deserialization into a mismatched type fails.
+    let bytes = Bytes::from(serialize_value(
+        SerdeValue("x"),
+        &ColumnType::Native(NativeType::Text),
+    ));
+    deserialize_value::<SerdeValue<i32>>(&ColumnType::Native(NativeType::Text), &bytes)
+        .unwrap_err();
+}
+
+#[test]
+fn test_type_check_rejects_unsupported_types() {
+    use crate::deserialize::row::{self, DeserializeRow};
+    use crate::deserialize::value::DeserializeValue;
+
+    let decimal = ColumnType::Native(NativeType::Decimal);
+    let duration = ColumnType::Native(NativeType::Duration);
+    let list_of_decimals = ColumnType::Collection {
+        frozen: false,
+        typ: CollectionType::List(Box::new(decimal.clone())),
+    };
+    let udt_with_duration = udt_def_with_fields([("d", duration.clone())]);
+    for typ in [&decimal, &duration, &list_of_decimals, &udt_with_duration] {
+        <SerdeValue<String>>::type_check(typ).unwrap_err();
+    }
+    <SerdeValue<Address>>::type_check(&address_type()).unwrap();
+
+    let specs = [
+        spec("id", ColumnType::Native(NativeType::BigInt)),
+        spec("price", decimal),
+    ];
+    let err = <SerdeRow<BTreeMap<String, i64>>>::type_check(&specs).unwrap_err();
+    let err = err.downcast_ref::<row::BuiltinTypeCheckError>().unwrap();
+    assert_matches!(
+        &err.kind,
+        row::BuiltinTypeCheckErrorKind::ColumnTypeCheckFailed { column_index: 1, column_name, .. }
+            if column_name == "price"
+    );
+    <SerdeRow<User>>::type_check(&user_specs()).unwrap();
+}
+
+#[test]
+fn test_row_is_empty() {
+    #[derive(Serialize)]
+    struct Empty {}
+
+    assert!(SerdeRow(()).is_empty());
+    assert!(SerdeRow(Empty {}).is_empty());
+    assert!(SerdeRow(Vec::<i32>::new()).is_empty());
+    assert!(SerdeRow(BTreeMap::<String, i32>::new()).is_empty());
+    assert!(!SerdeRow((1,)).is_empty());
+    assert!(!SerdeRow(vec![1, 2]).is_empty());
+    assert!(!SerdeRow(BTreeMap::from([("a".to_owned(), 1)])).is_empty());
+    assert!(!SerdeRow(Address {
+        street: String::new(),
+        number: 0,
+        flat: None,
+    })
+    .is_empty());
+}
+
+fn serialize_value_err<T: SerializeValue>(t: T, typ: &ColumnType) -> SerializationError {
+    let mut ret = Vec::new();
+    let writer = CellWriter::new(&mut ret);
+    t.serialize(typ, writer).unwrap_err()
+}
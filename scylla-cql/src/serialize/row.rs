@@ -233,6 +233,11 @@ impl<T: SerializeValue, S: BuildHasher> SerializeRow for HashMap<&str, T, S> {
     impl_serialize_row_for_map!();
 }
 
+#[cfg(feature = "serde_json-1")]
+impl SerializeRow for serde_json_1::Map<String, serde_json_1::Value> {
+    impl_serialize_row_for_map!();
+}
+
 impl<T: SerializeRow + ?Sized> SerializeRow for &T {
     fn serialize(
         &self,
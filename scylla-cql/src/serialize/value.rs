@@ -226,6 +226,18 @@ impl<V: SerializeValue + secrecy_08::Zeroize> SerializeValue for secrecy_08::Sec
         V::serialize(self.expose_secret(), typ, writer)
     }
 }
+#[cfg(feature = "serde_json-1")]
+impl SerializeValue for serde_json_1::Value {
+    fn serialize<'b>(
+        &self,
+        typ: &ColumnType,
+        writer: CellWriter<'b>,
+    ) -> Result<WrittenCellProof<'b>, SerializationError> {
+        let value =
+            crate::value::json::cql_value_from_json(self, typ).map_err(SerializationError::new)?;
+        <Option<CqlValue> as SerializeValue>::serialize(&value, typ, writer)
+    }
+}
 impl SerializeValue for bool {
     impl_serialize_via_writer!(|me, typ, writer| {
         exact_type_check!(typ, Boolean);
@@ -587,6 +587,66 @@ impl TryFrom<bigdecimal_04::BigDecimal> for CqlDecimal {
     }
 }
 
+/// Negates the big-endian two's complement number in place.
+#[cfg(any(feature = "serde_json-1", feature = "arrow-54"))]
+pub(crate) fn negate(bytes: &mut [u8]) {
+    let mut carry = true;
+    for byte in bytes.iter_mut().rev() {
+        let (negated, overflow) = (!*byte).overflowing_add(carry as u8);
+        *byte = negated;
+        carry = overflow;
+    }
+}
+
+/// Formats the big-endian two's complement number in decimal.
+#[cfg(any(feature = "serde_json-1", feature = "arrow-54"))]
+pub(crate) fn varint_to_string(bytes: &[u8]) -> String {
+    let negative = bytes.first().is_some_and(|byte| byte & 0x80 != 0);
+    let mut magnitude = bytes.to_vec();
+    if negative {
+        negate(&mut magnitude);
+    }
+    let mut digits = Vec::new();
+    while magnitude.iter().any(|&byte| byte != 0) {
+        let mut remainder = 0u32;
+        for byte in magnitude.iter_mut() {
+            let current = remainder * 256 + *byte as u32;
+            *byte = (current / 10) as u8;
+            remainder = current % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    if negative {
+        digits.push(b'-');
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+/// Formats the decimal `unscaled * 10^-scale`.
+#[cfg(any(feature = "serde_json-1", feature = "arrow-54"))]
+pub(crate) fn decimal_to_string(bytes: &[u8], scale: i32) -> String {
+    let unscaled = varint_to_string(bytes);
+    if scale <= 0 {
+        return if scale == 0 || unscaled == "0" {
+            unscaled
+        } else {
+            format!("{unscaled}e{}", -(scale as i64))
+        };
+    }
+    let (sign, digits) = match unscaled.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", unscaled.as_str()),
+    };
+    let scale = scale as usize;
+    let digits = format!("{digits:0>width$}", width = scale + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - scale);
+    format!("{sign}{integer}.{fraction}")
+}
+
 /// Native CQL date representation that allows for a bigger range of dates (-262145-1-1 to 262143-12-31).
 ///
 /// Represented as number of days since -5877641-06-23 i.e. 2^31 days before unix epoch.
@@ -606,7 +666,9 @@ pub struct CqlTimestamp(pub i64);
 pub struct CqlTime(pub i64);
 
 impl CqlDate {
-    fn try_to_chrono_04_naive_date(&self) -> Result<chrono_04::NaiveDate, ValueOverflow> {
+    pub(crate) fn try_to_chrono_04_naive_date(
+        &self,
+    ) -> Result<chrono_04::NaiveDate, ValueOverflow> {
         let days_since_unix_epoch = self.0 as i64 - (1 << 31);
 
         // date_days is u32 then converted to i64 then we subtract 2^31;
@@ -1380,6 +1442,9 @@ pub fn deser_cql_value(
     })
 }
 
+#[cfg(feature = "serde_json-1")]
+pub mod json;
+
 #[derive(Debug, Default, PartialEq)]
 pub struct Row {
     pub columns: Vec<Option<CqlValue>>,
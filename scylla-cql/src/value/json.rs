@@ -0,0 +1,890 @@
+//! Conversion of CQL values and rows to and from JSON ([serde_json](serde_json_1)).
+//!
+//! The encoding follows the rules of `SELECT JSON` and `INSERT JSON` statements:
+//!
+//! | CQL type                                          | JSON                                                    |
+//! |---------------------------------------------------|---------------------------------------------------------|
+//! | `ascii`, `text`, `inet`, `uuid`, `timeuuid`       | string                                                  |
+//! | `boolean`                                         | bool                                                    |
+//! | `tinyint`, `smallint`, `int`, `bigint`, `counter` | integer                                                 |
+//! | `varint`, `decimal`                               | number (or string, see below)                           |
+//! | `float`, `double`                                 | number (`"NaN"`, `"Infinity"` or `"-Infinity"` strings) |
+//! | `blob`                                            | hex string prefixed with `0x`                           |
+//! | `timestamp`                                       | string (`2024-01-02 03:04:05.678Z`)                     |
+//! | `date`                                            | string (`2024-01-02`)                                   |
+//! | `time`                                            | string (`03:04:05.678000000`)                           |
+//! | `duration`                                        | string (`1y2mo3d4h5m6s`)                                |
+//! | `list`, `set`, `vector`, `tuple`                  | array                                                   |
+//! | `map`                                             | object - keys which aren't JSON strings are JSON-encoded |
+//! | user defined type                                 | object                                                  |
+//! | null                                              | null                                                    |
+//!
+//! When converting from JSON, the values are checked against the CQL type.
+//! As in `INSERT JSON`, strings are also accepted for all the numeric types and booleans,
+//! integers for `timestamp` (milliseconds since unix epoch), `date` (days since -5877641-06-23)
+//! and `time` (nanoseconds since midnight), and various formats of timestamps.
+//!
+//! Unless `serde_json` is compiled with the `arbitrary_precision` feature, `varint` and `decimal`
+//! values which can't be represented exactly by `i64`, `u64` or `f64` are converted to strings.
+//!
+//! Besides the functions in this module, [serde_json::Value](serde_json_1::Value) implements
+//! [SerializeValue](crate::serialize::value::SerializeValue) and
+//! [DeserializeValue](crate::deserialize::value::DeserializeValue),
+//! and [serde_json::Map](serde_json_1::Map) (with column names as keys) implements
+//! [SerializeRow](crate::serialize::row::SerializeRow) and
+//! [DeserializeRow](crate::deserialize::row::DeserializeRow), so rows can be fetched as JSON objects
+//! directly from query results and pagers.
+//!
+//! This module is available only under the crate feature `serde_json-1`.
+
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use chrono_04::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serde_json_1::{Map, Number, Value};
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::{
+    decimal_to_string, negate, varint_to_string, Counter, CqlDate, CqlDecimal, CqlDuration,
+    CqlTime, CqlTimestamp, CqlTimeuuid, CqlValue, CqlVarint, Row,
+};
+use crate::frame::response::result::{CollectionType, ColumnSpec, ColumnType, NativeType};
+
+/// An error returned when a JSON value can't be converted to a value of the CQL type.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("{0}")]
+pub struct JsonConversionError(String);
+
+/// Converts the CQL value to JSON.
+pub fn cql_value_to_json(value: &CqlValue) -> Value {
+    match value {
+        CqlValue::Ascii(s) | CqlValue::Text(s) => Value::String(s.clone()),
+        CqlValue::Boolean(b) => Value::Bool(*b),
+        CqlValue::Blob(bytes) => Value::String(blob_to_string(bytes)),
+        CqlValue::Counter(Counter(i)) | CqlValue::BigInt(i) => Value::from(*i),
+        CqlValue::Int(i) => Value::from(*i),
+        CqlValue::SmallInt(i) => Value::from(*i),
+        CqlValue::TinyInt(i) => Value::from(*i),
+        CqlValue::Varint(varint) => {
+            number_from_str(&varint_to_string(varint.as_signed_bytes_be_slice()))
+        }
+        CqlValue::Decimal(decimal) => {
+            let (bytes, scale) = decimal.as_signed_be_bytes_slice_and_exponent();
+            number_from_str(&decimal_to_string(bytes, scale))
+        }
+        CqlValue::Float(f) => float_to_json((*f).into()),
+        CqlValue::Double(f) => float_to_json(*f),
+        CqlValue::Uuid(uuid) => Value::String(uuid.to_string()),
+        CqlValue::Timeuuid(uuid) => Value::String(uuid.to_string()),
+        CqlValue::Inet(addr) => Value::String(addr.to_string()),
+        CqlValue::Timestamp(CqlTimestamp(millis)) => match DateTime::from_timestamp_millis(*millis)
+        {
+            Some(datetime) => Value::String(datetime.format("%Y-%m-%d %H:%M:%S%.3fZ").to_string()),
+            None => Value::from(*millis),
+        },
+        CqlValue::Date(date) => match date.try_to_chrono_04_naive_date() {
+            Ok(date) => Value::String(date.to_string()),
+            Err(_) => Value::from(date.0),
+        },
+        CqlValue::Time(CqlTime(nanos)) => Value::String(time_to_string(*nanos)),
+        CqlValue::Duration(duration) => Value::String(duration_to_string(duration)),
+        CqlValue::List(elems) | CqlValue::Set(elems) | CqlValue::Vector(elems) => {
+            Value::Array(elems.iter().map(cql_value_to_json).collect())
+        }
+        CqlValue::Tuple(elems) => Value::Array(elems.iter().map(opt_cql_value_to_json).collect()),
+        CqlValue::Map(entries) => Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = match cql_value_to_json(key) {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    (key, cql_value_to_json(value))
+                })
+                .collect(),
+        ),
+        CqlValue::UserDefinedType { fields, .. } => Value::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), opt_cql_value_to_json(value)))
+                .collect(),
+        ),
+        CqlValue::Empty => Value::Null,
+    }
+}
+
+fn opt_cql_value_to_json(value: &Option<CqlValue>) -> Value {
+    value.as_ref().map_or(Value::Null, cql_value_to_json)
+}
+
+/// Converts the JSON value to a CQL value of the given type. `null` is converted to `None`.
+pub fn cql_value_from_json(
+    json: &Value,
+    typ: &ColumnType,
+) -> Result<Option<CqlValue>, JsonConversionError> {
+    if json.is_null() {
+        return Ok(None);
+    }
+    let err = |reason: &str| {
+        JsonConversionError(format!(
+            "cannot convert JSON {json} to CQL type {typ:?}: {reason}"
+        ))
+    };
+    let non_null = |json: &Value, typ: &ColumnType| {
+        cql_value_from_json(json, typ)?.ok_or_else(|| err("null elements are not allowed"))
+    };
+    let array = || json.as_array().ok_or_else(|| err("expected an array"));
+    let object = || json.as_object().ok_or_else(|| err("expected an object"));
+
+    let value = match typ {
+        ColumnType::Native(native) => {
+            native_from_json(json, native).map_err(|reason| err(&reason))?
+        }
+        ColumnType::Collection {
+            typ: CollectionType::List(elem_type),
+            ..
+        } => CqlValue::List(
+            array()?
+                .iter()
+                .map(|elem| non_null(elem, elem_type))
+                .collect::<Result<_, _>>()?,
+        ),
+        ColumnType::Collection {
+            typ: CollectionType::Set(elem_type),
+            ..
+        } => CqlValue::Set(
+            array()?
+                .iter()
+                .map(|elem| non_null(elem, elem_type))
+                .collect::<Result<_, _>>()?,
+        ),
+        ColumnType::Vector {
+            typ: elem_type,
+            dimensions,
+        } => {
+            let elems = array()?;
+            if elems.len() != *dimensions as usize {
+                return Err(err(&format!("expected {dimensions} elements")));
+            }
+            CqlValue::Vector(
+                elems
+                    .iter()
+                    .map(|elem| non_null(elem, elem_type))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        ColumnType::Collection {
+            typ: CollectionType::Map(key_type, value_type),
+            ..
+        } => CqlValue::Map(
+            object()?
+                .iter()
+                .map(|(key, value)| {
+                    // Keys of types other than strings are JSON-encoded.
+                    let key = match key_type.as_ref() {
+                        ColumnType::Native(_) => Value::String(key.clone()),
+                        _ => serde_json_1::from_str(key)
+                            .map_err(|_| err(&format!("invalid JSON in map key {key:?}")))?,
+                    };
+                    Ok((non_null(&key, key_type)?, non_null(value, value_type)?))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        ColumnType::Tuple(types) => {
+            let elems = array()?;
+            if elems.len() > types.len() {
+                return Err(err(&format!("expected at most {} elements", types.len())));
+            }
+            CqlValue::Tuple(
+                elems
+                    .iter()
+                    .zip(types)
+                    .map(|(elem, typ)| cql_value_from_json(elem, typ))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        ColumnType::UserDefinedType { definition, .. } => {
+            let fields = object()?;
+            if let Some(name) = fields.keys().find(|name| {
+                !definition
+                    .field_types
+                    .iter()
+                    .any(|(field, _)| field == *name)
+            }) {
+                return Err(err(&format!("unknown field {name}")));
+            }
+            // Missing fields are null.
+            CqlValue::UserDefinedType {
+                keyspace: definition.keyspace.to_string(),
+                name: definition.name.to_string(),
+                fields: definition
+                    .field_types
+                    .iter()
+                    .map(|(name, typ)| {
+                        let value = match fields.get(name.as_ref()) {
+                            Some(value) => cql_value_from_json(value, typ)?,
+                            None => None,
+                        };
+                        Ok((name.to_string(), value))
+                    })
+                    .collect::<Result<_, _>>()?,
+            }
+        }
+    };
+    Ok(Some(value))
+}
+
+/// Converts the row to a JSON object with column names as keys, as returned by `SELECT JSON`.
+pub fn row_to_json(row: &Row, specs: &[ColumnSpec]) -> Map<String, Value> {
+    specs
+        .iter()
+        .zip(&row.columns)
+        .map(|(spec, value)| (spec.name().to_owned(), opt_cql_value_to_json(value)))
+        .collect()
+}
+
+/// Converts the JSON object with column names as keys to a row.
+/// As in `INSERT JSON`, the columns missing from the object are null.
+pub fn row_from_json(
+    json: &Map<String, Value>,
+    specs: &[ColumnSpec],
+) -> Result<Row, JsonConversionError> {
+    if let Some(name) = json
+        .keys()
+        .find(|name| !specs.iter().any(|spec| spec.name() == *name))
+    {
+        return Err(JsonConversionError(format!("no column named {name}")));
+    }
+    let columns = specs
+        .iter()
+        .map(|spec| match json.get(spec.name()) {
+            Some(value) => cql_value_from_json(value, spec.typ()),
+            None => Ok(None),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Row { columns })
+}
+
+fn native_from_json(json: &Value, typ: &NativeType) -> Result<CqlValue, String> {
+    let string = || json.as_str().ok_or_else(|| "expected a string".to_owned());
+
+    let value = match typ {
+        NativeType::Ascii => {
+            let s = string()?;
+            if !s.is_ascii() {
+                return Err("non-ascii characters".to_owned());
+            }
+            CqlValue::Ascii(s.to_owned())
+        }
+        NativeType::Text => CqlValue::Text(string()?.to_owned()),
+        NativeType::Boolean => match json {
+            Value::Bool(b) => CqlValue::Boolean(*b),
+            Value::String(s) if s.eq_ignore_ascii_case("true") => CqlValue::Boolean(true),
+            Value::String(s) if s.eq_ignore_ascii_case("false") => CqlValue::Boolean(false),
+            _ => return Err("expected a bool".to_owned()),
+        },
+        NativeType::TinyInt => CqlValue::TinyInt(parse_int(json)?),
+        NativeType::SmallInt => CqlValue::SmallInt(parse_int(json)?),
+        NativeType::Int => CqlValue::Int(parse_int(json)?),
+        NativeType::BigInt => CqlValue::BigInt(parse_int(json)?),
+        NativeType::Counter => CqlValue::Counter(Counter(parse_int(json)?)),
+        NativeType::Varint => CqlValue::Varint(CqlVarint::from_signed_bytes_be(
+            parse_varint(&number_or_string(json)?).ok_or("invalid varint")?,
+        )),
+        NativeType::Decimal => {
+            let (bytes, scale) =
+                parse_decimal(&number_or_string(json)?).ok_or("invalid decimal")?;
+            CqlValue::Decimal(CqlDecimal::from_signed_be_bytes_and_exponent(bytes, scale))
+        }
+        NativeType::Float => CqlValue::Float(parse_float(json)? as f32),
+        NativeType::Double => CqlValue::Double(parse_float(json)?),
+        NativeType::Blob => {
+            let s = string()?;
+            let hex = s
+                .strip_prefix("0x")
+                .or_else(|| s.strip_prefix("0X"))
+                .ok_or("expected a hex string prefixed with 0x")?;
+            CqlValue::Blob(parse_hex(hex).ok_or("invalid hex string")?)
+        }
+        NativeType::Uuid => CqlValue::Uuid(parse_uuid(string()?)?),
+        NativeType::Timeuuid => CqlValue::Timeuuid(CqlTimeuuid::from(parse_uuid(string()?)?)),
+        NativeType::Inet => {
+            CqlValue::Inet(string()?.parse::<IpAddr>().map_err(|err| err.to_string())?)
+        }
+        NativeType::Timestamp => CqlValue::Timestamp(CqlTimestamp(match json {
+            Value::String(s) => match s.parse::<i64>() {
+                Ok(millis) => millis,
+                Err(_) => parse_timestamp(s).ok_or("invalid timestamp")?,
+            },
+            _ => parse_int(json)?,
+        })),
+        NativeType::Date => CqlValue::Date(match json {
+            Value::String(s) => match s.parse::<u32>() {
+                Ok(days) => CqlDate(days),
+                Err(_) => {
+                    let date = s.parse::<NaiveDate>().map_err(|err| err.to_string())?;
+                    let unix_epoch = NaiveDate::from_yo_opt(1970, 1).unwrap();
+                    let days = date.signed_duration_since(unix_epoch).num_days();
+                    // CqlDate counts days from 2^31 days before the unix epoch.
+                    CqlDate((days + (1 << 31)) as u32)
+                }
+            },
+            _ => CqlDate(parse_int(json)?),
+        }),
+        NativeType::Time => CqlValue::Time(CqlTime(match json {
+            Value::String(s) => match s.parse::<i64>() {
+                Ok(nanos) => nanos,
+                Err(_) => {
+                    let time = NaiveTime::parse_from_str(s, "%H:%M:%S%.f")
+                        .map_err(|err| err.to_string())?;
+                    time.num_seconds_from_midnight() as i64 * 1_000_000_000
+                        + time.nanosecond() as i64
+                }
+            },
+            _ => parse_int(json)?,
+        })),
+        NativeType::Duration => {
+            CqlValue::Duration(parse_duration(string()?).ok_or("invalid duration")?)
+        }
+    };
+    Ok(value)
+}
+
+fn number_or_string(json: &Value) -> Result<String, String> {
+    match json {
+        Value::Number(n) => Ok(n.to_string()),
+        Value::String(s) => Ok(s.trim().to_owned()),
+        _ => Err("expected a number".to_owned()),
+    }
+}
+
+fn parse_int<T: FromStr>(json: &Value) -> Result<T, String> {
+    number_or_string(json)?
+        .parse()
+        .map_err(|_| "expected an integer in the range of the type".to_owned())
+}
+
+fn parse_float(json: &Value) -> Result<f64, String> {
+    match json {
+        Value::Number(n) => n.as_f64().ok_or_else(|| "invalid number".to_owned()),
+        Value::String(s) => s.trim().parse().map_err(|_| "invalid number".to_owned()),
+        _ => Err("expected a number".to_owned()),
+    }
+}
+
+fn parse_uuid(s: &str) -> Result<Uuid, String> {
+    Uuid::parse_str(s).map_err(|err| err.to_string())
+}
+
+fn number_from_str(s: &str) -> Value {
+    // Without the `arbitrary_precision` feature of serde_json, numbers out of
+    // the range of `i64`/`u64` are parsed as `f64`, which may lose precision.
+    // Such numbers are kept as strings, so that they are converted losslessly.
+    match Number::from_str(s) {
+        Ok(number) if number.to_string() == s => Value::Number(number),
+        _ => Value::String(s.to_owned()),
+    }
+}
+
+fn float_to_json(f: f64) -> Value {
+    match Number::from_f64(f) {
+        Some(number) => Value::Number(number),
+        None if f.is_nan() => Value::String("NaN".to_owned()),
+        None if f > 0.0 => Value::String("Infinity".to_owned()),
+        None => Value::String("-Infinity".to_owned()),
+    }
+}
+
+fn blob_to_string(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(2 + 2 * bytes.len());
+    s.push_str("0x");
+    for byte in bytes {
+        s.push_str(&format!("{byte:02x}"));
+    }
+    s
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn time_to_string(nanos: i64) -> String {
+    let secs = nanos.div_euclid(1_000_000_000);
+    format!(
+        "{:02}:{:02}:{:02}.{:09}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        nanos.rem_euclid(1_000_000_000)
+    )
+}
+
+/// Parses the timestamp in one of the formats accepted by Cassandra, e.g. `2024-01-02 03:04:05.678+0000`,
+/// `2024-01-02T03:04:05Z` or `2024-01-02`. Timestamps without a timezone are in UTC.
+fn parse_timestamp(s: &str) -> Option<i64> {
+    let s = s.trim().replacen(' ', "T", 1);
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(&s) {
+        return Some(datetime.timestamp_millis());
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f%z", "%Y-%m-%dT%H:%M%z"] {
+        if let Ok(datetime) = DateTime::parse_from_str(&s, format) {
+            return Some(datetime.timestamp_millis());
+        }
+    }
+    let s = s.strip_suffix(['Z', 'z']).unwrap_or(&s);
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(s, format) {
+            return Some(datetime.and_utc().timestamp_millis());
+        }
+    }
+    NaiveDate::from_str(s)
+        .ok()
+        .map(|date| date.and_time(NaiveTime::MIN).and_utc().timestamp_millis())
+}
+
+const NANOS_PER_UNIT: [(&str, i64); 6] = [
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+/// Formats the duration as Cassandra does, e.g. `1y2mo3d4h5m6s7ms8us9ns`.
+fn duration_to_string(duration: &CqlDuration) -> String {
+    let mut s = String::new();
+    if duration.months < 0 || duration.days < 0 || duration.nanoseconds < 0 {
+        s.push('-');
+    }
+    let mut push = |value: u64, unit: &str| {
+        if value != 0 {
+            s.push_str(&value.to_string());
+            s.push_str(unit);
+        }
+    };
+    let months = duration.months.unsigned_abs() as u64;
+    push(months / 12, "y");
+    push(months % 12, "mo");
+    push(duration.days.unsigned_abs() as u64, "d");
+    let mut nanos = duration.nanoseconds.unsigned_abs();
+    for (unit, unit_nanos) in NANOS_PER_UNIT {
+        push(nanos / unit_nanos as u64, unit);
+        nanos %= unit_nanos as u64;
+    }
+    if s.is_empty() || s == "-" {
+        s = "0s".to_owned();
+    }
+    s
+}
+
+/// Parses the duration in the format returned by [duration_to_string],
+/// also accepting weeks (`w`) and `µs`.
+fn parse_duration(s: &str) -> Option<CqlDuration> {
+    let (negative, mut rest) = match s.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.trim()),
+    };
+    if rest.is_empty() {
+        return None;
+    }
+    let (mut months, mut days, mut nanos) = (0i32, 0i32, 0i64);
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let value: i64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = rest[..unit_len].to_ascii_lowercase();
+        rest = &rest[unit_len..];
+        match unit.as_str() {
+            "y" => months = months.checked_add(i32::try_from(value.checked_mul(12)?).ok()?)?,
+            "mo" => months = months.checked_add(i32::try_from(value).ok()?)?,
+            "w" => days = days.checked_add(i32::try_from(value.checked_mul(7)?).ok()?)?,
+            "d" => days = days.checked_add(i32::try_from(value).ok()?)?,
+            "µs" => nanos = nanos.checked_add(value.checked_mul(1_000)?)?,
+            unit => {
+                let (_, unit_nanos) = NANOS_PER_UNIT.iter().find(|(name, _)| *name == unit)?;
+                nanos = nanos.checked_add(value.checked_mul(*unit_nanos)?)?;
+            }
+        }
+    }
+    Some(if negative {
+        CqlDuration {
+            months: -months,
+            days: -days,
+            nanoseconds: -nanos,
+        }
+    } else {
+        CqlDuration {
+            months,
+            days,
+            nanoseconds: nanos,
+        }
+    })
+}
+
+/// Parses the decimal integer into a big-endian two's complement number.
+fn parse_varint(s: &str) -> Option<Vec<u8>> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    if digits.is_empty() || !digits.bytes().all(|digit| digit.is_ascii_digit()) {
+        return None;
+    }
+    let mut bytes = vec![0u8];
+    for digit in digits.bytes() {
+        let mut carry = (digit - b'0') as u32;
+        for byte in bytes.iter_mut().rev() {
+            let current = *byte as u32 * 10 + carry;
+            *byte = current as u8;
+            carry = current >> 8;
+        }
+        if carry > 0 {
+            bytes.insert(0, carry as u8);
+        }
+    }
+    // Make room for the sign bit.
+    if bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0);
+    }
+    if negative {
+        negate(&mut bytes);
+    }
+    // Strip the redundant leading bytes.
+    let start = (0..bytes.len() - 1)
+        .find(|&i| {
+            !(bytes[i] == 0 && bytes[i + 1] & 0x80 == 0
+                || bytes[i] == 0xff && bytes[i + 1] & 0x80 != 0)
+        })
+        .unwrap_or(bytes.len() - 1);
+    bytes.drain(..start);
+    Some(bytes)
+}
+
+/// Parses the decimal number, e.g. `-12.34e5`, into the unscaled value and the scale.
+fn parse_decimal(s: &str) -> Option<(Vec<u8>, i32)> {
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(index) => (&s[..index], s[index + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if !fraction.bytes().all(|digit| digit.is_ascii_digit()) {
+        return None;
+    }
+    let scale = i32::try_from(fraction.len() as i64 - exponent).ok()?;
+    let unscaled = parse_varint(&format!("{integer}{fraction}"))?;
+    Some((unscaled, scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json_1::json;
+
+    use super::{cql_value_from_json, cql_value_to_json, row_from_json, row_to_json};
+    use crate::frame::response::result::{
+        CollectionType, ColumnSpec, ColumnType, NativeType, TableSpec, UserDefinedType,
+    };
+    use crate::value::{
+        CqlDate, CqlDecimal, CqlDuration, CqlTime, CqlTimestamp, CqlValue, CqlVarint, Row,
+    };
+
+    fn round_trip(value: CqlValue, typ: ColumnType, expected: serde_json_1::Value) {
+        let json = cql_value_to_json(&value);
+        assert_eq!(json, expected);
+        assert_eq!(cql_value_from_json(&json, &typ).unwrap(), Some(value));
+    }
+
+    #[test]
+    fn test_native_types() {
+        round_trip(
+            CqlValue::Text("abc".to_owned()),
+            ColumnType::Native(NativeType::Text),
+            json!("abc"),
+        );
+        round_trip(
+            CqlValue::BigInt(-5),
+            ColumnType::Native(NativeType::BigInt),
+            json!(-5),
+        );
+        round_trip(
+            CqlValue::Blob(vec![0x00, 0xab, 0xff]),
+            ColumnType::Native(NativeType::Blob),
+            json!("0x00abff"),
+        );
+        round_trip(
+            CqlValue::Double(1.5),
+            ColumnType::Native(NativeType::Double),
+            json!(1.5),
+        );
+        round_trip(
+            CqlValue::Timestamp(CqlTimestamp(1_704_164_645_678)),
+            ColumnType::Native(NativeType::Timestamp),
+            json!("2024-01-02 03:04:05.678Z"),
+        );
+        round_trip(
+            CqlValue::Date(CqlDate((1 << 31) - 1)),
+            ColumnType::Native(NativeType::Date),
+            json!("1969-12-31"),
+        );
+        round_trip(
+            CqlValue::Time(CqlTime(3_600_000_000_001)),
+            ColumnType::Native(NativeType::Time),
+            json!("01:00:00.000000001"),
+        );
+        round_trip(
+            CqlValue::Duration(CqlDuration {
+                months: 14,
+                days: 3,
+                nanoseconds: 3_661_001_000_001,
+            }),
+            ColumnType::Native(NativeType::Duration),
+            json!("1y2mo3d1h1m1s1ms1ns"),
+        );
+        round_trip(
+            CqlValue::Duration(CqlDuration {
+                months: 0,
+                days: -1,
+                nanoseconds: 0,
+            }),
+            ColumnType::Native(NativeType::Duration),
+            json!("-1d"),
+        );
+
+        let nan = cql_value_to_json(&CqlValue::Float(f32::NAN));
+        assert_eq!(nan, json!("NaN"));
+        assert_matches::assert_matches!(
+            cql_value_from_json(&nan, &ColumnType::Native(NativeType::Float)),
+            Ok(Some(CqlValue::Float(f))) if f.is_nan()
+        );
+    }
+
+    #[test]
+    fn test_varint_and_decimal() {
+        for (n, bytes) in [
+            (0i64, vec![0x00]),
+            (127, vec![0x7f]),
+            (128, vec![0x00, 0x80]),
+            (-128, vec![0x80]),
+            (-129, vec![0xff, 0x7f]),
+            (i64::MIN, i64::MIN.to_be_bytes().to_vec()),
+        ] {
+            round_trip(
+                CqlValue::Varint(CqlVarint::from_signed_bytes_be(bytes)),
+                ColumnType::Native(NativeType::Varint),
+                json!(n),
+            );
+        }
+        // Varints out of range of i64 are accepted as strings.
+        let big = "123456789012345678901234567890";
+        let Some(CqlValue::Varint(varint)) =
+            cql_value_from_json(&json!(big), &ColumnType::Native(NativeType::Varint)).unwrap()
+        else {
+            panic!("expected a varint");
+        };
+        assert_eq!(
+            crate::value::varint_to_string(varint.as_signed_bytes_be_slice()),
+            big
+        );
+        // ...and converted back to strings, as they don't fit in a JSON number losslessly.
+        round_trip(
+            CqlValue::Varint(varint),
+            ColumnType::Native(NativeType::Varint),
+            json!(big),
+        );
+        round_trip(
+            CqlValue::Varint(CqlVarint::from_signed_bytes_be(
+                [&[0x00][..], &u64::MAX.to_be_bytes()].concat(),
+            )),
+            ColumnType::Native(NativeType::Varint),
+            json!(u64::MAX),
+        );
+
+        round_trip(
+            CqlValue::Decimal(CqlDecimal::from_signed_be_bytes_and_exponent(vec![0xfb], 2)),
+            ColumnType::Native(NativeType::Decimal),
+            json!(-0.05),
+        );
+        // Decimals which can't be represented exactly by `f64` are converted to strings.
+        let precise = "3.14159265358979323846264338327950288";
+        let decimal =
+            cql_value_from_json(&json!(precise), &ColumnType::Native(NativeType::Decimal))
+                .unwrap()
+                .unwrap();
+        round_trip(
+            decimal,
+            ColumnType::Native(NativeType::Decimal),
+            json!(precise),
+        );
+        assert_eq!(
+            cql_value_from_json(&json!("12.5e3"), &ColumnType::Native(NativeType::Decimal))
+                .unwrap(),
+            Some(CqlValue::Decimal(
+                CqlDecimal::from_signed_be_bytes_and_exponent(vec![0x7d], -2)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_composite_types() {
+        round_trip(
+            CqlValue::Map(vec![
+                (CqlValue::Int(1), CqlValue::Text("a".to_owned())),
+                (CqlValue::Int(2), CqlValue::Text("b".to_owned())),
+            ]),
+            ColumnType::Collection {
+                frozen: false,
+                typ: CollectionType::Map(
+                    Box::new(ColumnType::Native(NativeType::Int)),
+                    Box::new(ColumnType::Native(NativeType::Text)),
+                ),
+            },
+            json!({"1": "a", "2": "b"}),
+        );
+        round_trip(
+            CqlValue::Map(vec![(
+                CqlValue::Tuple(vec![Some(CqlValue::Int(1)), None]),
+                CqlValue::Boolean(true),
+            )]),
+            ColumnType::Collection {
+                frozen: false,
+                typ: CollectionType::Map(
+                    Box::new(ColumnType::Tuple(vec![
+                        ColumnType::Native(NativeType::Int),
+                        ColumnType::Native(NativeType::Int),
+                    ])),
+                    Box::new(ColumnType::Native(NativeType::Boolean)),
+                ),
+            },
+            json!({"[1,null]": true}),
+        );
+
+        let udt = ColumnType::UserDefinedType {
+            frozen: false,
+            definition: Arc::new(UserDefinedType {
+                name: "udt".into(),
+                keyspace: "ks".into(),
+                field_types: vec![
+                    ("a".into(), ColumnType::Native(NativeType::Int)),
+                    (
+                        "b".into(),
+                        ColumnType::Collection {
+                            frozen: false,
+                            typ: CollectionType::List(Box::new(ColumnType::Native(
+                                NativeType::Text,
+                            ))),
+                        },
+                    ),
+                ],
+            }),
+        };
+        round_trip(
+            CqlValue::UserDefinedType {
+                keyspace: "ks".to_owned(),
+                name: "udt".to_owned(),
+                fields: vec![
+                    ("a".to_owned(), Some(CqlValue::Int(1))),
+                    (
+                        "b".to_owned(),
+                        Some(CqlValue::List(vec![CqlValue::Text("x".to_owned())])),
+                    ),
+                ],
+            },
+            udt.clone(),
+            json!({"a": 1, "b": ["x"]}),
+        );
+        // Missing fields are null, unknown fields are rejected.
+        assert_eq!(
+            cql_value_from_json(&json!({"a": 1}), &udt).unwrap(),
+            Some(CqlValue::UserDefinedType {
+                keyspace: "ks".to_owned(),
+                name: "udt".to_owned(),
+                fields: vec![
+                    ("a".to_owned(), Some(CqlValue::Int(1))),
+                    ("b".to_owned(), None)
+                ],
+            })
+        );
+        cql_value_from_json(&json!({"c": 1}), &udt).unwrap_err();
+    }
+
+    #[test]
+    fn test_lenient_parsing() {
+        let int = ColumnType::Native(NativeType::Int);
+        assert_eq!(
+            cql_value_from_json(&json!("42"), &int).unwrap(),
+            Some(CqlValue::Int(42))
+        );
+        cql_value_from_json(&json!(1.5), &int).unwrap_err();
+        cql_value_from_json(&json!(i64::MAX), &int).unwrap_err();
+
+        let timestamp = ColumnType::Native(NativeType::Timestamp);
+        for s in [
+            "2024-01-02 03:04:05.678Z",
+            "2024-01-02T03:04:05.678+00:00",
+            "2024-01-02 04:04:05.678+0100",
+            "2024-01-02T03:04:05.678",
+        ] {
+            assert_eq!(
+                cql_value_from_json(&json!(s), &timestamp).unwrap(),
+                Some(CqlValue::Timestamp(CqlTimestamp(1_704_164_645_678))),
+                "{s}"
+            );
+        }
+        assert_eq!(
+            cql_value_from_json(&json!("2024-01-02"), &timestamp).unwrap(),
+            Some(CqlValue::Timestamp(CqlTimestamp(1_704_153_600_000)))
+        );
+        assert_eq!(
+            cql_value_from_json(&json!(1000), &timestamp).unwrap(),
+            Some(CqlValue::Timestamp(CqlTimestamp(1000)))
+        );
+    }
+
+    #[test]
+    fn test_rows() {
+        let specs = [
+            ColumnSpec::borrowed(
+                "a",
+                ColumnType::Native(NativeType::Int),
+                TableSpec::borrowed("ks", "tbl"),
+            ),
+            ColumnSpec::borrowed(
+                "b",
+                ColumnType::Native(NativeType::Text),
+                TableSpec::borrowed("ks", "tbl"),
+            ),
+        ];
+        let row = Row {
+            columns: vec![Some(CqlValue::Int(1)), None],
+        };
+        let json = row_to_json(&row, &specs);
+        assert_eq!(
+            serde_json_1::Value::Object(json.clone()),
+            json!({"a": 1, "b": null})
+        );
+        assert_eq!(row_from_json(&json, &specs).unwrap(), row);
+
+        let json = json!({"a": 1});
+        assert_eq!(
+            row_from_json(json.as_object().unwrap(), &specs).unwrap(),
+            row
+        );
+        let json = json!({"c": 1});
+        row_from_json(json.as_object().unwrap(), &specs).unwrap_err();
+    }
+}
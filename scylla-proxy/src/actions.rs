@@ -1,4 +1,4 @@
-use std::{fmt, sync::Arc, time::Duration};
+use std::{cell::OnceCell, fmt, sync::Arc, time::Duration};
 
 use bytes::Bytes;
 use rand::{Rng, RngCore};
@@ -9,9 +9,13 @@ use crate::setup_tracing;
 
 use crate::{
     frame::{FrameOpcode, FrameParams, RequestFrame, RequestOpcode, ResponseFrame, ResponseOpcode},
+    statement::{self, PreparedStatements, RequestInfo},
     TargetShard,
 };
+use scylla_cql::frame::protocol_features::ProtocolFeatures;
+use scylla_cql::frame::request::batch::BatchType;
 use scylla_cql::frame::response::error::DbError;
+use scylla_cql::frame::types::Consistency;
 
 /// Specifies when an associated [Reaction] will be performed.
 /// Conditions are subject to logic, with `not()`, `and()` and `or()`
@@ -51,6 +55,33 @@ pub enum Condition {
 
     // True if any REGISTER was sent on this connection. Useful to filter out control connection messages.
     ConnectionRegisteredAnyEvent,
+
+    /// True iff the request carries a CQL statement containing the given string, with case-insensitive
+    /// comparison (ASCII only). The statement is carried by QUERY and PREPARE requests, by EXECUTE requests
+    /// of statements prepared through the proxy, and by BATCH requests with any such statement.
+    StatementContains(String),
+
+    /// True iff the request is an EXECUTE of the prepared statement with the given id,
+    /// or a BATCH containing it.
+    PreparedId(Bytes),
+
+    /// True iff the request carries a statement (see [Condition::StatementContains]) operating
+    /// on the given table: SELECT, INSERT, UPDATE, DELETE, TRUNCATE or CREATE/ALTER/DROP TABLE.
+    /// The names are case-insensitive, unless enclosed in double quotes. If the keyspace is not given,
+    /// or the statement does not name it (relying on `USE`), only the table names are compared.
+    Table {
+        keyspace: Option<String>,
+        table: String,
+    },
+
+    /// True iff the request is a QUERY, EXECUTE or BATCH with the given consistency.
+    Consistency(Consistency),
+
+    /// True iff the request is a BATCH of the given type.
+    BatchType(BatchType),
+
+    /// True iff the response is an ERROR with the given error code (see [DbError::code]).
+    ResponseErrorCode(i32),
 }
 
 /// The context in which [`Conditions`](Condition) are evaluated.
@@ -59,6 +90,23 @@ pub(crate) struct EvaluationContext {
     pub(crate) connection_has_events: bool,
     pub(crate) opcode: FrameOpcode,
     pub(crate) frame_body: Bytes,
+    pub(crate) prepared_statements: Arc<PreparedStatements>,
+    // Parsed on the first evaluation of a condition that needs it.
+    pub(crate) request_info: OnceCell<RequestInfo>,
+}
+
+impl EvaluationContext {
+    fn request_info(&self, condition: &str) -> &RequestInfo {
+        match self.opcode {
+            FrameOpcode::Request(opcode) => self.request_info.get_or_init(|| {
+                RequestInfo::parse(opcode, &self.frame_body, &self.prepared_statements)
+            }),
+            FrameOpcode::Response(_) => panic!(
+                "Invalid type applied in rule condition: {} in cluster context",
+                condition
+            ),
+        }
+    }
 }
 
 impl Condition {
@@ -116,7 +164,52 @@ impl Condition {
                 val
             },
 
-            Condition::ConnectionRegisteredAnyEvent => ctx.connection_has_events
+            Condition::ConnectionRegisteredAnyEvent => ctx.connection_has_events,
+
+            Condition::StatementContains(pattern) => ctx
+                .request_info("statement")
+                .statements
+                .iter()
+                .any(|statement| {
+                    statement
+                        .to_ascii_lowercase()
+                        .contains(&pattern.to_ascii_lowercase())
+                }),
+
+            Condition::PreparedId(id) => ctx.request_info("prepared id").prepared_ids.contains(id),
+
+            Condition::Table { keyspace, table } => {
+                let keyspace = keyspace.as_deref().map(statement::normalize_name);
+                let table = statement::normalize_name(table);
+                ctx.request_info("table")
+                    .statements
+                    .iter()
+                    .filter_map(|statement| statement::statement_table(statement))
+                    .any(|name| {
+                        name.table == table
+                            && match (&keyspace, &name.keyspace) {
+                                (Some(expected), Some(actual)) => expected == actual,
+                                _ => true,
+                            }
+                    })
+            }
+
+            Condition::Consistency(consistency) => {
+                ctx.request_info("consistency").consistency == Some(*consistency)
+            }
+
+            Condition::BatchType(batch_type) => {
+                ctx.request_info("batch type").batch_type == Some(*batch_type)
+            }
+
+            Condition::ResponseErrorCode(code) => match ctx.opcode {
+                FrameOpcode::Request(_) => panic!(
+                    "Invalid type applied in rule condition: cluster response error code in driver context"
+                ),
+                FrameOpcode::Response(opcode) => {
+                    statement::error_code(opcode, &ctx.frame_body) == Some(*code)
+                }
+            },
         }
     }
 
@@ -135,6 +228,20 @@ impl Condition {
     pub fn or(self, c2: Self) -> Self {
         Self::Or(Box::new(self), Box::new(c2))
     }
+
+    /// A convenience function for creating [Condition::Table] variant.
+    pub fn table(keyspace: Option<&str>, table: &str) -> Self {
+        Self::Table {
+            keyspace: keyspace.map(str::to_owned),
+            table: table.to_owned(),
+        }
+    }
+
+    /// A convenience function for creating [Condition::ResponseErrorCode] variant
+    /// matching the code of the given error.
+    pub fn response_error(error: &DbError) -> Self {
+        Self::ResponseErrorCode(error.code(&ProtocolFeatures::default()))
+    }
 }
 
 /// Just a trait to unify API of both [RequestReaction] and [ResponseReaction].
@@ -738,8 +845,174 @@ fn condition_case_insensitive_matching() {
         opcode: FrameOpcode::Request(RequestOpcode::Options),
         frame_body: Bytes::from_static(b"\0\0x{0x223}Cassandra'sINEFFICIENCY\x12\x31"),
         connection_has_events: false,
+        prepared_statements: Default::default(),
+        request_info: Default::default(),
     };
 
     assert!(condition_matching.eval(&ctx));
     assert!(!condition_nonmatching.eval(&ctx));
 }
+
+#[test]
+fn condition_statement_matching() {
+    use scylla_cql::frame::request::batch::{Batch, BatchStatement};
+    use scylla_cql::frame::request::{Execute, Query, SerializableRequest};
+    use scylla_cql::serialize::row::SerializedValues;
+    use std::borrow::Cow;
+
+    use crate::statement::PreparedTracker;
+
+    setup_tracing();
+    let prepared_statements = Arc::new(PreparedStatements::default());
+    let ctx = |request: &dyn Fn(&mut Vec<u8>), opcode| {
+        let mut body = Vec::new();
+        request(&mut body);
+        EvaluationContext {
+            connection_seq_no: 0,
+            opcode: FrameOpcode::Request(opcode),
+            frame_body: body.into(),
+            connection_has_events: false,
+            prepared_statements: prepared_statements.clone(),
+            request_info: Default::default(),
+        }
+    };
+    let query_ctx = |contents: &'static str, consistency| {
+        ctx(
+            &|buf| {
+                Query {
+                    contents: Cow::Borrowed(contents),
+                    parameters: scylla_cql::frame::request::query::QueryParameters {
+                        consistency,
+                        ..Default::default()
+                    },
+                }
+                .serialize(buf)
+                .unwrap()
+            },
+            RequestOpcode::Query,
+        )
+    };
+    let execute_ctx = |id: &'static [u8], consistency| {
+        ctx(
+            &|buf| {
+                Execute {
+                    id: Bytes::from_static(id),
+                    parameters: scylla_cql::frame::request::query::QueryParameters {
+                        consistency,
+                        ..Default::default()
+                    },
+                }
+                .serialize(buf)
+                .unwrap()
+            },
+            RequestOpcode::Execute,
+        )
+    };
+
+    let insert = query_ctx("INSERT INTO ks.Tab (a) VALUES (1)", Consistency::Quorum);
+    let select = query_ctx("SELECT * FROM tab WHERE a = 1", Consistency::One);
+    assert!(Condition::StatementContains("insert into".to_owned()).eval(&insert));
+    assert!(!Condition::StatementContains("insert into".to_owned()).eval(&select));
+    assert!(Condition::table(Some("ks"), "TAB").eval(&insert));
+    assert!(!Condition::table(Some("other_ks"), "tab").eval(&insert));
+    assert!(!Condition::table(Some("ks"), "\"TAB\"").eval(&insert));
+    // The keyspace of the statement is not known.
+    assert!(Condition::table(Some("ks"), "tab").eval(&select));
+    let mut writes_at_quorum = Condition::table(None, "tab")
+        .and(Condition::StatementContains("INSERT".to_owned()))
+        .and(Condition::Consistency(Consistency::Quorum));
+    assert!(writes_at_quorum.eval(&insert));
+    assert!(!writes_at_quorum.eval(&select));
+
+    // EXECUTE is matched by the statement only if it was prepared through the proxy.
+    let mut by_statement = Condition::table(Some("ks"), "t");
+    let mut by_id = Condition::PreparedId(Bytes::from_static(b"id"));
+    let execute = execute_ctx(b"id", Consistency::Quorum);
+    assert!(!by_statement.eval(&execute));
+    assert!(by_id.eval(&execute));
+    assert!(Condition::Consistency(Consistency::Quorum).eval(&execute));
+
+    let tracker = PreparedTracker::new(prepared_statements.clone());
+    let params = FrameParams {
+        version: 0x04,
+        flags: 0,
+        stream: 3,
+    };
+    let mut prepare_body = Vec::new();
+    scylla_cql::frame::types::write_long_string("UPDATE ks.t SET a = ?", &mut prepare_body)
+        .unwrap();
+    tracker.track_request(&RequestFrame {
+        params,
+        opcode: RequestOpcode::Prepare,
+        body: prepare_body.into(),
+    });
+    let mut prepared_body = Vec::new();
+    scylla_cql::frame::types::write_int(0x0004, &mut prepared_body);
+    scylla_cql::frame::types::write_short_bytes(b"id", &mut prepared_body).unwrap();
+    tracker.track_response(&ResponseFrame {
+        params: params.for_response(),
+        opcode: ResponseOpcode::Result,
+        body: prepared_body.into(),
+    });
+    let execute = execute_ctx(b"id", Consistency::Quorum);
+    assert!(by_statement.eval(&execute));
+    assert!(Condition::StatementContains("set a".to_owned()).eval(&execute));
+    assert!(!by_id.eval(&execute_ctx(b"other_id", Consistency::Quorum)));
+
+    let batch = ctx(
+        &|buf| {
+            Batch {
+                statements: Cow::Owned(vec![
+                    BatchStatement::Query {
+                        text: Cow::Borrowed("INSERT INTO ks.other (a) VALUES (1)"),
+                    },
+                    BatchStatement::Prepared {
+                        id: Cow::Borrowed(b"id"),
+                    },
+                ]),
+                batch_type: BatchType::Unlogged,
+                consistency: Consistency::LocalQuorum,
+                serial_consistency: None,
+                timestamp: None,
+                values: vec![SerializedValues::new(), SerializedValues::new()],
+            }
+            .serialize(buf)
+            .unwrap()
+        },
+        RequestOpcode::Batch,
+    );
+    assert!(by_statement.eval(&batch));
+    assert!(by_id.eval(&batch));
+    assert!(Condition::table(None, "other").eval(&batch));
+    assert!(Condition::BatchType(BatchType::Unlogged).eval(&batch));
+    assert!(!Condition::BatchType(BatchType::Logged).eval(&batch));
+    assert!(Condition::Consistency(Consistency::LocalQuorum).eval(&batch));
+}
+
+#[test]
+fn condition_response_error_code_matching() {
+    setup_tracing();
+    let params = FrameParams {
+        version: 0x04,
+        flags: 0,
+        stream: 0,
+    };
+    let ctx = |frame: ResponseFrame| EvaluationContext {
+        connection_seq_no: 0,
+        opcode: FrameOpcode::Response(frame.opcode),
+        frame_body: frame.body,
+        connection_has_events: false,
+        prepared_statements: Default::default(),
+        request_info: Default::default(),
+    };
+    let overloaded = ctx(ResponseFrame::forged_error(params, DbError::Overloaded, None).unwrap());
+    let timeout =
+        ctx(ResponseFrame::forged_error(params, example_db_errors::write_timeout(), None).unwrap());
+    let ready = ctx(ResponseFrame::forged_ready(params));
+
+    let mut condition = Condition::response_error(&DbError::Overloaded);
+    assert!(condition.eval(&overloaded));
+    assert!(!condition.eval(&timeout));
+    assert!(!condition.eval(&ready));
+    assert!(Condition::response_error(&example_db_errors::write_timeout()).eval(&timeout));
+}
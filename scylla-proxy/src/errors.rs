@@ -39,6 +39,10 @@ pub enum DoorkeeperError {
     ObtainingShardNumberParseShardNumber(std::num::ParseIntError),
     #[error("0 as number of shards!")]
     ObtainingShardNumberGotZero,
+    #[error("TLS handshake with driver {0} failed: {1}")]
+    DriverTlsHandshake(SocketAddr, std::io::Error),
+    #[error("TLS handshake with node {0} failed: {1}")]
+    NodeTlsHandshake(SocketAddr, std::io::Error),
 }
 
 #[derive(Debug, Error)]
@@ -49,6 +53,27 @@ pub enum WorkerError {
     NodeDisconnected(SocketAddr),
 }
 
+#[derive(Debug, Error)]
+pub enum RecordingError {
+    #[error("Could not read or write the recording: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Not a proxy recording")]
+    BadMagic,
+    #[error("Unsupported recording format version {0}")]
+    UnsupportedVersion(u16),
+    #[error("Malformed recording: {0}")]
+    Malformed(String),
+}
+
+#[cfg(feature = "rustls-023")]
+#[derive(Debug, Error)]
+pub enum TlsConfigError {
+    #[error("Could not parse PEM: {0}")]
+    Pem(#[from] rustls::pki_types::pem::Error),
+    #[error("Invalid TLS configuration: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
 #[derive(Debug, Error)]
 pub enum ProxyError {
     #[error("Doorkeeper failed: {0}")]
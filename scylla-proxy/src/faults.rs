@@ -0,0 +1,95 @@
+//! Faults of the network between the driver and the nodes, simulated by the proxy
+//! on the level of whole connections rather than single frames.
+
+use std::num::NonZeroU64;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::io::{AsyncWrite, AsyncWriteExt as _};
+
+use crate::frame::{ResponseFrame, HEADER_SIZE};
+use crate::proxy::CompressionReader;
+use crate::stream::ConnectionResetter;
+
+/// The network faults currently simulated on all the connections to a single node.
+/// Shared by the [RunningNode](crate::proxy::RunningNode) handle with the workers,
+/// so that the faults can be changed at runtime, also affecting the already open connections.
+#[derive(Default)]
+pub(crate) struct NetworkFaults {
+    // In bytes per second, 0 meaning no limit.
+    bandwidth_limit: AtomicU64,
+    half_open: AtomicBool,
+    partitioned: AtomicBool,
+    reset_mid_frame: AtomicBool,
+}
+
+impl NetworkFaults {
+    pub(crate) fn set_bandwidth_limit(&self, bytes_per_second: Option<NonZeroU64>) {
+        self.bandwidth_limit.store(
+            bytes_per_second.map_or(0, NonZeroU64::get),
+            Ordering::Relaxed,
+        );
+    }
+
+    pub(crate) fn set_half_open(&self, half_open: bool) {
+        self.half_open.store(half_open, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_partitioned(&self, partitioned: bool) {
+        self.partitioned.store(partitioned, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_reset_mid_frame(&self, reset_mid_frame: bool) {
+        self.reset_mid_frame
+            .store(reset_mid_frame, Ordering::Relaxed);
+    }
+
+    pub(crate) fn clear(&self) {
+        self.set_bandwidth_limit(None);
+        self.set_half_open(false);
+        self.set_partitioned(false);
+        self.set_reset_mid_frame(false);
+    }
+
+    /// Whether the frames sent by the driver should be dropped instead of being passed to the node.
+    pub(crate) fn drops_requests(&self) -> bool {
+        self.partitioned.load(Ordering::Relaxed)
+    }
+
+    /// Whether the frames destined for the driver should be dropped instead of being sent.
+    pub(crate) fn drops_responses(&self) -> bool {
+        self.partitioned.load(Ordering::Relaxed) || self.half_open.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn resets_mid_frame(&self) -> bool {
+        self.reset_mid_frame.load(Ordering::Relaxed)
+    }
+
+    /// Waits for as long as it takes to transfer a frame with the body of given length
+    /// under the bandwidth limit.
+    pub(crate) async fn throttle(&self, body_len: usize) {
+        let limit = self.bandwidth_limit.load(Ordering::Relaxed);
+        if limit != 0 {
+            let len = HEADER_SIZE + body_len;
+            tokio::time::sleep(Duration::from_secs_f64(len as f64 / limit as f64)).await;
+        }
+    }
+}
+
+/// Writes the first half of the serialized frame and then makes the connection reset,
+/// so that the peer observes a TCP RST in the middle of the frame.
+///
+/// The connection is reset once it gets closed, i.e. when both its halves are dropped.
+pub(crate) async fn write_half_and_reset(
+    write_half: &mut (impl AsyncWrite + Unpin),
+    response: &ResponseFrame,
+    compression: &CompressionReader,
+    resetter: &ConnectionResetter,
+) -> Result<(), tokio::io::Error> {
+    let mut frame = Vec::new();
+    response.write(&mut frame, compression).await?;
+    write_half.write_all(&frame[..frame.len() / 2]).await?;
+    write_half.flush().await?;
+    resetter.reset_on_drop();
+    Ok(())
+}
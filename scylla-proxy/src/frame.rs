@@ -14,7 +14,7 @@ use tracing::warn;
 use crate::errors::ReadFrameError;
 use crate::proxy::CompressionReader;
 
-const HEADER_SIZE: usize = 9;
+pub(crate) const HEADER_SIZE: usize = 9;
 
 // Parts of the frame header which are not determined by the request/response type.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
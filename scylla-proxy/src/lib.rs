@@ -1,7 +1,13 @@
 mod actions;
 mod errors;
+mod faults;
 mod frame;
+mod mock;
 mod proxy;
+mod recording;
+mod statement;
+mod stream;
+mod tls;
 
 pub type TargetShard = u16;
 
@@ -9,9 +15,15 @@ pub use actions::{
     example_db_errors, Action, Condition, Reaction, RequestReaction, RequestRule, ResponseReaction,
     ResponseRule,
 };
-pub use errors::{DoorkeeperError, ProxyError, WorkerError};
+#[cfg(feature = "rustls-023")]
+pub use errors::TlsConfigError;
+pub use errors::{DoorkeeperError, ProxyError, RecordingError, WorkerError};
 pub use frame::{RequestFrame, RequestOpcode, ResponseFrame, ResponseOpcode};
+pub use mock::{MockCluster, MockNode, MockResponse, MockRows, MockRule};
 pub use proxy::{Node, Proxy, RunningProxy, ShardAwareness};
+pub use recording::{RecordedConnection, RecordedExchange, Recording};
+#[cfg(feature = "rustls-023")]
+pub use tls::{client_tls_config, server_tls_config};
 
 pub use proxy::get_exclusive_local_address;
 
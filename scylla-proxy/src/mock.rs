@@ -0,0 +1,1256 @@
+//! In-process fake nodes, answering the driver's requests without any ScyllaDB instance.
+//!
+//! A [MockCluster] consists of [MockNode]s, each listening on its own address
+//! (as a [Node] in dry mode does). Unlike dry mode nodes, mock nodes answer
+//! the requests that are not handled by their [RequestRule]s:
+//! - OPTIONS, STARTUP and REGISTER are answered as a ScyllaDB node would,
+//!   so that the driver can open connections,
+//! - queries to `system.local` and `system.peers` return the cluster topology,
+//! - queries to `system_schema` tables return empty results (an empty schema),
+//! - `USE <keyspace>` statements succeed,
+//! - other statements are answered with the response of the first matching [MockRule],
+//!   both when sent as QUERY and when prepared (PREPARE) and then executed (EXECUTE).
+//!   Statements matched by no rule fail with [DbError::Invalid].
+//!
+//! All the nodes must listen on the same port (but on different IPs), because
+//! the driver connects to the peers on the port of the contact point.
+//! Only protocol v4 (the driver's default) is supported.
+//!
+//! ```rust,no_run
+//! # use scylla_proxy::{get_exclusive_local_address, MockCluster, MockNode, MockResponse, MockRows, MockRule};
+//! # use scylla_cql::frame::response::result::{ColumnType, NativeType};
+//! # use scylla_cql::value::CqlValue;
+//! # use std::net::SocketAddr;
+//! # async fn check_only_compiles() {
+//! let nodes = (0..3).map(|_| MockNode::new(SocketAddr::new(get_exclusive_local_address(), 9042)));
+//! let rows = MockRows::new("ks", "t", [("a", ColumnType::Native(NativeType::Int))])
+//!     .with_row([Some(CqlValue::Int(1))])
+//!     .with_row([Some(CqlValue::Int(2))]);
+//! let running_proxy = MockCluster::new(nodes)
+//!     .with_rule(MockRule::exact("SELECT a FROM ks.t", MockResponse::Rows(rows)))
+//!     .with_rule(MockRule::contains("INSERT INTO ks.t", MockResponse::Void))
+//!     .into_proxy()
+//!     .run()
+//!     .await
+//!     .unwrap();
+//! // Connect the driver to any of the nodes' addresses...
+//! running_proxy.finish().await.unwrap();
+//! # }
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+use std::num::TryFromIntError;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use scylla_cql::frame::request::{options, Request};
+use scylla_cql::frame::response::error::DbError;
+use scylla_cql::frame::response::result::{CollectionType, ColumnType, NativeType};
+use scylla_cql::frame::types;
+use scylla_cql::serialize::value::SerializeValue;
+use scylla_cql::serialize::writers::CellWriter;
+use scylla_cql::value::CqlValue;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::frame::{FrameParams, RequestFrame, ResponseFrame};
+use crate::proxy::Responder;
+use crate::tls::TlsSettings;
+use crate::{Node, Proxy, RequestOpcode, RequestRule, ResponseOpcode};
+
+const PROTOCOL_VERSION: u8 = 0x04;
+const FLAG_COMPRESSION: u8 = 0x01;
+
+// Kinds of RESULT responses
+const RESULT_VOID: i32 = 0x0001;
+const RESULT_ROWS: i32 = 0x0002;
+const RESULT_SET_KEYSPACE: i32 = 0x0003;
+const RESULT_PREPARED: i32 = 0x0004;
+
+// Flags of result metadata
+const GLOBAL_TABLES_SPEC: i32 = 0x0001;
+const HAS_MORE_PAGES: i32 = 0x0002;
+const NO_METADATA: i32 = 0x0004;
+
+const PARTITIONER: &str = "org.apache.cassandra.dht.Murmur3Partitioner";
+
+/// A fake node of a [MockCluster].
+#[derive(Clone, Debug)]
+pub struct MockNode {
+    address: SocketAddr,
+    host_id: Uuid,
+    datacenter: String,
+    rack: String,
+    tokens: Option<Vec<i64>>,
+    request_rules: Option<Vec<RequestRule>>,
+    tls: TlsSettings,
+}
+
+impl MockNode {
+    /// Creates a node listening on the address, in datacenter `datacenter1` and rack `rack1`.
+    pub fn new(address: SocketAddr) -> Self {
+        Self {
+            address,
+            host_id: Uuid::from_u128(rand::random()),
+            datacenter: "datacenter1".to_owned(),
+            rack: "rack1".to_owned(),
+            tokens: None,
+            request_rules: None,
+            tls: Default::default(),
+        }
+    }
+
+    pub fn host_id(mut self, host_id: Uuid) -> Self {
+        self.host_id = host_id;
+        self
+    }
+
+    pub fn datacenter(mut self, datacenter: impl Into<String>) -> Self {
+        self.datacenter = datacenter.into();
+        self
+    }
+
+    pub fn rack(mut self, rack: impl Into<String>) -> Self {
+        self.rack = rack.into();
+        self
+    }
+
+    /// Sets the tokens owned by the node. By default, each node owns a single token,
+    /// and the tokens of the cluster are evenly spaced.
+    pub fn tokens(mut self, tokens: Vec<i64>) -> Self {
+        self.tokens = Some(tokens);
+        self
+    }
+
+    /// Sets the rules applied to the requests before they are answered by the node,
+    /// e.g. to inject errors or delays on a single node.
+    pub fn request_rules(mut self, request_rules: Vec<RequestRule>) -> Self {
+        self.request_rules = Some(request_rules);
+        self
+    }
+
+    /// Makes the node accept only TLS connections, using the given configuration
+    /// (see [server_tls_config](crate::server_tls_config)).
+    #[cfg(feature = "rustls-023")]
+    pub fn driver_tls(mut self, config: Arc<rustls::ServerConfig>) -> Self {
+        self.tls.set_driver_tls(config);
+        self
+    }
+}
+
+/// A set of [MockNode]s answering requests according to the same [MockRule]s.
+///
+/// See the [module-level docs](self) for what the nodes answer.
+pub struct MockCluster {
+    nodes: Vec<MockNode>,
+    rules: Vec<MockRule>,
+}
+
+impl MockCluster {
+    pub fn new(nodes: impl IntoIterator<Item = MockNode>) -> Self {
+        Self {
+            nodes: nodes.into_iter().collect(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Adds a rule, checked after the rules added before it.
+    pub fn with_rule(mut self, rule: MockRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Creates a proxy simulating the nodes.
+    pub fn into_proxy(self) -> Proxy {
+        Proxy::new(self.into_responders().into_iter().map(|(node, responder)| {
+            Node::new_responding(node.address, node.request_rules, responder).with_tls(node.tls)
+        }))
+    }
+
+    fn into_responders(self) -> Vec<(MockNode, Arc<MockResponder>)> {
+        let token_count = self.nodes.len();
+        let peers = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| PeerInfo {
+                address: node.address,
+                host_id: node.host_id,
+                datacenter: node.datacenter.clone(),
+                rack: node.rack.clone(),
+                tokens: node
+                    .tokens
+                    .clone()
+                    .unwrap_or_else(|| vec![evenly_spaced_token(i, token_count)]),
+            })
+            .collect();
+        let cluster = Arc::new(ClusterState {
+            peers,
+            rules: self.rules,
+            schema_version: Uuid::from_u128(rand::random()),
+            prepared: Mutex::new(HashMap::new()),
+        });
+
+        self.nodes
+            .into_iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let responder = Arc::new(MockResponder {
+                    node: i,
+                    cluster: cluster.clone(),
+                });
+                (node, responder)
+            })
+            .collect()
+    }
+}
+
+fn evenly_spaced_token(index: usize, count: usize) -> i64 {
+    let step = u64::MAX / count as u64;
+    i64::MIN.wrapping_add((step * index as u64) as i64)
+}
+
+/// Answers statements matching a query with a scripted response.
+///
+/// The queries are compared case-insensitively, with all the whitespace sequences
+/// treated as a single space.
+#[derive(Clone, Debug)]
+pub struct MockRule {
+    matcher: QueryMatcher,
+    variables: Vec<(String, ColumnType<'static>)>,
+    partition_key_indexes: Vec<u16>,
+    response: MockResponse,
+}
+
+#[derive(Clone, Debug)]
+enum QueryMatcher {
+    Exact(String),
+    Contains(String),
+}
+
+impl MockRule {
+    /// Matches statements equal to the query.
+    pub fn exact(query: &str, response: MockResponse) -> Self {
+        Self::new(QueryMatcher::Exact(normalize(query)), response)
+    }
+
+    /// Matches statements containing the query fragment.
+    pub fn contains(fragment: &str, response: MockResponse) -> Self {
+        Self::new(QueryMatcher::Contains(normalize(fragment)), response)
+    }
+
+    fn new(matcher: QueryMatcher, response: MockResponse) -> Self {
+        Self {
+            matcher,
+            variables: Vec::new(),
+            partition_key_indexes: Vec::new(),
+            response,
+        }
+    }
+
+    /// Sets the names and types of the bind markers, returned when the statement is prepared.
+    ///
+    /// Without them, the driver can't bind any values to the prepared statement.
+    pub fn with_variables<N: Into<String>>(
+        mut self,
+        variables: impl IntoIterator<Item = (N, ColumnType<'static>)>,
+    ) -> Self {
+        self.variables = variables
+            .into_iter()
+            .map(|(name, typ)| (name.into(), typ))
+            .collect();
+        self
+    }
+
+    /// Sets the indexes of the bind markers forming the partition key,
+    /// so that the driver can compute the tokens of the prepared statement.
+    pub fn with_partition_key_indexes(mut self, indexes: Vec<u16>) -> Self {
+        self.partition_key_indexes = indexes;
+        self
+    }
+
+    fn matches(&self, normalized_query: &str) -> bool {
+        match &self.matcher {
+            QueryMatcher::Exact(query) => normalized_query == query,
+            QueryMatcher::Contains(fragment) => normalized_query.contains(fragment.as_str()),
+        }
+    }
+}
+
+/// The response of a [MockRule].
+#[derive(Clone, Debug)]
+pub enum MockResponse {
+    /// A result without rows, as returned for modification statements.
+    Void,
+
+    /// A result with rows. The rows are paged according to the page size
+    /// requested by the driver.
+    Rows(MockRows),
+
+    /// A result of a `USE` statement.
+    SetKeyspace(String),
+
+    /// An error.
+    Error { error: DbError, message: String },
+}
+
+impl MockResponse {
+    /// Creates an error response with a default message.
+    pub fn error(error: DbError) -> Self {
+        Self::Error {
+            error,
+            message: "Mock-triggered error.".to_owned(),
+        }
+    }
+}
+
+/// Rows of a [MockResponse].
+#[derive(Clone, Debug)]
+pub struct MockRows {
+    keyspace: String,
+    table: String,
+    columns: Vec<(String, ColumnType<'static>)>,
+    rows: Vec<Vec<Option<CqlValue>>>,
+}
+
+impl MockRows {
+    /// Creates an empty set of rows of the given table and columns.
+    pub fn new<N: Into<String>>(
+        keyspace: impl Into<String>,
+        table: impl Into<String>,
+        columns: impl IntoIterator<Item = (N, ColumnType<'static>)>,
+    ) -> Self {
+        Self {
+            keyspace: keyspace.into(),
+            table: table.into(),
+            columns: columns
+                .into_iter()
+                .map(|(name, typ)| (name.into(), typ))
+                .collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Adds a row. `None` values are nulls.
+    ///
+    /// Panics if the number of values is different from the number of columns.
+    pub fn with_row(mut self, row: impl IntoIterator<Item = Option<CqlValue>>) -> Self {
+        let row: Vec<_> = row.into_iter().collect();
+        assert_eq!(
+            row.len(),
+            self.columns.len(),
+            "Number of values does not match the number of columns"
+        );
+        self.rows.push(row);
+        self
+    }
+}
+
+struct PeerInfo {
+    address: SocketAddr,
+    host_id: Uuid,
+    datacenter: String,
+    rack: String,
+    tokens: Vec<i64>,
+}
+
+struct ClusterState {
+    peers: Vec<PeerInfo>,
+    rules: Vec<MockRule>,
+    schema_version: Uuid,
+    // Statements prepared on any of the nodes, by their ids.
+    prepared: Mutex<HashMap<Bytes, String>>,
+}
+
+/// What a statement resolves to: the bind markers and the response.
+struct Resolved {
+    variables: Vec<(String, ColumnType<'static>)>,
+    partition_key_indexes: Vec<u16>,
+    response: MockResponse,
+}
+
+impl From<MockResponse> for Resolved {
+    fn from(response: MockResponse) -> Self {
+        Self {
+            variables: Vec::new(),
+            partition_key_indexes: Vec::new(),
+            response,
+        }
+    }
+}
+
+/// Answers the requests sent to a single mock node.
+pub(crate) struct MockResponder {
+    node: usize,
+    cluster: Arc<ClusterState>,
+}
+
+impl Responder for MockResponder {
+    fn respond(&self, request: &RequestFrame) -> ResponseFrame {
+        let params = FrameParams {
+            flags: request.params.flags & FLAG_COMPRESSION,
+            ..request.params.for_response()
+        };
+        if request.params.version & 0x7F != PROTOCOL_VERSION {
+            return error_frame(
+                params,
+                DbError::ProtocolError,
+                "Mock nodes support only protocol v4",
+            );
+        }
+
+        match request.opcode {
+            RequestOpcode::Options => ResponseFrame::forged_supported(params, &supported_options())
+                .expect("Supported options too long"),
+            RequestOpcode::Startup | RequestOpcode::Register => ResponseFrame::forged_ready(params),
+            RequestOpcode::Query => match request.deserialize() {
+                Ok(Request::Query(query)) => {
+                    self.execute(params, &query.contents, &query.parameters)
+                }
+                _ => error_frame(params, DbError::ProtocolError, "Malformed QUERY"),
+            },
+            RequestOpcode::Prepare => match types::read_long_string(&mut &request.body[..]) {
+                Ok(statement) => self.prepare(params, statement),
+                Err(_) => error_frame(params, DbError::ProtocolError, "Malformed PREPARE"),
+            },
+            RequestOpcode::Execute => match request.deserialize() {
+                Ok(Request::Execute(execute)) => {
+                    let statement = self
+                        .cluster
+                        .prepared
+                        .lock()
+                        .unwrap()
+                        .get(&execute.id)
+                        .cloned();
+                    match statement {
+                        Some(statement) => self.execute(params, &statement, &execute.parameters),
+                        None => ResponseFrame::forged_error(
+                            params,
+                            DbError::Unprepared {
+                                statement_id: execute.id,
+                            },
+                            Some("Unknown prepared statement"),
+                        )
+                        .expect("Error message too long"),
+                    }
+                }
+                _ => error_frame(params, DbError::ProtocolError, "Malformed EXECUTE"),
+            },
+            RequestOpcode::Batch => result_frame(params, |buf| {
+                types::write_int(RESULT_VOID, buf);
+                Ok(())
+            }),
+            opcode => error_frame(
+                params,
+                DbError::ProtocolError,
+                &format!("Unsupported request: {:?}", opcode),
+            ),
+        }
+    }
+}
+
+impl MockResponder {
+    fn prepare(&self, params: FrameParams, statement: &str) -> ResponseFrame {
+        let resolved = match self.resolve(statement) {
+            Ok(resolved) => resolved,
+            Err(response) => return response_frame(params, response, None, false),
+        };
+        if let MockResponse::Error { error, message } = resolved.response {
+            return error_frame(params, error, &message);
+        }
+
+        let id = statement_id(statement);
+        self.cluster
+            .prepared
+            .lock()
+            .unwrap()
+            .insert(id.clone(), statement.to_owned());
+
+        result_frame(params, |buf| {
+            types::write_int(RESULT_PREPARED, buf);
+            types::write_short_bytes(&id, buf)?;
+
+            // Prepared metadata
+            let (keyspace, table) = match &resolved.response {
+                MockResponse::Rows(rows) => (rows.keyspace.as_str(), rows.table.as_str()),
+                _ => ("", ""),
+            };
+            types::write_int(GLOBAL_TABLES_SPEC, buf);
+            types::write_int(resolved.variables.len().try_into()?, buf);
+            types::write_int(resolved.partition_key_indexes.len().try_into()?, buf);
+            for index in &resolved.partition_key_indexes {
+                types::write_short(*index, buf);
+            }
+            types::write_string(keyspace, buf)?;
+            types::write_string(table, buf)?;
+            write_col_specs(&resolved.variables, buf)?;
+
+            // Result metadata
+            match &resolved.response {
+                MockResponse::Rows(rows) => write_rows_metadata(rows, None, false, buf)?,
+                _ => {
+                    types::write_int(0, buf);
+                    types::write_int(0, buf);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn execute(
+        &self,
+        params: FrameParams,
+        statement: &str,
+        query_params: &scylla_cql::frame::request::query::QueryParameters,
+    ) -> ResponseFrame {
+        let response = match self.resolve(statement) {
+            Ok(resolved) => resolved.response,
+            Err(response) => response,
+        };
+
+        let page_size = query_params.page_size.filter(|size| *size > 0);
+        let offset = match query_params.paging_state.as_bytes_slice() {
+            Some(raw) => match <[u8; 4]>::try_from(&raw[..]) {
+                Ok(raw) => u32::from_be_bytes(raw) as usize,
+                Err(_) => {
+                    return error_frame(params, DbError::ProtocolError, "Invalid paging state")
+                }
+            },
+            None => 0,
+        };
+        let page = page_size.map(|size| (offset, size as usize));
+        response_frame(params, response, page, query_params.skip_metadata)
+    }
+
+    /// Finds the response to the statement: a rule's response, or a built-in one.
+    fn resolve(&self, statement: &str) -> Result<Resolved, MockResponse> {
+        let normalized = normalize(statement);
+
+        if let Some(rule) = self
+            .cluster
+            .rules
+            .iter()
+            .find(|rule| rule.matches(&normalized))
+        {
+            debug!(
+                "Mock rule {:?} matched statement {}",
+                rule.matcher, statement
+            );
+            return Ok(Resolved {
+                variables: rule.variables.clone(),
+                partition_key_indexes: rule.partition_key_indexes.clone(),
+                response: rule.response.clone(),
+            });
+        }
+
+        if normalized.starts_with("use ") {
+            // Keyspace names are case-sensitive only if quoted.
+            let keyspace = statement.trim().trim_end_matches(';')["use".len()..].trim();
+            let keyspace = match keyspace.strip_prefix('"') {
+                Some(quoted) => quoted.trim_end_matches('"').to_owned(),
+                None => keyspace.to_lowercase(),
+            };
+            return Ok(MockResponse::SetKeyspace(keyspace).into());
+        }
+
+        if let Some((columns, table)) = parse_select(&normalized) {
+            match table {
+                "system.local" => return self.select_peers(&columns, true).map(Into::into),
+                "system.peers" => return self.select_peers(&columns, false).map(Into::into),
+                _ => {
+                    if let Some(table) = table.strip_prefix("system_schema.") {
+                        return Ok(self.select_schema(&columns, table, &normalized));
+                    }
+                }
+            }
+        }
+
+        warn!("No mock rule matched statement {}", statement);
+        Err(MockResponse::Error {
+            error: DbError::Invalid,
+            message: format!("No mock response for statement: {}", statement),
+        })
+    }
+
+    /// Answers a query to `system.local` (`local == true`) or `system.peers`.
+    fn select_peers(&self, columns: &[&str], local: bool) -> Result<MockResponse, MockResponse> {
+        let (table, all_columns) = if local {
+            ("local", LOCAL_COLUMNS)
+        } else {
+            ("peers", PEERS_COLUMNS)
+        };
+        let columns: Vec<&str> = if columns == ["*"] {
+            all_columns.iter().map(|(name, _)| *name).collect()
+        } else {
+            columns.to_vec()
+        };
+
+        let mut specs = Vec::with_capacity(columns.len());
+        for column in &columns {
+            let Some((_, typ)) = all_columns.iter().find(|(name, _)| name == column) else {
+                return Err(MockResponse::Error {
+                    error: DbError::Invalid,
+                    message: format!("Undefined column name {}", column),
+                });
+            };
+            specs.push((column.to_string(), typ()));
+        }
+
+        let mut rows = MockRows::new("system", table, specs);
+        for (i, peer) in self.cluster.peers.iter().enumerate() {
+            if (i == self.node) != local {
+                continue;
+            }
+            let row = columns.iter().map(|column| self.peer_value(peer, column));
+            rows = rows.with_row(row);
+        }
+        Ok(MockResponse::Rows(rows))
+    }
+
+    fn peer_value(&self, peer: &PeerInfo, column: &str) -> Option<CqlValue> {
+        let text = |text: &str| Some(CqlValue::Text(text.to_owned()));
+        match column {
+            "key" => text("local"),
+            "host_id" => Some(CqlValue::Uuid(peer.host_id)),
+            "peer" | "rpc_address" | "broadcast_address" | "listen_address" | "preferred_ip" => {
+                Some(CqlValue::Inet(peer.address.ip()))
+            }
+            "data_center" => text(&peer.datacenter),
+            "rack" => text(&peer.rack),
+            "tokens" => Some(CqlValue::Set(
+                peer.tokens
+                    .iter()
+                    .map(|token| CqlValue::Text(token.to_string()))
+                    .collect(),
+            )),
+            "schema_version" => Some(CqlValue::Uuid(self.cluster.schema_version)),
+            "cluster_name" => text("Mock Cluster"),
+            "release_version" => text("3.0.8"),
+            "cql_version" => text("3.3.1"),
+            "partitioner" => text(PARTITIONER),
+            _ => None,
+        }
+    }
+
+    /// Answers a query to a `system_schema` table with no rows.
+    fn select_schema(&self, columns: &[&str], table: &str, normalized: &str) -> Resolved {
+        let columns: Vec<(String, ColumnType<'static>)> = if columns == ["*"] {
+            Vec::new()
+        } else {
+            columns
+                .iter()
+                .map(|column| (column.to_string(), schema_column_type(column)))
+                .collect()
+        };
+        let variables = if normalized.contains("where keyspace_name in ?") {
+            vec![(
+                "keyspace_name".to_owned(),
+                list_of(ColumnType::Native(NativeType::Text)),
+            )]
+        } else {
+            Vec::new()
+        };
+        Resolved {
+            variables,
+            partition_key_indexes: Vec::new(),
+            response: MockResponse::Rows(MockRows::new("system_schema", table, columns)),
+        }
+    }
+}
+
+impl std::fmt::Debug for MockResponder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockResponder")
+            .field("address", &self.cluster.peers[self.node].address)
+            .finish_non_exhaustive()
+    }
+}
+
+type ColumnTypeFn = fn() -> ColumnType<'static>;
+
+fn text() -> ColumnType<'static> {
+    ColumnType::Native(NativeType::Text)
+}
+
+fn uuid() -> ColumnType<'static> {
+    ColumnType::Native(NativeType::Uuid)
+}
+
+fn inet() -> ColumnType<'static> {
+    ColumnType::Native(NativeType::Inet)
+}
+
+fn text_set() -> ColumnType<'static> {
+    ColumnType::Collection {
+        frozen: false,
+        typ: CollectionType::Set(Box::new(text())),
+    }
+}
+
+fn list_of(typ: ColumnType<'static>) -> ColumnType<'static> {
+    ColumnType::Collection {
+        frozen: false,
+        typ: CollectionType::List(Box::new(typ)),
+    }
+}
+
+const LOCAL_COLUMNS: &[(&str, ColumnTypeFn)] = &[
+    ("key", text),
+    ("host_id", uuid),
+    ("rpc_address", inet),
+    ("broadcast_address", inet),
+    ("listen_address", inet),
+    ("data_center", text),
+    ("rack", text),
+    ("tokens", text_set),
+    ("schema_version", uuid),
+    ("cluster_name", text),
+    ("release_version", text),
+    ("cql_version", text),
+    ("partitioner", text),
+];
+
+const PEERS_COLUMNS: &[(&str, ColumnTypeFn)] = &[
+    ("peer", inet),
+    ("host_id", uuid),
+    ("rpc_address", inet),
+    ("preferred_ip", inet),
+    ("data_center", text),
+    ("rack", text),
+    ("tokens", text_set),
+    ("schema_version", uuid),
+    ("release_version", text),
+];
+
+/// Returns the type of a column of the `system_schema` tables queried by the driver.
+fn schema_column_type(column: &str) -> ColumnType<'static> {
+    match column {
+        "replication" | "options" => ColumnType::Collection {
+            frozen: false,
+            typ: CollectionType::Map(Box::new(text()), Box::new(text())),
+        },
+        "durable_writes" | "called_on_null_input" => ColumnType::Native(NativeType::Boolean),
+        "field_names" | "field_types" | "argument_names" | "argument_types" => list_of(text()),
+        "position" => ColumnType::Native(NativeType::Int),
+        _ => text(),
+    }
+}
+
+fn supported_options() -> HashMap<String, Vec<String>> {
+    HashMap::from([
+        (options::CQL_VERSION.to_owned(), vec!["3.0.0".to_owned()]),
+        (
+            options::COMPRESSION.to_owned(),
+            vec!["lz4".to_owned(), "snappy".to_owned()],
+        ),
+    ])
+}
+
+/// Lowercases the query, collapses whitespace and strips the trailing semicolon.
+fn normalize(query: &str) -> String {
+    let query = query.trim().trim_end_matches(';');
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Parses a normalized `SELECT <columns> FROM <table> ...` query.
+fn parse_select(normalized: &str) -> Option<(Vec<&str>, &str)> {
+    let rest = normalized.strip_prefix("select ")?;
+    let (columns, rest) = rest.split_once(" from ")?;
+    let table = rest.split(' ').next()?;
+    let columns = columns
+        .split(',')
+        .map(|column| column.trim().trim_matches('"'))
+        .collect();
+    Some((columns, table))
+}
+
+fn statement_id(statement: &str) -> Bytes {
+    let mut hasher = DefaultHasher::new();
+    statement.hash(&mut hasher);
+    Bytes::copy_from_slice(&hasher.finish().to_be_bytes())
+}
+
+fn error_frame(params: FrameParams, error: DbError, message: &str) -> ResponseFrame {
+    ResponseFrame::forged_error(params, error, Some(message)).expect("Error message too long")
+}
+
+fn result_frame(
+    params: FrameParams,
+    write_body: impl FnOnce(&mut Vec<u8>) -> Result<(), MockSerializationError>,
+) -> ResponseFrame {
+    let mut buf = Vec::new();
+    match write_body(&mut buf) {
+        Ok(()) => ResponseFrame {
+            params,
+            opcode: ResponseOpcode::Result,
+            body: buf.into(),
+        },
+        Err(err) => error_frame(
+            params,
+            DbError::ServerError,
+            &format!("Failed to serialize mock response: {}", err),
+        ),
+    }
+}
+
+/// Creates a frame with the response. `page` is the offset and the size
+/// of the page of rows to be returned.
+fn response_frame(
+    params: FrameParams,
+    response: MockResponse,
+    page: Option<(usize, usize)>,
+    skip_metadata: bool,
+) -> ResponseFrame {
+    match response {
+        MockResponse::Void => result_frame(params, |buf| {
+            types::write_int(RESULT_VOID, buf);
+            Ok(())
+        }),
+        MockResponse::SetKeyspace(keyspace) => result_frame(params, |buf| {
+            types::write_int(RESULT_SET_KEYSPACE, buf);
+            types::write_string(&keyspace, buf)?;
+            Ok(())
+        }),
+        MockResponse::Rows(rows) => result_frame(params, |buf| {
+            let (start, end) = match page {
+                Some((offset, size)) => (offset.min(rows.rows.len()), offset.saturating_add(size)),
+                None => (0, usize::MAX),
+            };
+            let end = end.min(rows.rows.len());
+            let paging_state = (end < rows.rows.len())
+                .then(|| u32::try_from(end).map(u32::to_be_bytes))
+                .transpose()?;
+
+            types::write_int(RESULT_ROWS, buf);
+            write_rows_metadata(
+                &rows,
+                paging_state.as_ref().map(|state| &state[..]),
+                skip_metadata,
+                buf,
+            )?;
+            types::write_int((end - start).try_into()?, buf);
+            for row in &rows.rows[start..end] {
+                for ((_, typ), value) in rows.columns.iter().zip(row) {
+                    let writer = CellWriter::new(buf);
+                    match value {
+                        Some(value) => {
+                            value
+                                .serialize(typ, writer)
+                                .map_err(|err| MockSerializationError(err.to_string()))?;
+                        }
+                        None => {
+                            writer.set_null();
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }),
+        MockResponse::Error { error, message } => error_frame(params, error, &message),
+    }
+}
+
+fn write_rows_metadata(
+    rows: &MockRows,
+    paging_state: Option<&[u8]>,
+    no_metadata: bool,
+    buf: &mut Vec<u8>,
+) -> Result<(), MockSerializationError> {
+    let mut flags = GLOBAL_TABLES_SPEC;
+    if paging_state.is_some() {
+        flags |= HAS_MORE_PAGES;
+    }
+    if no_metadata {
+        flags |= NO_METADATA;
+    }
+    types::write_int(flags, buf);
+    types::write_int(rows.columns.len().try_into()?, buf);
+    if let Some(paging_state) = paging_state {
+        types::write_bytes(paging_state, buf)?;
+    }
+    if !no_metadata {
+        types::write_string(&rows.keyspace, buf)?;
+        types::write_string(&rows.table, buf)?;
+        write_col_specs(&rows.columns, buf)?;
+    }
+    Ok(())
+}
+
+fn write_col_specs(
+    columns: &[(String, ColumnType<'static>)],
+    buf: &mut Vec<u8>,
+) -> Result<(), MockSerializationError> {
+    for (name, typ) in columns {
+        types::write_string(name, buf)?;
+        write_type(typ, buf)?;
+    }
+    Ok(())
+}
+
+/// Writes the `[option]` describing the type.
+fn write_type(typ: &ColumnType, buf: &mut Vec<u8>) -> Result<(), MockSerializationError> {
+    match typ {
+        ColumnType::Native(native) => types::write_short(native_type_id(native), buf),
+        ColumnType::Collection { typ, .. } => match typ {
+            CollectionType::List(elem) => {
+                types::write_short(0x0020, buf);
+                write_type(elem, buf)?;
+            }
+            CollectionType::Map(key, value) => {
+                types::write_short(0x0021, buf);
+                write_type(key, buf)?;
+                write_type(value, buf)?;
+            }
+            CollectionType::Set(elem) => {
+                types::write_short(0x0022, buf);
+                write_type(elem, buf)?;
+            }
+            _ => {
+                return Err(MockSerializationError(format!(
+                    "Unsupported type {:?}",
+                    typ
+                )))
+            }
+        },
+        ColumnType::UserDefinedType { definition, .. } => {
+            types::write_short(0x0030, buf);
+            types::write_string(&definition.keyspace, buf)?;
+            types::write_string(&definition.name, buf)?;
+            types::write_short(definition.field_types.len().try_into()?, buf);
+            for (name, typ) in &definition.field_types {
+                types::write_string(name, buf)?;
+                write_type(typ, buf)?;
+            }
+        }
+        ColumnType::Tuple(types) => {
+            types::write_short(0x0031, buf);
+            types::write_short(types.len().try_into()?, buf);
+            for typ in types {
+                write_type(typ, buf)?;
+            }
+        }
+        _ => {
+            return Err(MockSerializationError(format!(
+                "Unsupported type {:?}",
+                typ
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn native_type_id(typ: &NativeType) -> u16 {
+    match typ {
+        NativeType::Ascii => 0x0001,
+        NativeType::BigInt => 0x0002,
+        NativeType::Blob => 0x0003,
+        NativeType::Boolean => 0x0004,
+        NativeType::Counter => 0x0005,
+        NativeType::Decimal => 0x0006,
+        NativeType::Double => 0x0007,
+        NativeType::Float => 0x0008,
+        NativeType::Int => 0x0009,
+        NativeType::Timestamp => 0x000B,
+        NativeType::Uuid => 0x000C,
+        NativeType::Text => 0x000D,
+        NativeType::Varint => 0x000E,
+        NativeType::Timeuuid => 0x000F,
+        NativeType::Inet => 0x0010,
+        NativeType::Date => 0x0011,
+        NativeType::Time => 0x0012,
+        NativeType::SmallInt => 0x0013,
+        NativeType::TinyInt => 0x0014,
+        NativeType::Duration => 0x0015,
+        _ => 0x0000,
+    }
+}
+
+/// Failed to serialize a scripted response.
+#[derive(Debug)]
+struct MockSerializationError(String);
+
+impl std::fmt::Display for MockSerializationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<TryFromIntError> for MockSerializationError {
+    fn from(err: TryFromIntError) -> Self {
+        Self(format!("Length out of range: {}", err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::net::{IpAddr, SocketAddr};
+
+    use assert_matches::assert_matches;
+    use scylla_cql::frame::request::execute::Execute;
+    use scylla_cql::frame::request::prepare::Prepare;
+    use scylla_cql::frame::request::query::{
+        PagingState, PagingStateResponse, Query, QueryParameters,
+    };
+    use scylla_cql::frame::request::{RequestOpcode, SerializableRequest};
+    use scylla_cql::frame::response::error::DbError;
+    use scylla_cql::frame::response::result::{self, ColumnType, NativeType, Prepared};
+    use scylla_cql::frame::response::ResponseOpcode;
+    use scylla_cql::frame::types;
+    use scylla_cql::value::CqlValue;
+    use uuid::Uuid;
+
+    use super::{MockCluster, MockNode, MockResponder, MockResponse, MockRows, MockRule};
+    use crate::frame::{FrameParams, RequestFrame, ResponseFrame};
+    use crate::proxy::Responder as _;
+
+    const PARAMS: FrameParams = FrameParams {
+        version: 0x04,
+        flags: 0,
+        stream: 0,
+    };
+
+    fn responders(cluster: MockCluster) -> Vec<std::sync::Arc<MockResponder>> {
+        cluster
+            .into_responders()
+            .into_iter()
+            .map(|(_, responder)| responder)
+            .collect()
+    }
+
+    fn request(opcode: RequestOpcode, request: impl SerializableRequest) -> RequestFrame {
+        let mut body = Vec::new();
+        request.serialize(&mut body).unwrap();
+        RequestFrame {
+            params: PARAMS,
+            opcode,
+            body: body.into(),
+        }
+    }
+
+    fn query(contents: &str, page_size: Option<i32>, paging_state: PagingState) -> RequestFrame {
+        request(
+            RequestOpcode::Query,
+            Query {
+                contents: Cow::Borrowed(contents),
+                parameters: QueryParameters {
+                    page_size,
+                    paging_state,
+                    ..Default::default()
+                },
+            },
+        )
+    }
+
+    fn result(frame: ResponseFrame) -> result::Result {
+        assert_eq!(frame.opcode, ResponseOpcode::Result);
+        result::deserialize(frame.body, None).unwrap()
+    }
+
+    fn error(frame: ResponseFrame) -> DbError {
+        assert_eq!(frame.opcode, ResponseOpcode::Error);
+        let features = Default::default();
+        scylla_cql::frame::response::error::Error::deserialize(&features, &mut &frame.body[..])
+            .unwrap()
+            .error
+    }
+
+    fn rows<T>(result: result::Result) -> (Vec<T>, PagingStateResponse)
+    where
+        T: for<'frame, 'metadata> scylla_cql::deserialize::row::DeserializeRow<'frame, 'metadata>,
+    {
+        let (raw_rows, paging_state) = assert_matches!(result, result::Result::Rows(rows) => rows);
+        let rows = raw_rows.deserialize_metadata().unwrap();
+        let rows = rows.rows_iter::<T>().unwrap().map(Result::unwrap).collect();
+        (rows, paging_state)
+    }
+
+    fn address(i: u8) -> SocketAddr {
+        SocketAddr::new(IpAddr::from([127, 0, 0, i]), 9042)
+    }
+
+    #[test]
+    fn mock_answers_topology_queries() {
+        let host_id = Uuid::from_u128(42);
+        let nodes = [
+            MockNode::new(address(1))
+                .host_id(host_id)
+                .tokens(vec![-10, 10]),
+            MockNode::new(address(2)).datacenter("dc2").rack("r2"),
+            MockNode::new(address(3)),
+        ];
+        let responders = responders(MockCluster::new(nodes));
+
+        let options = responders[0].respond(&RequestFrame {
+            params: PARAMS,
+            opcode: RequestOpcode::Options,
+            body: Default::default(),
+        });
+        assert_eq!(options.opcode, ResponseOpcode::Supported);
+
+        let local = responders[0].respond(&query(
+            "select host_id, rpc_address, data_center, rack, tokens from system.local WHERE key='local'",
+            None,
+            PagingState::start(),
+        ));
+        let (local, _) = rows::<(Uuid, IpAddr, String, String, Vec<String>)>(result(local));
+        assert_eq!(
+            local,
+            [(
+                host_id,
+                address(1).ip(),
+                "datacenter1".to_owned(),
+                "rack1".to_owned(),
+                vec!["-10".to_owned(), "10".to_owned()]
+            )]
+        );
+
+        let peers = responders[0].respond(&query(
+            "SELECT peer, data_center, rack FROM system.peers",
+            None,
+            PagingState::start(),
+        ));
+        let (peers, _) = rows::<(IpAddr, String, String)>(result(peers));
+        assert_eq!(
+            peers,
+            [
+                (address(2).ip(), "dc2".to_owned(), "r2".to_owned()),
+                (
+                    address(3).ip(),
+                    "datacenter1".to_owned(),
+                    "rack1".to_owned()
+                ),
+            ]
+        );
+
+        let keyspaces = responders[1].respond(&query(
+            "select keyspace_name, replication, durable_writes from system_schema.keyspaces",
+            None,
+            PagingState::start(),
+        ));
+        let (keyspaces, _) =
+            rows::<(String, std::collections::HashMap<String, String>, bool)>(result(keyspaces));
+        assert!(keyspaces.is_empty());
+
+        let unknown_column = responders[1].respond(&query(
+            "SELECT foo FROM system.local",
+            None,
+            PagingState::start(),
+        ));
+        assert_matches!(error(unknown_column), DbError::Invalid);
+
+        let use_keyspace = responders[2].respond(&query("USE \"Ks\"", None, PagingState::start()));
+        assert_matches!(
+            result(use_keyspace),
+            result::Result::SetKeyspace(set_keyspace) if set_keyspace.keyspace_name == "Ks"
+        );
+    }
+
+    #[test]
+    fn mock_answers_with_rules() {
+        let mut mock_rows = MockRows::new("ks", "t", [("a", ColumnType::Native(NativeType::Int))]);
+        for a in 0..5 {
+            mock_rows = mock_rows.with_row([Some(CqlValue::Int(a))]);
+        }
+        let cluster = MockCluster::new([MockNode::new(address(1)), MockNode::new(address(2))])
+            .with_rule(
+                MockRule::exact(
+                    "SELECT a FROM ks.t WHERE pk = ?",
+                    MockResponse::Rows(mock_rows),
+                )
+                .with_variables([("pk", ColumnType::Native(NativeType::Int))])
+                .with_partition_key_indexes(vec![0]),
+            )
+            .with_rule(MockRule::contains("insert into", MockResponse::Void))
+            .with_rule(MockRule::contains(
+                "DELETE",
+                MockResponse::error(DbError::Overloaded),
+            ));
+        let responders = responders(cluster);
+
+        // Paged query
+        let select = "select a   from ks.t where pk = ?;";
+        let (page, paging_state) = rows::<(i32,)>(result(responders[0].respond(&query(
+            select,
+            Some(3),
+            PagingState::start(),
+        ))));
+        assert_eq!(page, [(0,), (1,), (2,)]);
+        let paging_state =
+            assert_matches!(paging_state, PagingStateResponse::HasMorePages { state } => state);
+        let (page, paging_state) = rows::<(i32,)>(result(responders[0].respond(&query(
+            select,
+            Some(3),
+            paging_state,
+        ))));
+        assert_eq!(page, [(3,), (4,)]);
+        assert_matches!(paging_state, PagingStateResponse::NoMorePages);
+
+        assert_matches!(
+            result(responders[0].respond(&query(
+                "INSERT INTO ks.t (a) VALUES (1)",
+                None,
+                PagingState::start()
+            ))),
+            result::Result::Void
+        );
+        assert_matches!(
+            error(responders[0].respond(&query("DELETE FROM ks.t", None, PagingState::start()))),
+            DbError::Overloaded
+        );
+        assert_matches!(
+            error(responders[0].respond(&query("SELECT b FROM ks.t", None, PagingState::start()))),
+            DbError::Invalid
+        );
+
+        // Prepared on one node, executed on another
+        let prepared = responders[0].respond(&request(
+            RequestOpcode::Prepare,
+            Prepare {
+                query: "SELECT a FROM ks.t WHERE pk = ?",
+            },
+        ));
+        let Prepared {
+            id,
+            prepared_metadata,
+            result_metadata,
+            ..
+        } = assert_matches!(result(prepared), result::Result::Prepared(prepared) => prepared);
+        assert_eq!(prepared_metadata.col_count, 1);
+        assert_eq!(prepared_metadata.col_specs[0].name(), "pk");
+        assert_eq!(prepared_metadata.pk_indexes.len(), 1);
+        assert_eq!(result_metadata.col_count(), 1);
+
+        let execute = |id: bytes::Bytes| {
+            request(
+                RequestOpcode::Execute,
+                Execute {
+                    id,
+                    parameters: Default::default(),
+                },
+            )
+        };
+        let (all_rows, _) = rows::<(i32,)>(result(responders[1].respond(&execute(id))));
+        assert_eq!(all_rows.len(), 5);
+
+        let unknown = responders[1].respond(&execute(bytes::Bytes::from_static(b"unknown")));
+        assert_matches!(error(unknown), DbError::Unprepared { .. });
+
+        // Unknown statements can't be prepared.
+        let mut body = Vec::new();
+        types::write_long_string("SELECT b FROM ks.t", &mut body).unwrap();
+        let unknown = responders[1].respond(&RequestFrame {
+            params: PARAMS,
+            opcode: RequestOpcode::Prepare,
+            body: body.into(),
+        });
+        assert_matches!(error(unknown), DbError::Invalid);
+    }
+}
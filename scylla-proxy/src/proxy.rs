@@ -1,8 +1,13 @@
 use crate::actions::{EvaluationContext, RequestRule, ResponseRule};
 use crate::errors::{DoorkeeperError, ProxyError, WorkerError};
+use crate::faults::{self, NetworkFaults};
 use crate::frame::{
     self, read_response_frame, write_frame, FrameOpcode, FrameParams, RequestFrame, ResponseFrame,
 };
+use crate::recording::{ConnectionRecorder, Recorder, Recording};
+use crate::statement::{PreparedStatements, PreparedTracker};
+use crate::stream::{ConnectionResetter, ProxyStream};
+use crate::tls::TlsSettings;
 use crate::{RequestOpcode, TargetShard};
 use bytes::Bytes;
 use compression::no_compression;
@@ -11,6 +16,7 @@ use std::collections::HashMap;
 use std::fmt::Display;
 use std::future::Future;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::num::NonZeroU64;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use tokio::io::{AsyncRead, AsyncWrite};
@@ -82,20 +88,30 @@ impl ShardAwareness {
 /// [driver] <- sender_to_driver <--------------------------+
 ///
 /// For Real node, the default reaction to a frame is to pass it to its intended addresse.
-/// For Simulated node, the default reaction to a request is to drop it.
+/// For Simulated node, the default reaction to a request is to drop it, unless the node
+/// has a [Responder] (e.g. a mock node, see [MockCluster](crate::MockCluster)),
+/// which answers the request itself.
 enum NodeType {
     Real {
         real_addr: SocketAddr,
         shard_awareness: ShardAwareness,
         response_rules: Option<Vec<ResponseRule>>,
     },
-    Simulated,
+    Simulated {
+        responder: Option<Arc<dyn Responder>>,
+    },
+}
+
+/// Answers the requests sent to a Simulated node in place of a real node.
+pub(crate) trait Responder: Send + Sync {
+    fn respond(&self, request: &RequestFrame) -> ResponseFrame;
 }
 
 pub struct Node {
     proxy_addr: SocketAddr,
     request_rules: Option<Vec<RequestRule>>,
     node_type: NodeType,
+    tls: TlsSettings,
 }
 
 impl Node {
@@ -115,6 +131,7 @@ impl Node {
                 shard_awareness,
                 response_rules,
             },
+            tls: Default::default(),
         }
     }
 
@@ -123,10 +140,32 @@ impl Node {
         Self {
             proxy_addr,
             request_rules,
-            node_type: NodeType::Simulated,
+            node_type: NodeType::Simulated { responder: None },
+            tls: Default::default(),
+        }
+    }
+
+    /// Creates a simulated node that answers the requests with the responder.
+    pub(crate) fn new_responding(
+        proxy_addr: SocketAddr,
+        request_rules: Option<Vec<RequestRule>>,
+        responder: Arc<dyn Responder>,
+    ) -> Self {
+        Self {
+            proxy_addr,
+            request_rules,
+            node_type: NodeType::Simulated {
+                responder: Some(responder),
+            },
+            tls: Default::default(),
         }
     }
 
+    pub(crate) fn with_tls(mut self, tls: TlsSettings) -> Self {
+        self.tls = tls;
+        self
+    }
+
     pub fn builder() -> NodeBuilder {
         NodeBuilder {
             real_addr: None,
@@ -134,6 +173,7 @@ impl Node {
             shard_awareness: None,
             request_rules: None,
             response_rules: None,
+            tls: Default::default(),
         }
     }
 }
@@ -144,6 +184,7 @@ pub struct NodeBuilder {
     shard_awareness: Option<ShardAwareness>,
     request_rules: Option<Vec<RequestRule>>,
     response_rules: Option<Vec<ResponseRule>>,
+    tls: TlsSettings,
 }
 
 impl NodeBuilder {
@@ -172,6 +213,23 @@ impl NodeBuilder {
         self
     }
 
+    /// Makes the proxy accept only TLS connections from the driver, using the given configuration
+    /// (see [server_tls_config](crate::server_tls_config)). The proxy terminates the TLS connections,
+    /// so the rules apply as for plain connections.
+    #[cfg(feature = "rustls-023")]
+    pub fn driver_tls(mut self, config: Arc<rustls::ServerConfig>) -> Self {
+        self.tls.set_driver_tls(config);
+        self
+    }
+
+    /// Makes the proxy connect to the real node over TLS, using the given configuration
+    /// (see [client_tls_config](crate::client_tls_config)).
+    #[cfg(feature = "rustls-023")]
+    pub fn node_tls(mut self, config: Arc<rustls::ClientConfig>) -> Self {
+        self.tls.set_node_tls(config);
+        self
+    }
+
     /// Creates an abstract node that is backed by a real Scylla node.
     pub fn build(self) -> Node {
         Node {
@@ -182,6 +240,7 @@ impl NodeBuilder {
                 shard_awareness: self.shard_awareness.expect("Shard awareness is required!"),
                 response_rules: self.response_rules,
             },
+            tls: self.tls,
         }
     }
 
@@ -190,7 +249,8 @@ impl NodeBuilder {
         Node {
             proxy_addr: self.proxy_addr.expect("Proxy addr is required!"),
             request_rules: self.request_rules,
-            node_type: NodeType::Simulated,
+            node_type: NodeType::Simulated { responder: None },
+            tls: self.tls,
         }
     }
 }
@@ -226,10 +286,15 @@ enum InternalNode {
         shard_awareness: ShardAwareness,
         request_rules: Arc<Mutex<Vec<RequestRule>>>,
         response_rules: Arc<Mutex<Vec<ResponseRule>>>,
+        network_faults: Arc<NetworkFaults>,
+        tls: TlsSettings,
     },
     Simulated {
         proxy_addr: SocketAddr,
         request_rules: Arc<Mutex<Vec<RequestRule>>>,
+        responder: Option<Arc<dyn Responder>>,
+        network_faults: Arc<NetworkFaults>,
+        tls: TlsSettings,
     },
 }
 
@@ -252,6 +317,18 @@ impl InternalNode {
             InternalNode::Simulated { request_rules, .. } => request_rules,
         }
     }
+    fn network_faults(&self) -> &Arc<NetworkFaults> {
+        match self {
+            InternalNode::Real { network_faults, .. } => network_faults,
+            InternalNode::Simulated { network_faults, .. } => network_faults,
+        }
+    }
+    fn tls(&self) -> &TlsSettings {
+        match self {
+            InternalNode::Real { tls, .. } => tls,
+            InternalNode::Simulated { tls, .. } => tls,
+        }
+    }
 }
 
 impl From<Node> for InternalNode {
@@ -272,13 +349,18 @@ impl From<Node> for InternalNode {
                 response_rules: response_rules
                     .map(|rules| Arc::new(Mutex::new(rules)))
                     .unwrap_or_default(),
+                network_faults: Default::default(),
+                tls: node.tls,
             },
-            NodeType::Simulated => InternalNode::Simulated {
+            NodeType::Simulated { responder } => InternalNode::Simulated {
                 proxy_addr: node.proxy_addr,
                 request_rules: node
                     .request_rules
                     .map(|rules| Arc::new(Mutex::new(rules)))
                     .unwrap_or_default(),
+                responder,
+                network_faults: Default::default(),
+                tls: node.tls,
             },
         }
     }
@@ -341,6 +423,8 @@ impl Proxy {
         let (finish_guard, finish_waiter) = mpsc::channel(1);
 
         let (error_propagator, error_sink) = mpsc::unbounded_channel();
+        let recorder = Arc::new(Recorder::default());
+        let prepared_statements = Arc::new(PreparedStatements::default());
         let (doorkeepers, running_nodes): (Vec<_>, Vec<RunningNode>) = self
             .nodes
             .into_iter()
@@ -359,6 +443,7 @@ impl Proxy {
                     RunningNode {
                         request_rules: request_rules.clone(),
                         response_rules: response_rules.cloned(),
+                        network_faults: node.network_faults().clone(),
                     }
                 };
                 (
@@ -367,6 +452,8 @@ impl Proxy {
                         terminate_signaler.clone(),
                         finish_guard.clone(),
                         error_propagator.clone(),
+                        recorder.clone(),
+                        prepared_statements.clone(),
                     ),
                     running,
                 )
@@ -382,6 +469,7 @@ impl Proxy {
             finish_waiter,
             running_nodes,
             error_sink,
+            recorder,
         })
     }
 }
@@ -390,6 +478,7 @@ impl Proxy {
 pub struct RunningNode {
     request_rules: Arc<Mutex<Vec<RequestRule>>>,
     response_rules: Option<Arc<Mutex<Vec<ResponseRule>>>>,
+    network_faults: Arc<NetworkFaults>,
 }
 
 impl RunningNode {
@@ -407,6 +496,31 @@ impl RunningNode {
             .lock()
             .unwrap() = rules.unwrap_or_default();
     }
+
+    /// Limits the throughput of each connection to the node to the given number of bytes per second,
+    /// in both directions. The frames sent by the driver are read slowly and the frames sent
+    /// to the driver are delayed accordingly. `None` removes the limit.
+    pub fn limit_bandwidth(&mut self, bytes_per_second: Option<NonZeroU64>) {
+        self.network_faults.set_bandwidth_limit(bytes_per_second);
+    }
+
+    /// Makes the connections to the node half-open: the frames sent by the driver
+    /// are still accepted and passed to the node, but no frame is ever sent back to the driver.
+    pub fn set_half_open(&mut self, half_open: bool) {
+        self.network_faults.set_half_open(half_open);
+    }
+
+    /// Makes each connection to the node send only a part of the next frame destined for the driver,
+    /// and then reset the connection with a TCP RST.
+    pub fn set_reset_mid_frame(&mut self, reset_mid_frame: bool) {
+        self.network_faults.set_reset_mid_frame(reset_mid_frame);
+    }
+
+    /// Cuts the node off from the driver: the connections stay open, but no frame passes
+    /// through them in either direction. New connections are accepted, but also get no frames through.
+    pub fn set_partitioned(&mut self, partitioned: bool) {
+        self.network_faults.set_partitioned(partitioned);
+    }
 }
 
 /// A handle that can be used to stop the proxy or change the rules.
@@ -415,6 +529,7 @@ pub struct RunningProxy {
     finish_waiter: FinishWaiter,
     pub running_nodes: Vec<RunningNode>,
     error_sink: ErrorSink,
+    recorder: Arc<Recorder>,
 }
 
 impl RunningProxy {
@@ -432,6 +547,40 @@ impl RunningProxy {
         }
     }
 
+    /// Partitions the cluster: the nodes of given indices in [RunningProxy::running_nodes]
+    /// get cut off from the driver, and the others get reachable again.
+    /// See [RunningNode::set_partitioned].
+    pub fn partition(&mut self, isolated_nodes: &[usize]) {
+        for (idx, node) in self.running_nodes.iter_mut().enumerate() {
+            node.set_partitioned(isolated_nodes.contains(&idx));
+        }
+    }
+
+    /// Makes all the nodes reachable again after [RunningProxy::partition].
+    pub fn heal_partition(&mut self) {
+        self.partition(&[]);
+    }
+
+    /// Removes all the network faults set on any of the nodes.
+    pub fn clear_network_faults(&mut self) {
+        for node in self.running_nodes.iter() {
+            node.network_faults.clear();
+        }
+    }
+
+    /// Starts recording the requests sent by the driver together with the responses
+    /// sent back to it, discarding the previously recorded ones.
+    /// Both the already open and the new connections are recorded.
+    /// See the [Recording] docs for details.
+    pub fn start_recording(&mut self) {
+        self.recorder.start();
+    }
+
+    /// Stops recording and returns the traffic recorded since [RunningProxy::start_recording].
+    pub fn stop_recording(&mut self) -> Recording {
+        self.recorder.stop()
+    }
+
     /// Attempts to fetch the first error that has occurred in proxy since last check.
     /// If no errors occurred, returns Ok(()).
     pub fn sanity_check(&mut self) -> Result<(), ProxyError> {
@@ -491,6 +640,8 @@ struct Doorkeeper {
     finish_guard: FinishGuard,
     shards_count: Option<u16>,
     error_propagator: ErrorPropagator,
+    recorder: Arc<Recorder>,
+    prepared_statements: Arc<PreparedStatements>,
 }
 
 impl Doorkeeper {
@@ -499,6 +650,8 @@ impl Doorkeeper {
         terminate_signaler: TerminateSignaler,
         finish_guard: FinishGuard,
         error_propagator: ErrorPropagator,
+        recorder: Arc<Recorder>,
+        prepared_statements: Arc<PreparedStatements>,
     ) -> Result<(), DoorkeeperError> {
         let listener = TcpListener::bind(node.proxy_addr())
             .await
@@ -534,6 +687,8 @@ impl Doorkeeper {
             terminate_signaler,
             finish_guard,
             error_propagator,
+            recorder,
+            prepared_statements,
         };
         tokio::task::spawn(doorkeeper.run());
         Ok(())
@@ -611,11 +766,12 @@ impl Doorkeeper {
         driver_addr: SocketAddr,
         connection_close_tx: &ConnectionCloseSignaler,
         connection_no: usize,
-        driver_stream: TcpStream,
-        cluster_stream: Option<TcpStream>,
+        driver_stream: ProxyStream,
+        cluster_stream: Option<ProxyStream>,
         shard: Option<TargetShard>,
     ) {
-        let (driver_read, driver_write) = driver_stream.into_split();
+        let driver_resetter = driver_stream.resetter();
+        let (driver_read, driver_write) = tokio::io::split(driver_stream);
 
         let new_worker = || ProxyWorker {
             terminate_notifier: self.terminate_signaler.subscribe(),
@@ -633,6 +789,10 @@ impl Doorkeeper {
         let (tx_cluster, rx_cluster) = mpsc::unbounded_channel::<RequestFrame>();
         let (tx_driver, rx_driver) = mpsc::unbounded_channel::<ResponseFrame>();
         let event_register_flag = Arc::new(AtomicBool::new(false));
+        let connection_recorder = self
+            .recorder
+            .new_connection(self.node.proxy_addr(), self.node.real_addr());
+        let prepared_tracker = Arc::new(PreparedTracker::new(self.prepared_statements.clone()));
 
         let (
             compression_writer_request_processor,
@@ -646,13 +806,20 @@ impl Doorkeeper {
             driver_read,
             tx_request,
             compression_reader_receiver_from_driver,
+            connection_recorder.clone(),
+            prepared_tracker.clone(),
+            self.node.network_faults().clone(),
         ));
         tokio::task::spawn(new_worker().sender_to_driver(
             driver_write,
             rx_driver,
-            connection_close_tx.subscribe(),
+            connection_close_tx.clone(),
             self.terminate_signaler.subscribe(),
             compression_reader_sender_to_driver,
+            connection_recorder,
+            prepared_tracker,
+            self.node.network_faults().clone(),
+            driver_resetter,
         ));
         tokio::task::spawn(new_worker().request_processor(
             rx_request,
@@ -663,12 +830,23 @@ impl Doorkeeper {
             connection_close_tx.clone(),
             event_register_flag.clone(),
             compression_writer_request_processor,
+            self.prepared_statements.clone(),
         ));
-        if let InternalNode::Real {
+        if let InternalNode::Simulated {
+            responder: Some(ref responder),
+            ..
+        } = self.node
+        {
+            tokio::task::spawn(new_worker().responder(
+                rx_cluster,
+                tx_driver.clone(),
+                responder.clone(),
+            ));
+        } else if let InternalNode::Real {
             ref response_rules, ..
         } = self.node
         {
-            let (cluster_read, cluster_write) = cluster_stream.unwrap().into_split();
+            let (cluster_read, cluster_write) = tokio::io::split(cluster_stream.unwrap());
             tokio::task::spawn(new_worker().sender_to_cluster(
                 cluster_write,
                 rx_cluster,
@@ -689,6 +867,7 @@ impl Doorkeeper {
                 response_rules.clone(),
                 connection_close_tx.clone(),
                 event_register_flag.clone(),
+                self.prepared_statements.clone(),
             ));
         }
         debug!(
@@ -704,6 +883,17 @@ impl Doorkeeper {
         connection_no: usize,
     ) -> Result<(), DoorkeeperError> {
         let (driver_stream, driver_addr) = self.make_driver_stream(connection_no).await?;
+        let driver_stream = match self.node.tls().accept(driver_stream).await {
+            Ok(driver_stream) => driver_stream,
+            Err(err) => {
+                // The doorkeeper keeps accepting connections, as it's the driver that misbehaves.
+                warn!("TLS handshake with driver {} failed: {}", driver_addr, err);
+                let _ = self
+                    .error_propagator
+                    .send(DoorkeeperError::DriverTlsHandshake(driver_addr, err).into());
+                return Ok(());
+            }
+        };
         let (cluster_stream, shard) = match self.node {
             InternalNode::Real { real_addr, .. } => {
                 let (cluster_stream, shard) =
@@ -747,8 +937,8 @@ impl Doorkeeper {
         &mut self,
         driver_addr: SocketAddr,
         real_addr: SocketAddr,
-    ) -> Result<(TcpStream, Option<TargetShard>), DoorkeeperError> {
-        let mut cluster_stream = if let Some(shards) = self.shards_count {
+    ) -> Result<(ProxyStream, Option<TargetShard>), DoorkeeperError> {
+        let cluster_stream = if let Some(shards) = self.shards_count {
             let socket = match self.node.proxy_addr().ip() {
                 std::net::IpAddr::V4(_) => TcpSocket::new_v4(),
                 std::net::IpAddr::V6(_) => TcpSocket::new_v6(),
@@ -787,6 +977,12 @@ impl Doorkeeper {
             stream
         }
         .map_err(|err| DoorkeeperError::NodeConnectionAttempt(real_addr, err))?;
+        let mut cluster_stream = self
+            .node
+            .tls()
+            .connect(cluster_stream, real_addr)
+            .await
+            .map_err(|err| DoorkeeperError::NodeTlsHandshake(real_addr, err))?;
 
         // If ShardAwareness is aware (QueryNode or FixedNum variants) and the
         // proxy succeeded to know the shards count (in FixedNum we get it for
@@ -809,7 +1005,7 @@ impl Doorkeeper {
     }
 
     async fn get_supported_options(
-        connection: &mut TcpStream,
+        connection: &mut (impl AsyncRead + AsyncWrite + Unpin),
     ) -> Result<HashMap<String, Vec<String>>, DoorkeeperError> {
         write_frame(
             HARDCODED_OPTIONS_PARAMS,
@@ -832,9 +1028,15 @@ impl Doorkeeper {
     }
 
     async fn obtain_shards_count(&self, real_addr: SocketAddr) -> Result<u16, DoorkeeperError> {
-        let mut connection = TcpStream::connect(real_addr)
+        let connection = TcpStream::connect(real_addr)
             .await
             .map_err(|err| DoorkeeperError::NodeConnectionAttempt(real_addr, err))?;
+        let mut connection = self
+            .node
+            .tls()
+            .connect(connection, real_addr)
+            .await
+            .map_err(|err| DoorkeeperError::NodeTlsHandshake(real_addr, err))?;
         let options = Self::get_supported_options(&mut connection).await?;
         let nr_shards_entry = options.get("SCYLLA_NR_SHARDS");
         let shards = match nr_shards_entry
@@ -853,7 +1055,7 @@ impl Doorkeeper {
     async fn obtain_shard_number(
         &self,
         real_addr: SocketAddr,
-        connection: &mut TcpStream,
+        connection: &mut ProxyStream,
     ) -> Result<Option<TargetShard>, DoorkeeperError> {
         let options = Self::get_supported_options(connection).await?;
         let shard_entry = options.get("SCYLLA_SHARD");
@@ -880,7 +1082,11 @@ mod compression {
     use scylla_cql::frame::request::{
         options, DeserializableRequest as _, RequestDeserializationError, Startup,
     };
-    use scylla_cql::frame::{compress_append, decompress, flag, Compression};
+    #[cfg(test)]
+    use scylla_cql::frame::Compression;
+    use scylla_cql::frame::{
+        compress_append_with_algorithm, decompress_with_algorithm, flag, CompressionAlgorithm,
+    };
     use tracing::{error, warn};
 
     #[derive(Debug, thiserror::Error)]
@@ -889,12 +1095,16 @@ mod compression {
         #[error("Snap compression error: {0}")]
         SnapCompressError(Arc<dyn Error + Sync + Send>),
 
+        /// Body compression with another algorithm failed.
+        #[error("Compression error: {0}")]
+        CompressError(CqlRequestSerializationError),
+
         /// Frame is to be compressed, but no compression was negotiated for the connection.
         #[error("Frame is to be compressed, but no compression negotiated for connection.")]
         NoCompressionNegotiated,
     }
 
-    type CompressionInfo = Arc<OnceLock<Option<Compression>>>;
+    type CompressionInfo = Arc<OnceLock<Option<CompressionAlgorithm>>>;
 
     /// The write end of compression config for a connection.
     ///
@@ -905,20 +1115,20 @@ mod compression {
     impl CompressionWriter {
         pub(crate) fn set(
             &self,
-            compression: Option<Compression>,
-        ) -> Result<(), Option<Compression>> {
+            compression: Option<CompressionAlgorithm>,
+        ) -> Result<(), Option<CompressionAlgorithm>> {
             self.0.set(compression)
         }
 
         pub(crate) fn set_from_startup(
             &self,
             mut body: &[u8],
-        ) -> Result<Option<Compression>, RequestDeserializationError> {
+        ) -> Result<Option<CompressionAlgorithm>, RequestDeserializationError> {
             let startup = Startup::deserialize(&mut body)?;
             let maybe_compression = startup.options.get(options::COMPRESSION);
             let maybe_compression = maybe_compression.and_then(|compression| {
                 compression
-                    .parse::<Compression>()
+                    .parse::<CompressionAlgorithm>()
                     .inspect_err(|err| error!("STARTUP compression error: {}", err))
                     .ok()
             });
@@ -940,7 +1150,7 @@ mod compression {
         ///
         /// Outer Option signifies whether the negotiation took place,
         /// inner Option is the compression (or lack of it) negotiated.
-        pub(crate) fn get(&self) -> Option<Option<Compression>> {
+        pub(crate) fn get(&self) -> Option<Option<CompressionAlgorithm>> {
             self.0.get().copied()
         }
 
@@ -952,11 +1162,13 @@ mod compression {
             match (flags & flag::COMPRESSION != 0, self.get().flatten()) {
                 (true, Some(compression)) => {
                     let mut buf = Vec::new();
-                    compress_append(body, compression, &mut buf).map_err(|err| {
-                        let CqlRequestSerializationError::SnapCompressError(err) = err else {
-                            unreachable!("BUG: compress_append returned variant different than SnapCompressError")
-                        };
-                        CompressionError::SnapCompressError(err)
+                    compress_append_with_algorithm(body, compression, &mut buf).map_err(|err| {
+                        match err {
+                            CqlRequestSerializationError::SnapCompressError(err) => {
+                                CompressionError::SnapCompressError(err)
+                            }
+                            other => CompressionError::CompressError(other),
+                        }
                     })?;
                     Ok(Some(Bytes::from(buf)))
                 }
@@ -971,7 +1183,9 @@ mod compression {
             body: Bytes,
         ) -> Result<Bytes, FrameBodyExtensionsParseError> {
             match (flags & flag::COMPRESSION != 0, self.get().flatten()) {
-                (true, Some(compression)) => decompress(&body, compression).map(Into::into),
+                (true, Some(compression)) => {
+                    decompress_with_algorithm(&body, compression).map(Into::into)
+                }
                 (true, None) => Err(FrameBodyExtensionsParseError::NoCompressionNegotiated),
                 (false, _) => Ok(body),
             }
@@ -995,7 +1209,7 @@ mod compression {
         )
     }
 
-    fn mock_compression_reader(compression: Option<Compression>) -> CompressionReader {
+    fn mock_compression_reader(compression: Option<CompressionAlgorithm>) -> CompressionReader {
         CompressionReader(Arc::new({
             let once = OnceLock::new();
             once.set(compression).unwrap();
@@ -1011,7 +1225,7 @@ mod compression {
     // Compression explicitly turned on.
     #[cfg(test)] // Currently only used for tests.
     pub(crate) fn with_compression(compression: Compression) -> CompressionReader {
-        mock_compression_reader(Some(compression))
+        mock_compression_reader(Some(compression.into()))
     }
 }
 pub(crate) use compression::{CompressionReader, CompressionWriter};
@@ -1065,6 +1279,9 @@ impl ProxyWorker {
         mut read_half: (impl AsyncRead + Unpin),
         request_processor_tx: mpsc::UnboundedSender<RequestFrame>,
         compression: CompressionReader,
+        recorder: Arc<ConnectionRecorder>,
+        prepared_tracker: Arc<PreparedTracker>,
+        network_faults: Arc<NetworkFaults>,
     ) {
         let shard = self.shard;
         self.run_until_interrupted(
@@ -1085,6 +1302,18 @@ impl ProxyWorker {
                         DisplayableShard(shard),
                         &frame.opcode
                     );
+                    // Requests are dropped before they are processed, so that they reach
+                    // neither the real nor the simulated nodes, nor the request rules.
+                    if network_faults.drops_requests() {
+                        debug!(
+                            "Dropping Driver ({}) -> Proxy ({}) frame due to network fault.",
+                            driver_addr, proxy_addr
+                        );
+                        continue;
+                    }
+                    network_faults.throttle(frame.body.len()).await;
+                    recorder.record_request(&frame);
+                    prepared_tracker.track_request(&frame);
                     if request_processor_tx.send(frame).is_err() {
                         warn!("request_processor had exited.");
                         return Result::<(), ProxyError>::Ok(());
@@ -1132,15 +1361,21 @@ impl ProxyWorker {
         .await;
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn sender_to_driver(
         self,
         mut write_half: (impl AsyncWrite + Unpin),
         mut responses_rx: mpsc::UnboundedReceiver<ResponseFrame>,
-        mut connection_close_notifier: ConnectionCloseNotifier,
+        connection_close_signaler: ConnectionCloseSignaler,
         mut terminate_notifier: TerminateNotifier,
         compression: CompressionReader,
+        recorder: Arc<ConnectionRecorder>,
+        prepared_tracker: Arc<PreparedTracker>,
+        network_faults: Arc<NetworkFaults>,
+        resetter: ConnectionResetter,
     ) {
         let shard = self.shard;
+        let mut connection_close_notifier = connection_close_signaler.subscribe();
         self.run_until_interrupted(
             "sender_to_driver",
             |driver_addr, proxy_addr, _real_addr| async move {
@@ -1164,6 +1399,33 @@ impl ProxyWorker {
                         driver_addr,
                         &response.opcode
                     );
+                    if network_faults.drops_responses() {
+                        debug!(
+                            "Dropping Proxy ({}) -> Driver ({}) frame due to network fault.",
+                            proxy_addr, driver_addr
+                        );
+                        continue;
+                    }
+                    network_faults.throttle(response.body.len()).await;
+                    recorder.record_response(&response);
+                    prepared_tracker.track_response(&response);
+                    if network_faults.resets_mid_frame() {
+                        debug!(
+                            "Resetting connection Proxy ({}) -> Driver ({}) in the middle of a frame.",
+                            proxy_addr, driver_addr
+                        );
+                        if let Err(err) = faults::write_half_and_reset(
+                            &mut write_half,
+                            &response,
+                            &compression,
+                            &resetter,
+                        )
+                        .await {
+                            warn!("Failed to reset connection to {}: {}", driver_addr, err);
+                        }
+                        let _ = connection_close_signaler.send(());
+                        return Ok(());
+                    }
                     if response.write(&mut write_half, &compression).await.is_err() {
                         if terminate_notifier.try_recv().is_err()
                             && connection_close_notifier.try_recv().is_err()
@@ -1212,7 +1474,6 @@ impl ProxyWorker {
                         DisplayableShard(shard),
                         &request.opcode
                     );
-
                     if request.write(&mut write_half, &compression).await.is_err() {
                         if terminate_notifier.try_recv().is_err()
                             && connection_close_notifier.try_recv().is_err()
@@ -1228,6 +1489,36 @@ impl ProxyWorker {
         .await;
     }
 
+    async fn responder(
+        self,
+        mut requests_rx: mpsc::UnboundedReceiver<RequestFrame>,
+        driver_tx: mpsc::UnboundedSender<ResponseFrame>,
+        responder: Arc<dyn Responder>,
+    ) {
+        let shard = self.shard;
+        self.run_until_interrupted(
+            "responder",
+            |driver_addr, proxy_addr, _real_addr| async move {
+                while let Some(request) = requests_rx.recv().await {
+                    debug!(
+                        "Answering Driver ({}) -> Simulated ({}) ({}) frame. opcode: {:?}.",
+                        driver_addr,
+                        proxy_addr,
+                        DisplayableShard(shard),
+                        &request.opcode
+                    );
+                    let response = responder.respond(&request);
+                    if driver_tx.send(response).is_err() {
+                        warn!("sender_to_driver had exited.");
+                        break;
+                    }
+                }
+                Ok::<(), ProxyError>(())
+            },
+        )
+        .await;
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn request_processor(
         self,
@@ -1239,6 +1530,7 @@ impl ProxyWorker {
         connection_close_signaler: ConnectionCloseSignaler,
         event_registered_flag: Arc<AtomicBool>,
         compression: CompressionWriter,
+        prepared_statements: Arc<PreparedStatements>,
     ) {
         let shard = self.shard;
         self.run_until_interrupted("request_processor", |driver_addr, _, real_addr| async move {
@@ -1265,6 +1557,8 @@ impl ProxyWorker {
                             opcode: FrameOpcode::Request(request.opcode),
                             frame_body: request.body.clone(),
                             connection_has_events: event_registered_flag.load(Ordering::Relaxed),
+                            prepared_statements: prepared_statements.clone(),
+                            request_info: Default::default(),
                         };
                         let mut guard = request_rules.lock().unwrap();
                         '_ruleloop: for (i, request_rule) in guard.iter_mut().enumerate() {
@@ -1360,6 +1654,7 @@ impl ProxyWorker {
         response_rules: Arc<Mutex<Vec<ResponseRule>>>,
         connection_close_signaler: ConnectionCloseSignaler,
         event_registered_flag: Arc<AtomicBool>,
+        prepared_statements: Arc<PreparedStatements>,
     ) {
         let shard = self.shard;
         self.run_until_interrupted("request_processor", |driver_addr, _, real_addr| async move {
@@ -1371,6 +1666,8 @@ impl ProxyWorker {
                             opcode: FrameOpcode::Response(response.opcode),
                             frame_body: response.body.clone(),
                             connection_has_events: event_registered_flag.load(Ordering::Relaxed),
+                            prepared_statements: prepared_statements.clone(),
+                            request_info: Default::default(),
                         };
                         let mut guard = response_rules.lock().unwrap();
                         '_ruleloop: for (i, response_rule) in guard.iter_mut().enumerate() {
@@ -2921,4 +3218,373 @@ mod tests {
 
         running_proxy.finish().await.unwrap();
     }
+
+    async fn run_proxy_with_mock_node() -> (RunningProxy, TcpStream, TcpStream) {
+        let node1_real_addr = next_local_address_with_port(9876);
+        let node1_proxy_addr = next_local_address_with_port(9876);
+        let proxy = Proxy::new([Node::new(
+            node1_real_addr,
+            node1_proxy_addr,
+            ShardAwareness::Unaware,
+            None,
+            None,
+        )]);
+        let running_proxy = proxy.run().await.unwrap();
+        let mock_node_listener = TcpListener::bind(node1_real_addr).await.unwrap();
+
+        let driver_conn = TcpStream::connect(node1_proxy_addr).await.unwrap();
+        let (node_conn, _) = mock_node_listener.accept().await.unwrap();
+        (running_proxy, driver_conn, node_conn)
+    }
+
+    const FAULTS_TEST_PARAMS: FrameParams = FrameParams {
+        flags: 0,
+        version: 0x04,
+        stream: 0,
+    };
+
+    async fn write_request(conn: &mut TcpStream, body: &Bytes) {
+        write_frame(
+            FAULTS_TEST_PARAMS,
+            FrameOpcode::Request(RequestOpcode::Query),
+            body,
+            conn,
+            &no_compression(),
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn write_response(conn: &mut TcpStream, body: &Bytes) {
+        write_frame(
+            FAULTS_TEST_PARAMS.for_response(),
+            FrameOpcode::Response(ResponseOpcode::Result),
+            body,
+            conn,
+            &no_compression(),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(2000)]
+    async fn half_open_proxy_passes_requests_but_drops_responses() {
+        setup_tracing();
+        let (mut running_proxy, mut driver_conn, mut node_conn) = run_proxy_with_mock_node().await;
+        running_proxy.running_nodes[0].set_half_open(true);
+
+        let request_body = random_body();
+        write_request(&mut driver_conn, &request_body).await;
+        let request = read_request_frame(&mut node_conn, &no_compression())
+            .await
+            .unwrap();
+        assert_eq!(request.body, request_body);
+
+        write_response(&mut node_conn, &random_body()).await;
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            read_response_frame(&mut driver_conn, &no_compression()),
+        )
+        .await
+        .unwrap_err();
+
+        running_proxy.running_nodes[0].set_half_open(false);
+        let response_body = random_body();
+        write_response(&mut node_conn, &response_body).await;
+        let response = read_response_frame(&mut driver_conn, &no_compression())
+            .await
+            .unwrap();
+        assert_eq!(response.body, response_body);
+
+        running_proxy.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(2000)]
+    async fn partitioned_proxy_passes_no_frames_until_healed() {
+        setup_tracing();
+        let (mut running_proxy, mut driver_conn, mut node_conn) = run_proxy_with_mock_node().await;
+        running_proxy.partition(&[0]);
+
+        write_request(&mut driver_conn, &random_body()).await;
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            read_request_frame(&mut node_conn, &no_compression()),
+        )
+        .await
+        .unwrap_err();
+
+        write_response(&mut node_conn, &random_body()).await;
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            read_response_frame(&mut driver_conn, &no_compression()),
+        )
+        .await
+        .unwrap_err();
+
+        running_proxy.heal_partition();
+        let request_body = random_body();
+        write_request(&mut driver_conn, &request_body).await;
+        let request = read_request_frame(&mut node_conn, &no_compression())
+            .await
+            .unwrap();
+        assert_eq!(request.body, request_body);
+
+        let response_body = random_body();
+        write_response(&mut node_conn, &response_body).await;
+        let response = read_response_frame(&mut driver_conn, &no_compression())
+            .await
+            .unwrap();
+        assert_eq!(response.body, response_body);
+
+        running_proxy.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(2000)]
+    async fn partitioned_dry_mode_node_processes_no_requests() {
+        setup_tracing();
+        let node1_proxy_addr = next_local_address_with_port(9876);
+        let (feedback_tx, mut feedback_rx) = mpsc::unbounded_channel();
+        let proxy = Proxy::new([Node::new_dry_mode(
+            node1_proxy_addr,
+            Some(vec![RequestRule(
+                Condition::True,
+                RequestReaction::forge_response(Arc::new(|RequestFrame { params, .. }| {
+                    ResponseFrame {
+                        params: params.for_response(),
+                        opcode: ResponseOpcode::Ready,
+                        body: Bytes::new(),
+                    }
+                }))
+                .with_feedback_when_performed(feedback_tx),
+            )]),
+        )]);
+        let mut running_proxy = proxy.run().await.unwrap();
+        let mut driver_conn = TcpStream::connect(node1_proxy_addr).await.unwrap();
+        running_proxy.partition(&[0]);
+
+        // The request is dropped before the rules are evaluated.
+        write_request(&mut driver_conn, &random_body()).await;
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            read_response_frame(&mut driver_conn, &no_compression()),
+        )
+        .await
+        .unwrap_err();
+        assert_matches!(feedback_rx.try_recv(), Err(TryRecvError::Empty));
+
+        running_proxy.heal_partition();
+        write_request(&mut driver_conn, &random_body()).await;
+        let response = read_response_frame(&mut driver_conn, &no_compression())
+            .await
+            .unwrap();
+        assert_eq!(response.opcode, ResponseOpcode::Ready);
+        feedback_rx.recv().await.unwrap();
+        assert_matches!(feedback_rx.try_recv(), Err(TryRecvError::Empty));
+
+        running_proxy.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(2000)]
+    async fn proxy_resets_connection_mid_frame() {
+        setup_tracing();
+        let (mut running_proxy, mut driver_conn, mut node_conn) = run_proxy_with_mock_node().await;
+        running_proxy.running_nodes[0].set_reset_mid_frame(true);
+
+        let body = Bytes::from(vec![0x42; 100]);
+        write_response(&mut node_conn, &body).await;
+
+        let mut received = Vec::new();
+        let err = driver_conn.read_to_end(&mut received).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionReset);
+        assert!(received.len() < 9 + body.len());
+
+        running_proxy.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(2000)]
+    async fn bandwidth_limited_proxy_delays_frames() {
+        setup_tracing();
+        let (mut running_proxy, mut driver_conn, mut node_conn) = run_proxy_with_mock_node().await;
+        // 1000-byte frames take 100 ms to get through.
+        running_proxy.running_nodes[0].limit_bandwidth(NonZeroU64::new(10_000));
+        let body = Bytes::from(vec![0x42; 1000 - 9]);
+
+        let start = tokio::time::Instant::now();
+        write_request(&mut driver_conn, &body).await;
+        read_request_frame(&mut node_conn, &no_compression())
+            .await
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        let start = tokio::time::Instant::now();
+        write_response(&mut node_conn, &body).await;
+        read_response_frame(&mut driver_conn, &no_compression())
+            .await
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        running_proxy.clear_network_faults();
+        let start = tokio::time::Instant::now();
+        write_request(&mut driver_conn, &body).await;
+        read_request_frame(&mut node_conn, &no_compression())
+            .await
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        running_proxy.finish().await.unwrap();
+    }
+
+    // A fixed address covered by the test certificates.
+    #[cfg(feature = "rustls-023")]
+    fn tls_test_address(port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3)), port)
+    }
+
+    #[cfg(feature = "rustls-023")]
+    fn tls_test_configs() -> (Arc<rustls::ServerConfig>, Arc<rustls::ClientConfig>) {
+        let server_config = crate::server_tls_config(
+            include_bytes!("../../test/tls/proxy.crt"),
+            include_bytes!("../../test/tls/proxy.key"),
+        )
+        .unwrap();
+        let client_config =
+            crate::client_tls_config(include_bytes!("../../test/tls/ca.crt")).unwrap();
+        (server_config, client_config)
+    }
+
+    #[cfg(feature = "rustls-023")]
+    async fn tls_connect(
+        client_config: Arc<rustls::ClientConfig>,
+        addr: SocketAddr,
+    ) -> std::io::Result<tokio_rustls::client::TlsStream<TcpStream>> {
+        let stream = TcpStream::connect(addr).await?;
+        tokio_rustls::TlsConnector::from(client_config)
+            .connect(
+                rustls::pki_types::ServerName::IpAddress(addr.ip().into()),
+                stream,
+            )
+            .await
+    }
+
+    #[cfg(feature = "rustls-023")]
+    #[tokio::test]
+    #[ntest::timeout(2000)]
+    async fn proxy_terminates_and_originates_tls() {
+        setup_tracing();
+        let (server_config, client_config) = tls_test_configs();
+        let real_addr = tls_test_address(9901);
+        let proxy_addr = tls_test_address(9902);
+        let running_proxy = Proxy::new([Node::builder()
+            .real_address(real_addr)
+            .proxy_address(proxy_addr)
+            .shard_awareness(ShardAwareness::Unaware)
+            .driver_tls(server_config.clone())
+            .node_tls(client_config.clone())
+            .build()])
+        .run()
+        .await
+        .unwrap();
+        let mock_node_listener = TcpListener::bind(real_addr).await.unwrap();
+
+        let mock_node = async {
+            let (conn, _) = mock_node_listener.accept().await.unwrap();
+            tokio_rustls::TlsAcceptor::from(server_config)
+                .accept(conn)
+                .await
+                .unwrap()
+        };
+        let (driver_conn, mut node_conn) =
+            join(tls_connect(client_config, proxy_addr), mock_node).await;
+        let mut driver_conn = driver_conn.unwrap();
+
+        let params = FrameParams {
+            flags: 0,
+            version: 0x04,
+            stream: 0,
+        };
+        let request_body = random_body();
+        write_frame(
+            params,
+            FrameOpcode::Request(RequestOpcode::Query),
+            &request_body,
+            &mut driver_conn,
+            &no_compression(),
+        )
+        .await
+        .unwrap();
+        let request = read_request_frame(&mut node_conn, &no_compression())
+            .await
+            .unwrap();
+        assert_eq!(request.body, request_body);
+
+        let response_body = random_body();
+        write_frame(
+            params.for_response(),
+            FrameOpcode::Response(ResponseOpcode::Result),
+            &response_body,
+            &mut node_conn,
+            &no_compression(),
+        )
+        .await
+        .unwrap();
+        let response = read_response_frame(&mut driver_conn, &no_compression())
+            .await
+            .unwrap();
+        assert_eq!(response.body, response_body);
+
+        running_proxy.finish().await.unwrap();
+    }
+
+    #[cfg(feature = "rustls-023")]
+    #[tokio::test]
+    #[ntest::timeout(2000)]
+    async fn proxy_reports_failed_tls_handshakes_and_keeps_accepting() {
+        setup_tracing();
+        let (server_config, client_config) = tls_test_configs();
+        let proxy_addr = tls_test_address(9903);
+        let mut running_proxy = Proxy::new([Node::builder()
+            .proxy_address(proxy_addr)
+            .driver_tls(server_config)
+            .build_dry_mode()])
+        .run()
+        .await
+        .unwrap();
+
+        // A plaintext frame is not a valid TLS handshake.
+        let mut plain_conn = TcpStream::connect(proxy_addr).await.unwrap();
+        write_frame(
+            HARDCODED_OPTIONS_PARAMS,
+            FrameOpcode::Request(RequestOpcode::Options),
+            &Bytes::new(),
+            &mut plain_conn,
+            &no_compression(),
+        )
+        .await
+        .unwrap();
+        assert_matches!(
+            running_proxy.wait_for_error().await,
+            Some(ProxyError::Doorkeeper(DoorkeeperError::DriverTlsHandshake(
+                ..
+            )))
+        );
+
+        // A client not trusting the proxy's certificate aborts the handshake.
+        let untrusted_config =
+            crate::client_tls_config(include_bytes!("../../test/tls/untrusted.crt")).unwrap();
+        tls_connect(untrusted_config, proxy_addr).await.unwrap_err();
+        assert_matches!(
+            running_proxy.wait_for_error().await,
+            Some(ProxyError::Doorkeeper(DoorkeeperError::DriverTlsHandshake(
+                ..
+            )))
+        );
+
+        tls_connect(client_config, proxy_addr).await.unwrap();
+        running_proxy.finish().await.unwrap();
+    }
 }
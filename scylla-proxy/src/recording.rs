@@ -0,0 +1,661 @@
+//! Recording the traffic going through a [RunningProxy](crate::RunningProxy) and replaying it.
+//!
+//! While recording is on (see [RunningProxy::start_recording](crate::RunningProxy::start_recording)),
+//! the proxy captures the requests sent by the driver together with the responses sent back
+//! to it, paired by the stream id, separately for each connection. The responses are captured
+//! as the driver receives them, i.e. after the response rules are applied, so the forged
+//! responses are recorded as well. Events (frames sent by the node on its own) are not recorded.
+//!
+//! A [Recording] can be saved to a file and loaded later, e.g. to capture the traffic
+//! once against a real cluster and then run regression tests without it.
+//! [Recording::into_proxy] creates a proxy of simulated nodes, one for each recorded proxy address,
+//! which answer the driver's requests with the recorded responses:
+//! - a request is matched with the recorded requests sent to the same address with the same
+//!   opcode and body (the stream id and the flags are ignored, and so is the order
+//!   of the STARTUP options),
+//! - if it was recorded multiple times, the responses are served in the recorded order,
+//!   and the last one is repeated once the others are used up,
+//! - if no request sent to the same address matches, the requests sent to the other addresses
+//!   are tried, because the driver's load balancing may pick other nodes than when recording,
+//! - requests matching no recorded request fail with [DbError::ServerError].
+//!
+//! ```rust,no_run
+//! # use scylla_proxy::{Node, Proxy, Recording, ShardAwareness};
+//! # use std::net::SocketAddr;
+//! # async fn check_only_compiles(real_addr: SocketAddr, proxy_addr: SocketAddr) {
+//! let proxy = Proxy::new([Node::builder()
+//!     .real_address(real_addr)
+//!     .proxy_address(proxy_addr)
+//!     .shard_awareness(ShardAwareness::QueryNode)
+//!     .build()]);
+//! let mut running_proxy = proxy.run().await.unwrap();
+//! running_proxy.start_recording();
+//! // Run the driver against the proxy...
+//! let recording = running_proxy.stop_recording();
+//! running_proxy.finish().await.unwrap();
+//! recording.save("traffic.rec").unwrap();
+//!
+//! // Later, without the real node:
+//! let recording = Recording::load("traffic.rec").unwrap();
+//! let translation_map = recording.translation_map();
+//! let running_proxy = recording.into_proxy().run().await.unwrap();
+//! // Run the driver against the replaying proxy (translating the addresses with `translation_map`)...
+//! running_proxy.finish().await.unwrap();
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bytes::{Buf, BufMut, Bytes};
+use scylla_cql::frame::response::error::DbError;
+use scylla_cql::frame::types;
+use tracing::warn;
+
+use crate::errors::RecordingError;
+use crate::frame::{FrameParams, RequestFrame, ResponseFrame};
+use crate::proxy::Responder;
+use crate::{Node, Proxy, RequestOpcode, ResponseOpcode};
+
+const MAGIC: &[u8] = b"SCYLLA-PROXY-REC";
+const FORMAT_VERSION: u16 = 1;
+
+/// A request sent by the driver together with the response it received.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedExchange {
+    pub request: RequestFrame,
+    pub response: ResponseFrame,
+}
+
+/// The exchanges of a single driver's connection, in order of the responses.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedConnection {
+    pub proxy_addr: SocketAddr,
+    /// Address of the real node, `None` for a simulated node.
+    pub real_addr: Option<SocketAddr>,
+    pub exchanges: Vec<RecordedExchange>,
+}
+
+/// The traffic captured by a [RunningProxy](crate::RunningProxy), see the [module docs](self).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Recording {
+    pub connections: Vec<RecordedConnection>,
+}
+
+impl Recording {
+    /// Writes the recording to a file, overwriting it if it exists.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RecordingError> {
+        let mut file = std::fs::File::create(path)?;
+        self.write_to(&mut file)
+    }
+
+    /// Reads a recording saved with [Recording::save].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        let mut file = std::fs::File::open(path)?;
+        Self::read_from(&mut file)
+    }
+
+    /// Serializes the recording into the writer.
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<(), RecordingError> {
+        let mut buf = Vec::new();
+        buf.put_slice(MAGIC);
+        buf.put_u16(FORMAT_VERSION);
+        write_len(&mut buf, self.connections.len())?;
+        for connection in &self.connections {
+            write_addr(&mut buf, connection.proxy_addr)?;
+            match connection.real_addr {
+                Some(real_addr) => {
+                    buf.put_u8(1);
+                    write_addr(&mut buf, real_addr)?;
+                }
+                None => buf.put_u8(0),
+            }
+            write_len(&mut buf, connection.exchanges.len())?;
+            for exchange in &connection.exchanges {
+                let request = &exchange.request;
+                write_frame(
+                    &mut buf,
+                    request.params,
+                    request.opcode as u8,
+                    &request.body,
+                )?;
+                let response = &exchange.response;
+                write_frame(
+                    &mut buf,
+                    response.params,
+                    response.opcode as u8,
+                    &response.body,
+                )?;
+            }
+        }
+        writer.write_all(&buf)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Deserializes a recording written with [Recording::write_to].
+    pub fn read_from(reader: &mut impl Read) -> Result<Self, RecordingError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut buf = &data[..];
+
+        if buf.len() < MAGIC.len() || &buf[..MAGIC.len()] != MAGIC {
+            return Err(RecordingError::BadMagic);
+        }
+        buf.advance(MAGIC.len());
+        let version = read_u16(&mut buf)?;
+        if version != FORMAT_VERSION {
+            return Err(RecordingError::UnsupportedVersion(version));
+        }
+
+        let connections_count = read_u32(&mut buf)?;
+        let mut connections = Vec::new();
+        for _ in 0..connections_count {
+            let proxy_addr = read_addr(&mut buf)?;
+            let real_addr = match read_u8(&mut buf)? {
+                0 => None,
+                1 => Some(read_addr(&mut buf)?),
+                other => {
+                    return Err(RecordingError::Malformed(format!(
+                        "invalid real address marker {}",
+                        other
+                    )))
+                }
+            };
+            let exchanges_count = read_u32(&mut buf)?;
+            let mut exchanges = Vec::new();
+            for _ in 0..exchanges_count {
+                let (params, opcode, body) = read_frame(&mut buf)?;
+                let request = RequestFrame {
+                    params,
+                    opcode: RequestOpcode::try_from(opcode).map_err(|_| {
+                        RecordingError::Malformed(format!("invalid request opcode {}", opcode))
+                    })?,
+                    body,
+                };
+                let (params, opcode, body) = read_frame(&mut buf)?;
+                let response = ResponseFrame {
+                    params,
+                    opcode: ResponseOpcode::try_from(opcode).map_err(|_| {
+                        RecordingError::Malformed(format!("invalid response opcode {}", opcode))
+                    })?,
+                    body,
+                };
+                exchanges.push(RecordedExchange { request, response });
+            }
+            connections.push(RecordedConnection {
+                proxy_addr,
+                real_addr,
+                exchanges,
+            });
+        }
+
+        if buf.has_remaining() {
+            return Err(RecordingError::Malformed(format!(
+                "{} trailing bytes",
+                buf.remaining()
+            )));
+        }
+        Ok(Recording { connections })
+    }
+
+    /// Builds a translation map from the addresses of the real nodes to the proxy addresses,
+    /// as [Proxy::translation_map] does for the proxy that the traffic was recorded with.
+    /// Pass it to the driver's address translator when replaying the recording.
+    pub fn translation_map(&self) -> HashMap<SocketAddr, SocketAddr> {
+        let mut translation_map = HashMap::new();
+        for connection in self.connections.iter() {
+            if let Some(real_addr) = connection.real_addr {
+                translation_map.insert(real_addr, connection.proxy_addr);
+                let shard_aware_real_addr = SocketAddr::new(real_addr.ip(), 19042);
+                translation_map.insert(shard_aware_real_addr, connection.proxy_addr);
+            }
+        }
+        translation_map
+    }
+
+    /// Creates a proxy replaying the recorded responses, with a simulated node
+    /// listening on each of the recorded proxy addresses.
+    pub fn into_proxy(self) -> Proxy {
+        Proxy::new(
+            self.into_replayers()
+                .into_iter()
+                .map(|(proxy_addr, replayer)| {
+                    Node::new_responding(proxy_addr, None, Arc::new(replayer))
+                }),
+        )
+    }
+
+    fn into_replayers(self) -> Vec<(SocketAddr, Replayer)> {
+        let mut all = RecordedResponses::default();
+        let mut by_node: Vec<(SocketAddr, RecordedResponses)> = Vec::new();
+        for connection in self.connections {
+            let responses = match by_node
+                .iter_mut()
+                .find(|(proxy_addr, _)| *proxy_addr == connection.proxy_addr)
+            {
+                Some((_, responses)) => responses,
+                None => {
+                    by_node.push((connection.proxy_addr, RecordedResponses::default()));
+                    &mut by_node.last_mut().unwrap().1
+                }
+            };
+            for RecordedExchange { request, response } in connection.exchanges {
+                all.add(&request, response.clone());
+                responses.add(&request, response);
+            }
+        }
+
+        let all = Arc::new(Mutex::new(all));
+        by_node
+            .into_iter()
+            .map(|(proxy_addr, responses)| {
+                let replayer = Replayer {
+                    own: Mutex::new(responses),
+                    all: all.clone(),
+                };
+                (proxy_addr, replayer)
+            })
+            .collect()
+    }
+}
+
+/// The recorded responses, by the requests they answer.
+#[derive(Default)]
+struct RecordedResponses(HashMap<(u8, Bytes), VecDeque<ResponseFrame>>);
+
+impl RecordedResponses {
+    fn add(&mut self, request: &RequestFrame, response: ResponseFrame) {
+        self.0
+            .entry(replay_key(request))
+            .or_default()
+            .push_back(response);
+    }
+
+    fn next(&mut self, request: &RequestFrame) -> Option<ResponseFrame> {
+        let recorded = self.0.get_mut(&replay_key(request))?;
+        if recorded.len() > 1 {
+            recorded.pop_front()
+        } else {
+            recorded.front().cloned()
+        }
+    }
+}
+
+/// Answers the requests sent to a single node with the recorded responses.
+struct Replayer {
+    /// Responses recorded on this node.
+    own: Mutex<RecordedResponses>,
+    /// Responses recorded on all the nodes, used if none of the node's own ones matches.
+    /// The driver may send a request to another node than when recording it,
+    /// e.g. because of load balancing.
+    all: Arc<Mutex<RecordedResponses>>,
+}
+
+impl Responder for Replayer {
+    fn respond(&self, request: &RequestFrame) -> ResponseFrame {
+        let recorded = self
+            .own
+            .lock()
+            .unwrap()
+            .next(request)
+            .or_else(|| self.all.lock().unwrap().next(request));
+
+        match recorded {
+            Some(response) => ResponseFrame {
+                params: FrameParams {
+                    stream: request.params.stream,
+                    ..response.params
+                },
+                ..response
+            },
+            None => {
+                warn!(
+                    "No recorded response for {:?} request: {:?}",
+                    request.opcode, request.body
+                );
+                ResponseFrame::forged_error(
+                    request.params,
+                    DbError::ServerError,
+                    Some("No recorded response for the request"),
+                )
+                .expect("Error message too long")
+            }
+        }
+    }
+}
+
+/// Requests are matched by their opcode and body. The options in STARTUP are a map,
+/// which the driver serializes in arbitrary order, so they are compared sorted.
+fn replay_key(request: &RequestFrame) -> (u8, Bytes) {
+    let body = match request.opcode {
+        RequestOpcode::Startup => match types::read_string_map(&mut &request.body[..]) {
+            Ok(options) => {
+                let options: BTreeMap<String, String> = options.into_iter().collect();
+                Bytes::from(format!("{:?}", options))
+            }
+            Err(_) => request.body.clone(),
+        },
+        _ => request.body.clone(),
+    };
+    (request.opcode as u8, body)
+}
+
+/// Keeps the exchanges of all the connections to the proxy, recorded while recording is on.
+#[derive(Default)]
+pub(crate) struct Recorder {
+    active: Arc<AtomicBool>,
+    connections: Mutex<Vec<Arc<ConnectionRecorder>>>,
+}
+
+impl Recorder {
+    pub(crate) fn new_connection(
+        &self,
+        proxy_addr: SocketAddr,
+        real_addr: Option<SocketAddr>,
+    ) -> Arc<ConnectionRecorder> {
+        let connection = Arc::new(ConnectionRecorder {
+            active: self.active.clone(),
+            proxy_addr,
+            real_addr,
+            state: Default::default(),
+        });
+        self.connections.lock().unwrap().push(connection.clone());
+        connection
+    }
+
+    pub(crate) fn start(&self) {
+        for connection in self.connections.lock().unwrap().iter() {
+            *connection.state.lock().unwrap() = Default::default();
+        }
+        self.active.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stop(&self) -> Recording {
+        self.active.store(false, Ordering::Relaxed);
+        let mut connections = self.connections.lock().unwrap();
+        let recorded = connections
+            .iter()
+            .filter_map(|connection| {
+                let state = std::mem::take(&mut *connection.state.lock().unwrap());
+                (!state.exchanges.is_empty()).then(|| RecordedConnection {
+                    proxy_addr: connection.proxy_addr,
+                    real_addr: connection.real_addr,
+                    exchanges: state.exchanges,
+                })
+            })
+            .collect();
+        // The workers of closed connections have dropped their references.
+        connections.retain(|connection| Arc::strong_count(connection) > 1);
+        Recording {
+            connections: recorded,
+        }
+    }
+}
+
+/// Pairs the requests and the responses of a single connection.
+pub(crate) struct ConnectionRecorder {
+    active: Arc<AtomicBool>,
+    proxy_addr: SocketAddr,
+    real_addr: Option<SocketAddr>,
+    state: Mutex<ConnectionRecording>,
+}
+
+#[derive(Default)]
+struct ConnectionRecording {
+    pending: HashMap<i16, RequestFrame>,
+    exchanges: Vec<RecordedExchange>,
+}
+
+impl ConnectionRecorder {
+    pub(crate) fn record_request(&self, request: &RequestFrame) {
+        if self.active.load(Ordering::Relaxed) {
+            let mut state = self.state.lock().unwrap();
+            state.pending.insert(request.params.stream, request.clone());
+        }
+    }
+
+    pub(crate) fn record_response(&self, response: &ResponseFrame) {
+        if self.active.load(Ordering::Relaxed) {
+            let mut state = self.state.lock().unwrap();
+            if let Some(request) = state.pending.remove(&response.params.stream) {
+                state.exchanges.push(RecordedExchange {
+                    request,
+                    response: response.clone(),
+                });
+            }
+        }
+    }
+}
+
+fn write_len(buf: &mut Vec<u8>, len: usize) -> Result<(), RecordingError> {
+    let len = u32::try_from(len)
+        .map_err(|_| RecordingError::Malformed(format!("length {} too big", len)))?;
+    buf.put_u32(len);
+    Ok(())
+}
+
+fn write_addr(buf: &mut Vec<u8>, addr: SocketAddr) -> Result<(), RecordingError> {
+    let addr = addr.to_string();
+    write_len(buf, addr.len())?;
+    buf.put_slice(addr.as_bytes());
+    Ok(())
+}
+
+fn write_frame(
+    buf: &mut Vec<u8>,
+    params: FrameParams,
+    opcode: u8,
+    body: &[u8],
+) -> Result<(), RecordingError> {
+    buf.put_u8(params.version);
+    buf.put_u8(params.flags);
+    buf.put_i16(params.stream);
+    buf.put_u8(opcode);
+    write_len(buf, body.len())?;
+    buf.put_slice(body);
+    Ok(())
+}
+
+fn ensure_remaining(buf: &[u8], len: usize) -> Result<(), RecordingError> {
+    if buf.remaining() < len {
+        return Err(RecordingError::Malformed(format!(
+            "expected {} more bytes, got {}",
+            len,
+            buf.remaining()
+        )));
+    }
+    Ok(())
+}
+
+fn read_u8(buf: &mut &[u8]) -> Result<u8, RecordingError> {
+    ensure_remaining(buf, 1)?;
+    Ok(buf.get_u8())
+}
+
+fn read_u16(buf: &mut &[u8]) -> Result<u16, RecordingError> {
+    ensure_remaining(buf, 2)?;
+    Ok(buf.get_u16())
+}
+
+fn read_u32(buf: &mut &[u8]) -> Result<u32, RecordingError> {
+    ensure_remaining(buf, 4)?;
+    Ok(buf.get_u32())
+}
+
+fn read_bytes<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], RecordingError> {
+    let len = read_u32(buf)? as usize;
+    ensure_remaining(buf, len)?;
+    let (bytes, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(bytes)
+}
+
+fn read_addr(buf: &mut &[u8]) -> Result<SocketAddr, RecordingError> {
+    let addr = read_bytes(buf)?;
+    std::str::from_utf8(addr)
+        .ok()
+        .and_then(|addr| addr.parse().ok())
+        .ok_or_else(|| {
+            RecordingError::Malformed(format!("invalid address {}", String::from_utf8_lossy(addr)))
+        })
+}
+
+fn read_frame(buf: &mut &[u8]) -> Result<(FrameParams, u8, Bytes), RecordingError> {
+    ensure_remaining(buf, 5)?;
+    let params = FrameParams {
+        version: buf.get_u8(),
+        flags: buf.get_u8(),
+        stream: buf.get_i16(),
+    };
+    let opcode = buf.get_u8();
+    let body = Bytes::copy_from_slice(read_bytes(buf)?);
+    Ok((params, opcode, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, SocketAddr};
+
+    use assert_matches::assert_matches;
+    use bytes::Bytes;
+    use scylla_cql::frame::response::error::DbError;
+    use scylla_cql::frame::types;
+
+    use super::{RecordedConnection, RecordedExchange, Recording};
+    use crate::errors::RecordingError;
+    use crate::frame::{FrameParams, RequestFrame, ResponseFrame};
+    use crate::proxy::Responder as _;
+    use crate::{RequestOpcode, ResponseOpcode};
+
+    const PARAMS: FrameParams = FrameParams {
+        version: 0x04,
+        flags: 0,
+        stream: 0,
+    };
+
+    fn query(stream: i16, body: &'static [u8]) -> RequestFrame {
+        RequestFrame {
+            params: FrameParams { stream, ..PARAMS },
+            opcode: RequestOpcode::Query,
+            body: Bytes::from_static(body),
+        }
+    }
+
+    fn exchange(stream: i16, body: &'static [u8], result: &'static [u8]) -> RecordedExchange {
+        RecordedExchange {
+            request: query(stream, body),
+            response: ResponseFrame {
+                params: FrameParams { stream, ..PARAMS }.for_response(),
+                opcode: ResponseOpcode::Result,
+                body: Bytes::from_static(result),
+            },
+        }
+    }
+
+    fn startup(options: &[(&str, &str)]) -> RequestFrame {
+        let mut body = Vec::new();
+        types::write_short(options.len() as u16, &mut body);
+        for (key, value) in options {
+            types::write_string(key, &mut body).unwrap();
+            types::write_string(value, &mut body).unwrap();
+        }
+        RequestFrame {
+            params: PARAMS,
+            opcode: RequestOpcode::Startup,
+            body: body.into(),
+        }
+    }
+
+    fn startup_exchange() -> RecordedExchange {
+        RecordedExchange {
+            request: startup(&[("CQL_VERSION", "4.0.0"), ("DRIVER_NAME", "driver")]),
+            response: ResponseFrame::forged_ready(PARAMS),
+        }
+    }
+
+    fn recording() -> Recording {
+        let proxy_addr = |i| SocketAddr::new(IpAddr::from([127, 0, 0, i]), 9042);
+        Recording {
+            connections: vec![
+                RecordedConnection {
+                    proxy_addr: proxy_addr(1),
+                    real_addr: Some(SocketAddr::new(IpAddr::from([127, 0, 1, 1]), 9042)),
+                    exchanges: vec![exchange(0, b"a", b"1"), exchange(1, b"b", b"2")],
+                },
+                RecordedConnection {
+                    proxy_addr: proxy_addr(2),
+                    real_addr: None,
+                    exchanges: vec![exchange(5, b"a", b"3")],
+                },
+                RecordedConnection {
+                    proxy_addr: proxy_addr(1),
+                    real_addr: Some(SocketAddr::new(IpAddr::from([127, 0, 1, 1]), 9042)),
+                    exchanges: vec![exchange(3, b"a", b"4"), startup_exchange()],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn recording_serialization_roundtrip() {
+        let recording = recording();
+        let mut buf = Vec::new();
+        recording.write_to(&mut buf).unwrap();
+        assert_eq!(Recording::read_from(&mut &buf[..]).unwrap(), recording);
+
+        assert_matches!(
+            Recording::read_from(&mut &buf[..buf.len() - 1]),
+            Err(RecordingError::Malformed(_))
+        );
+        assert_matches!(
+            Recording::read_from(&mut &b"not a recording"[..]),
+            Err(RecordingError::BadMagic)
+        );
+        let mut unsupported = buf.clone();
+        unsupported[super::MAGIC.len() + 1] = 0xFF;
+        assert_matches!(
+            Recording::read_from(&mut &unsupported[..]),
+            Err(RecordingError::UnsupportedVersion(_))
+        );
+    }
+
+    #[test]
+    fn replayer_serves_recorded_responses() {
+        let replayers = recording().into_replayers();
+        assert_eq!(replayers.len(), 2);
+        let (first, second) = (&replayers[0].1, &replayers[1].1);
+
+        // The responses are served in order, with the last one repeated.
+        for (stream, expected) in [(7, b"1"), (8, b"4"), (9, b"4")] {
+            let response = first.respond(&query(stream, b"a"));
+            assert_eq!(response.params.stream, stream);
+            assert_eq!(response.opcode, ResponseOpcode::Result);
+            assert_eq!(&response.body[..], expected);
+        }
+        assert_eq!(&second.respond(&query(3, b"a")).body[..], b"3");
+        // Requests recorded only on another node are answered as well.
+        assert_eq!(&second.respond(&query(3, b"b")).body[..], b"2");
+
+        // The order of STARTUP options doesn't matter.
+        let ready = first.respond(&startup(&[
+            ("DRIVER_NAME", "driver"),
+            ("CQL_VERSION", "4.0.0"),
+        ]));
+        assert_eq!(ready.opcode, ResponseOpcode::Ready);
+
+        let unknown = second.respond(&query(4, b"c"));
+        assert_eq!(unknown.params.stream, 4);
+        assert_eq!(unknown.opcode, ResponseOpcode::Error);
+        let error = scylla_cql::frame::response::error::Error::deserialize(
+            &Default::default(),
+            &mut &unknown.body[..],
+        )
+        .unwrap();
+        assert_eq!(error.error, DbError::ServerError);
+    }
+}
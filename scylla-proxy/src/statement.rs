@@ -0,0 +1,346 @@
+//! Inspection of the statements carried by the frames, used by the statement-aware [Condition](crate::Condition)s.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use scylla_cql::frame::request::batch::{Batch, BatchStatement, BatchType};
+use scylla_cql::frame::request::DeserializableRequest;
+use scylla_cql::frame::types::{self, Consistency};
+use tracing::debug;
+
+use crate::frame::{RequestFrame, ResponseFrame};
+use crate::{RequestOpcode, ResponseOpcode};
+
+// Kind of RESULT response carrying a prepared statement
+const RESULT_PREPARED: i32 = 0x0004;
+
+/// The CQL texts of the statements prepared through the proxy, by their ids.
+/// Prepared ids don't depend on the node, so the statements are shared by all the nodes.
+#[derive(Default)]
+pub(crate) struct PreparedStatements {
+    statements: Mutex<HashMap<Bytes, Arc<str>>>,
+}
+
+impl PreparedStatements {
+    fn get(&self, id: &[u8]) -> Option<Arc<str>> {
+        self.statements.lock().unwrap().get(id).cloned()
+    }
+}
+
+/// Pairs the PREPARE requests of a single connection with their responses,
+/// so that the EXECUTE requests can be matched by the text of the statement.
+pub(crate) struct PreparedTracker {
+    statements: Arc<PreparedStatements>,
+    pending: Mutex<HashMap<i16, Arc<str>>>,
+}
+
+impl PreparedTracker {
+    pub(crate) fn new(statements: Arc<PreparedStatements>) -> Self {
+        Self {
+            statements,
+            pending: Default::default(),
+        }
+    }
+
+    pub(crate) fn track_request(&self, request: &RequestFrame) {
+        if request.opcode == RequestOpcode::Prepare {
+            if let Ok(text) = types::read_long_string(&mut &request.body[..]) {
+                self.pending
+                    .lock()
+                    .unwrap()
+                    .insert(request.params.stream, text.into());
+            }
+        }
+    }
+
+    pub(crate) fn track_response(&self, response: &ResponseFrame) {
+        let Some(text) = self.pending.lock().unwrap().remove(&response.params.stream) else {
+            return;
+        };
+        if response.opcode != ResponseOpcode::Result {
+            return;
+        }
+        let mut buf = &response.body[..];
+        if let (Ok(RESULT_PREPARED), Ok(id)) =
+            (types::read_int(&mut buf), types::read_short_bytes(&mut buf))
+        {
+            debug!("Statement prepared with id {:?}: {}", id, text);
+            self.statements
+                .statements
+                .lock()
+                .unwrap()
+                .insert(Bytes::copy_from_slice(id), text);
+        }
+    }
+}
+
+/// A table name, possibly qualified with the keyspace name.
+/// Unquoted names are lowercased, as CQL identifiers are case-insensitive unless quoted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TableName {
+    pub(crate) keyspace: Option<String>,
+    pub(crate) table: String,
+}
+
+/// The information about a request, parsed from its body.
+#[derive(Debug, Default)]
+pub(crate) struct RequestInfo {
+    /// The CQL texts of the statements of QUERY, PREPARE and BATCH requests,
+    /// and of the statements executed by EXECUTE and BATCH requests, if prepared through the proxy.
+    pub(crate) statements: Vec<Arc<str>>,
+    /// The ids of the statements executed by EXECUTE and BATCH requests.
+    pub(crate) prepared_ids: Vec<Bytes>,
+    pub(crate) consistency: Option<Consistency>,
+    pub(crate) batch_type: Option<BatchType>,
+}
+
+impl RequestInfo {
+    pub(crate) fn parse(
+        opcode: RequestOpcode,
+        body: &[u8],
+        prepared_statements: &PreparedStatements,
+    ) -> Self {
+        let mut info = RequestInfo::default();
+        let mut buf = body;
+        match opcode {
+            RequestOpcode::Query => {
+                if let Ok(text) = types::read_long_string(&mut buf) {
+                    info.statements.push(text.into());
+                    info.consistency = types::read_consistency(&mut buf).ok();
+                }
+            }
+            RequestOpcode::Prepare => {
+                if let Ok(text) = types::read_long_string(&mut buf) {
+                    info.statements.push(text.into());
+                }
+            }
+            RequestOpcode::Execute => {
+                if let Ok(id) = types::read_short_bytes(&mut buf) {
+                    info.add_prepared(id, prepared_statements);
+                    info.consistency = types::read_consistency(&mut buf).ok();
+                }
+            }
+            RequestOpcode::Batch => {
+                if let Ok(batch) = Batch::deserialize(&mut buf) {
+                    for statement in batch.statements.iter() {
+                        match statement {
+                            BatchStatement::Query { text } => {
+                                info.statements.push(text.as_ref().into())
+                            }
+                            BatchStatement::Prepared { id } => {
+                                info.add_prepared(id, prepared_statements)
+                            }
+                        }
+                    }
+                    info.consistency = Some(batch.consistency);
+                    info.batch_type = Some(batch.batch_type);
+                }
+            }
+            _ => (),
+        }
+        info
+    }
+
+    fn add_prepared(&mut self, id: &[u8], prepared_statements: &PreparedStatements) {
+        if let Some(text) = prepared_statements.get(id) {
+            self.statements.push(text);
+        }
+        self.prepared_ids.push(Bytes::copy_from_slice(id));
+    }
+}
+
+/// Reads the error code of an ERROR response.
+pub(crate) fn error_code(opcode: ResponseOpcode, body: &[u8]) -> Option<i32> {
+    (opcode == ResponseOpcode::Error)
+        .then(|| types::read_int(&mut &body[..]).ok())
+        .flatten()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    /// An unquoted identifier or keyword (lowercased) or a quoted identifier (verbatim).
+    Name {
+        name: String,
+        quoted: bool,
+    },
+    Dot,
+    Other,
+}
+
+fn tokenize(statement: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = statement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => (),
+            '.' => tokens.push(Token::Dot),
+            '"' => {
+                let mut name = String::new();
+                while let Some(c) = chars.next() {
+                    if c == '"' {
+                        // A doubled quote stands for a quote in the name.
+                        if chars.peek() == Some(&'"') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    name.push(c);
+                }
+                tokens.push(Token::Name { name, quoted: true });
+            }
+            '\'' => {
+                // String literals are skipped.
+                while let Some(c) = chars.next() {
+                    if c == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                tokens.push(Token::Other);
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut name = c.to_lowercase().to_string();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    name.extend(c.to_lowercase());
+                    chars.next();
+                }
+                tokens.push(Token::Name {
+                    name,
+                    quoted: false,
+                });
+            }
+            _ => tokens.push(Token::Other),
+        }
+    }
+    tokens
+}
+
+fn is_keyword(token: Option<&Token>, keyword: &str) -> bool {
+    matches!(token, Some(Token::Name { name, quoted: false }) if name == keyword)
+}
+
+/// Finds the table that the statement operates on, for the DML statements
+/// (SELECT, INSERT, UPDATE, DELETE), TRUNCATE and CREATE/ALTER/DROP TABLE.
+pub(crate) fn statement_table(statement: &str) -> Option<TableName> {
+    let tokens = tokenize(statement);
+    let first = tokens.first()?;
+    let mut position = if is_keyword(Some(first), "select") || is_keyword(Some(first), "delete") {
+        tokens
+            .iter()
+            .position(|token| is_keyword(Some(token), "from"))?
+            + 1
+    } else if is_keyword(Some(first), "insert") {
+        tokens
+            .iter()
+            .position(|token| is_keyword(Some(token), "into"))?
+            + 1
+    } else if is_keyword(Some(first), "update") {
+        1
+    } else if is_keyword(Some(first), "truncate") {
+        if is_keyword(tokens.get(1), "table") {
+            2
+        } else {
+            1
+        }
+    } else if ["create", "alter", "drop"]
+        .iter()
+        .any(|keyword| is_keyword(Some(first), keyword))
+        && is_keyword(tokens.get(1), "table")
+    {
+        2
+    } else {
+        return None;
+    };
+
+    if is_keyword(tokens.get(position), "if") {
+        position += 1;
+        if is_keyword(tokens.get(position), "not") {
+            position += 1;
+        }
+        if is_keyword(tokens.get(position), "exists") {
+            position += 1;
+        }
+    }
+
+    let name = |token: Option<&Token>| match token {
+        Some(Token::Name { name, .. }) => Some(name.clone()),
+        _ => None,
+    };
+    let first_name = name(tokens.get(position))?;
+    if tokens.get(position + 1) == Some(&Token::Dot) {
+        Some(TableName {
+            keyspace: Some(first_name),
+            table: name(tokens.get(position + 2))?,
+        })
+    } else {
+        Some(TableName {
+            keyspace: None,
+            table: first_name,
+        })
+    }
+}
+
+/// Normalizes the name given by the user in the same way as the names in statements.
+pub(crate) fn normalize_name(name: &str) -> String {
+    match name
+        .strip_prefix('"')
+        .and_then(|name| name.strip_suffix('"'))
+    {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => name.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{statement_table, TableName};
+
+    #[test]
+    fn statement_table_finds_table() {
+        let table = |keyspace: Option<&str>, table: &str| {
+            Some(TableName {
+                keyspace: keyspace.map(str::to_owned),
+                table: table.to_owned(),
+            })
+        };
+
+        assert_eq!(
+            statement_table("SELECT a, b FROM ks.t WHERE a = ?"),
+            table(Some("ks"), "t")
+        );
+        assert_eq!(
+            statement_table("select \"from\" from \"Ks\".\"My\"\"Table\""),
+            table(Some("Ks"), "My\"Table")
+        );
+        assert_eq!(
+            statement_table("INSERT INTO Tab(a, b) VALUES ('from x', 1) IF NOT EXISTS"),
+            table(None, "tab")
+        );
+        assert_eq!(
+            statement_table("UPDATE ks.t USING TTL 10 SET a = 1 WHERE b = 2"),
+            table(Some("ks"), "t")
+        );
+        assert_eq!(
+            statement_table("DELETE a FROM ks.t WHERE b = 2"),
+            table(Some("ks"), "t")
+        );
+        assert_eq!(statement_table("TRUNCATE TABLE t"), table(None, "t"));
+        assert_eq!(statement_table("TRUNCATE ks.t"), table(Some("ks"), "t"));
+        assert_eq!(
+            statement_table("CREATE TABLE IF NOT EXISTS ks.t (a int PRIMARY KEY)"),
+            table(Some("ks"), "t")
+        );
+        assert_eq!(statement_table("DROP TABLE IF EXISTS t"), table(None, "t"));
+        assert_eq!(statement_table("CREATE KEYSPACE ks WITH ..."), None);
+        assert_eq!(statement_table("USE ks"), None);
+        assert_eq!(statement_table(""), None);
+    }
+}
@@ -0,0 +1,141 @@
+//! The streams that the proxy exchanges frames through, with the driver and with the nodes:
+//! plain TCP streams, or TLS over TCP.
+
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+/// A TCP stream which can be made to reset the connection once dropped, instead of closing it gracefully.
+pub(crate) struct ResettableStream {
+    stream: TcpStream,
+    reset_on_drop: Arc<AtomicBool>,
+}
+
+impl ResettableStream {
+    pub(crate) fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            reset_on_drop: Default::default(),
+        }
+    }
+}
+
+impl Drop for ResettableStream {
+    fn drop(&mut self) {
+        if self.reset_on_drop.load(Ordering::Relaxed) {
+            // Closing a socket with zero linger time discards the unsent data and sends RST instead of FIN.
+            // Zero linger time never blocks the thread on drop, which the deprecation warns about.
+            #[allow(deprecated)]
+            let _ = self.stream.set_linger(Some(Duration::ZERO));
+        }
+    }
+}
+
+/// Makes the connection of a [ResettableStream] get reset once the stream is dropped.
+#[derive(Clone)]
+pub(crate) struct ConnectionResetter(Arc<AtomicBool>);
+
+impl ConnectionResetter {
+    pub(crate) fn reset_on_drop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+impl AsyncRead for ResettableStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ResettableStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+/// A connection of the proxy with the driver or with a node, either plain or TLS-encrypted.
+pub(crate) enum ProxyStream {
+    Plain(ResettableStream),
+    #[cfg(feature = "rustls-023")]
+    Tls(Box<tokio_rustls::TlsStream<ResettableStream>>),
+}
+
+impl ProxyStream {
+    fn tcp(&self) -> &ResettableStream {
+        match self {
+            ProxyStream::Plain(stream) => stream,
+            #[cfg(feature = "rustls-023")]
+            ProxyStream::Tls(stream) => stream.get_ref().0,
+        }
+    }
+
+    pub(crate) fn resetter(&self) -> ConnectionResetter {
+        ConnectionResetter(self.tcp().reset_on_drop.clone())
+    }
+}
+
+impl AsyncRead for ProxyStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ProxyStream::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(feature = "rustls-023")]
+            ProxyStream::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for ProxyStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            ProxyStream::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(feature = "rustls-023")]
+            ProxyStream::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ProxyStream::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(feature = "rustls-023")]
+            ProxyStream::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ProxyStream::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(feature = "rustls-023")]
+            ProxyStream::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! TLS on the connections between the driver and the proxy, and between the proxy and the nodes.
+//!
+//! The proxy terminates the driver's TLS connections itself, so it can inspect and alter
+//! the frames as with plain connections. Independently, it can originate TLS connections
+//! to the real nodes.
+
+use std::io;
+use std::net::SocketAddr;
+#[cfg(feature = "rustls-023")]
+use std::sync::Arc;
+
+use tokio::net::TcpStream;
+
+#[cfg(feature = "rustls-023")]
+use crate::errors::TlsConfigError;
+use crate::stream::{ProxyStream, ResettableStream};
+
+/// The TLS configuration of a single node of the proxy.
+/// If neither side is configured, the proxy uses plain TCP connections.
+#[derive(Clone, Default)]
+pub(crate) struct TlsSettings {
+    /// Accepts the TLS connections from the driver.
+    #[cfg(feature = "rustls-023")]
+    acceptor: Option<tokio_rustls::TlsAcceptor>,
+    /// Originates the TLS connections to the real node.
+    #[cfg(feature = "rustls-023")]
+    connector: Option<tokio_rustls::TlsConnector>,
+}
+
+impl TlsSettings {
+    #[cfg(feature = "rustls-023")]
+    pub(crate) fn set_driver_tls(&mut self, config: Arc<rustls::ServerConfig>) {
+        self.acceptor = Some(config.into());
+    }
+
+    #[cfg(feature = "rustls-023")]
+    pub(crate) fn set_node_tls(&mut self, config: Arc<rustls::ClientConfig>) {
+        self.connector = Some(config.into());
+    }
+
+    /// Performs the TLS handshake with the driver, if the node accepts TLS connections.
+    pub(crate) async fn accept(&self, stream: TcpStream) -> io::Result<ProxyStream> {
+        let stream = ResettableStream::new(stream);
+        #[cfg(feature = "rustls-023")]
+        if let Some(acceptor) = &self.acceptor {
+            let stream = acceptor.accept(stream).await?;
+            return Ok(ProxyStream::Tls(Box::new(stream.into())));
+        }
+        Ok(ProxyStream::Plain(stream))
+    }
+
+    /// Performs the TLS handshake with the real node, if the node is connected to over TLS.
+    /// As the driver does, the proxy verifies the node's certificate against its IP address.
+    pub(crate) async fn connect(
+        &self,
+        stream: TcpStream,
+        #[allow(unused)] real_addr: SocketAddr,
+    ) -> io::Result<ProxyStream> {
+        let stream = ResettableStream::new(stream);
+        #[cfg(feature = "rustls-023")]
+        if let Some(connector) = &self.connector {
+            let server_name = rustls::pki_types::ServerName::IpAddress(real_addr.ip().into());
+            let stream = connector.connect(server_name, stream).await?;
+            return Ok(ProxyStream::Tls(Box::new(stream.into())));
+        }
+        Ok(ProxyStream::Plain(stream))
+    }
+}
+
+impl std::fmt::Debug for TlsSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("TlsSettings");
+        #[cfg(feature = "rustls-023")]
+        debug
+            .field("driver_tls", &self.acceptor.is_some())
+            .field("node_tls", &self.connector.is_some());
+        debug.finish()
+    }
+}
+
+/// Creates a configuration for accepting the driver's TLS connections, presenting
+/// the given certificate chain and using the given private key, both in PEM format.
+#[cfg(feature = "rustls-023")]
+pub fn server_tls_config(
+    cert_chain_pem: &[u8],
+    private_key_pem: &[u8],
+) -> Result<Arc<rustls::ServerConfig>, TlsConfigError> {
+    use rustls::pki_types::pem::PemObject as _;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+    let cert_chain = CertificateDer::pem_slice_iter(cert_chain_pem).collect::<Result<_, _>>()?;
+    let private_key = PrivateKeyDer::from_pem_slice(private_key_pem)?;
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, private_key)?;
+    Ok(Arc::new(config))
+}
+
+/// Creates a configuration for originating TLS connections to the nodes,
+/// trusting the CA certificates given in PEM format.
+#[cfg(feature = "rustls-023")]
+pub fn client_tls_config(ca_certs_pem: &[u8]) -> Result<Arc<rustls::ClientConfig>, TlsConfigError> {
+    use rustls::pki_types::pem::PemObject as _;
+    use rustls::pki_types::CertificateDer;
+
+    let mut root_store = rustls::RootCertStore::empty();
+    for cert in CertificateDer::pem_slice_iter(ca_certs_pem) {
+        root_store.add(cert?)?;
+    }
+    let config = rustls::ClientConfig::builder()
+        .with_root_certificates(root_store)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
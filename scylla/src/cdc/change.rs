@@ -0,0 +1,308 @@
+//! Changes read from CDC log tables.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use scylla_cql::value::{CqlTimeuuid, CqlValue, Row};
+
+use super::CdcError;
+
+const STREAM_ID: &str = "cdc$stream_id";
+const TIME: &str = "cdc$time";
+const BATCH_SEQ_NO: &str = "cdc$batch_seq_no";
+const OPERATION: &str = "cdc$operation";
+const TTL: &str = "cdc$ttl";
+const END_OF_BATCH: &str = "cdc$end_of_batch";
+const DELETED_PREFIX: &str = "cdc$deleted_";
+const DELETED_ELEMENTS_PREFIX: &str = "cdc$deleted_elements_";
+
+/// Identifier of a CDC stream.
+///
+/// Each partition of a CDC log table is a stream. The changes of a base table
+/// partition are written to a single stream within a CDC generation.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StreamId(Vec<u8>);
+
+impl StreamId {
+    /// Creates a stream id from its raw bytes.
+    pub fn new(raw: Vec<u8>) -> Self {
+        Self(raw)
+    }
+
+    /// Returns the raw bytes of the stream id.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x")?;
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StreamId({})", self)
+    }
+}
+
+/// The operation recorded in a CDC log row (the `cdc$operation` column).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OperationType {
+    PreImage,
+    RowUpdate,
+    RowInsert,
+    RowDelete,
+    PartitionDelete,
+    RowRangeDelInclLeft,
+    RowRangeDelExclLeft,
+    RowRangeDelInclRight,
+    RowRangeDelExclRight,
+    PostImage,
+}
+
+impl OperationType {
+    /// Returns the kind of the changes recording this operation.
+    pub fn kind(self) -> ChangeKind {
+        match self {
+            OperationType::PreImage => ChangeKind::PreImage,
+            OperationType::PostImage => ChangeKind::PostImage,
+            _ => ChangeKind::Delta,
+        }
+    }
+}
+
+impl TryFrom<i8> for OperationType {
+    type Error = i8;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => OperationType::PreImage,
+            1 => OperationType::RowUpdate,
+            2 => OperationType::RowInsert,
+            3 => OperationType::RowDelete,
+            4 => OperationType::PartitionDelete,
+            5 => OperationType::RowRangeDelInclLeft,
+            6 => OperationType::RowRangeDelExclLeft,
+            7 => OperationType::RowRangeDelInclRight,
+            8 => OperationType::RowRangeDelExclRight,
+            9 => OperationType::PostImage,
+            _ => return Err(value),
+        })
+    }
+}
+
+/// The kind of a CDC log row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// The state of the row before the change
+    /// (if the table has CDC preimages enabled).
+    PreImage,
+
+    /// The change itself.
+    Delta,
+
+    /// The state of the row after the change
+    /// (if the table has CDC postimages enabled).
+    PostImage,
+}
+
+/// A row of a CDC log table.
+///
+/// The values of the base table columns are accessed by their names. Columns
+/// not affected by a delta have null values, unless they are marked as deleted.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct CdcChange {
+    /// The stream the change belongs to.
+    pub stream_id: StreamId,
+
+    /// The time of the change.
+    pub time: CqlTimeuuid,
+
+    /// The number of the row among the rows recording the same write.
+    pub batch_seq_no: i32,
+
+    /// Whether this is the last row recording the write.
+    pub end_of_batch: bool,
+
+    /// The recorded operation.
+    pub operation: OperationType,
+
+    /// The TTL of the written values, if any.
+    pub ttl: Option<i64>,
+
+    values: HashMap<String, CqlValue>,
+    deleted: Vec<String>,
+    deleted_elements: HashMap<String, CqlValue>,
+}
+
+impl CdcChange {
+    /// Returns the kind of the change.
+    pub fn kind(&self) -> ChangeKind {
+        self.operation.kind()
+    }
+
+    /// Returns the value of the base table column, or `None` if it is null.
+    pub fn value(&self, column: &str) -> Option<&CqlValue> {
+        self.values.get(column)
+    }
+
+    /// Returns the names and values of the non-null base table columns.
+    pub fn values(&self) -> impl Iterator<Item = (&str, &CqlValue)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Returns `true` if the delta deleted the value of the base table column.
+    pub fn is_deleted(&self, column: &str) -> bool {
+        self.deleted.iter().any(|name| name == column)
+    }
+
+    /// Returns the elements removed by the delta from the collection
+    /// in the base table column.
+    pub fn deleted_elements(&self, column: &str) -> Option<&CqlValue> {
+        self.deleted_elements.get(column)
+    }
+
+    /// Creates a change from a row of a CDC log table, given the names of its columns.
+    pub(crate) fn from_row(column_names: &[String], row: Row) -> Result<Self, CdcError> {
+        let invalid = |reason: String| CdcError::InvalidLogRow(reason);
+
+        let mut stream_id = None;
+        let mut time = None;
+        let mut batch_seq_no = None;
+        let mut end_of_batch = false;
+        let mut operation = None;
+        let mut ttl = None;
+        let mut values = HashMap::new();
+        let mut deleted = Vec::new();
+        let mut deleted_elements = HashMap::new();
+
+        for (name, value) in column_names.iter().zip(row.columns) {
+            match (name.as_str(), value) {
+                (STREAM_ID, Some(CqlValue::Blob(raw))) => stream_id = Some(StreamId(raw)),
+                (TIME, Some(CqlValue::Timeuuid(value))) => time = Some(value),
+                (BATCH_SEQ_NO, Some(CqlValue::Int(value))) => batch_seq_no = Some(value),
+                (END_OF_BATCH, value) => end_of_batch = value == Some(CqlValue::Boolean(true)),
+                (OPERATION, Some(CqlValue::TinyInt(value))) => {
+                    operation = Some(
+                        OperationType::try_from(value)
+                            .map_err(|value| invalid(format!("unknown operation {}", value)))?,
+                    )
+                }
+                (TTL, value) => ttl = value.and_then(|value| value.as_bigint()),
+                (name, value) => {
+                    if let Some(column) = name.strip_prefix(DELETED_ELEMENTS_PREFIX) {
+                        if let Some(value) = value {
+                            deleted_elements.insert(column.to_owned(), value);
+                        }
+                    } else if let Some(column) = name.strip_prefix(DELETED_PREFIX) {
+                        if value == Some(CqlValue::Boolean(true)) {
+                            deleted.push(column.to_owned());
+                        }
+                    } else if name.starts_with("cdc$") {
+                        // Unknown CDC metadata column, e.g. added in a newer ScyllaDB version.
+                    } else if let Some(value) = value {
+                        values.insert(name.to_owned(), value);
+                    }
+                }
+            }
+        }
+
+        let missing = |column: &str| invalid(format!("missing or invalid column {}", column));
+        Ok(Self {
+            stream_id: stream_id.ok_or_else(|| missing(STREAM_ID))?,
+            time: time.ok_or_else(|| missing(TIME))?,
+            batch_seq_no: batch_seq_no.ok_or_else(|| missing(BATCH_SEQ_NO))?,
+            end_of_batch,
+            operation: operation.ok_or_else(|| missing(OPERATION))?,
+            ttl,
+            values,
+            deleted,
+            deleted_elements,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scylla_cql::value::{CqlTimeuuid, CqlValue, Row};
+
+    use super::{CdcChange, ChangeKind, OperationType, StreamId};
+
+    #[test]
+    fn change_from_row() {
+        let names: Vec<String> = [
+            "cdc$stream_id",
+            "cdc$time",
+            "cdc$batch_seq_no",
+            "cdc$deleted_v",
+            "cdc$deleted_elements_s",
+            "cdc$end_of_batch",
+            "cdc$operation",
+            "cdc$ttl",
+            "pk",
+            "s",
+            "v",
+        ]
+        .into_iter()
+        .map(ToOwned::to_owned)
+        .collect();
+        let time = CqlTimeuuid::from_u64_pair(0x1234_5678_9abc_1def, 0x8000_0000_0000_0001);
+        let row = Row {
+            columns: vec![
+                Some(CqlValue::Blob(vec![0xab, 0x01])),
+                Some(CqlValue::Timeuuid(time)),
+                Some(CqlValue::Int(0)),
+                Some(CqlValue::Boolean(true)),
+                Some(CqlValue::Set(vec![CqlValue::Int(5)])),
+                Some(CqlValue::Boolean(true)),
+                Some(CqlValue::TinyInt(1)),
+                None,
+                Some(CqlValue::Int(7)),
+                None,
+                None,
+            ],
+        };
+
+        let change = CdcChange::from_row(&names, row).unwrap();
+        assert_eq!(change.stream_id, StreamId::new(vec![0xab, 0x01]));
+        assert_eq!(change.stream_id.to_string(), "0xab01");
+        assert_eq!(change.time, time);
+        assert_eq!(change.operation, OperationType::RowUpdate);
+        assert_eq!(change.kind(), ChangeKind::Delta);
+        assert!(change.end_of_batch);
+        assert_eq!(change.ttl, None);
+        assert_eq!(change.value("pk"), Some(&CqlValue::Int(7)));
+        assert_eq!(change.value("v"), None);
+        assert_eq!(change.values().count(), 1);
+        assert!(change.is_deleted("v"));
+        assert!(!change.is_deleted("s"));
+        assert_eq!(
+            change.deleted_elements("s"),
+            Some(&CqlValue::Set(vec![CqlValue::Int(5)]))
+        );
+    }
+
+    #[test]
+    fn invalid_rows() {
+        let names = vec!["cdc$stream_id".to_owned(), "cdc$operation".to_owned()];
+        let row = Row {
+            columns: vec![Some(CqlValue::Blob(vec![1])), Some(CqlValue::TinyInt(42))],
+        };
+        CdcChange::from_row(&names, row).unwrap_err();
+
+        let row = Row {
+            columns: vec![Some(CqlValue::Blob(vec![1])), Some(CqlValue::TinyInt(0))],
+        };
+        CdcChange::from_row(&names, row).unwrap_err();
+    }
+}
@@ -0,0 +1,86 @@
+//! Discovery of CDC generations and their streams.
+//!
+//! A CDC generation is a mapping of the token ring to CDC streams, valid from
+//! the generation timestamp until the timestamp of the next generation.
+//! The generations are described by the tables in the `system_distributed` keyspace.
+
+use futures::TryStreamExt;
+use scylla_cql::value::CqlTimestamp;
+
+use crate::client::session::Session;
+
+use super::{CdcError, StreamId};
+
+const GENERATION_TIMESTAMPS_QUERY: &str =
+    "SELECT time FROM system_distributed.cdc_generation_timestamps WHERE key = 'timestamps'";
+const STREAMS_QUERY: &str =
+    "SELECT streams FROM system_distributed.cdc_streams_descriptions_v2 WHERE time = ?";
+
+/// Fetches the timestamps of all the CDC generations (in milliseconds), in ascending order.
+pub(crate) async fn fetch_generation_timestamps(session: &Session) -> Result<Vec<i64>, CdcError> {
+    let mut timestamps: Vec<i64> = session
+        .query_iter(GENERATION_TIMESTAMPS_QUERY, ())
+        .await?
+        .rows_stream::<(CqlTimestamp,)>()?
+        .map_ok(|(timestamp,)| timestamp.0)
+        .try_collect()
+        .await?;
+    timestamps.sort_unstable();
+    Ok(timestamps)
+}
+
+/// Fetches the streams of a CDC generation.
+///
+/// The streams are grouped by the vnodes: streams of a group are stored
+/// on the same replicas, so they can be queried together.
+pub(crate) async fn fetch_stream_groups(
+    session: &Session,
+    generation: i64,
+) -> Result<Vec<Vec<StreamId>>, CdcError> {
+    let groups = session
+        .query_iter(STREAMS_QUERY, (CqlTimestamp(generation),))
+        .await?
+        .rows_stream::<(Vec<Vec<u8>>,)>()?
+        .map_ok(|(streams,)| streams.into_iter().map(StreamId::new).collect())
+        .try_collect()
+        .await?;
+    Ok(groups)
+}
+
+/// Returns the generation which was active at the given time, or the first
+/// generation if the time precedes all of them.
+pub(crate) fn generation_at(timestamps: &[i64], time: i64) -> Option<i64> {
+    timestamps
+        .iter()
+        .rev()
+        .find(|&&timestamp| timestamp <= time)
+        .or_else(|| timestamps.first())
+        .copied()
+}
+
+/// Returns the generation following the given one, if it is already known.
+pub(crate) fn next_generation(timestamps: &[i64], generation: i64) -> Option<i64> {
+    timestamps
+        .iter()
+        .find(|&&timestamp| timestamp > generation)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generation_at, next_generation};
+
+    #[test]
+    fn generation_lookup() {
+        let timestamps = [100, 200, 300];
+        assert_eq!(generation_at(&timestamps, 50), Some(100));
+        assert_eq!(generation_at(&timestamps, 200), Some(200));
+        assert_eq!(generation_at(&timestamps, 250), Some(200));
+        assert_eq!(generation_at(&timestamps, 1000), Some(300));
+        assert_eq!(generation_at(&[], 1000), None);
+
+        assert_eq!(next_generation(&timestamps, 100), Some(200));
+        assert_eq!(next_generation(&timestamps, 150), Some(200));
+        assert_eq!(next_generation(&timestamps, 300), None);
+    }
+}
@@ -0,0 +1,122 @@
+//! Reading the changes of tables with [Change Data Capture](https://docs.scylladb.com/stable/features/cdc/)
+//! (CDC) enabled.
+//!
+//! The changes of a table with CDC enabled are written to its CDC log table
+//! (`<table>_scylla_cdc_log`). The log table is partitioned by CDC streams,
+//! which are described by CDC generations: a new generation (with a new set
+//! of streams) becomes active when the cluster topology changes.
+//!
+//! [CdcReader] follows the generations, discovers their streams, and polls
+//! the log table in windows of time:
+//! - the streams of a generation are split into groups stored on the same replicas,
+//!   and the groups are read concurrently,
+//! - the changes of each group are passed as [CdcChange]s to a [CdcConsumer],
+//!   created for the group by a [CdcConsumerFactory],
+//! - after each window, the progress of the group is saved with the
+//!   [CdcCheckpointSaver] (if any), so that a restarted reader can resume from it.
+//!
+//! ```rust
+//! # use scylla::client::session::Session;
+//! # use std::sync::Arc;
+//! # async fn check_only_compiles(session: Arc<Session>) -> Result<(), Box<dyn std::error::Error>> {
+//! use async_trait::async_trait;
+//! use scylla::cdc::{
+//!     CdcChange, CdcConsumer, CdcConsumerFactory, CdcHookError, CdcReader, StreamId,
+//! };
+//!
+//! struct Printer;
+//!
+//! #[async_trait]
+//! impl CdcConsumer for Printer {
+//!     async fn consume(&mut self, change: CdcChange) -> Result<(), CdcHookError> {
+//!         println!("{:?} in {}: {:?}", change.operation, change.stream_id, change.value("v"));
+//!         Ok(())
+//!     }
+//! }
+//!
+//! struct PrinterFactory;
+//!
+//! impl CdcConsumerFactory for PrinterFactory {
+//!     fn new_consumer(&self, _streams: &[StreamId]) -> Box<dyn CdcConsumer> {
+//!         Box::new(Printer)
+//!     }
+//! }
+//!
+//! let handle = CdcReader::new(session, "ks", "tab", Arc::new(PrinterFactory))
+//!     .start()
+//!     .await?;
+//! // ...
+//! handle.stop();
+//! handle.join().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::error::Error as StdError;
+use std::sync::Arc;
+
+use scylla_cql::deserialize::TypeCheckError;
+use thiserror::Error;
+
+use crate::errors::{NextRowError, PagerExecutionError, PrepareError};
+
+mod change;
+mod generation;
+mod reader;
+
+pub use change::{CdcChange, ChangeKind, OperationType, StreamId};
+pub use reader::{
+    CdcCheckpoint, CdcCheckpointSaver, CdcConsumer, CdcConsumerFactory, CdcHookError, CdcReader,
+    CdcReaderHandle,
+};
+
+/// An error returned by a [CdcReader].
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum CdcError {
+    /// The table was not found in the cluster metadata.
+    #[error("Table {keyspace}.{table} not found in the cluster metadata")]
+    TableNotFound { keyspace: String, table: String },
+
+    /// CDC is not enabled on the table.
+    #[error("CDC is not enabled on table {keyspace}.{table}")]
+    CdcNotEnabled { keyspace: String, table: String },
+
+    /// The cluster has no CDC generations.
+    #[error("No CDC generation found")]
+    NoGeneration,
+
+    /// Failed to prepare the query reading the CDC log table.
+    #[error("Failed to prepare the CDC log query: {0}")]
+    PrepareError(#[from] PrepareError),
+
+    /// Failed to execute a query.
+    #[error("Failed to execute a CDC query: {0}")]
+    PagerExecutionError(#[from] PagerExecutionError),
+
+    /// The rows returned by a query do not have the expected types.
+    #[error("Failed to type check the rows of a CDC query: {0}")]
+    TypeCheckError(#[from] TypeCheckError),
+
+    /// Failed to fetch or deserialize a row.
+    #[error("Failed to fetch a row of a CDC query: {0}")]
+    NextRowError(#[from] NextRowError),
+
+    /// A row of the CDC log table lacks some CDC columns or has invalid values in them.
+    #[error("Invalid CDC log row: {0}")]
+    InvalidLogRow(String),
+
+    /// The [CdcConsumer] returned an error.
+    #[error("CDC consumer failed: {0}")]
+    ConsumerError(Arc<dyn StdError + Send + Sync>),
+
+    /// The [CdcCheckpointSaver] returned an error.
+    #[error("CDC checkpoint saver failed: {0}")]
+    CheckpointSaverError(Arc<dyn StdError + Send + Sync>),
+}
+
+impl CdcError {
+    fn checkpoint_saver(error: CdcHookError) -> Self {
+        CdcError::CheckpointSaverError(error.into())
+    }
+}
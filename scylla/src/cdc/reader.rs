@@ -0,0 +1,531 @@
+//! Polling CDC log tables and passing the changes to consumers.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use scylla_cql::value::{CqlTimestamp, Row};
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::warn;
+
+use crate::client::session::Session;
+use crate::cluster::describe::Ident;
+use crate::errors::{NextRowError, PagerExecutionError};
+use crate::statement::prepared::PreparedStatement;
+
+use super::generation::{
+    fetch_generation_timestamps, fetch_stream_groups, generation_at, next_generation,
+};
+use super::{CdcChange, CdcError, StreamId};
+
+/// An error returned by the user-provided [CdcConsumer]s and [CdcCheckpointSaver]s.
+pub type CdcHookError = Box<dyn Error + Send + Sync>;
+
+/// Consumes the changes of a group of CDC streams.
+///
+/// The changes of each stream are passed in the order of their time.
+///
+/// If reading a window of time fails with a transient error (e.g. a timeout),
+/// the window is read again, so the changes of the window passed before
+/// the failure are passed again.
+#[async_trait]
+pub trait CdcConsumer: Send {
+    /// Processes a change.
+    ///
+    /// If an error is returned, the [CdcReader] stops with [CdcError::ConsumerError].
+    async fn consume(&mut self, change: CdcChange) -> Result<(), CdcHookError>;
+}
+
+/// Creates a [CdcConsumer] for each group of CDC streams read by a [CdcReader].
+///
+/// The groups of a generation are read concurrently. A new consumer is created
+/// for every group of every generation.
+pub trait CdcConsumerFactory: Send + Sync {
+    /// Creates a consumer of the changes of the given streams,
+    /// which belong to a single generation.
+    fn new_consumer(&self, streams: &[StreamId]) -> Box<dyn CdcConsumer>;
+}
+
+/// The progress of reading a group of CDC streams.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CdcCheckpoint {
+    /// The timestamp of the generation of the streams.
+    pub generation: CqlTimestamp,
+
+    /// The streams of the group.
+    pub streams: Vec<StreamId>,
+
+    /// All the changes of the streams older than this time were consumed.
+    pub time: CqlTimestamp,
+}
+
+/// Persists the progress of a [CdcReader], so that it can be resumed after a restart.
+#[async_trait]
+pub trait CdcCheckpointSaver: Send + Sync {
+    /// Saves the progress of a group of streams.
+    ///
+    /// Called after the changes of each window of time are consumed.
+    async fn save_checkpoint(&self, checkpoint: &CdcCheckpoint) -> Result<(), CdcHookError>;
+
+    /// Loads the time saved in the last checkpoint of the group of streams.
+    async fn load_checkpoint(
+        &self,
+        generation: CqlTimestamp,
+        streams: &[StreamId],
+    ) -> Result<Option<CqlTimestamp>, CdcHookError>;
+
+    /// Saves the timestamp of the generation that started being read.
+    async fn save_generation(&self, generation: CqlTimestamp) -> Result<(), CdcHookError>;
+
+    /// Loads the timestamp of the generation saved last.
+    async fn load_generation(&self) -> Result<Option<CqlTimestamp>, CdcHookError>;
+}
+
+/// Reads the changes of a table with CDC enabled.
+///
+/// The reader discovers the CDC generations and their streams, and polls
+/// the CDC log table of each group of streams in windows of time. The changes
+/// are passed to the [CdcConsumer]s created by the [CdcConsumerFactory],
+/// and the progress is saved with the [CdcCheckpointSaver] (if any) after each window.
+/// See the [module-level docs](super) for an example.
+///
+/// A window is read only once its end is older than the
+/// [safety interval](Self::with_safety_interval), so that the changes written
+/// with slightly delayed timestamps are not skipped.
+pub struct CdcReader {
+    session: Arc<Session>,
+    keyspace: String,
+    table: String,
+    consumer_factory: Arc<dyn CdcConsumerFactory>,
+    checkpoint_saver: Option<Arc<dyn CdcCheckpointSaver>>,
+    start_time: Option<CqlTimestamp>,
+    end_time: Option<CqlTimestamp>,
+    window_size: Duration,
+    safety_interval: Duration,
+    sleep_interval: Duration,
+}
+
+impl CdcReader {
+    /// Creates a reader of the changes of the table.
+    pub fn new(
+        session: Arc<Session>,
+        keyspace: impl Into<String>,
+        table: impl Into<String>,
+        consumer_factory: Arc<dyn CdcConsumerFactory>,
+    ) -> Self {
+        Self {
+            session,
+            keyspace: keyspace.into(),
+            table: table.into(),
+            consumer_factory,
+            checkpoint_saver: None,
+            start_time: None,
+            end_time: None,
+            window_size: Duration::from_secs(60),
+            safety_interval: Duration::from_secs(30),
+            sleep_interval: Duration::from_secs(10),
+        }
+    }
+
+    /// Sets the saver of checkpoints. If the saver has saved checkpoints,
+    /// the reading is resumed from them.
+    ///
+    /// When resuming from a saved generation, the groups of streams
+    /// without a checkpoint are read from the start of the generation.
+    pub fn with_checkpoint_saver(mut self, checkpoint_saver: Arc<dyn CdcCheckpointSaver>) -> Self {
+        self.checkpoint_saver = Some(checkpoint_saver);
+        self
+    }
+
+    /// Sets the time of the oldest changes to be read, unless there is a saved generation
+    /// or checkpoint to resume from.
+    ///
+    /// Defaults to the time when the reader is started.
+    pub fn with_start_time(mut self, start_time: CqlTimestamp) -> Self {
+        self.start_time = Some(start_time);
+        self
+    }
+
+    /// Sets the time after which the reader stops. The changes
+    /// not older than this time are not read.
+    ///
+    /// By default, the reader runs until it is stopped.
+    pub fn with_end_time(mut self, end_time: CqlTimestamp) -> Self {
+        self.end_time = Some(end_time);
+        self
+    }
+
+    /// Sets the maximum length of the window of time read with a single query.
+    ///
+    /// Defaults to 60 seconds.
+    pub fn with_window_size(mut self, window_size: Duration) -> Self {
+        self.window_size = window_size;
+        self
+    }
+
+    /// Sets how old the changes must be to be read.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn with_safety_interval(mut self, safety_interval: Duration) -> Self {
+        self.safety_interval = safety_interval;
+        self
+    }
+
+    /// Sets how long to wait for new changes, when all the changes old enough were read.
+    /// Reading a window which failed with a transient error is retried with an exponential
+    /// backoff, independent of this interval.
+    ///
+    /// Defaults to 10 seconds.
+    pub fn with_sleep_interval(mut self, sleep_interval: Duration) -> Self {
+        self.sleep_interval = sleep_interval;
+        self
+    }
+
+    /// Checks that CDC is enabled on the table and starts reading in the background.
+    pub async fn start(self) -> Result<CdcReaderHandle, CdcError> {
+        let log_table = format!("{}_scylla_cdc_log", self.table);
+        {
+            let cluster_state = self.session.get_cluster_state();
+            let keyspace = cluster_state.get_keyspace(&self.keyspace);
+            let table = keyspace.and_then(|keyspace| keyspace.tables.get(&self.table));
+            let Some(table) = table else {
+                return Err(CdcError::TableNotFound {
+                    keyspace: self.keyspace,
+                    table: self.table,
+                });
+            };
+            let cdc_enabled = table
+                .options
+                .cdc
+                .as_ref()
+                .and_then(|options| options.get("enabled"))
+                .is_some_and(|enabled| enabled == "true");
+            if !cdc_enabled || !keyspace.is_some_and(|ks| ks.tables.contains_key(&log_table)) {
+                return Err(CdcError::CdcNotEnabled {
+                    keyspace: self.keyspace,
+                    table: self.table,
+                });
+            }
+        }
+
+        let mut statement = self
+            .session
+            .prepare(format!(
+                "SELECT * FROM {}.{} WHERE \"cdc$stream_id\" IN ? \
+                AND \"cdc$time\" >= minTimeuuid(?) AND \"cdc$time\" < minTimeuuid(?) BYPASS CACHE",
+                Ident(&self.keyspace),
+                Ident(&log_table)
+            ))
+            .await?;
+        statement.set_is_idempotent(true);
+
+        let context = Arc::new(ReaderContext {
+            session: self.session,
+            statement,
+            consumer_factory: self.consumer_factory,
+            checkpoint_saver: self.checkpoint_saver,
+            start_time: self.start_time.map_or_else(now, |time| time.0),
+            end_time: self.end_time.map(|time| time.0),
+            window_size: duration_millis(self.window_size).max(1),
+            safety_interval: duration_millis(self.safety_interval),
+            sleep_interval: self.sleep_interval,
+        });
+        let (stop, stop_receiver) = watch::channel(false);
+        let task = tokio::spawn(read_generations(context, stop_receiver));
+        Ok(CdcReaderHandle { stop, task })
+    }
+}
+
+impl fmt::Debug for CdcReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CdcReader")
+            .field("keyspace", &self.keyspace)
+            .field("table", &self.table)
+            .field("start_time", &self.start_time)
+            .field("end_time", &self.end_time)
+            .field("window_size", &self.window_size)
+            .field("safety_interval", &self.safety_interval)
+            .field("sleep_interval", &self.sleep_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A handle to a running [CdcReader].
+///
+/// Dropping the handle stops the reader, like [stop](Self::stop).
+#[derive(Debug)]
+pub struct CdcReaderHandle {
+    stop: watch::Sender<bool>,
+    task: JoinHandle<Result<(), CdcError>>,
+}
+
+impl CdcReaderHandle {
+    /// Stops the reader. The windows of time being read are finished
+    /// (and their checkpoints saved) first.
+    pub fn stop(&self) {
+        self.stop.send_replace(true);
+    }
+
+    /// Waits until the reader stops: after [stop](Self::stop), after reaching
+    /// the [end time](CdcReader::with_end_time), or because of an error.
+    pub async fn join(self) -> Result<(), CdcError> {
+        let Self { stop, task } = self;
+        let result = task.await;
+        drop(stop);
+        // The task is never aborted, so it either finishes or panics.
+        result.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+    }
+}
+
+struct ReaderContext {
+    session: Arc<Session>,
+    statement: PreparedStatement,
+    consumer_factory: Arc<dyn CdcConsumerFactory>,
+    checkpoint_saver: Option<Arc<dyn CdcCheckpointSaver>>,
+    // Times and intervals below are in milliseconds.
+    start_time: i64,
+    end_time: Option<i64>,
+    window_size: i64,
+    safety_interval: i64,
+    sleep_interval: Duration,
+}
+
+/// The delay before the first retry of a window which failed with a transient error.
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The maximum delay between retries of a window.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Reads the generations one after another, until the end time or until stopped.
+async fn read_generations(
+    context: Arc<ReaderContext>,
+    stop: watch::Receiver<bool>,
+) -> Result<(), CdcError> {
+    let saved_generation = match &context.checkpoint_saver {
+        Some(saver) => saver
+            .load_generation()
+            .await
+            .map_err(CdcError::checkpoint_saver)?,
+        None => None,
+    };
+    // When resuming, the groups without a checkpoint are read from the start of
+    // the generation, as their changes since then were not consumed.
+    let start_time = match saved_generation {
+        Some(_) => None,
+        None => Some(context.start_time),
+    };
+    let mut generation = match saved_generation {
+        Some(generation) => generation.0,
+        None => {
+            let timestamps = fetch_generation_timestamps(&context.session).await?;
+            generation_at(&timestamps, context.start_time).ok_or(CdcError::NoGeneration)?
+        }
+    };
+
+    loop {
+        let timestamps = fetch_generation_timestamps(&context.session).await?;
+        let (next_sender, next_receiver) = watch::channel(next_generation(&timestamps, generation));
+
+        let mut tasks = JoinSet::new();
+        for streams in fetch_stream_groups(&context.session, generation).await? {
+            tasks.spawn(read_stream_group(
+                Arc::clone(&context),
+                generation,
+                start_time,
+                streams,
+                next_receiver.clone(),
+                stop.clone(),
+            ));
+        }
+
+        // Wait for the groups, looking for the next generation in the meantime.
+        // If a group fails, the other ones are aborted when the JoinSet is dropped.
+        loop {
+            tokio::select! {
+                result = tasks.join_next() => match result {
+                    Some(result) => result
+                        .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))?,
+                    None => break,
+                },
+                _ = tokio::time::sleep(context.sleep_interval), if next_sender.borrow().is_none() => {
+                    let timestamps = fetch_generation_timestamps(&context.session).await?;
+                    next_sender.send_replace(next_generation(&timestamps, generation));
+                }
+            }
+        }
+
+        let next = *next_sender.borrow();
+        match next {
+            Some(next) if !is_stopped(&stop) && context.end_time.map_or(true, |end| next < end) => {
+                if let Some(saver) = &context.checkpoint_saver {
+                    saver
+                        .save_generation(CqlTimestamp(next))
+                        .await
+                        .map_err(CdcError::checkpoint_saver)?;
+                }
+                generation = next;
+            }
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Reads a group of streams of a generation in windows of time, until the next
+/// generation or the end time is reached, or until stopped.
+///
+/// Without a checkpoint, the group is read from `start_time`
+/// (or from the start of the generation, if it is later or `None`).
+async fn read_stream_group(
+    context: Arc<ReaderContext>,
+    generation: i64,
+    start_time: Option<i64>,
+    streams: Vec<StreamId>,
+    mut next_generation: watch::Receiver<Option<i64>>,
+    mut stop: watch::Receiver<bool>,
+) -> Result<(), CdcError> {
+    let mut consumer = context.consumer_factory.new_consumer(&streams);
+    let checkpoint = match &context.checkpoint_saver {
+        Some(saver) => saver
+            .load_checkpoint(CqlTimestamp(generation), &streams)
+            .await
+            .map_err(CdcError::checkpoint_saver)?,
+        None => None,
+    };
+    let mut window_start = checkpoint
+        .map(|time| time.0)
+        .or(start_time)
+        .map_or(generation, |time| time.max(generation));
+    let raw_streams: Vec<&[u8]> = streams.iter().map(StreamId::as_bytes).collect();
+    let mut retry_delay = MIN_RETRY_DELAY;
+
+    loop {
+        if is_stopped(&stop) {
+            return Ok(());
+        }
+        let limit = [context.end_time, *next_generation.borrow()]
+            .into_iter()
+            .flatten()
+            .min();
+        if limit.is_some_and(|limit| window_start >= limit) {
+            return Ok(());
+        }
+
+        let mut window_end = (window_start + context.window_size)
+            .min(now() - context.safety_interval)
+            .min(limit.unwrap_or(i64::MAX));
+        if window_end <= window_start {
+            tokio::select! {
+                _ = tokio::time::sleep(context.sleep_interval) => {}
+                _ = stop.changed() => {}
+                _ = next_generation.changed() => {}
+            }
+            continue;
+        }
+        // The window must not end in the future, even if the safety interval is 0.
+        window_end = window_end.min(now());
+
+        let result = read_window(
+            &context,
+            &raw_streams,
+            window_start,
+            window_end,
+            consumer.as_mut(),
+        )
+        .await;
+        match result {
+            Ok(()) => retry_delay = MIN_RETRY_DELAY,
+            Err(err) if is_transient(&err) => {
+                warn!(
+                    "Failed to read CDC window [{}, {}) of generation {}, retrying in {:?}: {}",
+                    window_start, window_end, generation, retry_delay, err
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(retry_delay) => {}
+                    _ = stop.changed() => {}
+                }
+                retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                continue;
+            }
+            Err(err) => return Err(err),
+        }
+
+        if let Some(saver) = &context.checkpoint_saver {
+            let checkpoint = CdcCheckpoint {
+                generation: CqlTimestamp(generation),
+                streams: streams.clone(),
+                time: CqlTimestamp(window_end),
+            };
+            saver
+                .save_checkpoint(&checkpoint)
+                .await
+                .map_err(CdcError::checkpoint_saver)?;
+        }
+        window_start = window_end;
+    }
+}
+
+/// Reads the changes of the streams in the window of time and passes them to the consumer.
+async fn read_window(
+    context: &ReaderContext,
+    raw_streams: &[&[u8]],
+    window_start: i64,
+    window_end: i64,
+    consumer: &mut dyn CdcConsumer,
+) -> Result<(), CdcError> {
+    let pager = context
+        .session
+        .execute_iter(
+            context.statement.clone(),
+            (
+                raw_streams,
+                CqlTimestamp(window_start),
+                CqlTimestamp(window_end),
+            ),
+        )
+        .await?;
+    let column_names: Vec<String> = pager
+        .column_specs()
+        .iter()
+        .map(|spec| spec.name().to_owned())
+        .collect();
+    let mut rows = pager.rows_stream::<Row>()?;
+    while let Some(row) = rows.try_next().await? {
+        let change = CdcChange::from_row(&column_names, row)?;
+        consumer
+            .consume(change)
+            .await
+            .map_err(|err| CdcError::ConsumerError(err.into()))?;
+    }
+    Ok(())
+}
+
+/// Returns `true` if the error is caused by a failure to fetch a page,
+/// so reading the window again may succeed.
+fn is_transient(error: &CdcError) -> bool {
+    matches!(
+        error,
+        CdcError::PagerExecutionError(PagerExecutionError::NextPageError(_))
+            | CdcError::NextRowError(NextRowError::NextPageError(_))
+    )
+}
+
+/// Returns `true` if the reader was stopped, or its handle was dropped.
+fn is_stopped(stop: &watch::Receiver<bool>) -> bool {
+    *stop.borrow() || stop.has_changed().is_err()
+}
+
+/// Returns the current time in milliseconds since the Unix epoch.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, duration_millis)
+}
+
+fn duration_millis(duration: Duration) -> i64 {
+    duration.as_millis().try_into().unwrap_or(i64::MAX)
+}
@@ -0,0 +1,696 @@
+//! Loading large amounts of rows with a single prepared statement.
+//!
+//! [BulkWriter] executes a prepared statement (typically an `INSERT`) for many rows,
+//! taking care of the things that are easy to get wrong when it is done by hand:
+//! - rows are grouped by the replica (and shard) owning their partition,
+//!   and rows of the same partition are sent together in unlogged batches,
+//! - the number of requests in flight is bounded, so that the cluster and the driver
+//!   are not overwhelmed, and the rows are not buffered without limits,
+//! - rows which fail with retryable errors are retried with a backoff, and the ones
+//!   that can't be written are reported along with the errors, without aborting the whole load.
+//!
+//! ```rust
+//! # use scylla::client::session::Session;
+//! # use std::num::NonZeroUsize;
+//! # use std::sync::Arc;
+//! # async fn check_only_compiles(session: Arc<Session>) -> Result<(), Box<dyn std::error::Error>> {
+//! use scylla::client::bulk_writer::BulkWriter;
+//!
+//! let statement = session
+//!     .prepare("INSERT INTO ks.tab (a, b, c) VALUES (?, ?, ?)")
+//!     .await?;
+//! let mut writer = BulkWriter::new(session, statement)
+//!     .with_max_batch_size(NonZeroUsize::new(16).unwrap());
+//! for i in 0..100_000 {
+//!     writer.push((i % 100, i, "abc")).await;
+//! }
+//! let report = writer.finish().await;
+//! println!("Written {} rows", report.written);
+//! for failure in report.failures {
+//!     println!("Failed to write row {}: {}", failure.index, failure.error);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use scylla_cql::frame::response::result::TableSpec;
+use thiserror::Error;
+use tokio::sync::Semaphore;
+use tokio::task::{JoinError, JoinSet};
+use uuid::Uuid;
+
+use crate::client::session::Session;
+use crate::cluster::ClusterState;
+use crate::errors::ExecutionError;
+use crate::frame::types::Consistency;
+#[cfg(feature = "metrics")]
+use crate::observability::metrics::Metrics;
+use crate::policies::retry::{
+    backoff_delay, RequestInfo, RetryBudgetTracker, RetryDecision, RetryPolicy,
+};
+use crate::routing::{Shard, Token};
+use crate::serialize::row::SerializeRow;
+use crate::statement::batch::{Batch, BatchType};
+use crate::statement::prepared::{PartitionKeyError, PreparedStatement};
+
+/// The host ID of a node and the shard owning a partition.
+type ReplicaKey = (Uuid, Shard);
+
+/// Writes many rows using a prepared statement.
+///
+/// Rows are added with [push](Self::push), which returns as soon as the row
+/// is buffered, or waits if too many requests are in flight.
+/// [finish](Self::finish) waits until all the rows are written and returns
+/// a [BulkWriteReport]. See the [module-level docs](self) for an example.
+///
+/// Rows are buffered until the number of buffered rows reaches
+/// [max_buffered_rows](Self::with_max_buffered_rows). Then the rows
+/// of the replica with most rows buffered are sent: rows of the same partition
+/// are combined into unlogged batches of at most [max_batch_size](Self::with_max_batch_size)
+/// statements, while the other rows are executed on their own.
+///
+/// If a batch fails, its rows are executed one by one. A row that fails is executed again
+/// (up to [max_attempts](Self::with_max_attempts) times, in addition to the retries
+/// done by the [retry policy](crate::policies::retry)) only if the statement is
+/// [idempotent](PreparedStatement::set_is_idempotent) and the retry policy considers
+/// the error retryable. These attempts count as retries against the session's
+/// [retry budget](crate::client::session_builder::GenericSessionBuilder::retry_budget).
+/// Attempts are separated by an exponentially growing, randomized delay,
+/// see [with_retry_delay](Self::with_retry_delay). Rows which can't be written are reported as failed.
+///
+/// Rows of a failed batch of a non-idempotent statement are not executed again,
+/// because the batch might have been applied; they are all reported as failed.
+///
+/// The batches inherit consistency, serial consistency, timestamp, idempotence,
+/// execution profile, retry policy and load balancing policy of the statement.
+pub struct BulkWriter<V> {
+    session: Arc<Session>,
+    statement: PreparedStatement,
+    max_batch_size: NonZeroUsize,
+    max_buffered_rows: NonZeroUsize,
+    max_attempts: NonZeroUsize,
+    base_retry_delay: Duration,
+    max_retry_delay: Duration,
+    in_flight: Arc<Semaphore>,
+    tasks: JoinSet<BulkWriteReport<V>>,
+    buffers: HashMap<Option<ReplicaKey>, Vec<PendingRow<V>>>,
+    buffered_rows: usize,
+    next_index: usize,
+    report: BulkWriteReport<V>,
+}
+
+impl<V> BulkWriter<V>
+where
+    V: SerializeRow + Send + Sync + 'static,
+{
+    /// Creates a writer executing the statement on the session.
+    pub fn new(session: Arc<Session>, statement: PreparedStatement) -> Self {
+        Self {
+            session,
+            statement,
+            max_batch_size: NonZeroUsize::new(32).unwrap(),
+            max_buffered_rows: NonZeroUsize::new(10_000).unwrap(),
+            max_attempts: NonZeroUsize::new(3).unwrap(),
+            base_retry_delay: Duration::from_millis(100),
+            max_retry_delay: Duration::from_secs(5),
+            in_flight: Arc::new(Semaphore::new(256)),
+            tasks: JoinSet::new(),
+            buffers: HashMap::new(),
+            buffered_rows: 0,
+            next_index: 0,
+            report: BulkWriteReport::default(),
+        }
+    }
+
+    /// Sets the maximum number of statements in a single batch.
+    ///
+    /// Setting it to 1 disables batching. Defaults to 32.
+    pub fn with_max_batch_size(mut self, max_batch_size: NonZeroUsize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Sets the maximum number of rows buffered before they are sent.
+    ///
+    /// More buffered rows give more opportunities to combine rows of the same
+    /// partition into batches, at the cost of memory. Defaults to 10 000.
+    pub fn with_max_buffered_rows(mut self, max_buffered_rows: NonZeroUsize) -> Self {
+        self.max_buffered_rows = max_buffered_rows;
+        self
+    }
+
+    /// Sets the maximum number of requests (batches or single statements) in flight.
+    ///
+    /// Defaults to 256.
+    pub fn with_max_in_flight(mut self, max_in_flight: NonZeroUsize) -> Self {
+        self.in_flight = Arc::new(Semaphore::new(max_in_flight.get()));
+        self
+    }
+
+    /// Sets the maximum number of attempts to write a single row.
+    ///
+    /// Defaults to 3.
+    pub fn with_max_attempts(mut self, max_attempts: NonZeroUsize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the delays between attempts to write a row.
+    ///
+    /// The delay before the n-th retry is random, up to `min(max_delay, base_delay * 2^(n-1))`.
+    /// Defaults to 100ms base delay and 5s max delay.
+    pub fn with_retry_delay(mut self, base_delay: Duration, max_delay: Duration) -> Self {
+        self.base_retry_delay = base_delay;
+        self.max_retry_delay = max_delay;
+        self
+    }
+
+    /// Adds a row to be written.
+    ///
+    /// Waits if the buffer is full and the number of requests in flight is at the limit.
+    /// Rows are numbered by the order in which they are pushed, starting from 0;
+    /// the numbers identify the rows in [BulkWriteFailure]s.
+    pub async fn push(&mut self, row: V) {
+        let index = self.next_index;
+        self.next_index += 1;
+
+        let token = match self.statement.calculate_token(&row) {
+            Ok(token) => token,
+            Err(err) => {
+                self.report.failures.push(BulkWriteFailure {
+                    index,
+                    row,
+                    error: BulkWriteError::PartitionKeyError(err),
+                });
+                return;
+            }
+        };
+        let replica = token.and_then(|token| self.primary_replica(token));
+        self.buffers
+            .entry(replica)
+            .or_default()
+            .push(PendingRow { index, token, row });
+        self.buffered_rows += 1;
+
+        if self.buffered_rows >= self.max_buffered_rows.get() {
+            let fullest = self
+                .buffers
+                .iter()
+                .max_by_key(|(_, rows)| rows.len())
+                .map(|(replica, _)| *replica)
+                .expect("At least one row is buffered");
+            self.flush_replica(fullest).await;
+        }
+    }
+
+    /// Writes all the rows and returns the report, like [push](Self::push)
+    /// followed by [finish](Self::finish).
+    pub async fn write_all(mut self, rows: impl IntoIterator<Item = V>) -> BulkWriteReport<V> {
+        for row in rows {
+            self.push(row).await;
+        }
+        self.finish().await
+    }
+
+    /// Sends all the buffered rows, waits until all of them are written
+    /// and returns the report.
+    pub async fn finish(mut self) -> BulkWriteReport<V> {
+        let replicas: Vec<_> = self.buffers.keys().copied().collect();
+        for replica in replicas {
+            self.flush_replica(replica).await;
+        }
+        while let Some(result) = self.tasks.join_next().await {
+            self.record(result);
+        }
+        self.report.failures.sort_by_key(|failure| failure.index);
+        self.report
+    }
+
+    fn primary_replica(&self, token: Token) -> Option<ReplicaKey> {
+        let table_spec = self.statement.get_table_spec()?;
+        primary_replica(&self.session.get_cluster_state(), table_spec, token)
+    }
+
+    /// Sends the rows buffered for the replica, grouping the rows of the same partition.
+    async fn flush_replica(&mut self, replica: Option<ReplicaKey>) {
+        let rows = self.buffers.remove(&replica).unwrap_or_default();
+        self.buffered_rows -= rows.len();
+
+        for chunk in split_into_batches(rows, self.max_batch_size) {
+            self.send(chunk).await;
+        }
+    }
+
+    /// Spawns a task writing the rows, once there is a free slot for a request.
+    async fn send(&mut self, rows: Vec<PendingRow<V>>) {
+        let permit = Arc::clone(&self.in_flight)
+            .acquire_owned()
+            .await
+            .expect("In-flight limit semaphore closed");
+        while let Some(result) = self.tasks.try_join_next() {
+            self.record(result);
+        }
+
+        let session = Arc::clone(&self.session);
+        let statement = self.statement.clone();
+        let retries = RowRetries::new(
+            &session,
+            &statement,
+            self.max_attempts,
+            self.base_retry_delay,
+            self.max_retry_delay,
+        );
+        self.tasks.spawn(async move {
+            let report = write_rows(&session, &statement, rows, &retries).await;
+            drop(permit);
+            report
+        });
+    }
+
+    fn record(&mut self, result: Result<BulkWriteReport<V>, JoinError>) {
+        // The tasks are never aborted, so they either finish or panic.
+        let report = result.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()));
+        self.report.written += report.written;
+        self.report.failures.extend(report.failures);
+    }
+}
+
+impl<V> fmt::Debug for BulkWriter<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BulkWriter")
+            .field("statement", &self.statement.get_statement())
+            .field("max_batch_size", &self.max_batch_size)
+            .field("max_buffered_rows", &self.max_buffered_rows)
+            .field("max_attempts", &self.max_attempts)
+            .field("base_retry_delay", &self.base_retry_delay)
+            .field("max_retry_delay", &self.max_retry_delay)
+            .field("buffered_rows", &self.buffered_rows)
+            .field("tasks", &self.tasks.len())
+            .field("pushed_rows", &self.next_index)
+            .finish()
+    }
+}
+
+struct PendingRow<V> {
+    index: usize,
+    token: Option<Token>,
+    row: V,
+}
+
+/// Returns the host ID and shard of the primary replica owning the token.
+fn primary_replica(
+    cluster_state: &ClusterState,
+    table_spec: &TableSpec,
+    token: Token,
+) -> Option<ReplicaKey> {
+    cluster_state
+        .get_token_endpoints_iter(table_spec, token)
+        .next()
+        .map(|(node, shard)| (node.host_id, shard))
+}
+
+/// Splits the rows into groups sent as single requests: rows of the same partition
+/// are grouped into chunks of at most `max_batch_size` rows, other rows are sent alone.
+fn split_into_batches<V>(
+    mut rows: Vec<PendingRow<V>>,
+    max_batch_size: NonZeroUsize,
+) -> Vec<Vec<PendingRow<V>>> {
+    // The sort is stable, so the rows of a partition are kept in the order of pushing.
+    rows.sort_by_key(|row| row.token);
+    let mut batches = Vec::new();
+    let mut rows = rows.into_iter().peekable();
+    while let Some(first) = rows.next() {
+        let token = first.token;
+        let mut chunk = vec![first];
+        // Rows with unknown tokens are not batched together.
+        while token.is_some()
+            && chunk.len() < max_batch_size.get()
+            && rows.peek().is_some_and(|row| row.token == token)
+        {
+            chunk.extend(rows.next());
+        }
+        batches.push(chunk);
+    }
+    batches
+}
+
+/// Decides whether and when the rows which failed to be written are executed again.
+struct RowRetries {
+    max_attempts: NonZeroUsize,
+    base_delay: Duration,
+    max_delay: Duration,
+    is_idempotent: bool,
+    retry_policy: Arc<dyn RetryPolicy>,
+    consistency: Consistency,
+    retry_budget: Option<Arc<RetryBudgetTracker>>,
+    #[cfg(feature = "metrics")]
+    metrics: Arc<Metrics>,
+}
+
+impl RowRetries {
+    fn new(
+        session: &Session,
+        statement: &PreparedStatement,
+        max_attempts: NonZeroUsize,
+        base_delay: Duration,
+        max_delay: Duration,
+    ) -> Self {
+        let profile = statement
+            .get_execution_profile_handle()
+            .unwrap_or_else(|| session.get_default_execution_profile_handle())
+            .access();
+        Self {
+            max_attempts,
+            base_delay,
+            max_delay,
+            is_idempotent: statement.get_is_idempotent(),
+            retry_policy: statement
+                .get_retry_policy()
+                .cloned()
+                .unwrap_or_else(|| Arc::clone(&profile.retry_policy)),
+            consistency: statement.get_consistency().unwrap_or(profile.consistency),
+            retry_budget: session.get_retry_budget(),
+            #[cfg(feature = "metrics")]
+            metrics: session.get_metrics(),
+        }
+    }
+
+    /// Returns true if a request which failed after `attempts` attempts with the error
+    /// should be sent again. Only errors of the last attempt are considered retryable,
+    /// and only if the retry policy of the statement would retry them
+    /// and the retry budget of the session is not exhausted.
+    fn should_retry(&self, attempts: usize, error: &ExecutionError) -> bool {
+        if !self.is_idempotent || attempts >= self.max_attempts.get() {
+            return false;
+        }
+        let ExecutionError::LastAttemptError(error) = error else {
+            return false;
+        };
+        let decision = self
+            .retry_policy
+            .new_session()
+            .decide_should_retry(RequestInfo {
+                error,
+                is_idempotent: self.is_idempotent,
+                consistency: self.consistency,
+                retry_budget_exhausted: self
+                    .retry_budget
+                    .as_ref()
+                    .is_some_and(|budget| budget.is_exhausted()),
+            });
+        if let Some(retry_budget) = &self.retry_budget {
+            if !retry_budget.try_withdraw(&decision) {
+                #[cfg(feature = "metrics")]
+                self.metrics.inc_retry_budget_exhausted_num();
+                return false;
+            }
+        }
+        !matches!(
+            decision,
+            RetryDecision::DontRetry | RetryDecision::IgnoreWriteError
+        )
+    }
+
+    /// The randomized delay before the next attempt, after `attempts` attempts.
+    fn delay(&self, attempts: usize) -> Duration {
+        backoff_delay(self.base_delay, self.max_delay, attempts.saturating_sub(1))
+    }
+}
+
+/// Writes the rows in a batch, falling back to writing them one by one.
+async fn write_rows<V: SerializeRow>(
+    session: &Session,
+    statement: &PreparedStatement,
+    rows: Vec<PendingRow<V>>,
+    retries: &RowRetries,
+) -> BulkWriteReport<V> {
+    let mut report = BulkWriteReport::default();
+
+    if rows.len() > 1 {
+        let batch = same_partition_batch(statement, rows.len());
+        let values: Vec<&V> = rows.iter().map(|row| &row.row).collect();
+        match session.batch(&batch, values).await {
+            Ok(_) => {
+                report.written = rows.len();
+                return report;
+            }
+            // The batch might have been applied, so its rows can't be sent again.
+            Err(err) if !retries.is_idempotent => {
+                report.failures.extend(rows.into_iter().map(
+                    |PendingRow { index, row, .. }| BulkWriteFailure {
+                        index,
+                        row,
+                        error: BulkWriteError::ExecutionError(err.clone()),
+                    },
+                ));
+                return report;
+            }
+            // Write the rows one by one, so that only the failing ones are reported.
+            Err(_) => {}
+        }
+    }
+
+    for PendingRow { index, row, .. } in rows {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match session.execute_unpaged(statement, &row).await {
+                Ok(_) => {
+                    report.written += 1;
+                    break;
+                }
+                Err(err) if retries.should_retry(attempts, &err) => {
+                    tokio::time::sleep(retries.delay(attempts)).await;
+                }
+                Err(err) => {
+                    report.failures.push(BulkWriteFailure {
+                        index,
+                        row,
+                        error: BulkWriteError::ExecutionError(err),
+                    });
+                    break;
+                }
+            }
+        }
+    }
+    report
+}
+
+/// Creates an unlogged batch of `size` copies of the statement, with the statement's options.
+fn same_partition_batch(statement: &PreparedStatement, size: usize) -> Batch {
+    let mut batch = Batch::new(BatchType::Unlogged);
+    for _ in 0..size {
+        batch.append_statement(statement.clone());
+    }
+    if let Some(consistency) = statement.get_consistency() {
+        batch.set_consistency(consistency);
+    }
+    batch.set_serial_consistency(statement.get_serial_consistency());
+    batch.set_timestamp(statement.get_timestamp());
+    batch.set_is_idempotent(statement.get_is_idempotent());
+    batch.set_execution_profile_handle(statement.get_execution_profile_handle().cloned());
+    batch.set_retry_policy(statement.get_retry_policy().cloned());
+    batch.set_load_balancing_policy(statement.get_load_balancing_policy().cloned());
+    batch
+}
+
+/// The outcome of writing rows with a [BulkWriter].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct BulkWriteReport<V> {
+    /// The number of rows written successfully.
+    pub written: usize,
+
+    /// The rows that failed to be written, ordered by their indexes.
+    pub failures: Vec<BulkWriteFailure<V>>,
+}
+
+impl<V> Default for BulkWriteReport<V> {
+    fn default() -> Self {
+        Self {
+            written: 0,
+            failures: Vec::new(),
+        }
+    }
+}
+
+/// A row that failed to be written by a [BulkWriter].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct BulkWriteFailure<V> {
+    /// The index of the row, i.e. the number of rows pushed before it.
+    pub index: usize,
+
+    /// The row itself.
+    pub row: V,
+
+    /// The error of the last attempt to write the row.
+    pub error: BulkWriteError,
+}
+
+/// An error which prevented a [BulkWriter] from writing a row.
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum BulkWriteError {
+    /// Failed to compute the token of the row, e.g. because
+    /// the values of the partition key could not be serialized.
+    #[error("Failed to compute the token of the row: {0}")]
+    PartitionKeyError(#[from] PartitionKeyError),
+
+    /// Failed to execute the statement for the row.
+    #[error(transparent)]
+    ExecutionError(#[from] ExecutionError),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::num::NonZeroUsize;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use scylla_cql::frame::response::error::DbError;
+
+    use super::{primary_replica, split_into_batches, PendingRow, RowRetries};
+    use crate::cluster::ClusterState;
+    use crate::errors::{ExecutionError, RequestAttemptError};
+    use crate::frame::types::Consistency;
+    #[cfg(feature = "metrics")]
+    use crate::observability::metrics::Metrics;
+    use crate::policies::retry::{DefaultRetryPolicy, RetryBudget, RetryBudgetTracker};
+    use crate::routing::locator::tablets::TabletsInfo;
+    use crate::routing::locator::test::{mock_metadata_for_token_aware_tests, TABLE_SS_RF_2};
+    use crate::routing::Token;
+    use crate::test_utils::setup_tracing;
+
+    fn pending_rows(tokens: &[Option<i64>]) -> Vec<PendingRow<usize>> {
+        tokens
+            .iter()
+            .enumerate()
+            .map(|(index, token)| PendingRow {
+                index,
+                token: token.map(Token::new),
+                row: index,
+            })
+            .collect()
+    }
+
+    fn batch_indexes(batches: &[Vec<PendingRow<usize>>]) -> Vec<Vec<usize>> {
+        batches
+            .iter()
+            .map(|batch| batch.iter().map(|row| row.index).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_rows_of_same_partition_are_batched() {
+        setup_tracing();
+        let rows = pending_rows(&[Some(2), Some(1), Some(2), None, Some(1), None, Some(2)]);
+        let batches = split_into_batches(rows, NonZeroUsize::new(32).unwrap());
+        // Rows with unknown tokens are sent alone, the others are grouped
+        // by the token, in the order of pushing.
+        assert_eq!(
+            batch_indexes(&batches),
+            vec![vec![3], vec![5], vec![1, 4], vec![0, 2, 6]]
+        );
+    }
+
+    #[test]
+    fn test_batch_size_is_limited() {
+        setup_tracing();
+        let rows = pending_rows(&[Some(7); 5]);
+        let batches = split_into_batches(rows, NonZeroUsize::new(2).unwrap());
+        assert_eq!(
+            batch_indexes(&batches),
+            vec![vec![0, 1], vec![2, 3], vec![4]]
+        );
+
+        // Batch size 1 disables batching.
+        let rows = pending_rows(&[Some(7), Some(7), Some(8)]);
+        let batches = split_into_batches(rows, NonZeroUsize::new(1).unwrap());
+        assert_eq!(batch_indexes(&batches), vec![vec![0], vec![1], vec![2]]);
+    }
+
+    #[tokio::test]
+    async fn test_rows_are_grouped_by_primary_replica() {
+        setup_tracing();
+        let cluster_state = ClusterState::new(
+            mock_metadata_for_token_aware_tests(),
+            &Default::default(),
+            &HashMap::new(),
+            &None,
+            None,
+            TabletsInfo::new(),
+            &HashMap::new(),
+            #[cfg(feature = "metrics")]
+            &Default::default(),
+        )
+        .await;
+        let replica = |token| primary_replica(&cluster_state, TABLE_SS_RF_2, Token::new(token));
+
+        // Tokens 110 and 140 are owned by the node with token 150, and 160 by the one with token 200.
+        assert!(replica(110).is_some());
+        assert_eq!(replica(110), replica(140));
+        assert_ne!(replica(140), replica(160));
+        // The ring wraps around.
+        assert_eq!(replica(950), replica(10));
+    }
+
+    #[test]
+    fn test_retries_consult_retry_budget() {
+        setup_tracing();
+        let budget = RetryBudget::new(0.0).with_min_retries_per_second(1);
+        let tracker = Arc::new(RetryBudgetTracker::new(&budget));
+        let retries = RowRetries {
+            max_attempts: NonZeroUsize::new(100).unwrap(),
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            is_idempotent: true,
+            retry_policy: Arc::new(DefaultRetryPolicy::new()),
+            consistency: Consistency::One,
+            retry_budget: Some(Arc::clone(&tracker)),
+            #[cfg(feature = "metrics")]
+            metrics: Arc::new(Metrics::new()),
+        };
+        let error = ExecutionError::LastAttemptError(RequestAttemptError::DbError(
+            DbError::Overloaded,
+            "overloaded".to_owned(),
+        ));
+
+        // The budget allows 10 retries in the 10s window, each of them withdrawn from it.
+        for attempts in 1..=10 {
+            assert!(retries.should_retry(attempts, &error));
+        }
+        assert!(tracker.is_exhausted());
+        assert!(!retries.should_retry(11, &error));
+        #[cfg(feature = "metrics")]
+        assert_eq!(retries.metrics.get_retry_budget_exhausted_num(), 1);
+    }
+
+    #[test]
+    fn test_retry_delay_is_bounded() {
+        setup_tracing();
+        let retries = RowRetries {
+            max_attempts: NonZeroUsize::new(100).unwrap(),
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            is_idempotent: true,
+            retry_policy: Arc::new(DefaultRetryPolicy::new()),
+            consistency: Consistency::One,
+            retry_budget: None,
+            #[cfg(feature = "metrics")]
+            metrics: Arc::new(Metrics::new()),
+        };
+        for _ in 0..100 {
+            assert!(retries.delay(1) <= Duration::from_millis(100));
+            assert!(retries.delay(3) <= Duration::from_millis(400));
+            assert!(retries.delay(64) <= Duration::from_secs(1));
+        }
+    }
+}
@@ -1,14 +1,13 @@
 use crate::errors::{ExecutionError, PagerExecutionError, PrepareError};
 use crate::response::query_result::QueryResult;
+#[cfg(feature = "paging-cursor")]
+use crate::response::PagingCursor;
 use crate::response::{PagingState, PagingStateResponse};
-use crate::routing::partitioner::PartitionerName;
 use crate::statement::batch::{Batch, BatchStatement};
 use crate::statement::prepared::PreparedStatement;
 use crate::statement::unprepared::Statement;
-use bytes::Bytes;
 use dashmap::DashMap;
 use futures::future::try_join_all;
-use scylla_cql::frame::response::result::{PreparedMetadata, ResultMetadata};
 use scylla_cql::serialize::batch::BatchValues;
 use scylla_cql::serialize::row::SerializeRow;
 use std::collections::hash_map::RandomState;
@@ -19,17 +18,13 @@ use std::sync::Arc;
 use crate::client::pager::QueryPager;
 use crate::client::session::Session;
 
-/// Contains just the parts of a prepared statement that were returned
-/// from the database. All remaining parts (query string, page size,
-/// consistency, etc.) are taken from the Query passed
-/// to the `CachingSession::execute` family of methods.
-#[derive(Debug)]
-struct RawPreparedStatementData {
-    id: Bytes,
-    is_confirmed_lwt: bool,
-    metadata: PreparedMetadata,
-    result_metadata: Arc<ResultMetadata<'static>>,
-    partitioner_name: PartitionerName,
+/// Identifies a prepared statement in the cache. Statements with the same
+/// contents prepared in different keyspaces get different ids, so the keyspace
+/// set on the statement is a part of the key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct StatementCacheKey {
+    statement: String,
+    keyspace: Option<String>,
 }
 
 /// Provides auto caching while executing queries
@@ -42,7 +37,12 @@ where
     /// If a prepared statement is added while the limit is reached, the oldest prepared statement
     /// is removed from the cache
     max_capacity: usize,
-    cache: DashMap<String, RawPreparedStatementData, S>,
+    /// Only the parts of the cached statements that were returned from the database
+    /// are used. All remaining parts (page size, consistency, etc.) are taken from the
+    /// Query passed to the `CachingSession::execute` family of methods.
+    /// The cached statements share their result metadata with the statements
+    /// handed out to the users, so that metadata refreshes are visible to all of them.
+    cache: DashMap<StatementCacheKey, PreparedStatement, S>,
     use_cached_metadata: bool,
 }
 
@@ -117,6 +117,22 @@ where
         self.session.execute_iter(prepared, values).await
     }
 
+    /// Does the same thing as [`Session::execute_iter_from`]
+    /// but uses the prepared statement cache.
+    #[cfg(feature = "paging-cursor")]
+    pub async fn execute_iter_from(
+        &self,
+        query: impl Into<Statement>,
+        values: impl SerializeRow,
+        cursor: PagingCursor,
+    ) -> Result<QueryPager, PagerExecutionError> {
+        let query = query.into();
+        let prepared = self.add_prepared_statement_owned(query).await?;
+        self.session
+            .execute_iter_from(prepared, values, cursor)
+            .await
+    }
+
     /// Does the same thing as [`Session::execute_single_page`]
     /// but uses the prepared statement cache.
     pub async fn execute_single_page(
@@ -196,24 +212,20 @@ where
         &self,
         query: impl Into<Statement>,
     ) -> Result<PreparedStatement, PrepareError> {
-        let query = query.into();
+        let mut query = query.into();
+        let key = StatementCacheKey {
+            statement: std::mem::take(&mut query.contents),
+            keyspace: query.config.keyspace.clone(),
+        };
 
-        if let Some(raw) = self.cache.get(&query.contents) {
-            let page_size = query.get_validated_page_size();
-            let mut stmt = PreparedStatement::new(
-                raw.id.clone(),
-                raw.is_confirmed_lwt,
-                raw.metadata.clone(),
-                raw.result_metadata.clone(),
-                query.contents,
-                page_size,
-                query.config,
-            );
-            stmt.set_partitioner_name(raw.partitioner_name.clone());
+        if let Some(cached) = self.cache.get(&key) {
+            let mut stmt = cached.clone();
+            stmt.set_validated_page_size(query.get_validated_page_size());
+            stmt.config = query.config;
             stmt.set_use_cached_result_metadata(self.use_cached_metadata);
             Ok(stmt)
         } else {
-            let query_contents = query.contents.clone();
+            query.contents = key.statement.clone();
             let prepared = {
                 let mut stmt = self.session.prepare(query).await?;
                 stmt.set_use_cached_result_metadata(self.use_cached_metadata);
@@ -222,10 +234,10 @@ where
 
             if self.max_capacity == self.cache.len() {
                 // Cache is full, remove the first entry
-                // Don't hold a reference into the map (that's why the to_string() is called)
+                // Don't hold a reference into the map (that's why the clone() is called)
                 // This is because the documentation of the remove fn tells us that it may deadlock
                 // when holding some sort of reference into the map
-                let query = self.cache.iter().next().map(|c| c.key().to_string());
+                let query = self.cache.iter().next().map(|c| c.key().clone());
 
                 // Don't inline this: https://stackoverflow.com/questions/69873846/an-owned-value-is-still-references-somehow
                 if let Some(q) = query {
@@ -233,14 +245,7 @@ where
                 }
             }
 
-            let raw = RawPreparedStatementData {
-                id: prepared.get_id().clone(),
-                is_confirmed_lwt: prepared.is_confirmed_lwt(),
-                metadata: prepared.get_prepared_metadata().clone(),
-                result_metadata: prepared.get_result_metadata().clone(),
-                partitioner_name: prepared.get_partitioner_name().clone(),
-            };
-            self.cache.insert(query_contents, raw);
+            self.cache.insert(key, prepared.clone());
 
             Ok(prepared)
         }
@@ -415,7 +420,9 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::client::caching_session::{CachingSessionBuilder, DEFAULT_MAX_CAPACITY};
+    use crate::client::caching_session::{
+        CachingSessionBuilder, StatementCacheKey, DEFAULT_MAX_CAPACITY,
+    };
     use crate::client::session::Session;
     use crate::client::session_builder::SessionBuilder;
     use crate::response::PagingState;
@@ -519,12 +526,17 @@ mod tests {
 
         assert_eq!(2, session.cache.len());
 
+        let cache_key = |statement: &str| StatementCacheKey {
+            statement: statement.to_owned(),
+            keyspace: None,
+        };
+
         // This query should be in the cache
-        assert!(session.cache.get(last_query).is_some());
+        assert!(session.cache.get(&cache_key(last_query)).is_some());
 
         // Either the first or middle query should be removed
-        let first_query_removed = session.cache.get(first_query).is_none();
-        let middle_query_removed = session.cache.get(middle_query).is_none();
+        let first_query_removed = session.cache.get(&cache_key(first_query)).is_none();
+        let middle_query_removed = session.cache.get(&cache_key(middle_query)).is_none();
 
         assert!(first_query_removed || middle_query_removed);
     }
@@ -8,10 +8,17 @@
 //! - [SessionBuilder](session_builder::SessionBuilder) - just a convenient builder for a `Session`.
 //! - [CachingSession](caching_session::CachingSession) - a wrapper over a [Session](session::Session)
 //!   that keeps and manages a cache of prepared statements, so that a user can be free of such considerations.
+//! - [RequestLimits] - client-side limits on the concurrency and the rate of requests.
 //! - [SelfIdentity] - configuresd driver and application self-identifying information,
 //!   to be sent in STARTUP message.
 //! - [ExecutionProfile](execution_profile::ExecutionProfile) - a profile that groups various configuration
 //!   options relevant when executing a request against the DB.
+//! - `tower::SessionService` and `tower::CachingSessionService` - [tower](https://docs.rs/tower)
+//!   services wrapping a session (available under the crate feature `tower-service-03`).
+//! - [BulkWriter](bulk_writer::BulkWriter) - a helper for loading large amounts of rows
+//!   with a prepared statement, using token-aware batching and bounded concurrency.
+//! - [TokenRangeScanner](token_range_scanner::TokenRangeScanner) - a helper for reading whole tables,
+//!   scanning token ranges on their replicas in parallel.
 //! - [QueryPager](pager::QueryPager) and [TypedRowStream](pager::TypedRowStream) - entities that provide
 //!   automated transparent paging of a query.
 
@@ -19,8 +26,15 @@ pub mod execution_profile;
 
 pub mod pager;
 
+pub mod bulk_writer;
+
+pub mod token_range_scanner;
+
 pub mod caching_session;
 
+mod request_limits;
+pub use request_limits::{RateLimit, RequestLimits};
+
 mod self_identity;
 pub use self_identity::SelfIdentity;
 
@@ -28,6 +42,9 @@ pub mod session;
 
 pub mod session_builder;
 
-pub use scylla_cql::frame::Compression;
+#[cfg(feature = "tower-service-03")]
+pub mod tower;
+
+pub use scylla_cql::frame::{Compression, ProtocolVersion};
 
 pub use crate::network::{PoolSize, WriteCoalescingDelay};
@@ -10,6 +10,8 @@ use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use futures::Stream;
+#[cfg(feature = "arrow-54")]
+use scylla_cql::deserialize::arrow::RecordBatchBuilder;
 use scylla_cql::deserialize::result::RawRowLendingIterator;
 use scylla_cql::deserialize::row::{ColumnIterator, DeserializeRow};
 use scylla_cql::deserialize::{DeserializationError, TypeCheckError};
@@ -25,22 +27,25 @@ use thiserror::Error;
 use tokio::sync::mpsc;
 
 use crate::client::execution_profile::ExecutionProfileInner;
+use crate::client::request_limits::RequestLimiter;
 use crate::cluster::{ClusterState, NodeRef};
 use crate::deserialize::DeserializeOwnedRow;
 use crate::errors::{RequestAttemptError, RequestError};
 use crate::frame::response::result;
 use crate::network::Connection;
-use crate::observability::driver_tracing::RequestSpan;
+use crate::observability::driver_tracing::{attempt_span, RequestSpan};
 use crate::observability::history::{self, HistoryListener};
 #[cfg(feature = "metrics")]
-use crate::observability::metrics::Metrics;
+use crate::observability::metrics::{Metrics, RequestKind, RequestLabels};
 use crate::policies::load_balancing::{self, LoadBalancingPolicy, RoutingInfo};
-use crate::policies::retry::{RequestInfo, RetryDecision, RetrySession};
+use crate::policies::retry::{
+    RequestInfo, RetryBudgetTracker, RetryDecision, RetrySession, RetryTarget,
+};
 use crate::response::query_result::ColumnSpecs;
 use crate::response::{NonErrorQueryResponse, QueryResponse};
 use crate::statement::prepared::{PartitionKeyError, PreparedStatement};
 use crate::statement::unprepared::Statement;
-use tracing::{trace, trace_span, warn, Instrument};
+use tracing::{trace, warn, Instrument};
 use uuid::Uuid;
 
 // Like std::task::ready!, but handles the whole stack of Poll<Option<Result<>>>.
@@ -68,6 +73,9 @@ pub(crate) struct PreparedPagerConfig {
     pub(crate) values: SerializedValues,
     pub(crate) execution_profile: Arc<ExecutionProfileInner>,
     pub(crate) cluster_state: Arc<ClusterState>,
+    pub(crate) request_limiter: Arc<RequestLimiter>,
+    pub(crate) retry_budget: Option<Arc<RetryBudgetTracker>>,
+    pub(crate) paging_state: PagingState,
     #[cfg(feature = "metrics")]
     pub(crate) metrics: Arc<Metrics>,
 }
@@ -148,8 +156,12 @@ struct PagerWorker<'a, QueryFunc, SpanCreatorFunc> {
     query_is_idempotent: bool,
     query_consistency: Consistency,
     retry_session: Box<dyn RetrySession>,
+    request_limiter: Arc<RequestLimiter>,
+    retry_budget: Option<Arc<RetryBudgetTracker>>,
     #[cfg(feature = "metrics")]
     metrics: Arc<Metrics>,
+    #[cfg(feature = "metrics")]
+    request_kind: RequestKind,
 
     paging_state: PagingState,
 
@@ -178,20 +190,24 @@ where
         let mut current_consistency: Consistency = self.query_consistency;
 
         self.log_request_start();
+        if let Some(retry_budget) = &self.retry_budget {
+            retry_budget.record_request();
+        }
 
         'nodes_in_plan: for (node, shard) in query_plan {
-            let span = trace_span!(parent: &self.parent_span, "Executing query", node = %node.address, shard = %shard);
             // For each node in the plan choose a connection to use
             // This connection will be reused for same node retries to preserve paging cache on the shard
             let connection: Arc<Connection> = match node
                 .connection_for_shard(shard)
-                .instrument(span.clone())
+                .instrument(self.parent_span.clone())
                 .await
             {
                 Ok(connection) => connection,
                 Err(e) => {
                     trace!(
-                        parent: &span,
+                        parent: &self.parent_span,
+                        node = %node.address,
+                        shard = %shard,
                         error = %e,
                         "Choosing connection failed"
                     );
@@ -202,6 +218,13 @@ where
             };
 
             'same_node_retries: loop {
+                let span = attempt_span!(
+                    parent: &self.parent_span,
+                    "Executing query",
+                    node,
+                    shard,
+                    current_consistency
+                );
                 trace!(parent: &span, "Execution started");
 
                 let coordinator =
@@ -236,13 +259,25 @@ where
                     error: &request_error,
                     is_idempotent: self.query_is_idempotent,
                     consistency: self.query_consistency,
+                    retry_budget_exhausted: self
+                        .retry_budget
+                        .as_ref()
+                        .is_some_and(|budget| budget.is_exhausted()),
                 };
 
-                let retry_decision = self.retry_session.decide_should_retry(query_info);
+                let mut retry_decision = self.retry_session.decide_should_retry(query_info);
                 trace!(
                     parent: &span,
                     retry_decision = ?retry_decision
                 );
+                if let Some(retry_budget) = &self.retry_budget {
+                    if !retry_budget.try_withdraw(&retry_decision) {
+                        trace!(parent: &span, "Not retrying, the retry budget is exhausted");
+                        #[cfg(feature = "metrics")]
+                        self.metrics.inc_retry_budget_exhausted_num();
+                        retry_decision = RetryDecision::DontRetry;
+                    }
+                }
 
                 self.log_attempt_error(&request_error, &retry_decision);
 
@@ -251,16 +286,39 @@ where
                 match retry_decision {
                     RetryDecision::RetrySameTarget(cl) => {
                         #[cfg(feature = "metrics")]
-                        self.metrics.inc_retries_num();
+                        {
+                            self.metrics.inc_retries_num();
+                            self.metrics
+                                .inc_labelled_retries(&self.metrics_labels(node));
+                        }
                         current_consistency = cl.unwrap_or(current_consistency);
                         continue 'same_node_retries;
                     }
                     RetryDecision::RetryNextTarget(cl) => {
                         #[cfg(feature = "metrics")]
-                        self.metrics.inc_retries_num();
+                        {
+                            self.metrics.inc_retries_num();
+                            self.metrics
+                                .inc_labelled_retries(&self.metrics_labels(node));
+                        }
                         current_consistency = cl.unwrap_or(current_consistency);
                         continue 'nodes_in_plan;
                     }
+                    RetryDecision::RetryAfter(delay, target, cl) => {
+                        #[cfg(feature = "metrics")]
+                        {
+                            self.metrics.inc_retries_num();
+                            self.metrics
+                                .inc_labelled_retries(&self.metrics_labels(node));
+                        }
+                        current_consistency = cl.unwrap_or(current_consistency);
+                        trace!(parent: &span, delay = ?delay, "Backing off before retrying");
+                        tokio::time::sleep(delay).await;
+                        match target {
+                            RetryTarget::SameTarget => continue 'same_node_retries,
+                            RetryTarget::NextTarget => continue 'nodes_in_plan,
+                        }
+                    }
                     RetryDecision::DontRetry => break 'nodes_in_plan,
                     RetryDecision::IgnoreWriteError => {
                         warn!("Ignoring error during fetching pages; stopping fetching.");
@@ -302,6 +360,8 @@ where
     ) -> Result<PageSendAttemptedProof, RequestAttemptError> {
         loop {
             let request_span = (self.span_creator)();
+            #[cfg(feature = "opentelemetry")]
+            request_span.record_routing_attributes(&self.statement_info, self.query_is_idempotent);
             match self
                 .query_one_page(
                     connection,
@@ -327,6 +387,28 @@ where
         coordinator: Coordinator,
         request_span: &RequestSpan,
     ) -> Result<ControlFlow<PageSendAttemptedProof, ()>, RequestAttemptError> {
+        // Each page is subject to the request limits separately.
+        let admitted = match self.request_limiter.admit_request().await {
+            Ok(request_permit) => self
+                .request_limiter
+                .admit_attempt(node)
+                .await
+                .map(|attempt_permit| (request_permit, attempt_permit)),
+            Err(e) => Err(e),
+        };
+        let _limiter_permits = match admitted {
+            Ok(permits) => permits,
+            Err(e) => {
+                trace!(error = %e, "Page request not admitted by the request limits");
+                self.log_request_error(&e);
+                let (proof, _) = self
+                    .sender
+                    .send(Err(NextPageError::RequestFailure(e)))
+                    .await;
+                return Ok(ControlFlow::Break(proof));
+            }
+        };
+
         #[cfg(feature = "metrics")]
         self.metrics.inc_total_paged_queries();
         let query_start = std::time::Instant::now();
@@ -355,13 +437,19 @@ where
                 ..
             }) => {
                 #[cfg(feature = "metrics")]
-                let _ = self.metrics.log_query_latency(elapsed.as_millis() as u64);
+                {
+                    let _ = self.metrics.log_query_latency(elapsed.as_millis() as u64);
+                    self.metrics
+                        .log_request_attempt(&self.metrics_labels(node), elapsed, None);
+                }
                 self.log_attempt_success();
                 self.log_request_success();
                 self.load_balancing_policy
                     .on_request_success(&self.statement_info, elapsed, node);
 
                 request_span.record_raw_rows_fields(&rows);
+                #[cfg(feature = "opentelemetry")]
+                request_span.record_tracing_id(tracing_id);
 
                 let received_page = ReceivedPage {
                     rows,
@@ -389,12 +477,22 @@ where
                 // Query succeeded, reset retry policy for future retries
                 self.retry_session.reset();
                 self.log_request_start();
+                if let Some(retry_budget) = &self.retry_budget {
+                    retry_budget.record_request();
+                }
 
                 Ok(ControlFlow::Continue(()))
             }
             Err(err) => {
                 #[cfg(feature = "metrics")]
-                self.metrics.inc_failed_paged_queries();
+                {
+                    self.metrics.inc_failed_paged_queries();
+                    self.metrics.log_request_attempt(
+                        &self.metrics_labels(node),
+                        elapsed,
+                        Some(&err),
+                    );
+                }
                 self.load_balancing_policy.on_request_failure(
                     &self.statement_info,
                     elapsed,
@@ -419,10 +517,17 @@ where
                 Ok(ControlFlow::Break(proof))
             }
             Ok(response) => {
-                #[cfg(feature = "metrics")]
-                self.metrics.inc_failed_paged_queries();
                 let err =
                     RequestAttemptError::UnexpectedResponse(response.response.to_response_kind());
+                #[cfg(feature = "metrics")]
+                {
+                    self.metrics.inc_failed_paged_queries();
+                    self.metrics.log_request_attempt(
+                        &self.metrics_labels(node),
+                        elapsed,
+                        Some(&err),
+                    );
+                }
                 self.load_balancing_policy.on_request_failure(
                     &self.statement_info,
                     elapsed,
@@ -434,6 +539,11 @@ where
         }
     }
 
+    #[cfg(feature = "metrics")]
+    fn metrics_labels<'n>(&'n self, node: NodeRef<'n>) -> RequestLabels<'n> {
+        RequestLabels::new(node, self.request_kind, self.statement_info.table)
+    }
+
     fn log_request_start(&mut self) {
         let history_listener: &dyn HistoryListener = match &self.history_listener {
             Some(hl) => &**hl,
@@ -708,10 +818,26 @@ impl QueryPager {
         TypedRowStream::<RowT>::new(self)
     }
 
+    /// Casts the iterator to a [Stream] of Arrow [RecordBatch](arrow_array_54::RecordBatch)es,
+    /// one per received page.
+    ///
+    /// The rows are deserialized directly from the response frames, see
+    /// [crate::deserialize::arrow] for the schema of the batches.
+    #[cfg(feature = "arrow-54")]
+    #[inline]
+    pub fn record_batch_stream(self) -> RecordBatchStream {
+        RecordBatchStream {
+            raw_row_lending_stream: self,
+        }
+    }
+
     pub(crate) async fn new_for_query(
         statement: Statement,
         execution_profile: Arc<ExecutionProfileInner>,
         cluster_state: Arc<ClusterState>,
+        request_limiter: Arc<RequestLimiter>,
+        retry_budget: Option<Arc<RetryBudgetTracker>>,
+        paging_state: PagingState,
         #[cfg(feature = "metrics")] metrics: Arc<Metrics>,
     ) -> Result<Self, NextPageError> {
         let (sender, receiver) = mpsc::channel::<Result<ReceivedPage, NextPageError>>(1);
@@ -781,9 +907,13 @@ impl QueryPager {
                 query_consistency: consistency,
                 load_balancing_policy,
                 retry_session,
+                request_limiter,
+                retry_budget,
                 #[cfg(feature = "metrics")]
                 metrics,
-                paging_state: PagingState::start(),
+                #[cfg(feature = "metrics")]
+                request_kind: RequestKind::Unprepared,
+                paging_state,
                 history_listener: statement.config.history_listener.clone(),
                 current_request_id: None,
                 current_attempt_id: None,
@@ -891,6 +1021,7 @@ impl QueryPager {
 
             let span_creator = move || {
                 let span = RequestSpan::new_prepared(
+                    prepared_ref.get_statement(),
                     partition_key.as_ref().map(|pk| pk.iter()),
                     token,
                     serialized_values_size,
@@ -909,9 +1040,13 @@ impl QueryPager {
                 query_consistency: consistency,
                 load_balancing_policy,
                 retry_session,
+                request_limiter: config.request_limiter,
+                retry_budget: config.retry_budget,
                 #[cfg(feature = "metrics")]
                 metrics: config.metrics,
-                paging_state: PagingState::start(),
+                #[cfg(feature = "metrics")]
+                request_kind: RequestKind::Prepared,
+                paging_state: config.paging_state,
                 history_listener: config.prepared.config.history_listener.clone(),
                 current_request_id: None,
                 current_attempt_id: None,
@@ -1115,6 +1250,59 @@ where
     }
 }
 
+/// Returned by [QueryPager::record_batch_stream].
+///
+/// Implements [Stream], yielding an Arrow [RecordBatch](arrow_array_54::RecordBatch)
+/// with the rows of each received page. Empty pages are skipped.
+/// If a row fails to deserialize, the error is yielded instead of the batch
+/// of its page, and the stream continues with the next page.
+#[cfg(feature = "arrow-54")]
+#[derive(Debug)]
+pub struct RecordBatchStream {
+    raw_row_lending_stream: QueryPager,
+}
+
+#[cfg(feature = "arrow-54")]
+impl RecordBatchStream {
+    /// If tracing was enabled, returns tracing ids of all finished page queries.
+    #[inline]
+    pub fn tracing_ids(&self) -> &[Uuid] {
+        self.raw_row_lending_stream.tracing_ids()
+    }
+
+    /// Returns the targets that served finished page queries, in query order.
+    #[inline]
+    pub fn request_coordinators(&self) -> impl Iterator<Item = &Coordinator> {
+        self.raw_row_lending_stream.request_coordinators()
+    }
+
+    /// Returns specification of row columns
+    #[inline]
+    pub fn column_specs(&self) -> ColumnSpecs<'_, '_> {
+        self.raw_row_lending_stream.column_specs()
+    }
+}
+
+#[cfg(feature = "arrow-54")]
+impl Stream for RecordBatchStream {
+    type Item = Result<arrow_array_54::RecordBatch, NextRowError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let pager = &mut self.raw_row_lending_stream;
+        ready_some_ok!(Pin::new(&mut *pager).poll_fill_page(cx));
+
+        let mut builder = RecordBatchBuilder::new(pager.current_page.metadata().col_specs());
+        while let Some(row) = pager.current_page.next() {
+            if let Err(err) = row.and_then(|row| builder.append_row(row)) {
+                // Skip the rest of the page, so that the next poll starts with the next page.
+                while pager.current_page.next().is_some() {}
+                return Poll::Ready(Some(Err(NextRowError::RowDeserializationError(err))));
+            }
+        }
+        Poll::Ready(Some(Ok(builder.finish())))
+    }
+}
+
 /// An error returned that occurred during next page fetch.
 #[derive(Error, Debug, Clone)]
 #[non_exhaustive]
@@ -0,0 +1,356 @@
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dashmap::DashMap;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::cluster::Node;
+use crate::errors::RequestError;
+
+/// Client-side limits on the requests issued by a [Session](crate::client::session::Session).
+///
+/// The limits provide backpressure: requests over a limit wait in a queue
+/// until they can be sent, instead of being sent to the cluster right away.
+/// This allows bulk jobs to saturate the cluster without overloading it
+/// (which would otherwise result in `Overloaded` or `RateLimitReached` errors).
+///
+/// The following limits are available; all of them are disabled by default:
+/// - the number of requests in flight in the whole session,
+/// - the number of requests in flight to a single node,
+/// - the rate of requests in the whole session (see [RateLimit]).
+///
+/// Paged requests are subject to the limits separately for each page.
+/// Retries and speculative executions of a request share its session-wide
+/// slot, but need a slot of the node they are sent to.
+///
+/// By default, a request waits in the queue indefinitely (or until
+/// the request timeout elapses). A [queue timeout](Self::with_queue_timeout)
+/// can be set, after which the request fails with
+/// [RequestQueueTimeout](crate::errors::ExecutionError::RequestQueueTimeout).
+#[derive(Debug, Clone, Default)]
+pub struct RequestLimits {
+    max_concurrent_requests: Option<NonZeroUsize>,
+    max_concurrent_requests_per_node: Option<NonZeroUsize>,
+    rate_limit: Option<RateLimit>,
+    queue_timeout: Option<Duration>,
+}
+
+impl RequestLimits {
+    /// Creates limits with all limits disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of requests in flight in the whole session.
+    pub fn set_max_concurrent_requests(&mut self, max: Option<NonZeroUsize>) {
+        self.max_concurrent_requests = max;
+    }
+
+    /// Limits the number of requests in flight in the whole session.
+    /// See [Self::set_max_concurrent_requests].
+    pub fn with_max_concurrent_requests(mut self, max: NonZeroUsize) -> Self {
+        self.max_concurrent_requests = Some(max);
+        self
+    }
+
+    /// The limit on the number of requests in flight in the whole session.
+    pub fn get_max_concurrent_requests(&self) -> Option<NonZeroUsize> {
+        self.max_concurrent_requests
+    }
+
+    /// Limits the number of requests in flight to a single node.
+    pub fn set_max_concurrent_requests_per_node(&mut self, max: Option<NonZeroUsize>) {
+        self.max_concurrent_requests_per_node = max;
+    }
+
+    /// Limits the number of requests in flight to a single node.
+    /// See [Self::set_max_concurrent_requests_per_node].
+    pub fn with_max_concurrent_requests_per_node(mut self, max: NonZeroUsize) -> Self {
+        self.max_concurrent_requests_per_node = Some(max);
+        self
+    }
+
+    /// The limit on the number of requests in flight to a single node.
+    pub fn get_max_concurrent_requests_per_node(&self) -> Option<NonZeroUsize> {
+        self.max_concurrent_requests_per_node
+    }
+
+    /// Limits the rate of requests in the whole session.
+    pub fn set_rate_limit(&mut self, rate_limit: Option<RateLimit>) {
+        self.rate_limit = rate_limit;
+    }
+
+    /// Limits the rate of requests in the whole session.
+    /// See [Self::set_rate_limit].
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// The limit on the rate of requests in the whole session.
+    pub fn get_rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit
+    }
+
+    /// Sets the maximum time a request may wait for being admitted by any of the limits.
+    /// `None` means waiting indefinitely.
+    pub fn set_queue_timeout(&mut self, timeout: Option<Duration>) {
+        self.queue_timeout = timeout;
+    }
+
+    /// Sets the maximum time a request may wait for being admitted by any of the limits.
+    /// See [Self::set_queue_timeout].
+    pub fn with_queue_timeout(mut self, timeout: Duration) -> Self {
+        self.queue_timeout = Some(timeout);
+        self
+    }
+
+    /// The maximum time a request may wait for being admitted by any of the limits.
+    pub fn get_queue_timeout(&self) -> Option<Duration> {
+        self.queue_timeout
+    }
+}
+
+/// A limit on the rate of requests, enforced using a token bucket.
+///
+/// The bucket holds up to `burst` tokens and is refilled with `requests_per_second`
+/// tokens per second. Each request consumes one token, so after a period of inactivity,
+/// up to `burst` requests can be sent at once. By default, `burst` equals
+/// `requests_per_second`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    requests_per_second: NonZeroU32,
+    burst: NonZeroU32,
+}
+
+impl RateLimit {
+    /// Creates a limit of `requests_per_second` requests per second.
+    pub fn new(requests_per_second: NonZeroU32) -> Self {
+        Self {
+            requests_per_second,
+            burst: requests_per_second,
+        }
+    }
+
+    /// Sets the maximum number of requests that can be sent at once after a period of inactivity.
+    pub fn with_burst(mut self, burst: NonZeroU32) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    /// The number of requests allowed per second.
+    pub fn get_requests_per_second(&self) -> NonZeroU32 {
+        self.requests_per_second
+    }
+
+    /// The maximum number of requests that can be sent at once.
+    pub fn get_burst(&self) -> NonZeroU32 {
+        self.burst
+    }
+}
+
+/// Enforces [RequestLimits] of a session.
+#[derive(Debug)]
+pub(crate) struct RequestLimiter {
+    session_semaphore: Option<Arc<Semaphore>>,
+    max_concurrent_requests_per_node: Option<NonZeroUsize>,
+    node_semaphores: DashMap<Uuid, Arc<Semaphore>>,
+    token_bucket: Option<TokenBucket>,
+    queue_timeout: Option<Duration>,
+}
+
+/// Holds a slot of a limit until dropped.
+#[derive(Debug)]
+pub(crate) struct LimiterPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl RequestLimiter {
+    pub(crate) fn new(limits: &RequestLimits) -> Self {
+        Self {
+            session_semaphore: limits
+                .max_concurrent_requests
+                .map(|max| Arc::new(Semaphore::new(max.get()))),
+            max_concurrent_requests_per_node: limits.max_concurrent_requests_per_node,
+            node_semaphores: DashMap::new(),
+            token_bucket: limits.rate_limit.map(TokenBucket::new),
+            queue_timeout: limits.queue_timeout,
+        }
+    }
+
+    /// Waits until a new request is admitted by the session-wide limits.
+    /// The returned permit should be held until the request is completed.
+    pub(crate) async fn admit_request(&self) -> Result<LimiterPermit, RequestError> {
+        let deadline = self.queue_timeout.map(|timeout| Instant::now() + timeout);
+
+        let permit = match &self.session_semaphore {
+            Some(semaphore) => Some(self.acquire(semaphore, deadline).await?),
+            None => None,
+        };
+
+        if let Some(token_bucket) = &self.token_bucket {
+            let wait = token_bucket.reserve();
+            if let Some(deadline) = deadline {
+                if Instant::now() + wait > deadline {
+                    token_bucket.cancel_reservation();
+                    return Err(self.timeout_error());
+                }
+            }
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+        }
+
+        Ok(LimiterPermit { _permit: permit })
+    }
+
+    /// Waits until an attempt to send a request to the given node is admitted
+    /// by the per-node limit. The returned permit should be held until the attempt
+    /// is completed.
+    pub(crate) async fn admit_attempt(&self, node: &Node) -> Result<LimiterPermit, RequestError> {
+        let Some(max) = self.max_concurrent_requests_per_node else {
+            return Ok(LimiterPermit { _permit: None });
+        };
+        let semaphore = self
+            .node_semaphores
+            .entry(node.host_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(max.get())))
+            .clone();
+
+        let deadline = self.queue_timeout.map(|timeout| Instant::now() + timeout);
+        let permit = self.acquire(&semaphore, deadline).await?;
+        Ok(LimiterPermit {
+            _permit: Some(permit),
+        })
+    }
+
+    async fn acquire(
+        &self,
+        semaphore: &Arc<Semaphore>,
+        deadline: Option<Instant>,
+    ) -> Result<OwnedSemaphorePermit, RequestError> {
+        let acquire = Arc::clone(semaphore).acquire_owned();
+        let permit = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, acquire)
+                .await
+                .map_err(|_| self.timeout_error())?,
+            None => acquire.await,
+        };
+        // The semaphores are never closed.
+        Ok(permit.expect("Request limiter semaphore closed"))
+    }
+
+    fn timeout_error(&self) -> RequestError {
+        RequestError::RequestQueueTimeout(self.queue_timeout.unwrap_or_default())
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    state: Mutex<TokenBucketState>,
+}
+
+#[derive(Debug)]
+struct TokenBucketState {
+    // Negative when the tokens are reserved by the requests waiting for a refill.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        let capacity = limit.burst.get() as f64;
+        Self {
+            rate: limit.requests_per_second.get() as f64,
+            capacity,
+            state: Mutex::new(TokenBucketState {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token from the bucket, possibly in advance.
+    /// Returns how long the caller has to wait until the token is actually available.
+    fn reserve(&self) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let refill = now.duration_since(state.last_refill).as_secs_f64() * self.rate;
+        state.tokens = (state.tokens + refill).min(self.capacity);
+        state.last_refill = now;
+
+        state.tokens -= 1.0;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.rate)
+        }
+    }
+
+    /// Gives back the token taken by the last [Self::reserve].
+    fn cancel_reservation(&self) {
+        self.state.lock().unwrap().tokens += 1.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::{NonZeroU32, NonZeroUsize};
+    use std::time::Duration;
+
+    use assert_matches::assert_matches;
+
+    use crate::errors::RequestError;
+
+    use super::{RateLimit, RequestLimiter, RequestLimits};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrency_limit_with_queue_timeout() {
+        let limits = RequestLimits::new()
+            .with_max_concurrent_requests(NonZeroUsize::new(2).unwrap())
+            .with_queue_timeout(Duration::from_millis(100));
+        let limiter = RequestLimiter::new(&limits);
+
+        let first = limiter.admit_request().await.unwrap();
+        let _second = limiter.admit_request().await.unwrap();
+        assert_matches!(
+            limiter.admit_request().await,
+            Err(RequestError::RequestQueueTimeout(timeout)) if timeout == Duration::from_millis(100)
+        );
+
+        drop(first);
+        limiter.admit_request().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit() {
+        let limits = RequestLimits::new()
+            .with_rate_limit(
+                RateLimit::new(NonZeroU32::new(10).unwrap())
+                    .with_burst(NonZeroU32::new(2).unwrap()),
+            )
+            .with_queue_timeout(Duration::from_millis(150));
+        let limiter = RequestLimiter::new(&limits);
+
+        let start = tokio::time::Instant::now();
+        // The burst is available immediately.
+        limiter.admit_request().await.unwrap();
+        limiter.admit_request().await.unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // Then the requests are admitted at the configured rate.
+        limiter.admit_request().await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+
+        // Two more requests would have to wait for 100ms and 200ms respectively;
+        // the latter exceeds the queue timeout.
+        let (third, fourth) = tokio::join!(limiter.admit_request(), limiter.admit_request());
+        assert!(third.is_ok());
+        assert_matches!(fourth, Err(RequestError::RequestQueueTimeout(_)));
+    }
+}
@@ -3,10 +3,14 @@
 
 use super::execution_profile::{ExecutionProfile, ExecutionProfileHandle, ExecutionProfileInner};
 use super::pager::{PreparedPagerConfig, QueryPager};
-use super::{Compression, PoolSize, SelfIdentity, WriteCoalescingDelay};
+use super::request_limits::RequestLimiter;
+use super::{
+    Compression, PoolSize, ProtocolVersion, RequestLimits, SelfIdentity, WriteCoalescingDelay,
+};
 use crate::authentication::AuthenticatorProvider;
 #[cfg(feature = "unstable-cloud")]
 use crate::cloud::CloudConfig;
+use crate::cluster::events::ClusterEventStream;
 #[cfg(feature = "unstable-cloud")]
 use crate::cluster::node::CloudEndpoint;
 use crate::cluster::node::{InternalKnownNode, KnownNode, NodeRef};
@@ -18,18 +22,22 @@ use crate::errors::{
 use crate::frame::response::result;
 use crate::network::tls::TlsProvider;
 use crate::network::{Connection, ConnectionConfig, PoolConfig, VerifiedKeyspaceName};
-use crate::observability::driver_tracing::RequestSpan;
+use crate::observability::driver_tracing::{attempt_span, RequestSpan};
 use crate::observability::history::{self, HistoryListener};
 #[cfg(feature = "metrics")]
-use crate::observability::metrics::Metrics;
+use crate::observability::metrics::{Metrics, RequestLabels};
 use crate::observability::tracing::TracingInfo;
 use crate::policies::address_translator::AddressTranslator;
 use crate::policies::host_filter::HostFilter;
 use crate::policies::load_balancing::{self, RoutingInfo};
-use crate::policies::retry::{RequestInfo, RetryDecision, RetrySession};
+use crate::policies::retry::{
+    RequestInfo, RetryBudget, RetryBudgetTracker, RetryDecision, RetrySession, RetryTarget,
+};
 use crate::policies::speculative_execution;
 use crate::policies::timestamp_generator::TimestampGenerator;
 use crate::response::query_result::{MaybeFirstRowError, QueryResult, RowsError};
+#[cfg(feature = "paging-cursor")]
+use crate::response::PagingCursor;
 use crate::response::{
     Coordinator, NonErrorQueryResponse, PagingState, PagingStateResponse, QueryResponse,
 };
@@ -37,7 +45,7 @@ use crate::routing::partitioner::PartitionerName;
 use crate::routing::{Shard, ShardAwarePortRange};
 use crate::statement::batch::batch_values;
 use crate::statement::batch::{Batch, BatchStatement};
-use crate::statement::prepared::{PartitionKeyError, PreparedStatement};
+use crate::statement::prepared::{PartitionKeyError, PreparedStatement, SchemaEpochs};
 use crate::statement::unprepared::Statement;
 use crate::statement::{Consistency, PageSize, StatementConfig};
 use arc_swap::ArcSwapOption;
@@ -56,7 +64,7 @@ use std::time::Duration;
 use tokio::time::timeout;
 #[cfg(feature = "unstable-cloud")]
 use tracing::warn;
-use tracing::{debug, error, trace, trace_span, Instrument};
+use tracing::{debug, error, trace, Instrument};
 use uuid::Uuid;
 
 pub(crate) const TABLET_CHANNEL_SIZE: usize = 8192;
@@ -74,9 +82,12 @@ pub struct Session {
     schema_agreement_automatic_waiting: bool,
     refresh_metadata_on_auto_schema_agreement: bool,
     keyspace_name: Arc<ArcSwapOption<String>>,
+    schema_epochs: Arc<SchemaEpochs>,
     tracing_info_fetch_attempts: NonZeroU32,
     tracing_info_fetch_interval: Duration,
     tracing_info_fetch_consistency: Consistency,
+    request_limiter: Arc<RequestLimiter>,
+    retry_budget: Option<Arc<RetryBudgetTracker>>,
 }
 
 /// This implementation deliberately omits some details from Cluster in order
@@ -172,6 +183,20 @@ pub struct SessionConfig {
     /// Preferred compression algorithm to use on connections.
     /// If it's not supported by database server Session will fall back to no compression.
     pub compression: Option<Compression>,
+
+    /// If set, zstd compression with the given level is preferred over [`compression`](Self::compression).
+    /// Zstd is used only if the database server advertises it and the `zstd` feature is enabled;
+    /// otherwise Session falls back to [`compression`](Self::compression).
+    pub zstd_compression_level: Option<i32>,
+
+    /// Version of the CQL native protocol used on connections.
+    /// With protocol v5, frames are wrapped in checksummed segments, and
+    /// compression (lz4 only) is applied per segment.
+    /// If a node does not support the requested version, the connection
+    /// falls back to protocol v4.
+    ///
+    /// By default set to [`ProtocolVersion::V4`].
+    pub protocol_version: ProtocolVersion,
     pub tcp_nodelay: bool,
     pub tcp_keepalive_interval: Option<Duration>,
 
@@ -291,6 +316,14 @@ pub struct SessionConfig {
     /// Driver and application self-identifying information,
     /// to be sent to server in STARTUP message.
     pub identity: SelfIdentity<'static>,
+
+    /// Client-side limits on the concurrency and the rate of requests
+    /// issued by the session. No limits are set by default.
+    pub request_limits: RequestLimits,
+
+    /// Session-wide limit on the number of retries, relative to the number of requests.
+    /// If `None`, the number of retries is limited only by the retry policies.
+    pub retry_budget: Option<RetryBudget>,
 }
 
 impl SessionConfig {
@@ -310,6 +343,8 @@ impl SessionConfig {
             local_ip_address: None,
             shard_aware_local_port_range: ShardAwarePortRange::EPHEMERAL_PORT_RANGE,
             compression: None,
+            zstd_compression_level: None,
+            protocol_version: ProtocolVersion::V4,
             tcp_nodelay: true,
             tcp_keepalive_interval: None,
             schema_agreement_interval: Duration::from_millis(200),
@@ -342,6 +377,8 @@ impl SessionConfig {
             tracing_info_fetch_consistency: Consistency::One,
             cluster_metadata_refresh_interval: Duration::from_secs(60),
             identity: SelfIdentity::default(),
+            request_limits: RequestLimits::default(),
+            retry_budget: None,
         }
     }
 
@@ -588,7 +625,30 @@ impl Session {
         statement: impl Into<Statement>,
         values: impl SerializeRow,
     ) -> Result<QueryPager, PagerExecutionError> {
-        self.do_query_iter(statement.into(), values).await
+        self.do_query_iter(statement.into(), values, PagingState::start())
+            .await
+    }
+
+    /// Execute an unprepared CQL statement with paging, starting from the given cursor.\
+    /// This method will query all the remaining pages of the result.
+    ///
+    /// Works like [`Session::query_iter()`], but resumes the query from a cursor decoded
+    /// with a [`PagingCursorCodec`](crate::response::PagingCursorCodec). The cursor must
+    /// have been encoded from a paging state returned by a previous execution of the same
+    /// statement with the same values.
+    ///
+    /// This method is available only under the crate feature `paging-cursor`.
+    ///
+    /// See [the book](https://rust-driver.docs.scylladb.com/stable/statements/paged.html) for more information.
+    #[cfg(feature = "paging-cursor")]
+    pub async fn query_iter_from(
+        &self,
+        statement: impl Into<Statement>,
+        values: impl SerializeRow,
+        cursor: PagingCursor,
+    ) -> Result<QueryPager, PagerExecutionError> {
+        self.do_query_iter(statement.into(), values, cursor.into_paging_state())
+            .await
     }
 
     /// Execute a prepared statement. Requires a [PreparedStatement]
@@ -752,7 +812,30 @@ impl Session {
         prepared: impl Into<PreparedStatement>,
         values: impl SerializeRow,
     ) -> Result<QueryPager, PagerExecutionError> {
-        self.do_execute_iter(prepared.into(), values).await
+        self.do_execute_iter(prepared.into(), values, PagingState::start())
+            .await
+    }
+
+    /// Execute a prepared statement with paging, starting from the given cursor.\
+    /// This method will query all the remaining pages of the result.
+    ///
+    /// Works like [`Session::execute_iter()`], but resumes the query from a cursor decoded
+    /// with a [`PagingCursorCodec`](crate::response::PagingCursorCodec). The cursor must
+    /// have been encoded from a paging state returned by a previous execution of the same
+    /// statement with the same values, e.g. by [`Session::execute_single_page()`].
+    ///
+    /// This method is available only under the crate feature `paging-cursor`.
+    ///
+    /// See [the book](https://rust-driver.docs.scylladb.com/stable/statements/paged.html) for more information.
+    #[cfg(feature = "paging-cursor")]
+    pub async fn execute_iter_from(
+        &self,
+        prepared: impl Into<PreparedStatement>,
+        values: impl SerializeRow,
+        cursor: PagingCursor,
+    ) -> Result<QueryPager, PagerExecutionError> {
+        self.do_execute_iter(prepared.into(), values, cursor.into_paging_state())
+            .await
     }
 
     /// Execute a batch statement\
@@ -910,10 +993,14 @@ impl Session {
             None
         };
 
+        let schema_epochs = Arc::new(SchemaEpochs::new());
+
         let connection_config = ConnectionConfig {
             local_ip_address: config.local_ip_address,
             shard_aware_local_port_range: config.shard_aware_local_port_range,
-            compression: config.compression,
+            compression: config.compression.map(Into::into),
+            zstd_compression_level: config.zstd_compression_level,
+            protocol_version: config.protocol_version,
             tcp_nodelay: config.tcp_nodelay,
             tcp_keepalive_interval: config.tcp_keepalive_interval,
             timestamp_generator: config.timestamp_generator,
@@ -929,6 +1016,7 @@ impl Session {
             keepalive_interval: config.keepalive_interval,
             keepalive_timeout: config.keepalive_timeout,
             tablet_sender: Some(tablet_sender),
+            schema_epochs: Arc::clone(&schema_epochs),
             identity: config.identity,
         };
 
@@ -968,9 +1056,15 @@ impl Session {
             refresh_metadata_on_auto_schema_agreement: config
                 .refresh_metadata_on_auto_schema_agreement,
             keyspace_name: Arc::new(ArcSwapOption::default()), // will be set by use_keyspace
+            schema_epochs,
             tracing_info_fetch_attempts: config.tracing_info_fetch_attempts,
             tracing_info_fetch_interval: config.tracing_info_fetch_interval,
             tracing_info_fetch_consistency: config.tracing_info_fetch_consistency,
+            request_limiter: Arc::new(RequestLimiter::new(&config.request_limits)),
+            retry_budget: config
+                .retry_budget
+                .as_ref()
+                .map(|budget| Arc::new(RetryBudgetTracker::new(budget))),
         };
 
         if let Some(keyspace_name) = config.used_keyspace {
@@ -1144,6 +1238,11 @@ impl Session {
         &self,
         response: &NonErrorQueryResponse,
     ) -> Result<(), ExecutionError> {
+        if let Some(schema_change) = response.as_schema_change() {
+            // Don't wait for the schema change event to arrive on the control connection.
+            self.schema_epochs.invalidate_on(&schema_change.event);
+        }
+
         if self.schema_agreement_automatic_waiting {
             if response.as_schema_change().is_some() {
                 self.await_schema_agreement().await?;
@@ -1163,6 +1262,7 @@ impl Session {
         &self,
         statement: Statement,
         values: impl SerializeRow,
+        paging_state: PagingState,
     ) -> Result<QueryPager, PagerExecutionError> {
         let execution_profile = statement
             .get_execution_profile_handle()
@@ -1174,6 +1274,9 @@ impl Session {
                 statement,
                 execution_profile,
                 self.cluster.get_state(),
+                Arc::clone(&self.request_limiter),
+                self.retry_budget.clone(),
+                paging_state,
                 #[cfg(feature = "metrics")]
                 Arc::clone(&self.metrics),
             )
@@ -1190,6 +1293,9 @@ impl Session {
                 values,
                 execution_profile,
                 cluster_state: self.cluster.get_state(),
+                request_limiter: Arc::clone(&self.request_limiter),
+                retry_budget: self.retry_budget.clone(),
+                paging_state,
                 #[cfg(feature = "metrics")]
                 metrics: Arc::clone(&self.metrics),
             })
@@ -1431,6 +1537,7 @@ impl Session {
         };
 
         let span = RequestSpan::new_prepared(
+            prepared.get_statement(),
             partition_key.as_ref().map(|pk| pk.iter()),
             token,
             serialized_values.buffer_size(),
@@ -1501,6 +1608,7 @@ impl Session {
         &self,
         prepared: PreparedStatement,
         values: impl SerializeRow,
+        paging_state: PagingState,
     ) -> Result<QueryPager, PagerExecutionError> {
         let serialized_values = prepared.serialize_values(&values)?;
 
@@ -1514,6 +1622,9 @@ impl Session {
             values: serialized_values,
             execution_profile,
             cluster_state: self.cluster.get_state(),
+            request_limiter: Arc::clone(&self.request_limiter),
+            retry_budget: self.retry_budget.clone(),
+            paging_state,
             #[cfg(feature = "metrics")]
             metrics: Arc::clone(&self.metrics),
         })
@@ -1737,6 +1848,11 @@ impl Session {
         Arc::clone(&self.metrics)
     }
 
+    /// The tracker of the session's retry budget, if the budget is set.
+    pub(crate) fn get_retry_budget(&self) -> Option<Arc<RetryBudgetTracker>> {
+        self.retry_budget.clone()
+    }
+
     /// Access cluster state visible by the driver.
     ///
     /// Driver collects various information about network topology or schema.
@@ -1745,6 +1861,37 @@ impl Session {
         self.cluster.get_state()
     }
 
+    /// Subscribe to events pushed by the cluster.
+    ///
+    /// The returned stream yields topology (node added/removed/moved),
+    /// status (node up/down) and schema (keyspace/table/type/function/aggregate
+    /// created/updated/dropped) events received by the driver after this call.
+    /// Events are yielded as soon as they arrive, so [ClusterState] may not
+    /// reflect them yet - use [Session::refresh_metadata] if up-to-date
+    /// metadata is needed.
+    ///
+    /// If the subscriber does not keep up with the events, the oldest ones are
+    /// dropped and an error with the number of dropped events is yielded.
+    ///
+    /// ```rust
+    /// # use scylla::client::session::Session;
+    /// # async fn example(session: &Session) -> Result<(), Box<dyn std::error::Error>> {
+    /// use futures::StreamExt;
+    /// use scylla::cluster::events::{ClusterEvent, SchemaEvent};
+    ///
+    /// let mut events = session.subscribe_events();
+    /// while let Some(event) = events.next().await {
+    ///     if let ClusterEvent::Schema(SchemaEvent::Table { keyspace_name, table_name, .. }) = event? {
+    ///         println!("Table {}.{} changed", keyspace_name, table_name);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn subscribe_events(&self) -> ClusterEventStream {
+        ClusterEventStream::new(self.cluster.subscribe_events())
+    }
+
     /// Get [`TracingInfo`] of a traced query performed earlier
     ///
     /// See [the book](https://rust-driver.docs.scylladb.com/stable/tracing/tracing.html)
@@ -1871,12 +2018,21 @@ impl Session {
                 .as_ref()
                 .map(|hl| (&**hl, hl.log_request_start()));
 
+        #[cfg(feature = "opentelemetry")]
+        request_span.record_routing_attributes(&statement_info, statement_config.is_idempotent);
+
         let load_balancer = statement_config
             .load_balancing_policy
             .as_deref()
             .unwrap_or(execution_profile.load_balancing_policy.as_ref());
 
         let runner = async {
+            // Held until the request is completed, including retries and speculative executions.
+            let _limiter_permit = self.request_limiter.admit_request().await?;
+            if let Some(retry_budget) = &self.retry_budget {
+                retry_budget.record_request();
+            }
+
             let cluster_state = self.cluster.get_state();
             let request_plan =
                 load_balancing::Plan::new(load_balancer, &statement_info, &cluster_state);
@@ -2041,8 +2197,8 @@ impl Session {
             .unwrap_or(execution_profile.consistency);
 
         'nodes_in_plan: for (node, shard) in request_plan {
-            let span = trace_span!("Executing request", node = %node.address, shard = %shard);
             'same_node_retries: loop {
+                let span = attempt_span!("Executing request", node, shard, current_consistency);
                 trace!(parent: &span, "Execution started");
                 let connection = match node.connection_for_shard(shard).await {
                     Ok(connection) => connection,
@@ -2059,6 +2215,19 @@ impl Session {
                 };
                 context.request_span.record_shard_id(&connection);
 
+                let attempt_permit = match self.request_limiter.admit_attempt(node).await {
+                    Ok(permit) => permit,
+                    Err(e) => {
+                        trace!(
+                            parent: &span,
+                            error = %e,
+                            "Request not admitted to the node by the request limits"
+                        );
+                        last_error = Some(e);
+                        continue 'nodes_in_plan;
+                    }
+                };
+
                 #[cfg(feature = "metrics")]
                 self.metrics.inc_total_nonpaged_queries();
                 let request_start = std::time::Instant::now();
@@ -2080,11 +2249,18 @@ impl Session {
                         .await;
 
                 let elapsed = request_start.elapsed();
+                #[cfg(feature = "metrics")]
+                let metrics_labels =
+                    RequestLabels::new(node, context.request_span.kind(), context.query_info.table);
                 let request_error: RequestAttemptError = match request_result {
                     Ok(response) => {
                         trace!(parent: &span, "Request succeeded");
                         #[cfg(feature = "metrics")]
-                        let _ = self.metrics.log_query_latency(elapsed.as_millis() as u64);
+                        {
+                            let _ = self.metrics.log_query_latency(elapsed.as_millis() as u64);
+                            self.metrics
+                                .log_request_attempt(&metrics_labels, elapsed, None);
+                        }
                         context.log_attempt_success(&attempt_id);
                         context.load_balancing_policy.on_request_success(
                             context.query_info,
@@ -2100,7 +2276,11 @@ impl Session {
                             "Request failed"
                         );
                         #[cfg(feature = "metrics")]
-                        self.metrics.inc_failed_nonpaged_queries();
+                        {
+                            self.metrics.inc_failed_nonpaged_queries();
+                            self.metrics
+                                .log_request_attempt(&metrics_labels, elapsed, Some(&e));
+                        }
                         context.load_balancing_policy.on_request_failure(
                             context.query_info,
                             elapsed,
@@ -2118,13 +2298,25 @@ impl Session {
                     consistency: context
                         .consistency_set_on_statement
                         .unwrap_or(execution_profile.consistency),
+                    retry_budget_exhausted: self
+                        .retry_budget
+                        .as_ref()
+                        .is_some_and(|budget| budget.is_exhausted()),
                 };
 
-                let retry_decision = context.retry_session.decide_should_retry(query_info);
+                let mut retry_decision = context.retry_session.decide_should_retry(query_info);
                 trace!(
                     parent: &span,
                     retry_decision = ?retry_decision
                 );
+                if let Some(retry_budget) = &self.retry_budget {
+                    if !retry_budget.try_withdraw(&retry_decision) {
+                        trace!(parent: &span, "Not retrying, the retry budget is exhausted");
+                        #[cfg(feature = "metrics")]
+                        self.metrics.inc_retry_budget_exhausted_num();
+                        retry_decision = RetryDecision::DontRetry;
+                    }
+                }
 
                 context.log_attempt_error(&attempt_id, &request_error, &retry_decision);
 
@@ -2133,16 +2325,38 @@ impl Session {
                 match retry_decision {
                     RetryDecision::RetrySameTarget(new_cl) => {
                         #[cfg(feature = "metrics")]
-                        self.metrics.inc_retries_num();
+                        {
+                            self.metrics.inc_retries_num();
+                            self.metrics.inc_labelled_retries(&metrics_labels);
+                        }
                         current_consistency = new_cl.unwrap_or(current_consistency);
                         continue 'same_node_retries;
                     }
                     RetryDecision::RetryNextTarget(new_cl) => {
                         #[cfg(feature = "metrics")]
-                        self.metrics.inc_retries_num();
+                        {
+                            self.metrics.inc_retries_num();
+                            self.metrics.inc_labelled_retries(&metrics_labels);
+                        }
                         current_consistency = new_cl.unwrap_or(current_consistency);
                         continue 'nodes_in_plan;
                     }
+                    RetryDecision::RetryAfter(delay, target, new_cl) => {
+                        #[cfg(feature = "metrics")]
+                        {
+                            self.metrics.inc_retries_num();
+                            self.metrics.inc_labelled_retries(&metrics_labels);
+                        }
+                        current_consistency = new_cl.unwrap_or(current_consistency);
+                        // Don't occupy the node's slot while backing off.
+                        drop(attempt_permit);
+                        trace!(parent: &span, delay = ?delay, "Backing off before retrying");
+                        tokio::time::sleep(delay).await;
+                        match target {
+                            RetryTarget::SameTarget => continue 'same_node_retries,
+                            RetryTarget::NextTarget => continue 'nodes_in_plan,
+                        }
+                    }
                     RetryDecision::DontRetry => break 'nodes_in_plan,
 
                     RetryDecision::IgnoreWriteError => {
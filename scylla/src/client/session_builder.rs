@@ -4,7 +4,9 @@
 use super::execution_profile::ExecutionProfile;
 use super::execution_profile::ExecutionProfileHandle;
 use super::session::{Session, SessionConfig};
-use super::{Compression, PoolSize, SelfIdentity, WriteCoalescingDelay};
+use super::{
+    Compression, PoolSize, ProtocolVersion, RequestLimits, SelfIdentity, WriteCoalescingDelay,
+};
 use crate::authentication::{AuthenticatorProvider, PlainTextAuthenticator};
 use crate::client::session::TlsContext;
 #[cfg(feature = "unstable-cloud")]
@@ -12,6 +14,7 @@ use crate::cloud::{CloudConfig, CloudConfigError, CloudTlsProvider};
 use crate::errors::NewSessionError;
 use crate::policies::address_translator::AddressTranslator;
 use crate::policies::host_filter::HostFilter;
+use crate::policies::retry::RetryBudget;
 use crate::policies::timestamp_generator::TimestampGenerator;
 use crate::routing::ShardAwarePortRange;
 use crate::statement::Consistency;
@@ -472,6 +475,61 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Prefer zstd compression with the given level over the one set with
+    /// [`compression`](Self::compression). The default is not to use zstd.
+    ///
+    /// Zstd is not a part of the CQL protocol specification, so it is used only
+    /// if the database server advertises it, and only with the `zstd` feature enabled.
+    /// Otherwise Session falls back to the compression set with [`compression`](Self::compression).
+    /// Higher levels trade speed for ratio, negative levels trade ratio for speed
+    /// and `0` selects zstd's default level.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::client::session::Session;
+    /// # use scylla::client::session_builder::SessionBuilder;
+    /// # use scylla::client::Compression;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .zstd_compression(Some(3))
+    ///     .compression(Some(Compression::Lz4))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn zstd_compression(mut self, level: Option<i32>) -> Self {
+        self.config.zstd_compression_level = level;
+        self
+    }
+
+    /// Set the version of the CQL native protocol to use.
+    /// The default is [`ProtocolVersion::V4`].
+    ///
+    /// Protocol v5 wraps frames in checksummed segments. Only lz4 compression
+    /// can be used with it; other compression algorithms are ignored.
+    /// If a node does not support protocol v5, connections to it fall back to v4.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::client::session::Session;
+    /// # use scylla::client::session_builder::SessionBuilder;
+    /// # use scylla::client::ProtocolVersion;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .protocol_version(ProtocolVersion::V5)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn protocol_version(mut self, protocol_version: ProtocolVersion) -> Self {
+        self.config.protocol_version = protocol_version;
+        self
+    }
+
     /// Set the delay for schema agreement check. How often driver should ask if schema is in agreement
     /// The default is 200 milliseconds.
     ///
@@ -1157,6 +1215,63 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self.config.identity = identity;
         self
     }
+
+    /// Set the client-side limits on the concurrency and the rate of requests
+    /// issued by the session. See [`RequestLimits`] for details.
+    ///
+    /// By default, requests are not limited.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::client::session::Session;
+    /// # use scylla::client::session_builder::SessionBuilder;
+    /// # use scylla::client::{RateLimit, RequestLimits};
+    /// # use std::num::{NonZeroU32, NonZeroUsize};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let session: Session = SessionBuilder::new()
+    ///         .known_node("127.0.0.1:9042")
+    ///         .request_limits(
+    ///             RequestLimits::new()
+    ///                 .with_max_concurrent_requests(NonZeroUsize::new(1024).unwrap())
+    ///                 .with_max_concurrent_requests_per_node(NonZeroUsize::new(256).unwrap())
+    ///                 .with_rate_limit(RateLimit::new(NonZeroU32::new(10_000).unwrap()))
+    ///                 .with_queue_timeout(Duration::from_secs(5))
+    ///         )
+    ///         .build()
+    ///         .await?;
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn request_limits(mut self, limits: RequestLimits) -> Self {
+        self.config.request_limits = limits;
+        self
+    }
+
+    /// Set the session-wide limit on the number of retries, relative to the number of requests.
+    /// See [`RetryBudget`] for details.
+    ///
+    /// By default, the number of retries is limited only by the retry policies.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::client::session::Session;
+    /// # use scylla::client::session_builder::SessionBuilder;
+    /// # use scylla::policies::retry::RetryBudget;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     // Allow at most one retry per ten requests.
+    ///     let session: Session = SessionBuilder::new()
+    ///         .known_node("127.0.0.1:9042")
+    ///         .retry_budget(RetryBudget::new(0.1))
+    ///         .build()
+    ///         .await?;
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn retry_budget(mut self, budget: RetryBudget) -> Self {
+        self.config.retry_budget = Some(budget);
+        self
+    }
 }
 
 /// Creates a [`SessionBuilder`] with default configuration, same as [`SessionBuilder::new`]
@@ -1171,7 +1286,7 @@ mod tests {
     use scylla_cql::frame::types::SerialConsistency;
     use scylla_cql::Consistency;
 
-    use super::super::Compression;
+    use super::super::{Compression, ProtocolVersion};
     use super::SessionBuilder;
     use crate::client::execution_profile::{defaults, ExecutionProfile};
     use crate::cluster::node::KnownNode;
@@ -1264,6 +1379,33 @@ mod tests {
         assert_eq!(builder.config.compression, None);
     }
 
+    #[test]
+    fn zstd_compression() {
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.zstd_compression_level, None);
+
+        builder = builder.zstd_compression(Some(3));
+        assert_eq!(builder.config.zstd_compression_level, Some(3));
+        // The fallback compression is kept separately.
+        assert_eq!(builder.config.compression, None);
+
+        builder = builder.zstd_compression(None);
+        assert_eq!(builder.config.zstd_compression_level, None);
+    }
+
+    #[test]
+    fn protocol_version() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.protocol_version, ProtocolVersion::V4);
+
+        builder = builder.protocol_version(ProtocolVersion::V5);
+        assert_eq!(builder.config.protocol_version, ProtocolVersion::V5);
+
+        builder = builder.protocol_version(ProtocolVersion::V4);
+        assert_eq!(builder.config.protocol_version, ProtocolVersion::V4);
+    }
+
     #[test]
     fn tcp_nodelay() {
         setup_tracing();
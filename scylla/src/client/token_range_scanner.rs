@@ -0,0 +1,624 @@
+//! Reading whole tables in parallel, split by token ranges.
+//!
+//! [TokenRangeScanner] reads all the rows of a table with paged
+//! `SELECT ... WHERE token(pk) > ? AND token(pk) <= ?` queries:
+//! - the token ring is split into ranges owned by a single set of replicas
+//!   (vnodes or tablets), and each range is queried on its replicas, preferably
+//!   on the shard owning it,
+//! - at most a configured number of ranges is scanned at the same time,
+//! - the rows of all the ranges are merged into a single [Stream],
+//! - the progress is tracked per range, so that an interrupted scan can be resumed
+//!   from a [ScanCheckpoint] instead of starting over.
+//!
+//! ```rust
+//! # use scylla::client::session::Session;
+//! # use std::sync::Arc;
+//! # async fn check_only_compiles(session: Arc<Session>) -> Result<(), Box<dyn std::error::Error>> {
+//! use futures::TryStreamExt;
+//! use scylla::client::token_range_scanner::TokenRangeScanner;
+//!
+//! let mut scan = TokenRangeScanner::new(session, "ks", "tab")
+//!     .with_columns(["a", "b"])
+//!     .scan::<(i32, String)>()
+//!     .await?;
+//! while let Some((a, b)) = scan.try_next().await? {
+//!     println!("a, b: {}, {}", a, b);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::{Stream, StreamExt};
+use scylla_cql::deserialize::row::DeserializeRow;
+use scylla_cql::deserialize::{DeserializationError, TypeCheckError};
+use scylla_cql::frame::request::query::{PagingState, PagingStateResponse};
+use scylla_cql::frame::response::result::TableSpec;
+use scylla_cql::Consistency;
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::client::execution_profile::ExecutionProfileHandle;
+use crate::client::session::Session;
+use crate::cluster::describe::Ident;
+use crate::cluster::{ClusterState, Node, NodeRef};
+use crate::deserialize::DeserializeOwnedRow;
+use crate::errors::{ExecutionError, PrepareError};
+use crate::policies::load_balancing::{FallbackPlan, LoadBalancingPolicy, RoutingInfo};
+use crate::response::query_result::{IntoRowsResultError, RowsError};
+use crate::routing::{Shard, Token};
+use crate::statement::prepared::PreparedStatement;
+
+/// Scans a whole table, querying token ranges in parallel.
+///
+/// The scan is started with [scan](Self::scan), which returns a [TokenRangeScan]
+/// stream of rows. See the [module-level docs](self) for an example.
+///
+/// The ranges are computed from the token ring (and the tablets of the table
+/// known to the driver) at the start of the scan. Each range is read with
+/// a paged query executed on the replicas of the range, so the load balancing
+/// policy of the execution profile is not used. The rows of a range come
+/// in the token order, but the rows of different ranges are interleaved.
+pub struct TokenRangeScanner {
+    session: Arc<Session>,
+    keyspace: String,
+    table: String,
+    columns: Option<Vec<String>>,
+    parallelism: NonZeroUsize,
+    page_size: Option<i32>,
+    consistency: Option<Consistency>,
+    execution_profile_handle: Option<ExecutionProfileHandle>,
+    checkpoint: Option<ScanCheckpoint>,
+}
+
+impl TokenRangeScanner {
+    /// Creates a scanner reading all the columns of the table.
+    ///
+    /// The table must be present in the cluster metadata of the session.
+    pub fn new(
+        session: Arc<Session>,
+        keyspace: impl Into<String>,
+        table: impl Into<String>,
+    ) -> Self {
+        Self {
+            session,
+            keyspace: keyspace.into(),
+            table: table.into(),
+            columns: None,
+            parallelism: NonZeroUsize::new(16).unwrap(),
+            page_size: None,
+            consistency: None,
+            execution_profile_handle: None,
+            checkpoint: None,
+        }
+    }
+
+    /// Sets the columns to be read, in the order of the values in the rows.
+    ///
+    /// The names are quoted when needed, so they should be given
+    /// as they are stored in the schema. By default, all the columns are read.
+    pub fn with_columns(mut self, columns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.columns = Some(columns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets the maximum number of ranges scanned at the same time.
+    ///
+    /// This is also the maximum number of pages waiting to be consumed. Defaults to 16.
+    pub fn with_parallelism(mut self, parallelism: NonZeroUsize) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Sets the page size of the queries.
+    ///
+    /// Panics if given number is nonpositive.
+    pub fn with_page_size(mut self, page_size: i32) -> Self {
+        assert!(
+            page_size > 0,
+            "TokenRangeScanner::with_page_size: page size must be positive"
+        );
+        self.page_size = Some(page_size);
+        self
+    }
+
+    /// Sets the consistency of the queries.
+    pub fn with_consistency(mut self, consistency: Consistency) -> Self {
+        self.consistency = Some(consistency);
+        self
+    }
+
+    /// Sets the execution profile of the queries.
+    pub fn with_execution_profile_handle(mut self, profile_handle: ExecutionProfileHandle) -> Self {
+        self.execution_profile_handle = Some(profile_handle);
+        self
+    }
+
+    /// Resumes a scan from a checkpoint obtained with [TokenRangeScan::checkpoint].
+    ///
+    /// The ranges of the checkpoint are used instead of the ones computed from
+    /// the token ring, and the finished ones are skipped. The checkpoint must come
+    /// from a scan of the same table with the same columns.
+    pub fn with_checkpoint(mut self, checkpoint: ScanCheckpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// Prepares the query and starts scanning the ranges in the background.
+    ///
+    /// Performs a type check of the rows before the scan is started.
+    pub async fn scan<RowT>(self) -> Result<TokenRangeScan<RowT>, TokenRangeScanError>
+    where
+        RowT: for<'frame, 'metadata> DeserializeRow<'frame, 'metadata> + Send + 'static,
+    {
+        let cluster_state = self.session.get_cluster_state();
+        let table = cluster_state
+            .get_keyspace(&self.keyspace)
+            .and_then(|keyspace| keyspace.tables.get(&self.table))
+            .ok_or_else(|| TokenRangeScanError::TableNotFound {
+                keyspace: self.keyspace.clone(),
+                table: self.table.clone(),
+            })?;
+
+        let mut statement = self
+            .session
+            .prepare(scan_query(
+                &self.keyspace,
+                &self.table,
+                &table.partition_key,
+                self.columns.as_deref(),
+            ))
+            .await?;
+        RowT::type_check(statement.get_result_set_col_specs().as_slice())?;
+        statement.set_is_idempotent(true);
+        if let Some(page_size) = self.page_size {
+            statement.set_page_size(page_size);
+        }
+        if let Some(consistency) = self.consistency {
+            statement.set_consistency(consistency);
+        }
+        statement.set_execution_profile_handle(self.execution_profile_handle);
+
+        let checkpoint = match self.checkpoint {
+            Some(checkpoint) => checkpoint,
+            None => {
+                let table_spec = TableSpec::borrowed(&self.keyspace, &self.table);
+                ScanCheckpoint::new(
+                    token_ranges(&cluster_state, &table_spec)
+                        .into_iter()
+                        .map(|range| (range, RangeProgress::Pending(PagingState::start()))),
+                )
+            }
+        };
+        let pending: Vec<_> = checkpoint
+            .ranges
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (range, progress))| match progress {
+                RangeProgress::Pending(paging_state) => Some((index, *range, paging_state.clone())),
+                RangeProgress::Done => None,
+            })
+            .collect();
+
+        let (sender, receiver) = mpsc::channel(self.parallelism.get());
+        let session = self.session;
+        let parallelism = self.parallelism.get();
+        let worker = tokio::spawn(async move {
+            futures::stream::iter(pending)
+                .for_each_concurrent(parallelism, |(index, range, paging_state)| {
+                    scan_range(&session, &statement, &sender, index, range, paging_state)
+                })
+                .await;
+        });
+
+        Ok(TokenRangeScan {
+            receiver,
+            worker,
+            current_page: None,
+            checkpoint,
+        })
+    }
+}
+
+impl fmt::Debug for TokenRangeScanner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenRangeScanner")
+            .field("keyspace", &self.keyspace)
+            .field("table", &self.table)
+            .field("columns", &self.columns)
+            .field("parallelism", &self.parallelism)
+            .field("page_size", &self.page_size)
+            .field("consistency", &self.consistency)
+            .field("checkpoint", &self.checkpoint)
+            .finish()
+    }
+}
+
+/// Builds the query reading a token range of the table.
+fn scan_query(
+    keyspace: &str,
+    table: &str,
+    partition_key: &[String],
+    columns: Option<&[String]>,
+) -> String {
+    let join = |names: &[String]| {
+        names
+            .iter()
+            .map(|name| Ident(name).to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let columns = columns.map_or_else(|| "*".to_string(), join);
+    let token = format!("token({})", join(partition_key));
+    format!(
+        "SELECT {} FROM {}.{} WHERE {} > ? AND {} <= ?",
+        columns,
+        Ident(keyspace),
+        Ident(table),
+        token,
+        token
+    )
+}
+
+/// Splits the whole token ring into ranges, each of them owned by a single
+/// vnode and (if the tablets of the table are known) a single tablet.
+fn token_ranges(cluster_state: &ClusterState, table_spec: &TableSpec) -> Vec<TokenRange> {
+    let replica_locator = cluster_state.replica_locator();
+    let mut boundaries: Vec<i64> = replica_locator
+        .ring()
+        .iter()
+        .map(|(token, _)| token.value())
+        .collect();
+    if let Some(tablets) = replica_locator.tablets.tablets_for_table(table_spec) {
+        boundaries.extend(tablets.tablet_ranges().map(|(_, last)| last.value()));
+    }
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    let mut ranges = Vec::with_capacity(boundaries.len() + 1);
+    let mut start = i64::MIN;
+    for end in boundaries {
+        ranges.push(TokenRange { start, end });
+        start = end;
+    }
+    if start != i64::MAX {
+        ranges.push(TokenRange {
+            start,
+            end: i64::MAX,
+        });
+    }
+    ranges
+}
+
+/// Reads all the remaining pages of a range, sending them to the stream.
+///
+/// Stops after the first error, or when the stream is dropped.
+async fn scan_range<RowT: DeserializeOwnedRow>(
+    session: &Session,
+    statement: &PreparedStatement,
+    sender: &mpsc::Sender<Result<ScannedPage<RowT>, TokenRangeScanError>>,
+    index: usize,
+    range: TokenRange,
+    mut paging_state: PagingState,
+) {
+    let statement = routed_statement(session, statement, range);
+    loop {
+        let page = read_page(session, &statement, range, paging_state).await;
+        let next_paging_state = match &page {
+            Ok(ScannedPage {
+                progress: RangeProgress::Pending(paging_state),
+                ..
+            }) => Some(paging_state.clone()),
+            _ => None,
+        };
+        if sender
+            .send(page.map(|page| ScannedPage { index, ..page }))
+            .await
+            .is_err()
+        {
+            // The stream was dropped.
+            return;
+        }
+        match next_paging_state {
+            Some(next_paging_state) => paging_state = next_paging_state,
+            None => return,
+        }
+    }
+}
+
+async fn read_page<RowT: DeserializeOwnedRow>(
+    session: &Session,
+    statement: &PreparedStatement,
+    range: TokenRange,
+    paging_state: PagingState,
+) -> Result<ScannedPage<RowT>, TokenRangeScanError> {
+    let (result, paging_state_response) = session
+        .execute_single_page(statement, (range.start, range.end), paging_state)
+        .await
+        .map_err(|error| TokenRangeScanError::ExecutionError { range, error })?;
+    let rows_result = result
+        .into_rows_result()
+        .map_err(|error| TokenRangeScanError::IntoRowsResultError { range, error })?;
+    let rows = rows_result
+        .rows::<RowT>()
+        .map_err(|RowsError::TypeCheckFailed(err)| TokenRangeScanError::TypeCheckError(err))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| TokenRangeScanError::DeserializationError { range, error })?;
+    let progress = match paging_state_response {
+        PagingStateResponse::HasMorePages { state } => RangeProgress::Pending(state),
+        PagingStateResponse::NoMorePages => RangeProgress::Done,
+    };
+    Ok(ScannedPage {
+        index: 0,
+        rows: rows.into_iter(),
+        progress,
+    })
+}
+
+/// Returns the statement with a load balancing policy targeting the replicas of the range.
+fn routed_statement(
+    session: &Session,
+    statement: &PreparedStatement,
+    range: TokenRange,
+) -> PreparedStatement {
+    let mut statement = statement.clone();
+    let Some(table_spec) = statement.get_table_spec() else {
+        return statement;
+    };
+    let cluster_state = session.get_cluster_state();
+    // The range is owned by the replicas of its end token.
+    let replicas: Vec<_> = cluster_state
+        .get_token_endpoints_iter(table_spec, Token::new(range.end))
+        .map(|(node, shard)| (Arc::clone(node), shard))
+        .collect();
+    if !replicas.is_empty() {
+        statement.set_load_balancing_policy(Some(Arc::new(RangeReplicasPolicy { replicas })));
+    }
+    statement
+}
+
+/// Load balancing policy querying the replicas of a token range, in order.
+#[derive(Debug)]
+struct RangeReplicasPolicy {
+    replicas: Vec<(Arc<Node>, Shard)>,
+}
+
+impl LoadBalancingPolicy for RangeReplicasPolicy {
+    fn pick<'a>(
+        &'a self,
+        request: &'a RoutingInfo,
+        cluster: &'a ClusterState,
+    ) -> Option<(NodeRef<'a>, Option<Shard>)> {
+        self.fallback(request, cluster).next()
+    }
+
+    fn fallback<'a>(
+        &'a self,
+        _request: &'a RoutingInfo,
+        _cluster: &'a ClusterState,
+    ) -> FallbackPlan<'a> {
+        Box::new(
+            self.replicas
+                .iter()
+                .filter(|(node, _)| node.is_enabled())
+                .map(|(node, shard)| (node, Some(*shard))),
+        )
+    }
+
+    fn name(&self) -> String {
+        "TokenRangeScanner".to_string()
+    }
+}
+
+/// A range of tokens: from `start` (exclusive) to `end` (inclusive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TokenRange {
+    start: i64,
+    end: i64,
+}
+
+impl TokenRange {
+    /// Creates a range of the tokens greater than `start` and not greater than `end`.
+    ///
+    /// `i64::MIN` as `start` denotes the beginning of the token ring.
+    pub fn new(start: i64, end: i64) -> Self {
+        Self { start, end }
+    }
+
+    /// The exclusive start of the range.
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// The inclusive end of the range.
+    pub fn end(&self) -> i64 {
+        self.end
+    }
+}
+
+impl fmt::Display for TokenRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}]", self.start, self.end)
+    }
+}
+
+/// The progress of scanning a [TokenRange].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RangeProgress {
+    /// The range is yet to be (fully) scanned, starting from the paging state.
+    ///
+    /// [PagingState::start] means that no rows of the range were returned.
+    Pending(PagingState),
+
+    /// All the rows of the range were returned.
+    Done,
+}
+
+/// The progress of a scan, recorded per token range.
+///
+/// Can be persisted (using [PagingState::as_bytes_slice] and
+/// [PagingState::new_from_raw_bytes] for the paging states) and passed
+/// to [TokenRangeScanner::with_checkpoint] to resume the scan.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanCheckpoint {
+    ranges: Vec<(TokenRange, RangeProgress)>,
+}
+
+impl ScanCheckpoint {
+    /// Creates a checkpoint from the progress of all the ranges of a scan.
+    pub fn new(ranges: impl IntoIterator<Item = (TokenRange, RangeProgress)>) -> Self {
+        Self {
+            ranges: ranges.into_iter().collect(),
+        }
+    }
+
+    /// Returns the ranges of the scan with their progress.
+    pub fn ranges(&self) -> &[(TokenRange, RangeProgress)] {
+        &self.ranges
+    }
+
+    /// Returns `true` if all the ranges were scanned.
+    pub fn is_finished(&self) -> bool {
+        self.ranges
+            .iter()
+            .all(|(_, progress)| *progress == RangeProgress::Done)
+    }
+}
+
+/// A page of rows read from a range, along with the progress after the page.
+struct ScannedPage<RowT> {
+    index: usize,
+    rows: std::vec::IntoIter<RowT>,
+    progress: RangeProgress,
+}
+
+/// A [Stream] of rows of a table, returned by [TokenRangeScanner::scan].
+///
+/// If scanning a range fails, the error is returned by the stream, and the
+/// other ranges continue to be scanned. The failed range is left pending
+/// in the [checkpoint](Self::checkpoint), so it can be retried later
+/// by resuming the scan. Dropping the stream stops the scan.
+pub struct TokenRangeScan<RowT> {
+    receiver: mpsc::Receiver<Result<ScannedPage<RowT>, TokenRangeScanError>>,
+    worker: JoinHandle<()>,
+    current_page: Option<ScannedPage<RowT>>,
+    checkpoint: ScanCheckpoint,
+}
+
+impl<RowT> TokenRangeScan<RowT> {
+    /// Returns the progress of the scan, covering the rows returned so far.
+    ///
+    /// The progress of a range is updated once all the rows of a page are returned,
+    /// so resuming from the checkpoint may return some of the rows again.
+    pub fn checkpoint(&self) -> ScanCheckpoint {
+        self.checkpoint.clone()
+    }
+}
+
+impl<RowT> Stream for TokenRangeScan<RowT> {
+    type Item = Result<RowT, TokenRangeScanError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(page) = &mut this.current_page {
+                let row = page.rows.next();
+                if page.rows.len() == 0 {
+                    let page = this.current_page.take().expect("Checked above");
+                    this.checkpoint.ranges[page.index].1 = page.progress;
+                }
+                if let Some(row) = row {
+                    return Poll::Ready(Some(Ok(row)));
+                }
+            }
+
+            match this.receiver.poll_recv(cx) {
+                Poll::Ready(Some(Ok(page))) => this.current_page = Some(page),
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+// TokenRangeScan is not self-referential and never pinned internally.
+impl<RowT> Unpin for TokenRangeScan<RowT> {}
+
+impl<RowT> Drop for TokenRangeScan<RowT> {
+    fn drop(&mut self) {
+        self.worker.abort();
+    }
+}
+
+impl<RowT> fmt::Debug for TokenRangeScan<RowT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenRangeScan")
+            .field("checkpoint", &self.checkpoint)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An error returned by a [TokenRangeScanner].
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum TokenRangeScanError {
+    /// The table was not found in the cluster metadata.
+    #[error("Table {keyspace}.{table} not found in the cluster metadata")]
+    TableNotFound { keyspace: String, table: String },
+
+    /// Failed to prepare the query.
+    #[error("Failed to prepare the scan query: {0}")]
+    PrepareError(#[from] PrepareError),
+
+    /// The row type does not match the columns.
+    #[error("Failed to type check the rows: {0}")]
+    TypeCheckError(#[from] TypeCheckError),
+
+    /// Failed to execute the query for a range.
+    #[error("Failed to scan token range {range}: {error}")]
+    ExecutionError {
+        range: TokenRange,
+        error: ExecutionError,
+    },
+
+    /// The response for a range was not a valid rows result.
+    #[error("Invalid result of scanning token range {range}: {error}")]
+    IntoRowsResultError {
+        range: TokenRange,
+        error: IntoRowsResultError,
+    },
+
+    /// Failed to deserialize a row of a range.
+    #[error("Failed to deserialize a row of token range {range}: {error}")]
+    DeserializationError {
+        range: TokenRange,
+        error: DeserializationError,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scan_query;
+
+    #[test]
+    fn scan_query_quotes_identifiers() {
+        let partition_key = ["a".to_owned(), "Bb".to_owned()];
+        assert_eq!(
+            scan_query("ks", "tab", &partition_key, None),
+            r#"SELECT * FROM ks.tab WHERE token(a, "Bb") > ? AND token(a, "Bb") <= ?"#
+        );
+        let columns = ["c".to_owned(), "select".to_owned()];
+        assert_eq!(
+            scan_query("Ks", "tab", &partition_key[..1], Some(&columns)),
+            r#"SELECT c, "select" FROM "Ks".tab WHERE token(a) > ? AND token(a) <= ?"#
+        );
+    }
+}
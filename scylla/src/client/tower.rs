@@ -0,0 +1,632 @@
+//! Integration with [tower](https://docs.rs/tower).
+//!
+//! [SessionService] and [CachingSessionService] implement [tower_service::Service]
+//! for unprepared statements, prepared statements and batches, so that requests
+//! can be composed with standard tower middleware (timeouts, load shedding,
+//! buffering, etc.) or with custom layers.
+//!
+//! The services apply backpressure: [poll_ready](tower_service::Service::poll_ready)
+//! reserves a slot for a request in flight, and is pending while all slots are taken.
+//! The number of slots follows the capacity of the connection pools, i.e. the number
+//! of requests that can be in flight on all working connections of the session.
+//! It is computed whenever a slot is reserved, so it grows as connections are opened
+//! and shrinks as they break. It can be further limited with
+//! [SessionService::with_max_concurrent_requests].
+//!
+//! The same backpressure can be applied to any other service (e.g. a custom service
+//! built on top of a [Session]) with [PoolCapacityLayer].
+//!
+//! Clones of a service share the slots.
+//!
+//! This module is available only under the crate feature `tower-service-03`.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::num::NonZeroUsize;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::time::Sleep;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::client::caching_session::CachingSession;
+use crate::client::session::Session;
+use crate::errors::ExecutionError;
+use crate::network::MAX_STREAMS_PER_CONNECTION;
+use crate::response::query_result::QueryResult;
+use crate::serialize::batch::BatchValues;
+use crate::serialize::row::SerializeRow;
+use crate::statement::batch::Batch;
+use crate::statement::prepared::PreparedStatement;
+use crate::statement::unprepared::Statement;
+
+/// How often a service which is not ready checks whether the capacity
+/// of the connection pools has grown.
+const CAPACITY_RECHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Computes the number of requests that can be in flight
+/// on all connections currently open by the session.
+fn pool_capacity(session: &Session) -> usize {
+    let connections: usize = session
+        .get_cluster_state()
+        .get_nodes_info()
+        .iter()
+        .filter_map(|node| node.working_connections_count().ok())
+        .sum();
+    connections.saturating_mul(MAX_STREAMS_PER_CONNECTION)
+}
+
+/// The number of requests in flight, shared by clones of a service.
+#[derive(Debug, Default)]
+struct InFlightState {
+    in_flight: AtomicUsize,
+    // Tasks waiting for a request to finish.
+    waiters: Mutex<Vec<Waker>>,
+}
+
+impl InFlightState {
+    fn try_acquire(self: &Arc<Self>, limit: usize) -> Option<InFlightPermit> {
+        self.in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |in_flight| {
+                (in_flight < limit).then_some(in_flight + 1)
+            })
+            .ok()
+            .map(|_| InFlightPermit {
+                state: Arc::clone(self),
+            })
+    }
+
+    fn register_waiter(&self, waker: &Waker) {
+        let mut waiters = self.waiters.lock().unwrap();
+        if !waiters.iter().any(|waiter| waiter.will_wake(waker)) {
+            waiters.push(waker.clone());
+        }
+    }
+}
+
+/// A slot of a request in flight, released when dropped.
+#[derive(Debug)]
+struct InFlightPermit {
+    state: Arc<InFlightState>,
+}
+
+impl Drop for InFlightPermit {
+    fn drop(&mut self) {
+        self.state.in_flight.fetch_sub(1, Ordering::AcqRel);
+        let waiters = std::mem::take(&mut *self.state.waiters.lock().unwrap());
+        for waiter in waiters {
+            waiter.wake();
+        }
+    }
+}
+
+/// Slots for requests in flight, shared by clones of a service.
+struct InFlightLimit {
+    state: Arc<InFlightState>,
+    max: Option<NonZeroUsize>,
+    recheck: Option<Pin<Box<Sleep>>>,
+    permit: Option<InFlightPermit>,
+}
+
+impl InFlightLimit {
+    fn new(state: Arc<InFlightState>, max: Option<NonZeroUsize>) -> Self {
+        Self {
+            state,
+            max,
+            recheck: None,
+            permit: None,
+        }
+    }
+
+    /// Reserves a slot, if less than `min(max, capacity)` requests are in flight.
+    /// The capacity is computed lazily, only if a slot has to be reserved.
+    fn poll_ready(&mut self, cx: &mut Context<'_>, capacity: impl Fn() -> usize) -> Poll<()> {
+        if self.permit.is_some() {
+            return Poll::Ready(());
+        }
+        let limit = || match self.max {
+            Some(max) => max.get().min(capacity()),
+            None => capacity(),
+        };
+
+        let mut permit = self.state.try_acquire(limit());
+        if permit.is_none() {
+            // Registering before trying again ensures that a request
+            // which finishes in the meantime wakes this task.
+            self.state.register_waiter(cx.waker());
+            permit = self.state.try_acquire(limit());
+        }
+        if let Some(permit) = permit {
+            self.permit = Some(permit);
+            self.recheck = None;
+            return Poll::Ready(());
+        }
+
+        // The capacity may also grow (e.g. when connections are opened)
+        // without any request finishing, so check it again after a while.
+        let recheck = self
+            .recheck
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep(CAPACITY_RECHECK_INTERVAL)));
+        if recheck.as_mut().poll(cx).is_ready() {
+            self.recheck = None;
+            cx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    }
+
+    fn take_permit(&mut self) -> InFlightPermit {
+        self.permit
+            .take()
+            .expect("Service::call invoked without a successful Service::poll_ready")
+    }
+}
+
+impl Clone for InFlightLimit {
+    fn clone(&self) -> Self {
+        // A reserved slot is not shared with the clone.
+        Self::new(Arc::clone(&self.state), self.max)
+    }
+}
+
+impl fmt::Debug for InFlightLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InFlightLimit")
+            .field("in_flight", &self.state.in_flight.load(Ordering::Relaxed))
+            .field("max", &self.max)
+            .field("is_ready", &self.permit.is_some())
+            .finish()
+    }
+}
+
+/// Awaits the future, holding the slot of the request until it completes.
+fn hold_permit<F>(permit: InFlightPermit, fut: F) -> BoxFuture<'static, F::Output>
+where
+    F: Future + Send + 'static,
+{
+    async move {
+        let _permit = permit;
+        fut.await
+    }
+    .boxed()
+}
+
+/// A [tower_layer::Layer] applying the backpressure of [SessionService]
+/// to the inner service: the resulting [PoolCapacity] service is ready only while
+/// the number of requests in flight is below the capacity of the connection pools
+/// of the session (and, optionally, below a fixed limit).
+///
+/// All services created by the layer (and their clones) share the slots.
+#[derive(Debug, Clone)]
+pub struct PoolCapacityLayer {
+    session: Arc<Session>,
+    state: Arc<InFlightState>,
+    max: Option<NonZeroUsize>,
+}
+
+impl PoolCapacityLayer {
+    /// Creates a layer limiting the number of requests in flight
+    /// to the capacity of the connection pools of the session.
+    pub fn new(session: Arc<Session>) -> Self {
+        Self {
+            session,
+            state: Arc::new(InFlightState::default()),
+            max: None,
+        }
+    }
+
+    /// Additionally limits the number of requests in flight.
+    pub fn with_max_concurrent_requests(mut self, max: NonZeroUsize) -> Self {
+        self.max = Some(max);
+        self
+    }
+}
+
+impl<S> Layer<S> for PoolCapacityLayer {
+    type Service = PoolCapacity<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PoolCapacity {
+            inner,
+            session: Arc::clone(&self.session),
+            limit: InFlightLimit::new(Arc::clone(&self.state), self.max),
+        }
+    }
+}
+
+/// A service created by [PoolCapacityLayer].
+#[derive(Debug, Clone)]
+pub struct PoolCapacity<S> {
+    inner: S,
+    session: Arc<Session>,
+    limit: InFlightLimit,
+}
+
+impl<S> PoolCapacity<S> {
+    /// Returns the inner service.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S, R> Service<R> for PoolCapacity<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let session = &self.session;
+        futures::ready!(self.limit.poll_ready(cx, || pool_capacity(session)));
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let permit = self.limit.take_permit();
+        hold_permit(permit, self.inner.call(request))
+    }
+}
+
+/// A [tower_service::Service] executing requests on a [Session].
+///
+/// Handles the following requests, all of them resulting in a [QueryResult]:
+/// - [Statement] - an unprepared statement without bound values,
+///   executed with [Session::query_unpaged],
+/// - `(Statement, V)` - an unprepared statement with bound values,
+///   executed with [Session::query_unpaged],
+/// - `(PreparedStatement, V)` - executed with [Session::execute_unpaged],
+/// - `(Batch, V)` - executed with [Session::batch].
+///
+/// See the [module-level docs](self) for details about backpressure.
+#[derive(Debug, Clone)]
+pub struct SessionService {
+    session: Arc<Session>,
+    limit: InFlightLimit,
+}
+
+impl SessionService {
+    /// Creates a service, with the number of requests in flight limited
+    /// to the capacity of the connection pools.
+    pub fn new(session: Arc<Session>) -> Self {
+        let limit = InFlightLimit::new(Arc::new(InFlightState::default()), None);
+        Self { session, limit }
+    }
+
+    /// Additionally limits the number of requests in flight,
+    /// sent through this service and its clones.
+    pub fn with_max_concurrent_requests(mut self, max: NonZeroUsize) -> Self {
+        self.limit = InFlightLimit::new(Arc::new(InFlightState::default()), Some(max));
+        self
+    }
+
+    /// Returns the underlying session.
+    pub fn get_session(&self) -> &Arc<Session> {
+        &self.session
+    }
+
+    fn poll_limit(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ExecutionError>> {
+        let session = &self.session;
+        self.limit.poll_ready(cx, || pool_capacity(session)).map(Ok)
+    }
+
+    fn call_with<F>(
+        &mut self,
+        request: impl FnOnce(Arc<Session>) -> F,
+    ) -> BoxFuture<'static, Result<QueryResult, ExecutionError>>
+    where
+        F: std::future::Future<Output = Result<QueryResult, ExecutionError>> + Send + 'static,
+    {
+        let permit = self.limit.take_permit();
+        hold_permit(permit, request(Arc::clone(&self.session)))
+    }
+}
+
+impl Service<Statement> for SessionService {
+    type Response = QueryResult;
+    type Error = ExecutionError;
+    type Future = BoxFuture<'static, Result<QueryResult, ExecutionError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_limit(cx)
+    }
+
+    fn call(&mut self, statement: Statement) -> Self::Future {
+        self.call_with(|session| async move { session.query_unpaged(statement, &[]).await })
+    }
+}
+
+impl<V> Service<(Statement, V)> for SessionService
+where
+    V: SerializeRow + Send + Sync + 'static,
+{
+    type Response = QueryResult;
+    type Error = ExecutionError;
+    type Future = BoxFuture<'static, Result<QueryResult, ExecutionError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_limit(cx)
+    }
+
+    fn call(&mut self, (statement, values): (Statement, V)) -> Self::Future {
+        self.call_with(|session| async move { session.query_unpaged(statement, values).await })
+    }
+}
+
+impl<V> Service<(PreparedStatement, V)> for SessionService
+where
+    V: SerializeRow + Send + Sync + 'static,
+{
+    type Response = QueryResult;
+    type Error = ExecutionError;
+    type Future = BoxFuture<'static, Result<QueryResult, ExecutionError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_limit(cx)
+    }
+
+    fn call(&mut self, (prepared, values): (PreparedStatement, V)) -> Self::Future {
+        self.call_with(|session| async move { session.execute_unpaged(&prepared, values).await })
+    }
+}
+
+impl<V> Service<(Batch, V)> for SessionService
+where
+    V: BatchValues + Send + Sync + 'static,
+{
+    type Response = QueryResult;
+    type Error = ExecutionError;
+    type Future = BoxFuture<'static, Result<QueryResult, ExecutionError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_limit(cx)
+    }
+
+    fn call(&mut self, (batch, values): (Batch, V)) -> Self::Future {
+        self.call_with(|session| async move { session.batch(&batch, values).await })
+    }
+}
+
+/// A [tower_service::Service] executing requests on a [CachingSession],
+/// i.e. preparing the statements and caching the prepared statements.
+///
+/// Handles the following requests, all of them resulting in a [QueryResult]:
+/// - [Statement] - a statement without bound values,
+///   executed with [CachingSession::execute_unpaged],
+/// - `(Statement, V)` - a statement with bound values,
+///   executed with [CachingSession::execute_unpaged],
+/// - `(PreparedStatement, V)` - an already prepared statement, executed
+///   with [Session::execute_unpaged] on the underlying session,
+/// - `(Batch, V)` - executed with [CachingSession::batch].
+///
+/// See the [module-level docs](self) for details about backpressure.
+pub struct CachingSessionService<S = RandomState>
+where
+    S: Clone + BuildHasher,
+{
+    session: Arc<CachingSession<S>>,
+    limit: InFlightLimit,
+}
+
+impl<S> CachingSessionService<S>
+where
+    S: Clone + BuildHasher,
+{
+    /// Creates a service, with the number of requests in flight limited
+    /// to the capacity of the connection pools.
+    pub fn new(session: Arc<CachingSession<S>>) -> Self {
+        let limit = InFlightLimit::new(Arc::new(InFlightState::default()), None);
+        Self { session, limit }
+    }
+
+    /// Additionally limits the number of requests in flight,
+    /// sent through this service and its clones.
+    pub fn with_max_concurrent_requests(mut self, max: NonZeroUsize) -> Self {
+        self.limit = InFlightLimit::new(Arc::new(InFlightState::default()), Some(max));
+        self
+    }
+
+    /// Returns the underlying caching session.
+    pub fn get_session(&self) -> &Arc<CachingSession<S>> {
+        &self.session
+    }
+
+    fn poll_limit(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ExecutionError>> {
+        let session = self.session.get_session();
+        self.limit.poll_ready(cx, || pool_capacity(session)).map(Ok)
+    }
+
+    fn call_with<F>(
+        &mut self,
+        request: impl FnOnce(Arc<CachingSession<S>>) -> F,
+    ) -> BoxFuture<'static, Result<QueryResult, ExecutionError>>
+    where
+        F: std::future::Future<Output = Result<QueryResult, ExecutionError>> + Send + 'static,
+    {
+        let permit = self.limit.take_permit();
+        hold_permit(permit, request(Arc::clone(&self.session)))
+    }
+}
+
+impl<S> Clone for CachingSessionService<S>
+where
+    S: Clone + BuildHasher,
+{
+    fn clone(&self) -> Self {
+        Self {
+            session: Arc::clone(&self.session),
+            limit: self.limit.clone(),
+        }
+    }
+}
+
+impl<S> fmt::Debug for CachingSessionService<S>
+where
+    S: Clone + BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachingSessionService")
+            .field("session", &self.session)
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+impl<S> Service<Statement> for CachingSessionService<S>
+where
+    S: Clone + BuildHasher + Send + Sync + 'static,
+{
+    type Response = QueryResult;
+    type Error = ExecutionError;
+    type Future = BoxFuture<'static, Result<QueryResult, ExecutionError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_limit(cx)
+    }
+
+    fn call(&mut self, statement: Statement) -> Self::Future {
+        self.call_with(|session| async move { session.execute_unpaged(statement, &[]).await })
+    }
+}
+
+impl<S, V> Service<(Statement, V)> for CachingSessionService<S>
+where
+    S: Clone + BuildHasher + Send + Sync + 'static,
+    V: SerializeRow + Send + Sync + 'static,
+{
+    type Response = QueryResult;
+    type Error = ExecutionError;
+    type Future = BoxFuture<'static, Result<QueryResult, ExecutionError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_limit(cx)
+    }
+
+    fn call(&mut self, (statement, values): (Statement, V)) -> Self::Future {
+        self.call_with(|session| async move { session.execute_unpaged(statement, values).await })
+    }
+}
+
+impl<S, V> Service<(PreparedStatement, V)> for CachingSessionService<S>
+where
+    S: Clone + BuildHasher + Send + Sync + 'static,
+    V: SerializeRow + Send + Sync + 'static,
+{
+    type Response = QueryResult;
+    type Error = ExecutionError;
+    type Future = BoxFuture<'static, Result<QueryResult, ExecutionError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_limit(cx)
+    }
+
+    fn call(&mut self, (prepared, values): (PreparedStatement, V)) -> Self::Future {
+        self.call_with(|session| async move {
+            session
+                .get_session()
+                .execute_unpaged(&prepared, values)
+                .await
+        })
+    }
+}
+
+impl<S, V> Service<(Batch, V)> for CachingSessionService<S>
+where
+    S: Clone + BuildHasher + Send + Sync + 'static,
+    V: BatchValues + Send + Sync + 'static,
+{
+    type Response = QueryResult;
+    type Error = ExecutionError;
+    type Future = BoxFuture<'static, Result<QueryResult, ExecutionError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_limit(cx)
+    }
+
+    fn call(&mut self, (batch, values): (Batch, V)) -> Self::Future {
+        self.call_with(|session| async move { session.batch(&batch, values).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::Poll;
+
+    use futures::task::noop_waker_ref;
+
+    use super::{InFlightLimit, InFlightState, CAPACITY_RECHECK_INTERVAL};
+    use crate::test_utils::setup_tracing;
+
+    #[tokio::test]
+    async fn in_flight_limit_is_shared_by_clones() {
+        setup_tracing();
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        let mut limit = InFlightLimit::new(Arc::new(InFlightState::default()), None);
+        let mut clone = limit.clone();
+        let capacity = || 1;
+
+        assert_eq!(limit.poll_ready(&mut cx, capacity), Poll::Ready(()));
+        // Polling again doesn't reserve another slot.
+        assert_eq!(limit.poll_ready(&mut cx, capacity), Poll::Ready(()));
+        assert_eq!(clone.poll_ready(&mut cx, capacity), Poll::Pending);
+
+        let permit = limit.take_permit();
+        assert_eq!(clone.poll_ready(&mut cx, capacity), Poll::Pending);
+        drop(permit);
+        assert_eq!(clone.poll_ready(&mut cx, capacity), Poll::Ready(()));
+        assert_eq!(limit.poll_ready(&mut cx, capacity), Poll::Pending);
+    }
+
+    #[tokio::test]
+    async fn in_flight_limit_follows_capacity() {
+        setup_tracing();
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        let capacity = AtomicUsize::new(0);
+        let current_capacity = || capacity.load(Ordering::Relaxed);
+        let mut limit = InFlightLimit::new(Arc::new(InFlightState::default()), None);
+        let mut clone = limit.clone();
+
+        // No connections are open yet.
+        assert_eq!(limit.poll_ready(&mut cx, current_capacity), Poll::Pending);
+
+        capacity.store(2, Ordering::Relaxed);
+        assert_eq!(limit.poll_ready(&mut cx, current_capacity), Poll::Ready(()));
+        assert_eq!(clone.poll_ready(&mut cx, current_capacity), Poll::Ready(()));
+        let _permits = (limit.take_permit(), clone.take_permit());
+
+        // Connections broke, so no more requests are admitted
+        // until the ones in flight finish.
+        capacity.store(1, Ordering::Relaxed);
+        assert_eq!(limit.poll_ready(&mut cx, current_capacity), Poll::Pending);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn in_flight_limit_is_capped_by_max() {
+        setup_tracing();
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        let mut limit = InFlightLimit::new(
+            Arc::new(InFlightState::default()),
+            Some(NonZeroUsize::new(1).unwrap()),
+        );
+        let mut clone = limit.clone();
+        let capacity = || 100;
+
+        assert_eq!(limit.poll_ready(&mut cx, capacity), Poll::Ready(()));
+        assert_eq!(clone.poll_ready(&mut cx, capacity), Poll::Pending);
+        // Pending services check the capacity again after a while.
+        tokio::time::advance(CAPACITY_RECHECK_INTERVAL).await;
+        assert_eq!(clone.poll_ready(&mut cx, capacity), Poll::Pending);
+    }
+}
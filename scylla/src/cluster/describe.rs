@@ -0,0 +1,1016 @@
+//! Rendering of schema metadata as CQL DDL statements, similar to the output
+//! of `DESCRIBE` command of cqlsh.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Write};
+
+use itertools::Itertools;
+
+use crate::errors::DescribeError;
+
+use super::metadata::{
+    ClusteringOrder, CollectionType, ColumnKind, ColumnType, Index, IndexKind, Keyspace,
+    MaterializedView, NativeType, Strategy, Table, TableOptions, UserDefinedAggregate,
+    UserDefinedFunction, UserDefinedType,
+};
+
+impl Keyspace {
+    /// Returns a `CREATE KEYSPACE` statement which recreates the keyspace,
+    /// without any of its contents.
+    pub fn as_cql_query(&self, keyspace_name: &str) -> String {
+        format!(
+            "CREATE KEYSPACE {} WITH replication = {} AND durable_writes = {};",
+            Ident(keyspace_name),
+            StrategyLiteral(&self.strategy),
+            self.durable_writes
+        )
+    }
+
+    /// Returns CQL statements which recreate the keyspace with all of its contents.
+    ///
+    /// The statements are ordered so that every entity is created after the entities
+    /// it depends on: the keyspace, user-defined types, user-defined functions,
+    /// user-defined aggregates, tables followed by their secondary indexes,
+    /// and finally materialized views. Statements are separated by empty lines.
+    ///
+    /// Materialized views which back secondary indexes are not included,
+    /// as they are created by `CREATE INDEX` statements.
+    ///
+    /// Fails if the schema contains a CQL type which this version of the driver cannot render.
+    pub fn describe(&self, keyspace_name: &str) -> Result<String, DescribeError> {
+        let mut statements = vec![self.as_cql_query(keyspace_name)];
+
+        for udt in sorted_udts(&self.user_defined_types) {
+            statements.push(udt_as_cql_query(udt)?);
+        }
+
+        for (signature, function) in
+            self.user_defined_functions
+                .iter()
+                .sorted_by(|(a, _), (b, _)| {
+                    (&a.name, &a.argument_types).cmp(&(&b.name, &b.argument_types))
+                })
+        {
+            statements.push(function.as_cql_query(keyspace_name, &signature.name)?);
+        }
+
+        for (signature, aggregate) in
+            self.user_defined_aggregates
+                .iter()
+                .sorted_by(|(a, _), (b, _)| {
+                    (&a.name, &a.argument_types).cmp(&(&b.name, &b.argument_types))
+                })
+        {
+            statements.push(aggregate.as_cql_query(keyspace_name, &signature.name)?);
+        }
+
+        let mut index_views = HashSet::new();
+        for (table_name, table) in self.tables.iter().sorted_by_key(|(name, _)| *name) {
+            statements.push(table.as_cql_query(keyspace_name, table_name)?);
+            for (index_name, index) in table.indexes.iter().sorted_by_key(|(name, _)| *name) {
+                statements.push(index.as_cql_query(keyspace_name, table_name, index_name));
+                // ScyllaDB implements secondary indexes with materialized views named this way.
+                index_views.insert(format!("{}_index", index_name));
+            }
+        }
+
+        statements.extend(
+            self.views
+                .iter()
+                .filter(|(view_name, _)| !index_views.contains(*view_name))
+                .sorted_by_key(|(name, _)| *name)
+                .map(|(view_name, view)| view.as_cql_query(keyspace_name, view_name)),
+        );
+
+        Ok(statements.join("\n\n"))
+    }
+}
+
+impl Table {
+    /// Returns a `CREATE TABLE` statement which recreates the table
+    /// with its options, but without its secondary indexes.
+    ///
+    /// Fails if a column has a CQL type which this version of the driver cannot render.
+    pub fn as_cql_query(
+        &self,
+        keyspace_name: &str,
+        table_name: &str,
+    ) -> Result<String, DescribeError> {
+        let mut query = format!(
+            "CREATE TABLE {}.{} (\n",
+            Ident(keyspace_name),
+            Ident(table_name)
+        );
+        for column_name in self.column_names_in_order() {
+            let column = &self.columns[column_name];
+            write!(
+                query,
+                "    {} {}",
+                Ident(column_name),
+                cql_type(&column.typ)?
+            )
+            .unwrap();
+            if column.kind == ColumnKind::Static {
+                query.push_str(" static");
+            }
+            query.push_str(",\n");
+        }
+        writeln!(query, "    PRIMARY KEY {}", self.primary_key()).unwrap();
+        query.push(')');
+
+        let mut clauses = self.clustering_order_clause().into_iter().collect_vec();
+        clauses.extend(table_options_clauses(&self.options, false));
+        push_with_clauses(&mut query, " ", clauses);
+        Ok(query)
+    }
+
+    /// Partition key columns, clustering key columns,
+    /// and then the remaining columns ordered by name.
+    fn column_names_in_order(&self) -> impl Iterator<Item = &String> {
+        let other_columns = self
+            .columns
+            .iter()
+            .filter(|(_, column)| {
+                !matches!(
+                    column.kind,
+                    ColumnKind::PartitionKey | ColumnKind::Clustering
+                )
+            })
+            .map(|(name, _)| name)
+            .sorted();
+
+        self.partition_key
+            .iter()
+            .chain(self.clustering_key.iter())
+            .chain(other_columns)
+    }
+
+    fn primary_key(&self) -> String {
+        let partition_key = if self.partition_key.len() == 1 {
+            Ident(&self.partition_key[0]).to_string()
+        } else {
+            format!(
+                "({})",
+                self.partition_key.iter().map(|name| Ident(name)).join(", ")
+            )
+        };
+
+        format!(
+            "({})",
+            std::iter::once(partition_key)
+                .chain(
+                    self.clustering_key
+                        .iter()
+                        .map(|name| Ident(name).to_string())
+                )
+                .join(", ")
+        )
+    }
+
+    fn clustering_order_clause(&self) -> Option<String> {
+        if self.clustering_key.is_empty() {
+            return None;
+        }
+
+        let orders = self.clustering_key.iter().map(|name| {
+            let order = match self.columns.get(name).and_then(|c| c.clustering_order) {
+                Some(ClusteringOrder::Descending) => "DESC",
+                Some(ClusteringOrder::Ascending) | None => "ASC",
+            };
+            format!("{} {}", Ident(name), order)
+        });
+        Some(format!("CLUSTERING ORDER BY ({})", orders.format(", ")))
+    }
+}
+
+impl MaterializedView {
+    /// Returns a `CREATE MATERIALIZED VIEW` statement which recreates the view
+    /// with its options.
+    pub fn as_cql_query(&self, keyspace_name: &str, view_name: &str) -> String {
+        let view = &self.view_metadata;
+        let selected_columns = if self.include_all_columns {
+            "*".to_owned()
+        } else {
+            view.column_names_in_order()
+                .map(|name| Ident(name))
+                .join(", ")
+        };
+
+        let mut query = format!(
+            "CREATE MATERIALIZED VIEW {}.{} AS\n    \
+            SELECT {} FROM {}.{}\n    \
+            WHERE {}\n    \
+            PRIMARY KEY {}",
+            Ident(keyspace_name),
+            Ident(view_name),
+            selected_columns,
+            Ident(keyspace_name),
+            Ident(&self.base_table_name),
+            self.where_clause,
+            view.primary_key(),
+        );
+
+        let mut clauses = view.clustering_order_clause().into_iter().collect_vec();
+        clauses.extend(table_options_clauses(&view.options, true));
+        push_with_clauses(&mut query, "\n    ", clauses);
+        query
+    }
+}
+
+impl Index {
+    /// Returns a `CREATE INDEX` statement which recreates the index.
+    pub fn as_cql_query(&self, keyspace_name: &str, table_name: &str, index_name: &str) -> String {
+        let target = self.target().unwrap_or_default();
+        let target = match parse_local_index_target(target) {
+            Some((partition_key, clustering_key)) => format!(
+                "({}), {}",
+                partition_key.into_iter().map(Ident).join(", "),
+                clustering_key.into_iter().map(Ident).join(", ")
+            ),
+            // Targets are stored with identifiers already quoted if needed.
+            None => target.to_owned(),
+        };
+
+        let custom = if self.kind == IndexKind::Custom {
+            "CUSTOM "
+        } else {
+            ""
+        };
+        let mut query = format!(
+            "CREATE {}INDEX {} ON {}.{} ({})",
+            custom,
+            Ident(index_name),
+            Ident(keyspace_name),
+            Ident(table_name),
+            target
+        );
+
+        if self.kind == IndexKind::Custom {
+            if let Some(class_name) = self.options.get("class_name") {
+                write!(query, " USING {}", StringLiteral(class_name)).unwrap();
+            }
+            let other_options: HashMap<String, String> = self
+                .options
+                .iter()
+                .filter(|(name, _)| !matches!(name.as_str(), "target" | "class_name"))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            if !other_options.is_empty() {
+                write!(
+                    query,
+                    " WITH OPTIONS = {}",
+                    StringMapLiteral(&other_options)
+                )
+                .unwrap();
+            }
+        }
+
+        query.push(';');
+        query
+    }
+}
+
+impl UserDefinedFunction {
+    /// Returns a `CREATE FUNCTION` statement which recreates the function.
+    ///
+    /// Fails if the function uses a CQL type which this version of the driver cannot render.
+    pub fn as_cql_query(
+        &self,
+        keyspace_name: &str,
+        function_name: &str,
+    ) -> Result<String, DescribeError> {
+        let arguments = self
+            .argument_names
+            .iter()
+            .zip(self.argument_types.iter())
+            .map(|(name, typ)| Ok(format!("{} {}", Ident(name), cql_type(typ)?)))
+            .collect::<Result<Vec<_>, DescribeError>>()?
+            .join(", ");
+        let null_input = if self.called_on_null_input {
+            "CALLED ON NULL INPUT"
+        } else {
+            "RETURNS NULL ON NULL INPUT"
+        };
+        let body = if self.body.contains("$$") {
+            StringLiteral(&self.body).to_string()
+        } else {
+            format!("$${}$$", self.body)
+        };
+
+        Ok(format!(
+            "CREATE FUNCTION {}.{}({})\n    {}\n    RETURNS {}\n    LANGUAGE {}\n    AS {};",
+            Ident(keyspace_name),
+            Ident(function_name),
+            arguments,
+            null_input,
+            cql_type(&self.return_type)?,
+            self.language,
+            body
+        ))
+    }
+}
+
+impl UserDefinedAggregate {
+    /// Returns a `CREATE AGGREGATE` statement which recreates the aggregate.
+    ///
+    /// Fails if the aggregate uses a CQL type which this version of the driver cannot render.
+    pub fn as_cql_query(
+        &self,
+        keyspace_name: &str,
+        aggregate_name: &str,
+    ) -> Result<String, DescribeError> {
+        let mut query = format!(
+            "CREATE AGGREGATE {}.{}({})\n    SFUNC {}\n    STYPE {}",
+            Ident(keyspace_name),
+            Ident(aggregate_name),
+            cql_types(&self.argument_types)?,
+            Ident(&self.state_func),
+            cql_type(&self.state_type)?
+        );
+        if let Some(final_func) = &self.final_func {
+            write!(query, "\n    FINALFUNC {}", Ident(final_func)).unwrap();
+        }
+        if let Some(initcond) = &self.initcond {
+            write!(query, "\n    INITCOND {}", initcond).unwrap();
+        }
+        query.push(';');
+        Ok(query)
+    }
+}
+
+fn udt_as_cql_query(udt: &UserDefinedType<'_>) -> Result<String, DescribeError> {
+    let fields = udt
+        .field_types
+        .iter()
+        .map(|(name, typ)| Ok(format!("    {} {}", Ident(name), cql_type(typ)?)))
+        .collect::<Result<Vec<_>, DescribeError>>()?
+        .join(",\n");
+    Ok(format!(
+        "CREATE TYPE {}.{} (\n{}\n);",
+        Ident(&udt.keyspace),
+        Ident(&udt.name),
+        fields
+    ))
+}
+
+/// Orders UDTs so that every UDT comes after the UDTs used by its fields.
+/// Independent UDTs are ordered by name.
+fn sorted_udts<'a>(
+    udts: &'a HashMap<String, std::sync::Arc<UserDefinedType<'static>>>,
+) -> Vec<&'a UserDefinedType<'static>> {
+    fn visit<'a>(
+        udt: &'a UserDefinedType<'static>,
+        visited: &mut HashSet<&'a str>,
+        result: &mut Vec<&'a UserDefinedType<'static>>,
+    ) {
+        if !visited.insert(&udt.name) {
+            return;
+        }
+        for (_, field_type) in &udt.field_types {
+            visit_type(field_type, visited, result);
+        }
+        result.push(udt);
+    }
+
+    fn visit_type<'a>(
+        typ: &'a ColumnType<'static>,
+        visited: &mut HashSet<&'a str>,
+        result: &mut Vec<&'a UserDefinedType<'static>>,
+    ) {
+        match typ {
+            ColumnType::UserDefinedType { definition, .. } => visit(definition, visited, result),
+            ColumnType::Collection {
+                typ: CollectionType::List(elem) | CollectionType::Set(elem),
+                ..
+            } => visit_type(elem, visited, result),
+            ColumnType::Collection {
+                typ: CollectionType::Map(key, value),
+                ..
+            } => {
+                visit_type(key, visited, result);
+                visit_type(value, visited, result);
+            }
+            ColumnType::Vector { typ, .. } => visit_type(typ, visited, result),
+            ColumnType::Tuple(types) => {
+                for typ in types {
+                    visit_type(typ, visited, result);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut visited = HashSet::new();
+    let mut result = Vec::with_capacity(udts.len());
+    for (_, udt) in udts.iter().sorted_by_key(|(name, _)| *name) {
+        visit(udt, &mut visited, &mut result);
+    }
+    result
+}
+
+/// Returns `name = value` clauses of table options, ordered by name.
+fn table_options_clauses(options: &TableOptions, is_view: bool) -> Vec<String> {
+    let mut clauses: Vec<(&str, String)> = vec![
+        ("comment", StringLiteral(&options.comment).to_string()),
+        ("gc_grace_seconds", options.gc_grace_seconds.to_string()),
+    ];
+    // Materialized views inherit TTL from their base tables.
+    if !is_view {
+        clauses.push((
+            "default_time_to_live",
+            options.default_time_to_live.to_string(),
+        ));
+    }
+
+    let maps = [
+        ("caching", Some(&options.caching)),
+        ("compaction", Some(&options.compaction)),
+        ("compression", Some(&options.compression)),
+        ("tombstone_gc", options.tombstone_gc.as_ref()),
+        (
+            "cdc",
+            options
+                .cdc
+                .as_ref()
+                // Disabled CDC is the same as not setting the option at all,
+                // which also works with clusters that do not support CDC.
+                .filter(|cdc| cdc.get("enabled").map(String::as_str) != Some("false")),
+        ),
+    ];
+    for (name, map) in maps {
+        if let Some(map) = map.filter(|map| !map.is_empty()) {
+            clauses.push((name, StringMapLiteral(map).to_string()));
+        }
+    }
+
+    clauses.extend(
+        options
+            .other
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.clone())),
+    );
+
+    clauses.sort_unstable_by_key(|(name, _)| *name);
+    clauses
+        .into_iter()
+        .map(|(name, value)| format!("{} = {}", name, value))
+        .collect()
+}
+
+/// Appends `WITH ... AND ...` clauses, each one in a separate line, and terminates the statement.
+fn push_with_clauses(query: &mut String, with_prefix: &str, clauses: Vec<String>) {
+    if !clauses.is_empty() {
+        write!(query, "{}WITH {}", with_prefix, clauses.join("\n    AND ")).unwrap();
+    }
+    query.push(';');
+}
+
+/// Parses the target of a ScyllaDB local secondary index,
+/// e.g. `{"pk":["p1","p2"],"ck":["v"]}`, into partition key and indexed columns.
+fn parse_local_index_target(target: &str) -> Option<(Vec<&str>, Vec<&str>)> {
+    fn parse_list<'a>(target: &'a str, key: &str) -> Option<Vec<&'a str>> {
+        let start = target.find(key)? + key.len();
+        let rest = target[start..].trim_start().strip_prefix(':')?;
+        let rest = rest.trim_start().strip_prefix('[')?;
+        let list = &rest[..rest.find(']')?];
+        Some(
+            list.split(',')
+                .map(|name| name.trim().trim_matches('"'))
+                .filter(|name| !name.is_empty())
+                .collect(),
+        )
+    }
+
+    if !target.starts_with('{') {
+        return None;
+    }
+    Some((parse_list(target, "\"pk\"")?, parse_list(target, "\"ck\"")?))
+}
+
+/// CQL keywords which cannot be used as unquoted identifiers.
+const RESERVED_KEYWORDS: &[&str] = &[
+    "add",
+    "allow",
+    "alter",
+    "and",
+    "apply",
+    "asc",
+    "authorize",
+    "batch",
+    "begin",
+    "by",
+    "columnfamily",
+    "create",
+    "delete",
+    "desc",
+    "describe",
+    "drop",
+    "entries",
+    "execute",
+    "from",
+    "full",
+    "grant",
+    "if",
+    "in",
+    "index",
+    "infinity",
+    "insert",
+    "into",
+    "is",
+    "keyspace",
+    "limit",
+    "modify",
+    "nan",
+    "norecursive",
+    "not",
+    "null",
+    "of",
+    "on",
+    "or",
+    "order",
+    "primary",
+    "rename",
+    "replace",
+    "revoke",
+    "schema",
+    "select",
+    "set",
+    "table",
+    "to",
+    "token",
+    "truncate",
+    "unlogged",
+    "unset",
+    "update",
+    "use",
+    "using",
+    "view",
+    "where",
+    "with",
+];
+
+/// Displays a CQL identifier, quoting it if it would not be parsed correctly otherwise.
+pub(crate) struct Ident<'a>(pub(crate) &'a str);
+
+impl Display for Ident<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut chars = self.0.chars();
+        let is_plain = chars.next().is_some_and(|c| c.is_ascii_lowercase())
+            && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            && !RESERVED_KEYWORDS.contains(&self.0);
+
+        if is_plain {
+            f.write_str(self.0)
+        } else {
+            write!(f, "\"{}\"", self.0.replace('"', "\"\""))
+        }
+    }
+}
+
+/// Displays a CQL string literal.
+struct StringLiteral<'a>(&'a str);
+
+impl Display for StringLiteral<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}'", self.0.replace('\'', "''"))
+    }
+}
+
+/// Displays a CQL map literal with text keys and values, ordered by key.
+struct StringMapLiteral<'a>(&'a HashMap<String, String>);
+
+impl Display for StringMapLiteral<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self
+            .0
+            .iter()
+            .sorted()
+            .map(|(key, value)| format!("{}: {}", StringLiteral(key), StringLiteral(value)));
+        write!(f, "{{{}}}", entries.format(", "))
+    }
+}
+
+/// Displays the replication options of a keyspace as a CQL map literal.
+struct StrategyLiteral<'a>(&'a Strategy);
+
+impl Display for StrategyLiteral<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (class, options): (&str, HashMap<String, String>) = match self.0 {
+            Strategy::SimpleStrategy { replication_factor } => (
+                "org.apache.cassandra.locator.SimpleStrategy",
+                HashMap::from([(
+                    "replication_factor".to_owned(),
+                    replication_factor.to_string(),
+                )]),
+            ),
+            Strategy::NetworkTopologyStrategy {
+                datacenter_repfactors,
+            } => (
+                "org.apache.cassandra.locator.NetworkTopologyStrategy",
+                datacenter_repfactors
+                    .iter()
+                    .map(|(dc, rf)| (dc.clone(), rf.to_string()))
+                    .collect(),
+            ),
+            Strategy::LocalStrategy => {
+                ("org.apache.cassandra.locator.LocalStrategy", HashMap::new())
+            }
+            Strategy::Other { name, data } => (name, data.clone()),
+        };
+
+        let entries = std::iter::once(format!(
+            "{}: {}",
+            StringLiteral("class"),
+            StringLiteral(class)
+        ))
+        .chain(
+            options
+                .iter()
+                .sorted()
+                .map(|(key, value)| format!("{}: {}", StringLiteral(key), StringLiteral(value))),
+        );
+        write!(f, "{{{}}}", entries.format(", "))
+    }
+}
+
+/// Renders a CQL type as it would be written in a DDL statement.
+///
+/// The types are non-exhaustive, so a newer version of scylla-cql may produce
+/// a type which is not known here. Such types are reported as errors
+/// rather than rendered as invalid CQL.
+fn cql_type(typ: &ColumnType<'_>) -> Result<String, DescribeError> {
+    let unsupported = || DescribeError::UnsupportedType {
+        typ: format!("{:?}", typ),
+    };
+
+    let rendered = match typ {
+        ColumnType::Native(native) => {
+            let name = match native {
+                NativeType::Ascii => "ascii",
+                NativeType::Boolean => "boolean",
+                NativeType::Blob => "blob",
+                NativeType::Counter => "counter",
+                NativeType::Date => "date",
+                NativeType::Decimal => "decimal",
+                NativeType::Double => "double",
+                NativeType::Duration => "duration",
+                NativeType::Float => "float",
+                NativeType::Int => "int",
+                NativeType::BigInt => "bigint",
+                NativeType::Text => "text",
+                NativeType::Timestamp => "timestamp",
+                NativeType::Inet => "inet",
+                NativeType::SmallInt => "smallint",
+                NativeType::TinyInt => "tinyint",
+                NativeType::Time => "time",
+                NativeType::Timeuuid => "timeuuid",
+                NativeType::Uuid => "uuid",
+                NativeType::Varint => "varint",
+                _ => return Err(unsupported()),
+            };
+            name.to_owned()
+        }
+        ColumnType::Collection { frozen, typ } => {
+            let collection = match typ {
+                CollectionType::List(elem) => format!("list<{}>", cql_type(elem)?),
+                CollectionType::Set(elem) => format!("set<{}>", cql_type(elem)?),
+                CollectionType::Map(key, value) => {
+                    format!("map<{}, {}>", cql_type(key)?, cql_type(value)?)
+                }
+                _ => return Err(unsupported()),
+            };
+            if *frozen {
+                format!("frozen<{}>", collection)
+            } else {
+                collection
+            }
+        }
+        ColumnType::Vector { typ, dimensions } => {
+            format!("vector<{}, {}>", cql_type(typ)?, dimensions)
+        }
+        ColumnType::UserDefinedType { frozen, definition } => {
+            // UDTs can only be used within their own keyspace,
+            // so the name does not need to be qualified.
+            if *frozen {
+                format!("frozen<{}>", Ident(&definition.name))
+            } else {
+                Ident(&definition.name).to_string()
+            }
+        }
+        ColumnType::Tuple(types) => format!("tuple<{}>", cql_types(types)?),
+        _ => return Err(unsupported()),
+    };
+    Ok(rendered)
+}
+
+/// Renders a comma-separated list of CQL types.
+fn cql_types(types: &[ColumnType<'_>]) -> Result<String, DescribeError> {
+    Ok(types
+        .iter()
+        .map(cql_type)
+        .collect::<Result<Vec<_>, _>>()?
+        .join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use crate::cluster::metadata::{
+        ClusteringOrder, CollectionType, Column, ColumnKind, ColumnType, Index, IndexKind,
+        Keyspace, MaterializedView, NativeType, Strategy, Table, TableOptions, UserDefinedType,
+    };
+    use crate::test_utils::setup_tracing;
+
+    fn column(typ: ColumnType<'static>, kind: ColumnKind) -> Column {
+        let clustering_order =
+            (kind == ColumnKind::Clustering).then_some(ClusteringOrder::Ascending);
+        Column {
+            typ,
+            kind,
+            clustering_order,
+        }
+    }
+
+    fn table(
+        columns: Vec<(&str, Column)>,
+        partition_key: &[&str],
+        clustering_key: &[&str],
+    ) -> Table {
+        Table {
+            columns: columns
+                .into_iter()
+                .map(|(name, column)| (name.to_owned(), column))
+                .collect(),
+            partition_key: partition_key.iter().map(|s| s.to_string()).collect(),
+            clustering_key: clustering_key.iter().map(|s| s.to_string()).collect(),
+            partitioner: None,
+            indexes: HashMap::new(),
+            options: TableOptions::default(),
+            pk_column_specs: vec![],
+        }
+    }
+
+    #[test]
+    fn test_table_as_cql_query() {
+        setup_tracing();
+        let mut table = table(
+            vec![
+                (
+                    "v",
+                    column(ColumnType::Native(NativeType::Text), ColumnKind::Regular),
+                ),
+                (
+                    "p2",
+                    column(
+                        ColumnType::Native(NativeType::Int),
+                        ColumnKind::PartitionKey,
+                    ),
+                ),
+                (
+                    "p1",
+                    column(
+                        ColumnType::Native(NativeType::Int),
+                        ColumnKind::PartitionKey,
+                    ),
+                ),
+                (
+                    "Ck",
+                    column(
+                        ColumnType::Native(NativeType::BigInt),
+                        ColumnKind::Clustering,
+                    ),
+                ),
+                (
+                    "s",
+                    column(
+                        ColumnType::Collection {
+                            frozen: true,
+                            typ: CollectionType::Map(
+                                Box::new(ColumnType::Native(NativeType::Text)),
+                                Box::new(ColumnType::Native(NativeType::Int)),
+                            ),
+                        },
+                        ColumnKind::Static,
+                    ),
+                ),
+            ],
+            &["p1", "p2"],
+            &["Ck"],
+        );
+        table.columns.get_mut("Ck").unwrap().clustering_order = Some(ClusteringOrder::Descending);
+        table.options.comment = "It's a table".to_owned();
+        table.options.compaction = HashMap::from([(
+            "class".to_owned(),
+            "SizeTieredCompactionStrategy".to_owned(),
+        )]);
+        table.options.cdc = Some(HashMap::from([("enabled".to_owned(), "false".to_owned())]));
+        table.options.other =
+            HashMap::from([("bloom_filter_fp_chance".to_owned(), "0.01".to_owned())]);
+
+        assert_eq!(
+            table.as_cql_query("ks", "select").unwrap(),
+            "CREATE TABLE ks.\"select\" (\n    \
+                p1 int,\n    \
+                p2 int,\n    \
+                \"Ck\" bigint,\n    \
+                s frozen<map<text, int>> static,\n    \
+                v text,\n    \
+                PRIMARY KEY ((p1, p2), \"Ck\")\n\
+            ) WITH CLUSTERING ORDER BY (\"Ck\" DESC)\n    \
+                AND bloom_filter_fp_chance = 0.01\n    \
+                AND comment = 'It''s a table'\n    \
+                AND compaction = {'class': 'SizeTieredCompactionStrategy'}\n    \
+                AND default_time_to_live = 0\n    \
+                AND gc_grace_seconds = 0;"
+        );
+    }
+
+    #[test]
+    fn test_keyspace_describe_order() {
+        setup_tracing();
+
+        let type_b = Arc::new(UserDefinedType {
+            name: "b".into(),
+            keyspace: "ks".into(),
+            field_types: vec![("x".into(), ColumnType::Native(NativeType::Int))],
+        });
+        let type_a = Arc::new(UserDefinedType {
+            name: "a".into(),
+            keyspace: "ks".into(),
+            field_types: vec![(
+                "b".into(),
+                ColumnType::Collection {
+                    frozen: false,
+                    typ: CollectionType::List(Box::new(ColumnType::UserDefinedType {
+                        frozen: true,
+                        definition: type_b.clone(),
+                    })),
+                },
+            )],
+        });
+
+        let mut base = table(
+            vec![
+                (
+                    "id",
+                    column(
+                        ColumnType::Native(NativeType::Int),
+                        ColumnKind::PartitionKey,
+                    ),
+                ),
+                (
+                    "v",
+                    column(ColumnType::Native(NativeType::Int), ColumnKind::Regular),
+                ),
+            ],
+            &["id"],
+            &[],
+        );
+        base.indexes = HashMap::from([(
+            "v_idx".to_owned(),
+            Index {
+                kind: IndexKind::Composites,
+                options: HashMap::from([("target".to_owned(), "v".to_owned())]),
+            },
+        )]);
+
+        let view = |include_all_columns| MaterializedView {
+            view_metadata: table(
+                vec![
+                    (
+                        "v",
+                        column(
+                            ColumnType::Native(NativeType::Int),
+                            ColumnKind::PartitionKey,
+                        ),
+                    ),
+                    (
+                        "id",
+                        column(ColumnType::Native(NativeType::Int), ColumnKind::Clustering),
+                    ),
+                ],
+                &["v"],
+                &["id"],
+            ),
+            base_table_name: "t".to_owned(),
+            where_clause: "v IS NOT NULL AND id IS NOT NULL".to_owned(),
+            include_all_columns,
+        };
+
+        let keyspace = Keyspace {
+            strategy: Strategy::NetworkTopologyStrategy {
+                datacenter_repfactors: HashMap::from([
+                    ("dc2".to_owned(), 1),
+                    ("dc1".to_owned(), 3),
+                ]),
+            },
+            durable_writes: true,
+            tables: HashMap::from([("t".to_owned(), base)]),
+            views: HashMap::from([
+                ("mv".to_owned(), view(false)),
+                // Backs the `v_idx` index, so it is not described.
+                ("v_idx_index".to_owned(), view(true)),
+            ]),
+            user_defined_types: HashMap::from([("a".to_owned(), type_a), ("b".to_owned(), type_b)]),
+            user_defined_functions: HashMap::new(),
+            user_defined_aggregates: HashMap::new(),
+        };
+
+        assert_eq!(
+            keyspace.describe("ks").unwrap(),
+            "CREATE KEYSPACE ks WITH replication = {'class': 'org.apache.cassandra.locator.NetworkTopologyStrategy', 'dc1': '3', 'dc2': '1'} AND durable_writes = true;\n\
+            \n\
+            CREATE TYPE ks.b (\n    \
+                x int\n\
+            );\n\
+            \n\
+            CREATE TYPE ks.a (\n    \
+                b list<frozen<b>>\n\
+            );\n\
+            \n\
+            CREATE TABLE ks.t (\n    \
+                id int,\n    \
+                v int,\n    \
+                PRIMARY KEY (id)\n\
+            ) WITH comment = ''\n    \
+                AND default_time_to_live = 0\n    \
+                AND gc_grace_seconds = 0;\n\
+            \n\
+            CREATE INDEX v_idx ON ks.t (v);\n\
+            \n\
+            CREATE MATERIALIZED VIEW ks.mv AS\n    \
+                SELECT v, id FROM ks.t\n    \
+                WHERE v IS NOT NULL AND id IS NOT NULL\n    \
+                PRIMARY KEY (v, id)\n    \
+                WITH CLUSTERING ORDER BY (id ASC)\n    \
+                AND comment = ''\n    \
+                AND gc_grace_seconds = 0;"
+        );
+    }
+
+    #[test]
+    fn test_functions_and_indexes_as_cql_query() {
+        setup_tracing();
+
+        let function = crate::cluster::metadata::UserDefinedFunction {
+            argument_names: vec!["a".to_owned(), "b".to_owned()],
+            argument_types: vec![
+                ColumnType::Native(NativeType::Int),
+                ColumnType::Native(NativeType::Int),
+            ],
+            return_type: ColumnType::Native(NativeType::Int),
+            language: "lua".to_owned(),
+            body: "return a + b".to_owned(),
+            called_on_null_input: false,
+        };
+        assert_eq!(
+            function.as_cql_query("ks", "plus").unwrap(),
+            "CREATE FUNCTION ks.plus(a int, b int)\n    \
+                RETURNS NULL ON NULL INPUT\n    \
+                RETURNS int\n    \
+                LANGUAGE lua\n    \
+                AS $$return a + b$$;"
+        );
+
+        let aggregate = crate::cluster::metadata::UserDefinedAggregate {
+            argument_types: vec![ColumnType::Native(NativeType::Int)],
+            state_func: "plus".to_owned(),
+            state_type: ColumnType::Native(NativeType::Int),
+            final_func: None,
+            initcond: Some("0".to_owned()),
+            return_type: ColumnType::Native(NativeType::Int),
+        };
+        assert_eq!(
+            aggregate.as_cql_query("ks", "sum").unwrap(),
+            "CREATE AGGREGATE ks.sum(int)\n    SFUNC plus\n    STYPE int\n    INITCOND 0;"
+        );
+
+        let local_index = Index {
+            kind: IndexKind::Composites,
+            options: HashMap::from([(
+                "target".to_owned(),
+                r#"{"pk":["p1","p2"],"ck":["v"]}"#.to_owned(),
+            )]),
+        };
+        assert_eq!(
+            local_index.as_cql_query("ks", "t", "idx"),
+            "CREATE INDEX idx ON ks.t ((p1, p2), v);"
+        );
+
+        let custom_index = Index {
+            kind: IndexKind::Custom,
+            options: HashMap::from([
+                ("target".to_owned(), "v".to_owned()),
+                ("class_name".to_owned(), "vector_index".to_owned()),
+                ("similarity_function".to_owned(), "cosine".to_owned()),
+            ]),
+        };
+        assert_eq!(
+            custom_index.as_cql_query("ks", "t", "idx"),
+            "CREATE CUSTOM INDEX idx ON ks.t (v) USING 'vector_index' \
+            WITH OPTIONS = {'similarity_function': 'cosine'};"
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! This module holds entities that represent events pushed by the cluster
+//! to the driver, and a way to subscribe to them:
+//! - [ClusterEvent] - a single topology, status or schema change event,
+//! - [ClusterEventStream] - a [Stream] of events received from the cluster,
+//!   obtained through [Session::subscribe_events](crate::client::session::Session::subscribe_events).
+
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use crate::frame::response::event::{
+    Event, SchemaChangeEvent as CqlSchemaChangeEvent, SchemaChangeType as CqlSchemaChangeType,
+    StatusChangeEvent as CqlStatusChangeEvent, TopologyChangeEvent as CqlTopologyChangeEvent,
+};
+
+/// Maximum number of events that can be buffered for a single subscriber.
+/// If a subscriber does not keep up, the oldest events are dropped
+/// and the subscriber is notified with [ClusterEventsLaggedError].
+pub(crate) const CLUSTER_EVENTS_CHANNEL_SIZE: usize = 1024;
+
+/// An event pushed by the cluster to the driver.
+///
+/// The driver registers for all kinds of events on its control connection.
+/// Events are delivered to subscribers as soon as they are received,
+/// so the [ClusterState](super::ClusterState) may not reflect them yet.
+/// If a subscriber needs up-to-date metadata, it should call
+/// [Session::refresh_metadata](crate::client::session::Session::refresh_metadata).
+///
+/// Events are only hints: if the control connection is broken, events sent
+/// by the cluster in the meantime are lost.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClusterEvent {
+    /// A node was added to, removed from or moved within the cluster.
+    Topology(TopologyEvent),
+
+    /// A node was marked as up or down by the cluster.
+    Status(StatusEvent),
+
+    /// Some schema entity was created, updated or dropped.
+    Schema(SchemaEvent),
+}
+
+/// A change of the cluster topology.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TopologyEvent {
+    /// A new node joined the cluster.
+    NodeAdded(SocketAddr),
+
+    /// A node left the cluster.
+    NodeRemoved(SocketAddr),
+
+    /// A node changed its token ownership.
+    NodeMoved(SocketAddr),
+}
+
+/// A change of a node's status, as seen by the cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StatusEvent {
+    /// A node is now considered up.
+    NodeUp(SocketAddr),
+
+    /// A node is now considered down.
+    NodeDown(SocketAddr),
+}
+
+/// Describes how a schema entity was changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SchemaChangeKind {
+    Created,
+    Updated,
+    Dropped,
+}
+
+/// A change of the schema, together with the entity that was changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SchemaEvent {
+    /// A keyspace was changed.
+    Keyspace {
+        kind: SchemaChangeKind,
+        keyspace_name: String,
+    },
+
+    /// A table or a materialized view was changed.
+    Table {
+        kind: SchemaChangeKind,
+        keyspace_name: String,
+        table_name: String,
+    },
+
+    /// A user-defined type was changed.
+    Type {
+        kind: SchemaChangeKind,
+        keyspace_name: String,
+        type_name: String,
+    },
+
+    /// A user-defined function was changed.
+    Function {
+        kind: SchemaChangeKind,
+        keyspace_name: String,
+        function_name: String,
+        /// CQL types of the function's arguments.
+        arguments: Vec<String>,
+    },
+
+    /// A user-defined aggregate was changed.
+    Aggregate {
+        kind: SchemaChangeKind,
+        keyspace_name: String,
+        aggregate_name: String,
+        /// CQL types of the aggregate's arguments.
+        arguments: Vec<String>,
+    },
+}
+
+impl SchemaEvent {
+    /// Returns how the schema entity was changed.
+    pub fn kind(&self) -> SchemaChangeKind {
+        match self {
+            SchemaEvent::Keyspace { kind, .. }
+            | SchemaEvent::Table { kind, .. }
+            | SchemaEvent::Type { kind, .. }
+            | SchemaEvent::Function { kind, .. }
+            | SchemaEvent::Aggregate { kind, .. } => *kind,
+        }
+    }
+
+    /// Returns the name of the keyspace that the changed entity belongs to.
+    pub fn keyspace_name(&self) -> &str {
+        match self {
+            SchemaEvent::Keyspace { keyspace_name, .. }
+            | SchemaEvent::Table { keyspace_name, .. }
+            | SchemaEvent::Type { keyspace_name, .. }
+            | SchemaEvent::Function { keyspace_name, .. }
+            | SchemaEvent::Aggregate { keyspace_name, .. } => keyspace_name,
+        }
+    }
+}
+
+/// An event received by the control connection, passed to the cluster worker.
+#[derive(Debug)]
+pub(crate) enum ServerEvent {
+    /// An event deserialized by scylla-cql.
+    Cql(Event),
+
+    /// A MOVED_NODE topology change, which is not represented by
+    /// scylla-cql's [TopologyChangeEvent](CqlTopologyChangeEvent).
+    NodeMoved(SocketAddr),
+}
+
+impl ClusterEvent {
+    /// Converts an event received from the server into its public representation.
+    ///
+    /// Returns `None` for schema change events with an unrecognized change type.
+    pub(crate) fn from_server_event(event: &ServerEvent) -> Option<Self> {
+        let event = match event {
+            ServerEvent::Cql(event) => event,
+            ServerEvent::NodeMoved(addr) => {
+                return Some(ClusterEvent::Topology(TopologyEvent::NodeMoved(*addr)))
+            }
+        };
+        let cluster_event = match event {
+            Event::TopologyChange(topology) => ClusterEvent::Topology(match *topology {
+                CqlTopologyChangeEvent::NewNode(addr) => TopologyEvent::NodeAdded(addr),
+                CqlTopologyChangeEvent::RemovedNode(addr) => TopologyEvent::NodeRemoved(addr),
+            }),
+            Event::StatusChange(status) => ClusterEvent::Status(match *status {
+                CqlStatusChangeEvent::Up(addr) => StatusEvent::NodeUp(addr),
+                CqlStatusChangeEvent::Down(addr) => StatusEvent::NodeDown(addr),
+            }),
+            Event::SchemaChange(schema) => ClusterEvent::Schema(SchemaEvent::from_cql(schema)?),
+        };
+
+        Some(cluster_event)
+    }
+}
+
+impl SchemaEvent {
+    fn from_cql(event: &CqlSchemaChangeEvent) -> Option<Self> {
+        fn kind(change_type: &CqlSchemaChangeType) -> Option<SchemaChangeKind> {
+            match change_type {
+                CqlSchemaChangeType::Created => Some(SchemaChangeKind::Created),
+                CqlSchemaChangeType::Updated => Some(SchemaChangeKind::Updated),
+                CqlSchemaChangeType::Dropped => Some(SchemaChangeKind::Dropped),
+                CqlSchemaChangeType::Invalid => None,
+            }
+        }
+
+        let schema_event = match event {
+            CqlSchemaChangeEvent::KeyspaceChange {
+                change_type,
+                keyspace_name,
+            } => SchemaEvent::Keyspace {
+                kind: kind(change_type)?,
+                keyspace_name: keyspace_name.clone(),
+            },
+            CqlSchemaChangeEvent::TableChange {
+                change_type,
+                keyspace_name,
+                object_name,
+            } => SchemaEvent::Table {
+                kind: kind(change_type)?,
+                keyspace_name: keyspace_name.clone(),
+                table_name: object_name.clone(),
+            },
+            CqlSchemaChangeEvent::TypeChange {
+                change_type,
+                keyspace_name,
+                type_name,
+            } => SchemaEvent::Type {
+                kind: kind(change_type)?,
+                keyspace_name: keyspace_name.clone(),
+                type_name: type_name.clone(),
+            },
+            CqlSchemaChangeEvent::FunctionChange {
+                change_type,
+                keyspace_name,
+                function_name,
+                arguments,
+            } => SchemaEvent::Function {
+                kind: kind(change_type)?,
+                keyspace_name: keyspace_name.clone(),
+                function_name: function_name.clone(),
+                arguments: arguments.clone(),
+            },
+            CqlSchemaChangeEvent::AggregateChange {
+                change_type,
+                keyspace_name,
+                aggregate_name,
+                arguments,
+            } => SchemaEvent::Aggregate {
+                kind: kind(change_type)?,
+                keyspace_name: keyspace_name.clone(),
+                aggregate_name: aggregate_name.clone(),
+                arguments: arguments.clone(),
+            },
+        };
+
+        Some(schema_event)
+    }
+}
+
+/// An error returned by [ClusterEventStream] when the subscriber did not keep up
+/// with the events and some of them had to be dropped.
+///
+/// The stream can still be polled after this error is returned;
+/// it will continue with the oldest event that was not dropped.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Cluster event subscriber lagged behind, {skipped} events were dropped")]
+pub struct ClusterEventsLaggedError {
+    /// Number of events that were dropped.
+    pub skipped: u64,
+}
+
+/// A [Stream] of events pushed by the cluster.
+///
+/// Created by [Session::subscribe_events](crate::client::session::Session::subscribe_events).
+/// Only events received after the subscription are yielded.
+/// The stream ends when the session is dropped.
+pub struct ClusterEventStream {
+    inner:
+        Pin<Box<dyn Stream<Item = Result<ClusterEvent, ClusterEventsLaggedError>> + Send + Sync>>,
+}
+
+impl std::fmt::Debug for ClusterEventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClusterEventStream").finish_non_exhaustive()
+    }
+}
+
+impl ClusterEventStream {
+    pub(crate) fn new(receiver: broadcast::Receiver<ClusterEvent>) -> Self {
+        // The receive future has to be kept between polls, because dropping it
+        // would unregister the waker from the channel.
+        let inner = futures::stream::unfold(receiver, |mut receiver| async move {
+            let item = match receiver.recv().await {
+                Ok(event) => Ok(event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    Err(ClusterEventsLaggedError { skipped })
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+            Some((item, receiver))
+        });
+
+        Self {
+            inner: Box::pin(inner),
+        }
+    }
+}
+
+impl Stream for ClusterEventStream {
+    type Item = Result<ClusterEvent, ClusterEventsLaggedError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    use futures::StreamExt;
+    use tokio::sync::broadcast;
+
+    use super::{
+        ClusterEvent, ClusterEventStream, ClusterEventsLaggedError, SchemaChangeKind, SchemaEvent,
+        ServerEvent, StatusEvent, TopologyEvent,
+    };
+    use crate::frame::response::event::{
+        Event, SchemaChangeEvent, SchemaChangeType, StatusChangeEvent, TopologyChangeEvent,
+    };
+
+    const ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9042);
+
+    #[test]
+    fn cluster_event_conversion() {
+        let from_cql_event = |event| ClusterEvent::from_server_event(&ServerEvent::Cql(event));
+
+        assert_eq!(
+            from_cql_event(Event::TopologyChange(TopologyChangeEvent::NewNode(ADDR))),
+            Some(ClusterEvent::Topology(TopologyEvent::NodeAdded(ADDR)))
+        );
+        assert_eq!(
+            ClusterEvent::from_server_event(&ServerEvent::NodeMoved(ADDR)),
+            Some(ClusterEvent::Topology(TopologyEvent::NodeMoved(ADDR)))
+        );
+        assert_eq!(
+            from_cql_event(Event::StatusChange(StatusChangeEvent::Down(ADDR))),
+            Some(ClusterEvent::Status(StatusEvent::NodeDown(ADDR)))
+        );
+
+        let function_event =
+            from_cql_event(Event::SchemaChange(SchemaChangeEvent::FunctionChange {
+                change_type: SchemaChangeType::Dropped,
+                keyspace_name: "ks".to_owned(),
+                function_name: "f".to_owned(),
+                arguments: vec!["int".to_owned()],
+            }));
+        let Some(ClusterEvent::Schema(schema_event)) = function_event else {
+            panic!("Expected a schema event, got {:?}", function_event);
+        };
+        assert_eq!(schema_event.kind(), SchemaChangeKind::Dropped);
+        assert_eq!(schema_event.keyspace_name(), "ks");
+        assert_eq!(
+            schema_event,
+            SchemaEvent::Function {
+                kind: SchemaChangeKind::Dropped,
+                keyspace_name: "ks".to_owned(),
+                function_name: "f".to_owned(),
+                arguments: vec!["int".to_owned()],
+            }
+        );
+
+        // Schema changes of unknown type are not propagated.
+        assert_eq!(
+            from_cql_event(Event::SchemaChange(SchemaChangeEvent::KeyspaceChange {
+                change_type: SchemaChangeType::Invalid,
+                keyspace_name: "ks".to_owned(),
+            })),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn cluster_event_stream_reports_lag_and_ends() {
+        let (sender, receiver) = broadcast::channel(2);
+        let mut stream = ClusterEventStream::new(receiver);
+
+        for event in [
+            StatusEvent::NodeUp(ADDR),
+            StatusEvent::NodeDown(ADDR),
+            StatusEvent::NodeUp(ADDR),
+        ] {
+            sender.send(ClusterEvent::Status(event)).unwrap();
+        }
+        drop(sender);
+
+        assert_eq!(
+            stream.next().await,
+            Some(Err(ClusterEventsLaggedError { skipped: 1 }))
+        );
+        assert_eq!(
+            stream.next().await,
+            Some(Ok(ClusterEvent::Status(StatusEvent::NodeDown(ADDR))))
+        );
+        assert_eq!(
+            stream.next().await,
+            Some(Ok(ClusterEvent::Status(StatusEvent::NodeUp(ADDR))))
+        );
+        assert_eq!(stream.next().await, None);
+    }
+}
@@ -8,7 +8,11 @@
 //!   - [Table],
 //!   - [Column],
 //!   - [ColumnKind],
+//!   - [ClusteringOrder],
 //!   - [MaterializedView],
+//!   - [TableOptions],
+//!   - [Index],
+//!   - [UserDefinedFunction], [UserDefinedAggregate], identified by [FunctionSignature],
 //!   - CQL types (re-exported from scylla-cql):
 //!     - [ColumnType],
 //!     - [NativeType],
@@ -18,20 +22,23 @@
 
 use crate::client::pager::{NextPageError, NextRowError, QueryPager};
 use crate::cluster::node::resolve_contact_points;
-use crate::deserialize::DeserializeOwnedRow;
+use crate::deserialize::row::ColumnIterator;
+use crate::deserialize::value::DeserializeValue;
+use crate::deserialize::{DeserializationError, DeserializeOwnedRow, TypeCheckError};
 use crate::errors::{
     DbError, MetadataFetchError, MetadataFetchErrorKind, NewSessionError, RequestAttemptError,
 };
-use crate::frame::response::event::Event;
 use crate::network::{ConnectionConfig, NodeConnectionPool, PoolConfig, PoolSize};
 #[cfg(feature = "metrics")]
 use crate::observability::metrics::Metrics;
 use crate::policies::host_filter::HostFilter;
 use crate::routing::Token;
 use crate::statement::unprepared::Statement;
+use crate::value::CqlValue;
 use crate::DeserializeRow;
 use scylla_cql::utils::parse::{ParseErrorCause, ParseResult, ParserState};
 
+use byteorder::{LittleEndian, ReadBytesExt};
 use futures::future::{self, FutureExt};
 use futures::stream::{self, StreamExt, TryStreamExt};
 use futures::Stream;
@@ -55,8 +62,8 @@ use uuid::Uuid;
 
 use crate::cluster::node::{InternalKnownNode, NodeAddr, ResolvedContactPoint};
 use crate::errors::{
-    KeyspaceStrategyError, KeyspacesMetadataError, MetadataError, PeersMetadataError, RequestError,
-    TablesMetadataError, UdtMetadataError,
+    FunctionsMetadataError, KeyspaceStrategyError, KeyspacesMetadataError, MetadataError,
+    PeersMetadataError, RequestError, TablesMetadataError, UdtMetadataError,
 };
 
 // Re-export of CQL types.
@@ -65,6 +72,7 @@ pub use scylla_cql::frame::response::result::{
 };
 
 use super::control_connection::ControlConnection;
+use super::events::ServerEvent;
 
 type PerKeyspace<T> = HashMap<String, T>;
 type PerKeyspaceResult<T, E> = PerKeyspace<Result<T, E>>;
@@ -196,12 +204,18 @@ impl Peer {
 #[non_exhaustive]
 pub struct Keyspace {
     pub strategy: Strategy,
+    /// Whether writes to the keyspace go through the commit log.
+    pub durable_writes: bool,
     /// Empty HashMap may as well mean that the client disabled schema fetching in SessionConfig
     pub tables: HashMap<String, Table>,
     /// Empty HashMap may as well mean that the client disabled schema fetching in SessionConfig
     pub views: HashMap<String, MaterializedView>,
     /// Empty HashMap may as well mean that the client disabled schema fetching in SessionConfig
     pub user_defined_types: HashMap<String, Arc<UserDefinedType<'static>>>,
+    /// Empty HashMap may as well mean that the client disabled schema fetching in SessionConfig
+    pub user_defined_functions: HashMap<FunctionSignature, UserDefinedFunction>,
+    /// Empty HashMap may as well mean that the client disabled schema fetching in SessionConfig
+    pub user_defined_aggregates: HashMap<FunctionSignature, UserDefinedAggregate>,
 }
 
 /// Describes a table in the cluster.
@@ -216,15 +230,140 @@ pub struct Table {
     /// All of the names are guaranteed to be present in `columns` field.
     pub clustering_key: Vec<String>,
     pub partitioner: Option<String>,
+    /// Secondary indexes of the table, by index name.
+    /// Always empty for materialized views. Indexes of kinds unknown to the driver are omitted.
+    pub indexes: HashMap<String, Index>,
+    pub options: TableOptions,
     pub(crate) pk_column_specs: Vec<ColumnSpec<'static>>,
 }
 
+/// Options of a table or a materialized view, as set by `WITH` clause of
+/// `CREATE TABLE`/`ALTER TABLE` statements.
+///
+/// Maps contain sub-options as stored in `system_schema`, e.g. `compaction`
+/// contains `class` and the parameters of the compaction strategy.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TableOptions {
+    pub comment: String,
+    pub default_time_to_live: i32,
+    pub gc_grace_seconds: i32,
+    pub caching: HashMap<String, String>,
+    pub compaction: HashMap<String, String>,
+    pub compression: HashMap<String, String>,
+    /// `None` if the cluster does not support CDC, or CDC options were never set on the table.
+    pub cdc: Option<HashMap<String, String>>,
+    /// `None` if the cluster does not support the option, or it was never set on the table.
+    pub tombstone_gc: Option<HashMap<String, String>>,
+    /// The remaining options supported by the cluster (e.g. `bloom_filter_fp_chance`),
+    /// with values formatted as CQL literals.
+    pub other: HashMap<String, String>,
+}
+
+/// Describes a secondary index of a table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Index {
+    pub kind: IndexKind,
+    /// Options of the index. The indexed column is stored as `target`,
+    /// and the implementing class of a custom index as `class_name`.
+    pub options: HashMap<String, String>,
+}
+
+impl Index {
+    /// Returns the target of the index, i.e. the indexed column,
+    /// possibly wrapped in a function like `keys(...)` or `full(...)`.
+    pub fn target(&self) -> Option<&str> {
+        self.options.get("target").map(String::as_str)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IndexKind {
+    Keys,
+    Composites,
+    Custom,
+}
+
+/// [IndexKind] parse error
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexKindFromStrError;
+
+impl std::str::FromStr for IndexKind {
+    type Err = IndexKindFromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "KEYS" => Ok(Self::Keys),
+            "COMPOSITES" => Ok(Self::Composites),
+            "CUSTOM" => Ok(Self::Custom),
+            _ => Err(IndexKindFromStrError),
+        }
+    }
+}
+
+/// Identifies a user-defined function or aggregate within a keyspace.
+///
+/// Functions can be overloaded, so the name alone is not enough.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct FunctionSignature {
+    pub name: String,
+    /// CQL types of the arguments, as stored in `system_schema`
+    /// (e.g. `frozen<list<int>>`).
+    pub argument_types: Vec<String>,
+}
+
+impl FunctionSignature {
+    pub fn new(name: impl Into<String>, argument_types: Vec<String>) -> Self {
+        Self {
+            name: name.into(),
+            argument_types,
+        }
+    }
+}
+
+/// Describes a user-defined function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UserDefinedFunction {
+    pub argument_names: Vec<String>,
+    pub argument_types: Vec<ColumnType<'static>>,
+    pub return_type: ColumnType<'static>,
+    pub language: String,
+    pub body: String,
+    /// If false, the function returns null when called with any null argument,
+    /// without executing its body.
+    pub called_on_null_input: bool,
+}
+
+/// Describes a user-defined aggregate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UserDefinedAggregate {
+    pub argument_types: Vec<ColumnType<'static>>,
+    /// Name of the function called for each aggregated row.
+    pub state_func: String,
+    pub state_type: ColumnType<'static>,
+    /// Name of the function called on the final state, if any.
+    pub final_func: Option<String>,
+    /// Initial state, formatted as a CQL literal.
+    pub initcond: Option<String>,
+    pub return_type: ColumnType<'static>,
+}
+
 /// Describes a materialized view in the cluster.
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub struct MaterializedView {
     pub view_metadata: Table,
     pub base_table_name: String,
+    /// The `WHERE` clause of the view's `SELECT` statement, without the `WHERE` keyword.
+    pub where_clause: String,
+    /// Whether the view includes all columns of the base table,
+    /// i.e. it was created with `SELECT *`.
+    pub include_all_columns: bool,
 }
 
 /// Describes a column of the table.
@@ -233,6 +372,8 @@ pub struct MaterializedView {
 pub struct Column {
     pub typ: ColumnType<'static>,
     pub kind: ColumnKind,
+    /// Set only for clustering key columns.
+    pub clustering_order: Option<ClusteringOrder>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -357,6 +498,30 @@ impl std::str::FromStr for ColumnKind {
     }
 }
 
+/// Order of rows within a partition, defined per clustering key column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClusteringOrder {
+    Ascending,
+    Descending,
+}
+
+/// [ClusteringOrder] parse error
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClusteringOrderFromStrError;
+
+impl std::str::FromStr for ClusteringOrder {
+    type Err = ClusteringOrderFromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "asc" => Ok(Self::Ascending),
+            "desc" => Ok(Self::Descending),
+            _ => Err(ClusteringOrderFromStrError),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 #[allow(clippy::enum_variant_names)]
@@ -427,7 +592,7 @@ impl MetadataReader {
         control_connection_repair_requester: broadcast::Sender<()>,
         mut connection_config: ConnectionConfig,
         request_serverside_timeout: Option<Duration>,
-        server_event_sender: mpsc::Sender<Event>,
+        server_event_sender: mpsc::Sender<ServerEvent>,
         keyspaces_to_fetch: Vec<String>,
         fetch_schema: bool,
         host_filter: &Option<Arc<dyn HostFilter>>,
@@ -961,8 +1126,8 @@ impl ControlConnection {
         fetch_schema: bool,
     ) -> Result<PerKeyspaceResult<Keyspace, SingleKeyspaceMetadataError>, MetadataError> {
         let rows = self
-            .query_filter_keyspace_name::<(String, HashMap<String, String>)>(
-                "select keyspace_name, replication from system_schema.keyspaces",
+            .query_filter_keyspace_name::<(String, HashMap<String, String>, bool)>(
+                "select keyspace_name, replication, durable_writes from system_schema.keyspaces",
                 keyspaces_to_fetch,
             )
             .map_err(|error| MetadataFetchError {
@@ -970,9 +1135,22 @@ impl ControlConnection {
                 table: "system_schema.keyspaces",
             });
 
-        let (mut all_tables, mut all_views, mut all_user_defined_types) = if fetch_schema {
+        let (
+            mut all_tables,
+            mut all_views,
+            mut all_user_defined_types,
+            mut all_user_defined_functions,
+            mut all_user_defined_aggregates,
+        ) = if fetch_schema {
             let udts = self.query_user_defined_types(keyspaces_to_fetch).await?;
             let mut tables_schema = self.query_tables_schema(keyspaces_to_fetch, &udts).await?;
+            let mut indexes = self.query_indexes(keyspaces_to_fetch).await?;
+            let functions = self
+                .query_user_defined_functions(keyspaces_to_fetch, &udts)
+                .await?;
+            let aggregates = self
+                .query_user_defined_aggregates(keyspaces_to_fetch, &udts)
+                .await?;
             (
                 // We pass the mutable reference to the same map to the both functions.
                 // First function fetches `system_schema.tables`, and removes found
@@ -981,18 +1159,26 @@ impl ControlConnection {
                 // The assumption here is that no keys (table names) can appear in both
                 // of those schema table.
                 // As far as we know this assumption is true for Scylla and Cassandra.
-                self.query_tables(keyspaces_to_fetch, &mut tables_schema)
+                self.query_tables(keyspaces_to_fetch, &mut tables_schema, &mut indexes)
                     .await?,
                 self.query_views(keyspaces_to_fetch, &mut tables_schema)
                     .await?,
                 udts,
+                functions,
+                aggregates,
             )
         } else {
-            (HashMap::new(), HashMap::new(), HashMap::new())
+            (
+                HashMap::new(),
+                HashMap::new(),
+                HashMap::new(),
+                HashMap::new(),
+                HashMap::new(),
+            )
         };
 
         rows.map(|row_result| {
-            let (keyspace_name, strategy_map) = row_result?;
+            let (keyspace_name, strategy_map, durable_writes) = row_result?;
 
             let strategy: Strategy = strategy_from_string_map(strategy_map).map_err(|error| {
                 KeyspacesMetadataError::Strategy {
@@ -1009,18 +1195,37 @@ impl ControlConnection {
             let user_defined_types = all_user_defined_types
                 .remove(&keyspace_name)
                 .unwrap_or_else(|| Ok(HashMap::new()));
+            let user_defined_functions = all_user_defined_functions
+                .remove(&keyspace_name)
+                .unwrap_or_else(|| Ok(HashMap::new()));
+            let user_defined_aggregates = all_user_defined_aggregates
+                .remove(&keyspace_name)
+                .unwrap_or_else(|| Ok(HashMap::new()));
 
             // As you can notice, in this file we generally operate on two layers of errors:
             // - Outer (MetadataError) if something went wrong with querying the cluster.
             // - Inner (SingleKeyspaceMetadataError) if the fetched metadata turned out to not be fully consistent.
             // If there is an inner error, we want to drop metadata for the whole keyspace.
-            // This logic checks if either tables, views, or UDTs have such inner error, and returns it if so.
+            // This logic checks if either tables, views, UDTs, UDFs or UDAs have such inner error,
+            // and returns it if so.
             // Notice that in the error branch, return value is wrapped in `Ok` - but this is the
             // outer error, so it just means there was no error while querying the cluster.
-            let (tables, views, user_defined_types) = match (tables, views, user_defined_types) {
-                (Ok(t), Ok(v), Ok(u)) => (t, v, u),
-                (Err(e), _, _) | (_, Err(e), _) => return Ok((keyspace_name, Err(e))),
-                (_, _, Err(e)) => {
+            let (
+                tables,
+                views,
+                user_defined_types,
+                user_defined_functions,
+                user_defined_aggregates,
+            ) = match (
+                tables,
+                views,
+                user_defined_types,
+                user_defined_functions,
+                user_defined_aggregates,
+            ) {
+                (Ok(t), Ok(v), Ok(u), Ok(f), Ok(a)) => (t, v, u, f, a),
+                (Err(e), _, _, _, _) | (_, Err(e), _, _, _) => return Ok((keyspace_name, Err(e))),
+                (_, _, Err(e), _, _) | (_, _, _, Err(e), _) | (_, _, _, _, Err(e)) => {
                     return Ok((
                         keyspace_name,
                         Err(SingleKeyspaceMetadataError::MissingUDT(e)),
@@ -1030,9 +1235,12 @@ impl ControlConnection {
 
             let keyspace = Keyspace {
                 strategy,
+                durable_writes,
                 tables,
                 views,
                 user_defined_types,
+                user_defined_functions,
+                user_defined_aggregates,
             };
 
             Ok((keyspace_name, Ok(keyspace)))
@@ -1427,16 +1635,178 @@ mod toposort_tests {
     }
 }
 
+/// A row of `system_schema.tables` or `system_schema.views`.
+///
+/// The set of columns holding table options differs between ScyllaDB and Cassandra,
+/// and between their versions, so all columns are fetched and kept by name
+/// instead of being deserialized into a fixed set of fields.
+struct SchemaTableRow {
+    keyspace_name: String,
+    /// `table_name` or `view_name` column.
+    table_name: String,
+    /// Remaining non-null columns.
+    columns: HashMap<String, CqlValue>,
+}
+
+#[derive(Debug, Error)]
+#[error("Column {0} is missing or null in a schema table row")]
+struct MissingSchemaColumn(&'static str);
+
+impl<'frame, 'metadata> crate::deserialize::row::DeserializeRow<'frame, 'metadata>
+    for SchemaTableRow
+{
+    fn type_check(_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        // CqlValues accept all types, presence of the name columns is checked
+        // during deserialization.
+        Ok(())
+    }
+
+    fn deserialize(row: ColumnIterator<'frame, 'metadata>) -> Result<Self, DeserializationError> {
+        let mut columns = HashMap::new();
+        for column in row {
+            let column = column?;
+            if let Some(value) = <Option<CqlValue>>::deserialize(column.spec.typ(), column.slice)? {
+                columns.insert(column.spec.name().to_owned(), value);
+            }
+        }
+
+        let mut take_text = |name| match columns.remove(name) {
+            Some(CqlValue::Text(text) | CqlValue::Ascii(text)) => Some(text),
+            _ => None,
+        };
+        let keyspace_name = take_text("keyspace_name")
+            .ok_or_else(|| DeserializationError::new(MissingSchemaColumn("keyspace_name")))?;
+        let table_name = take_text("table_name")
+            .or_else(|| take_text("view_name"))
+            .ok_or_else(|| DeserializationError::new(MissingSchemaColumn("table_name")))?;
+
+        Ok(Self {
+            keyspace_name,
+            table_name,
+            columns,
+        })
+    }
+}
+
+impl TableOptions {
+    /// Builds options from the columns of a `system_schema.tables` or `system_schema.views` row.
+    /// Columns that are not table options are ignored.
+    fn from_schema_columns(columns: HashMap<String, CqlValue>) -> Self {
+        let mut options = TableOptions::default();
+
+        for (name, value) in columns {
+            match (name.as_str(), value) {
+                ("comment", CqlValue::Text(comment)) => options.comment = comment,
+                ("default_time_to_live", CqlValue::Int(ttl)) => options.default_time_to_live = ttl,
+                ("gc_grace_seconds", CqlValue::Int(seconds)) => options.gc_grace_seconds = seconds,
+                ("caching", value) => options.caching = cql_value_into_string_map(value),
+                ("compaction", value) => options.compaction = cql_value_into_string_map(value),
+                ("compression", value) => options.compression = cql_value_into_string_map(value),
+                // Cassandra stores only a flag telling whether CDC is enabled.
+                ("cdc", CqlValue::Boolean(enabled)) => {
+                    options.cdc = Some(HashMap::from([("enabled".to_owned(), enabled.to_string())]))
+                }
+                // ScyllaDB stores CDC and tombstone GC options as schema extensions.
+                ("extensions", CqlValue::Map(extensions)) => {
+                    for (extension_name, extension_value) in extensions {
+                        let (CqlValue::Text(extension_name), CqlValue::Blob(extension_value)) =
+                            (extension_name, extension_value)
+                        else {
+                            continue;
+                        };
+                        let target = match extension_name.as_str() {
+                            "cdc" => &mut options.cdc,
+                            "tombstone_gc" => &mut options.tombstone_gc,
+                            _ => continue,
+                        };
+                        *target = decode_scylla_extension_options(&extension_value);
+                        if target.is_none() {
+                            warn!(
+                                "Failed to decode options of {} table extension",
+                                extension_name
+                            );
+                        }
+                    }
+                }
+                // Columns that identify the table or a view's base table,
+                // and internal columns.
+                (
+                    "id"
+                    | "flags"
+                    | "extensions"
+                    | "base_table_id"
+                    | "base_table_name"
+                    | "include_all_columns"
+                    | "where_clause",
+                    _,
+                ) => (),
+                (_, value) => {
+                    options.other.insert(name, value.to_string());
+                }
+            }
+        }
+
+        options
+    }
+}
+
+/// Converts a `map<text, text>` value into a [HashMap].
+/// Non-text keys and values are formatted as CQL literals.
+fn cql_value_into_string_map(value: CqlValue) -> HashMap<String, String> {
+    fn into_string(value: CqlValue) -> String {
+        match value {
+            CqlValue::Text(text) | CqlValue::Ascii(text) => text,
+            other => other.to_string(),
+        }
+    }
+
+    match value {
+        CqlValue::Map(entries) => entries
+            .into_iter()
+            .map(|(key, value)| (into_string(key), into_string(value)))
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
+
+/// Decodes options of a ScyllaDB schema extension (e.g. `cdc` or `tombstone_gc`).
+///
+/// The options are a `map<string, string>` in ScyllaDB's internal serialization format:
+/// a little-endian u32 number of entries, followed by keys and values, each one
+/// being a little-endian u32 length followed by the bytes of the string.
+fn decode_scylla_extension_options(mut buf: &[u8]) -> Option<HashMap<String, String>> {
+    fn read_string(buf: &mut &[u8]) -> Option<String> {
+        let len = buf.read_u32::<LittleEndian>().ok()? as usize;
+        if buf.len() < len {
+            return None;
+        }
+        let (string, rest) = buf.split_at(len);
+        *buf = rest;
+        String::from_utf8(string.to_vec()).ok()
+    }
+
+    let count = buf.read_u32::<LittleEndian>().ok()?;
+    let mut options = HashMap::new();
+    for _ in 0..count {
+        let key = read_string(&mut buf)?;
+        let value = read_string(&mut buf)?;
+        options.insert(key, value);
+    }
+
+    buf.is_empty().then_some(options)
+}
+
 impl ControlConnection {
     async fn query_tables(
         &self,
         keyspaces_to_fetch: &[String],
         tables: &mut PerKsTableResult<Table, SingleKeyspaceMetadataError>,
+        indexes: &mut PerKsTable<PerTable<Index>>,
     ) -> Result<PerKeyspaceResult<PerTable<Table>, SingleKeyspaceMetadataError>, MetadataError>
     {
         let rows = self
-            .query_filter_keyspace_name::<(String, String)>(
-                "SELECT keyspace_name, table_name FROM system_schema.tables",
+            .query_filter_keyspace_name::<SchemaTableRow>(
+                "SELECT * FROM system_schema.tables",
                 keyspaces_to_fetch,
             )
             .map_err(|error| MetadataFetchError {
@@ -1446,15 +1816,29 @@ impl ControlConnection {
         let mut result = HashMap::new();
 
         rows.map(|row_result| {
-            let keyspace_and_table_name = row_result?;
+            let SchemaTableRow {
+                keyspace_name,
+                table_name,
+                columns,
+            } = row_result?;
+            let keyspace_and_table_name = (keyspace_name, table_name);
 
-            let table = tables.remove(&keyspace_and_table_name).unwrap_or(Ok(Table {
-                columns: HashMap::new(),
-                partition_key: vec![],
-                clustering_key: vec![],
-                partitioner: None,
-                pk_column_specs: vec![],
-            }));
+            let table = tables
+                .remove(&keyspace_and_table_name)
+                .unwrap_or(Ok(Table {
+                    columns: HashMap::new(),
+                    partition_key: vec![],
+                    clustering_key: vec![],
+                    partitioner: None,
+                    indexes: HashMap::new(),
+                    options: TableOptions::default(),
+                    pk_column_specs: vec![],
+                }))
+                .map(|table| Table {
+                    indexes: indexes.remove(&keyspace_and_table_name).unwrap_or_default(),
+                    options: TableOptions::from_schema_columns(columns),
+                    ..table
+                });
 
             let mut entry = result
                 .entry(keyspace_and_table_name.0)
@@ -1484,8 +1868,8 @@ impl ControlConnection {
         MetadataError,
     > {
         let rows = self
-            .query_filter_keyspace_name::<(String, String, String)>(
-                "SELECT keyspace_name, view_name, base_table_name FROM system_schema.views",
+            .query_filter_keyspace_name::<SchemaTableRow>(
+                "SELECT * FROM system_schema.views",
                 keyspaces_to_fetch,
             )
             .map_err(|error| MetadataFetchError {
@@ -1496,7 +1880,30 @@ impl ControlConnection {
         let mut result = HashMap::new();
 
         rows.map(|row_result| {
-            let (keyspace_name, view_name, base_table_name) = row_result?;
+            let SchemaTableRow {
+                keyspace_name,
+                table_name: view_name,
+                mut columns,
+            } = row_result?;
+
+            let where_clause = match columns.remove("where_clause") {
+                Some(CqlValue::Text(where_clause)) => where_clause,
+                _ => String::new(),
+            };
+            let include_all_columns = matches!(
+                columns.remove("include_all_columns"),
+                Some(CqlValue::Boolean(true))
+            );
+            let Some(CqlValue::Text(base_table_name)) = columns.remove("base_table_name") else {
+                let error = NextRowError::from(DeserializationError::new(MissingSchemaColumn(
+                    "base_table_name",
+                )));
+                return Err(MetadataFetchError {
+                    error: error.into(),
+                    table: "system_schema.views",
+                }
+                .into());
+            };
 
             let keyspace_and_view_name = (keyspace_name, view_name);
 
@@ -1507,11 +1914,18 @@ impl ControlConnection {
                     partition_key: vec![],
                     clustering_key: vec![],
                     partitioner: None,
+                    indexes: HashMap::new(),
+                    options: TableOptions::default(),
                     pk_column_specs: vec![],
                 }))
                 .map(|table| MaterializedView {
-                    view_metadata: table,
+                    view_metadata: Table {
+                        options: TableOptions::from_schema_columns(columns),
+                        ..table
+                    },
                     base_table_name,
+                    where_clause,
+                    include_all_columns,
                 });
 
             let mut entry = result
@@ -1544,10 +1958,10 @@ impl ControlConnection {
         // This column shouldn't be exposed to the user but is currently exposed in system tables.
         const THRIFT_EMPTY_TYPE: &str = "empty";
 
-        type RowType = (String, String, String, String, i32, String);
+        type RowType = (String, String, String, String, i32, String, Option<String>);
 
         let rows = self.query_filter_keyspace_name::<RowType>(
-        "select keyspace_name, table_name, column_name, kind, position, type from system_schema.columns",
+        "select keyspace_name, table_name, column_name, kind, position, type, clustering_order from system_schema.columns",
         keyspaces_to_fetch
     ).map_err(|error| MetadataFetchError {
         error,
@@ -1559,7 +1973,8 @@ impl ControlConnection {
         let mut tables_schema: HashMap<_, Result<_, SingleKeyspaceMetadataError>> = HashMap::new();
 
         rows.map(|row_result| {
-            let (keyspace_name, table_name, column_name, kind, position, type_) = row_result?;
+            let (keyspace_name, table_name, column_name, kind, position, type_, clustering_order) =
+                row_result?;
 
             if type_ == THRIFT_EMPTY_TYPE {
                 return Ok::<_, MetadataError>(());
@@ -1645,11 +2060,19 @@ impl ControlConnection {
                 key_list.push((position, column_name.clone()));
             }
 
+            // Clustering order is "none" for columns other than clustering key columns.
+            let clustering_order = if kind == ColumnKind::Clustering {
+                clustering_order.and_then(|order| ClusteringOrder::from_str(&order).ok())
+            } else {
+                None
+            };
+
             entry.0.insert(
                 column_name,
                 Column {
                     typ: cql_type,
                     kind,
+                    clustering_order,
                 },
             );
 
@@ -1749,6 +2172,8 @@ impl ControlConnection {
                     partition_key,
                     clustering_key,
                     partitioner,
+                    indexes: HashMap::new(),
+                    options: TableOptions::default(),
                     pk_column_specs,
                 }),
             );
@@ -1970,6 +2395,298 @@ impl ControlConnection {
     }
 }
 
+#[derive(DeserializeRow, Debug)]
+#[scylla(crate = "crate")]
+struct IndexRow {
+    keyspace_name: String,
+    table_name: String,
+    index_name: String,
+    kind: String,
+    options: Option<HashMap<String, String>>,
+}
+
+#[derive(DeserializeRow, Debug)]
+#[scylla(crate = "crate")]
+struct FunctionRow {
+    keyspace_name: String,
+    function_name: String,
+    argument_names: Option<Vec<String>>,
+    argument_types: Option<Vec<String>>,
+    return_type: String,
+    language: String,
+    body: String,
+    called_on_null_input: bool,
+}
+
+#[derive(DeserializeRow, Debug)]
+#[scylla(crate = "crate")]
+struct AggregateRow {
+    keyspace_name: String,
+    aggregate_name: String,
+    argument_types: Option<Vec<String>>,
+    state_func: String,
+    state_type: String,
+    final_func: Option<String>,
+    initcond: Option<String>,
+    return_type: String,
+}
+
+/// Parses CQL types of a function or an aggregate.
+///
+/// The outer error means that a type could not be parsed at all,
+/// the inner one that it refers to a UDT missing from the keyspace.
+#[allow(clippy::type_complexity)]
+fn parse_function_types<'a>(
+    types: impl IntoIterator<Item = &'a String>,
+    table: &'static str,
+    keyspace_name: &String,
+    keyspace_udts: &PerTable<Arc<UserDefinedType<'static>>>,
+) -> Result<Result<Vec<ColumnType<'static>>, MissingUserDefinedType>, FunctionsMetadataError> {
+    let pre_types = types
+        .into_iter()
+        .map(|typ| {
+            map_string_to_cql_type(typ).map_err(|err: InvalidCqlType| {
+                FunctionsMetadataError::InvalidCqlType {
+                    table,
+                    typ: err.typ,
+                    position: err.position,
+                    reason: err.reason,
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(pre_types
+        .into_iter()
+        .map(|pre_type| pre_type.into_cql_type(keyspace_name, keyspace_udts))
+        .collect())
+}
+
+impl ControlConnection {
+    async fn query_indexes(
+        &self,
+        keyspaces_to_fetch: &[String],
+    ) -> Result<PerKsTable<PerTable<Index>>, MetadataError> {
+        let rows = self
+            .query_filter_keyspace_name::<IndexRow>(
+                "select keyspace_name, table_name, index_name, kind, options from system_schema.indexes",
+                keyspaces_to_fetch,
+            )
+            .map_err(|error| MetadataFetchError {
+                error,
+                table: "system_schema.indexes",
+            });
+
+        let mut result: PerKsTable<PerTable<Index>> = HashMap::new();
+
+        rows.map(|row_result| {
+            let IndexRow {
+                keyspace_name,
+                table_name,
+                index_name,
+                kind,
+                options,
+            } = row_result?;
+
+            // Indexes of kinds unknown to the driver (e.g. introduced by a newer server version)
+            // must not make the metadata of the whole keyspace unavailable.
+            let Ok(kind) = IndexKind::from_str(&kind) else {
+                warn!(
+                    "Unknown index kind '{}' for index {}.{}; skipping the index.",
+                    kind, keyspace_name, index_name
+                );
+                return Ok(());
+            };
+
+            result
+                .entry((keyspace_name, table_name))
+                .or_default()
+                .insert(
+                    index_name,
+                    Index {
+                        kind,
+                        options: options.unwrap_or_default(),
+                    },
+                );
+
+            Ok::<_, MetadataError>(())
+        })
+        .try_for_each(|_| future::ok(()))
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn query_user_defined_functions(
+        &self,
+        keyspaces_to_fetch: &[String],
+        udts: &PerKeyspaceResult<PerTable<Arc<UserDefinedType<'static>>>, MissingUserDefinedType>,
+    ) -> Result<
+        PerKeyspaceResult<HashMap<FunctionSignature, UserDefinedFunction>, MissingUserDefinedType>,
+        MetadataError,
+    > {
+        const TABLE: &str = "system_schema.functions";
+
+        let rows = self
+            .query_filter_keyspace_name::<FunctionRow>(
+                "select keyspace_name, function_name, argument_names, argument_types, \
+                return_type, language, body, called_on_null_input from system_schema.functions",
+                keyspaces_to_fetch,
+            )
+            .map_err(|error| MetadataFetchError {
+                error,
+                table: TABLE,
+            });
+
+        let empty_ok_map = Ok(HashMap::new());
+        let mut result = HashMap::new();
+
+        rows.map(|row_result| {
+            let FunctionRow {
+                keyspace_name,
+                function_name,
+                argument_names,
+                argument_types,
+                return_type,
+                language,
+                body,
+                called_on_null_input,
+            } = row_result?;
+            let argument_types = argument_types.unwrap_or_default();
+
+            let parsed_types = match udts.get(&keyspace_name).unwrap_or(&empty_ok_map) {
+                Ok(keyspace_udts) => parse_function_types(
+                    argument_types.iter().chain(std::iter::once(&return_type)),
+                    TABLE,
+                    &keyspace_name,
+                    keyspace_udts,
+                )?,
+                // See the comment in `query_tables_schema` about UDT errors.
+                Err(e) => Err(e.clone()),
+            };
+
+            let entry = result
+                .entry(keyspace_name)
+                .or_insert_with(|| Ok(HashMap::new()));
+            let Ok(functions) = entry else {
+                return Ok::<_, MetadataError>(());
+            };
+            let mut parsed_types = match parsed_types {
+                Ok(parsed_types) => parsed_types,
+                Err(e) => {
+                    *entry = Err(e);
+                    return Ok::<_, MetadataError>(());
+                }
+            };
+
+            // unwrap: the return type was parsed as the last one.
+            let parsed_return_type = parsed_types.pop().unwrap();
+            functions.insert(
+                FunctionSignature::new(function_name, argument_types),
+                UserDefinedFunction {
+                    argument_names: argument_names.unwrap_or_default(),
+                    argument_types: parsed_types,
+                    return_type: parsed_return_type,
+                    language,
+                    body,
+                    called_on_null_input,
+                },
+            );
+
+            Ok::<_, MetadataError>(())
+        })
+        .try_for_each(|_| future::ok(()))
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn query_user_defined_aggregates(
+        &self,
+        keyspaces_to_fetch: &[String],
+        udts: &PerKeyspaceResult<PerTable<Arc<UserDefinedType<'static>>>, MissingUserDefinedType>,
+    ) -> Result<
+        PerKeyspaceResult<HashMap<FunctionSignature, UserDefinedAggregate>, MissingUserDefinedType>,
+        MetadataError,
+    > {
+        const TABLE: &str = "system_schema.aggregates";
+
+        let rows = self
+            .query_filter_keyspace_name::<AggregateRow>(
+                "select keyspace_name, aggregate_name, argument_types, state_func, state_type, \
+                final_func, initcond, return_type from system_schema.aggregates",
+                keyspaces_to_fetch,
+            )
+            .map_err(|error| MetadataFetchError {
+                error,
+                table: TABLE,
+            });
+
+        let empty_ok_map = Ok(HashMap::new());
+        let mut result = HashMap::new();
+
+        rows.map(|row_result| {
+            let AggregateRow {
+                keyspace_name,
+                aggregate_name,
+                argument_types,
+                state_func,
+                state_type,
+                final_func,
+                initcond,
+                return_type,
+            } = row_result?;
+            let argument_types = argument_types.unwrap_or_default();
+
+            let parsed_types = match udts.get(&keyspace_name).unwrap_or(&empty_ok_map) {
+                Ok(keyspace_udts) => parse_function_types(
+                    argument_types.iter().chain([&state_type, &return_type]),
+                    TABLE,
+                    &keyspace_name,
+                    keyspace_udts,
+                )?,
+                // See the comment in `query_tables_schema` about UDT errors.
+                Err(e) => Err(e.clone()),
+            };
+
+            let entry = result
+                .entry(keyspace_name)
+                .or_insert_with(|| Ok(HashMap::new()));
+            let Ok(aggregates) = entry else {
+                return Ok::<_, MetadataError>(());
+            };
+            let mut parsed_types = match parsed_types {
+                Ok(parsed_types) => parsed_types,
+                Err(e) => {
+                    *entry = Err(e);
+                    return Ok::<_, MetadataError>(());
+                }
+            };
+
+            // unwraps: the state type and the return type were parsed as the last ones.
+            let parsed_return_type = parsed_types.pop().unwrap();
+            let parsed_state_type = parsed_types.pop().unwrap();
+            aggregates.insert(
+                FunctionSignature::new(aggregate_name, argument_types),
+                UserDefinedAggregate {
+                    argument_types: parsed_types,
+                    state_func,
+                    state_type: parsed_state_type,
+                    final_func,
+                    initcond,
+                    return_type: parsed_return_type,
+                },
+            );
+
+            Ok::<_, MetadataError>(())
+        })
+        .try_for_each(|_| future::ok(()))
+        .await?;
+
+        Ok(result)
+    }
+}
+
 fn strategy_from_string_map(
     mut strategy_map: HashMap<String, String>,
 ) -> Result<Strategy, KeyspaceStrategyError> {
@@ -2206,4 +2923,102 @@ mod tests {
             assert_eq!(parsed, expected);
         }
     }
+
+    #[test]
+    fn test_table_options_from_schema_columns() {
+        setup_tracing();
+
+        fn text_map(entries: &[(&str, &str)]) -> CqlValue {
+            CqlValue::Map(
+                entries
+                    .iter()
+                    .map(|(k, v)| (CqlValue::Text(k.to_string()), CqlValue::Text(v.to_string())))
+                    .collect(),
+            )
+        }
+
+        // Scylla's serialization of {"mode": "repair"}.
+        let tombstone_gc_extension = [
+            &1u32.to_le_bytes()[..],
+            &4u32.to_le_bytes(),
+            b"mode",
+            &6u32.to_le_bytes(),
+            b"repair",
+        ]
+        .concat();
+
+        let columns = HashMap::from([
+            ("comment".to_owned(), CqlValue::Text("my table".to_owned())),
+            ("default_time_to_live".to_owned(), CqlValue::Int(3600)),
+            ("gc_grace_seconds".to_owned(), CqlValue::Int(864000)),
+            (
+                "compaction".to_owned(),
+                text_map(&[("class", "SizeTieredCompactionStrategy")]),
+            ),
+            ("caching".to_owned(), text_map(&[("keys", "ALL")])),
+            ("bloom_filter_fp_chance".to_owned(), CqlValue::Double(0.01)),
+            (
+                "speculative_retry".to_owned(),
+                CqlValue::Text("99.0PERCENTILE".to_owned()),
+            ),
+            ("id".to_owned(), CqlValue::Uuid(Uuid::nil())),
+            (
+                "extensions".to_owned(),
+                CqlValue::Map(vec![
+                    (
+                        CqlValue::Text("tombstone_gc".to_owned()),
+                        CqlValue::Blob(tombstone_gc_extension),
+                    ),
+                    (CqlValue::Text("cdc".to_owned()), CqlValue::Blob(vec![0xff])),
+                ]),
+            ),
+        ]);
+
+        let options = TableOptions::from_schema_columns(columns);
+
+        assert_eq!(options.comment, "my table");
+        assert_eq!(options.default_time_to_live, 3600);
+        assert_eq!(options.gc_grace_seconds, 864000);
+        assert_eq!(
+            options.compaction,
+            HashMap::from([(
+                "class".to_owned(),
+                "SizeTieredCompactionStrategy".to_owned()
+            )])
+        );
+        assert_eq!(
+            options.caching,
+            HashMap::from([("keys".to_owned(), "ALL".to_owned())])
+        );
+        assert!(options.compression.is_empty());
+        assert_eq!(
+            options.tombstone_gc,
+            Some(HashMap::from([("mode".to_owned(), "repair".to_owned())]))
+        );
+        // Malformed extension is ignored.
+        assert_eq!(options.cdc, None);
+        assert_eq!(
+            options.other,
+            HashMap::from([
+                ("bloom_filter_fp_chance".to_owned(), "0.01".to_owned()),
+                (
+                    "speculative_retry".to_owned(),
+                    "'99.0PERCENTILE'".to_owned()
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_cassandra_cdc_table_option() {
+        setup_tracing();
+        let options = TableOptions::from_schema_columns(HashMap::from([(
+            "cdc".to_owned(),
+            CqlValue::Boolean(true),
+        )]));
+        assert_eq!(
+            options.cdc,
+            Some(HashMap::from([("enabled".to_owned(), "true".to_owned())]))
+        );
+    }
 }
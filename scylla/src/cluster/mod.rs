@@ -11,6 +11,7 @@
 //! - [ClusterState], which is a snapshot of the cluster's state.
 //!   - [ClusterState] is replaced atomically upon a metadata refresh,
 //!     preventing any issues arising from mutability, including races.
+//! - [events] pushed by the cluster, which can be subscribed to.
 //  - [ControlConnection](control_connection::ControlConnection), which
 //    is the single connection used to fetch metadata and receive events
 //    from the cluster.
@@ -27,3 +28,7 @@ pub use node::{KnownNode, Node, NodeAddr, NodeRef};
 mod control_connection;
 
 pub mod metadata;
+
+pub(crate) mod describe;
+
+pub mod events;
@@ -207,6 +207,11 @@ impl Node {
         self.get_pool()?.get_working_connections()
     }
 
+    #[cfg(feature = "tower-service-03")]
+    pub(crate) fn working_connections_count(&self) -> Result<usize, ConnectionPoolError> {
+        self.get_pool()?.working_connections_count()
+    }
+
     pub(crate) fn get_random_connection(&self) -> Result<Arc<Connection>, ConnectionPoolError> {
         self.get_pool()?.random_connection()
     }
@@ -17,6 +17,7 @@ use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, warn};
 
+use super::events::{ClusterEvent, ServerEvent, CLUSTER_EVENTS_CHANNEL_SIZE};
 use super::metadata::MetadataReader;
 use super::node::{InternalKnownNode, NodeAddr};
 use super::state::{ClusterState, ClusterStateNeatDebug};
@@ -31,6 +32,9 @@ pub(crate) struct Cluster {
     refresh_channel: tokio::sync::mpsc::Sender<RefreshRequest>,
     use_keyspace_channel: tokio::sync::mpsc::Sender<UseKeyspaceRequest>,
 
+    // Used to create new subscriptions to cluster events
+    cluster_events_channel: tokio::sync::broadcast::Sender<ClusterEvent>,
+
     _worker_handle: RemoteHandle<()>,
 }
 
@@ -62,7 +66,10 @@ struct ClusterWorker {
     use_keyspace_channel: tokio::sync::mpsc::Receiver<UseKeyspaceRequest>,
 
     // Channel used to receive server events
-    server_events_channel: tokio::sync::mpsc::Receiver<Event>,
+    server_events_channel: tokio::sync::mpsc::Receiver<ServerEvent>,
+
+    // Channel used to publish server events to subscribers
+    cluster_events_channel: tokio::sync::broadcast::Sender<ClusterEvent>,
 
     // Channel used to receive signals that control connection is broken
     control_connection_repair_channel: tokio::sync::broadcast::Receiver<()>,
@@ -113,6 +120,8 @@ impl Cluster {
         let (refresh_sender, refresh_receiver) = tokio::sync::mpsc::channel(32);
         let (use_keyspace_sender, use_keyspace_receiver) = tokio::sync::mpsc::channel(32);
         let (server_events_sender, server_events_receiver) = tokio::sync::mpsc::channel(32);
+        let (cluster_events_sender, _) =
+            tokio::sync::broadcast::channel(CLUSTER_EVENTS_CHANNEL_SIZE);
         let (control_connection_repair_sender, control_connection_repair_receiver) =
             tokio::sync::broadcast::channel(32);
 
@@ -155,6 +164,7 @@ impl Cluster {
 
             refresh_channel: refresh_receiver,
             server_events_channel: server_events_receiver,
+            cluster_events_channel: cluster_events_sender.clone(),
             control_connection_repair_channel: control_connection_repair_receiver,
             tablets_channel: tablet_receiver,
 
@@ -175,6 +185,7 @@ impl Cluster {
             state: cluster_state,
             refresh_channel: refresh_sender,
             use_keyspace_channel: use_keyspace_sender,
+            cluster_events_channel: cluster_events_sender,
             _worker_handle: worker_handle,
         };
 
@@ -219,6 +230,10 @@ impl Cluster {
 
         response_receiver.await.unwrap() // ClusterWorker always responds
     }
+
+    pub(crate) fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<ClusterEvent> {
+        self.cluster_events_channel.subscribe()
+    }
 }
 
 impl ClusterWorker {
@@ -288,9 +303,15 @@ impl ClusterWorker {
                 recv_res = self.server_events_channel.recv() => {
                     if let Some(event) = recv_res {
                         debug!("Received server event: {:?}", event);
+                        self.publish_event(&event);
                         match event {
-                            Event::TopologyChange(_) => (), // Refresh immediately
-                            Event::StatusChange(status) => {
+                            ServerEvent::Cql(Event::TopologyChange(_)) | ServerEvent::NodeMoved(_) => (), // Refresh immediately
+                            ServerEvent::Cql(Event::SchemaChange(schema_change)) => {
+                                // Result metadata of prepared statements might have changed.
+                                self.pool_config.connection_config.schema_epochs.invalidate_on(&schema_change);
+                                continue; // Don't go to refreshing
+                            }
+                            ServerEvent::Cql(Event::StatusChange(status)) => {
                                 // If some node went down/up, update it's marker and refresh
                                 // later as planned.
 
@@ -310,7 +331,6 @@ impl ClusterWorker {
                                 }
                                 continue;
                             },
-                            _ => continue, // Don't go to refreshing
                         }
                     } else {
                         // If server_events_channel was closed, than MetadataReader was dropped,
@@ -335,6 +355,8 @@ impl ClusterWorker {
                 recv_res = self.control_connection_repair_channel.recv() => {
                     match recv_res {
                         Ok(()) => {
+                            // Schema change events might have been lost while the control connection was broken.
+                            self.pool_config.connection_config.schema_epochs.invalidate_all();
                             // The control connection was broken. Acknowledge that and start attempting to reconnect.
                             // The first reconnect attempt will be immediate (by attempting metadata refresh below),
                             // and if it does not succeed, then `control_connection_works` will be set to `false`,
@@ -369,6 +391,15 @@ impl ClusterWorker {
         }
     }
 
+    fn publish_event(&self, event: &ServerEvent) {
+        let Some(cluster_event) = ClusterEvent::from_server_event(event) else {
+            warn!("Not publishing server event of unknown kind: {:?}", event);
+            return;
+        };
+        // Sending fails only if there are no subscribers, which is fine.
+        let _ = self.cluster_events_channel.send(cluster_event);
+    }
+
     fn change_node_down_marker(&mut self, addr: SocketAddr, is_down: bool) {
         let cluster_state = self.cluster_state.load_full();
 
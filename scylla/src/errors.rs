@@ -19,6 +19,9 @@ pub use crate::response::query_result::{
     SingleRowError,
 };
 
+// Re-export error type from cluster events module.
+pub use crate::cluster::events::ClusterEventsLaggedError;
+
 // Re-export error type from authentication module.
 pub use crate::authentication::AuthError;
 
@@ -31,7 +34,7 @@ pub use scylla_cql::frame::frame_errors::{
     CqlAuthChallengeParseError, CqlAuthSuccessParseError, CqlAuthenticateParseError,
     CqlErrorParseError, CqlEventParseError, CqlRequestSerializationError, CqlResponseParseError,
     CqlResultParseError, CqlSupportedParseError, FrameBodyExtensionsParseError,
-    FrameHeaderParseError,
+    FrameHeaderParseError, SegmentParseError,
 };
 pub use scylla_cql::frame::request::CqlRequestKind;
 pub use scylla_cql::frame::response::error::{DbError, OperationType, WriteType};
@@ -75,6 +78,14 @@ pub enum ExecutionError {
     )]
     RequestTimeout(std::time::Duration),
 
+    /// The request was not admitted by the client-side request limits
+    /// within the configured queue timeout.
+    #[error(
+        "Request was not admitted by the client-side request limits within {}ms",
+        std::time::Duration::as_millis(.0)
+    )]
+    RequestQueueTimeout(std::time::Duration),
+
     /// 'USE KEYSPACE <>' request failed.
     #[error("'USE KEYSPACE <>' request failed: {0}")]
     UseKeyspaceError(#[from] UseKeyspaceError),
@@ -260,6 +271,8 @@ pub enum TracingError {
 /// - UDTs
 /// - tables
 /// - views
+/// - indexes
+/// - user-defined functions and aggregates
 /// - peers (topology)
 ///
 /// The errors that occur during metadata fetch are contained in [`MetadataFetchError`].
@@ -291,6 +304,10 @@ pub enum MetadataError {
     /// Bad tables metadata.
     #[error("Bad tables metadata: {0}")]
     Tables(#[from] TablesMetadataError),
+
+    /// Bad user-defined functions or aggregates metadata.
+    #[error("Bad user-defined functions metadata: {0}")]
+    Functions(#[from] FunctionsMetadataError),
 }
 
 /// An error occurred during metadata fetch.
@@ -417,6 +434,33 @@ pub enum TablesMetadataError {
     },
 }
 
+/// An error that occurred during user-defined functions or aggregates metadata fetch.
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum FunctionsMetadataError {
+    /// Failed to parse CQL type returned from system_schema.functions
+    /// or system_schema.aggregates query.
+    #[error(
+        "Failed to parse a CQL type returned from {table} query. \
+        Type '{typ}', at position {position}: {reason}"
+    )]
+    InvalidCqlType {
+        table: &'static str,
+        typ: String,
+        position: usize,
+        reason: String,
+    },
+}
+
+/// An error that occurred when rendering schema metadata as CQL statements.
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum DescribeError {
+    /// The metadata contains a CQL type which this version of the driver cannot render.
+    #[error("CQL type {typ} cannot be rendered in a CQL statement")]
+    UnsupportedType { typ: String },
+}
+
 /// Error caused by caller creating an invalid statement.
 #[derive(Error, Debug, Clone)]
 #[error("Invalid statement passed to Session")]
@@ -678,6 +722,10 @@ pub enum BrokenConnectionErrorKind {
     #[error("Failed to deserialize frame: {0}")]
     FrameHeaderParseError(FrameHeaderParseError),
 
+    /// Failed to deserialize a protocol v5 segment.
+    #[error("Failed to deserialize segment: {0}")]
+    SegmentParseError(SegmentParseError),
+
     /// Failed to handle a CQL event (server response received on stream -1).
     #[error("Failed to handle server event: {0}")]
     CqlEventHandlingError(#[from] CqlEventHandlingError),
@@ -769,6 +817,14 @@ pub enum RequestError {
         )]
     RequestTimeout(std::time::Duration),
 
+    /// The request was not admitted by the client-side request limits
+    /// within the configured queue timeout.
+    #[error(
+            "Request was not admitted by the client-side request limits within {}ms",
+            std::time::Duration::as_millis(.0)
+        )]
+    RequestQueueTimeout(std::time::Duration),
+
     /// Failed to execute request.
     #[error(transparent)]
     LastAttemptError(#[from] RequestAttemptError),
@@ -780,6 +836,7 @@ impl RequestError {
             RequestError::EmptyPlan => ExecutionError::EmptyPlan,
             RequestError::ConnectionPoolError(e) => e.into(),
             RequestError::RequestTimeout(dur) => ExecutionError::RequestTimeout(dur),
+            RequestError::RequestQueueTimeout(dur) => ExecutionError::RequestQueueTimeout(dur),
             RequestError::LastAttemptError(e) => ExecutionError::LastAttemptError(e),
         }
     }
@@ -107,13 +107,31 @@ pub mod value {
         Counter, CqlDate, CqlDecimal, CqlDecimalBorrowed, CqlDuration, CqlTime, CqlTimestamp,
         CqlTimeuuid, CqlValue, CqlVarint, CqlVarintBorrowed, MaybeUnset, Row, Unset, ValueOverflow,
     };
+
+    /// Conversion of CQL values and rows to and from JSON.
+    ///
+    /// This module is available only under the crate feature `serde_json-1`.
+    #[cfg(feature = "serde_json-1")]
+    pub mod json {
+        pub use scylla_cql::value::json::{
+            cql_value_from_json, cql_value_to_json, row_from_json, row_to_json, JsonConversionError,
+        };
+    }
+}
+
+/// Bridge between [serde](https://docs.rs/serde) and the CQL (de)serialization traits.
+///
+/// This module is available only under the crate feature `serde`.
+#[cfg(feature = "serde")]
+pub mod serde_bridge {
+    pub use scylla_cql::serde_bridge::{SerdeBridgeError, SerdeRow, SerdeValue};
 }
 
 pub mod frame {
-    pub use scylla_cql::frame::{frame_errors, Authenticator, Compression};
+    pub use scylla_cql::frame::{frame_errors, Authenticator, Compression, ProtocolVersion};
     pub(crate) use scylla_cql::frame::{
-        parse_response_body_extensions, protocol_features, read_response_frame, request,
-        server_event_type, FrameParams, SerializedRequest,
+        parse_response_body_extensions_with_algorithm, protocol_features, read_response_frame,
+        request, segment, server_event_type, FrameParams, SerializedRequest,
     };
 
     pub mod types {
@@ -215,6 +233,14 @@ pub mod deserialize {
         };
     }
 
+    /// Converting query results to Apache Arrow record batches.
+    #[cfg(feature = "arrow-54")]
+    pub mod arrow {
+        pub use scylla_cql::deserialize::arrow::{
+            arrow_data_type, arrow_schema, RecordBatchBuilder,
+        };
+    }
+
     // Shorthands for better readability.
     pub(crate) trait DeserializeOwnedRow:
         for<'frame, 'metadata> row::DeserializeRow<'frame, 'metadata>
@@ -227,6 +253,7 @@ pub mod deserialize {
 }
 
 pub mod authentication;
+pub mod cdc;
 pub mod client;
 #[cfg(feature = "unstable-cloud")]
 pub mod cloud;
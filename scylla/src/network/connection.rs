@@ -1,8 +1,9 @@
 use super::tls::{TlsConfig, TlsProvider};
 use crate::authentication::AuthenticatorProvider;
 use crate::client::pager::{NextRowError, QueryPager};
-use crate::client::Compression;
+use crate::client::ProtocolVersion;
 use crate::client::SelfIdentity;
+use crate::cluster::events::ServerEvent;
 use crate::cluster::metadata::{PeerEndpoint, UntranslatedEndpoint};
 use crate::cluster::NodeAddr;
 use crate::errors::{
@@ -14,8 +15,9 @@ use crate::errors::{
 use crate::frame::protocol_features::ProtocolFeatures;
 use crate::frame::{
     self,
-    request::{self, batch, execute, query, register, SerializableRequest},
-    response::{event::Event, result, Response, ResponseOpcode},
+    request::{self, batch, execute, query, register, RequestOpcode, SerializableRequest},
+    response::{result, Response, ResponseOpcode},
+    segment::{self, FrameDecoder, SegmentEncoder},
     server_event_type::EventType,
     FrameParams, SerializedRequest,
 };
@@ -28,19 +30,24 @@ use crate::response::{
 use crate::routing::locator::tablets::{RawTablet, TabletParsingError};
 use crate::routing::{Shard, ShardAwarePortRange, ShardInfo, Sharder, ShardingError};
 use crate::statement::batch::{Batch, BatchStatement};
-use crate::statement::prepared::PreparedStatement;
+use crate::statement::prepared::{
+    PreparedStatement, ResultMetadataSnapshot, SchemaEpoch, SchemaEpochs,
+};
 use crate::statement::unprepared::Statement;
 use crate::statement::{Consistency, PageSize};
 use bytes::Bytes;
 use futures::{future::RemoteHandle, FutureExt};
-use scylla_cql::frame::frame_errors::CqlResponseParseError;
+use scylla_cql::frame::frame_errors::{
+    ClusterChangeEventParseError, CqlEventParseError, CqlResponseParseError,
+};
 use scylla_cql::frame::request::options::{self, Options};
-use scylla_cql::frame::request::CqlRequestKind;
+use scylla_cql::frame::request::{CqlRequestKind, ExtendedRequest, RequestExtensions};
 use scylla_cql::frame::response::authenticate::Authenticate;
 use scylla_cql::frame::response::result::{ResultMetadata, TableSpec};
 use scylla_cql::frame::response::Error;
 use scylla_cql::frame::response::{self, error};
-use scylla_cql::frame::types::SerialConsistency;
+use scylla_cql::frame::types::{self, SerialConsistency};
+use scylla_cql::frame::CompressionAlgorithm;
 use scylla_cql::serialize::batch::{BatchValues, BatchValuesIterator};
 use scylla_cql::serialize::raw_batch::RawBatchValuesAdapter;
 use scylla_cql::serialize::row::{RowSerializationContext, SerializedValues};
@@ -53,6 +60,7 @@ use std::num::NonZeroU64;
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use std::sync::Mutex as StdMutex;
+use std::sync::OnceLock;
 use std::time::Duration;
 use std::{
     cmp::Ordering,
@@ -77,6 +85,10 @@ const LOCAL_VERSION: &str = "SELECT schema_version FROM system.local WHERE key='
 const OLD_ORPHAN_COUNT_THRESHOLD: usize = 1024;
 const OLD_AGE_ORPHAN_THRESHOLD: std::time::Duration = std::time::Duration::from_secs(1);
 
+/// The maximum number of requests in flight on a single connection,
+/// i.e. the number of available stream ids.
+pub(crate) const MAX_STREAMS_PER_CONNECTION: usize = i16::MAX as usize + 1;
+
 /// Represents a write coalescing delay configuration option.
 #[derive(Debug, Clone)]
 #[non_exhaustive]
@@ -117,6 +129,13 @@ struct RouterHandle {
     // pushing values in a synchronous way (without an `.await`), which is
     // needed for pushing values in `Drop` implementations.
     orphan_notification_sender: mpsc::UnboundedSender<RequestId>,
+
+    // Protocol version used for all frames sent over this connection.
+    protocol_version: ProtocolVersion,
+    // Whether segments are compressed with lz4. Set during the handshake,
+    // before STARTUP is sent; read by the router when it switches to
+    // segment framing (protocol v5 and newer only).
+    segment_compression: OnceLock<bool>,
 }
 
 impl RouterHandle {
@@ -128,10 +147,17 @@ impl RouterHandle {
     async fn send_request(
         &self,
         request: &impl SerializableRequest,
-        compression: Option<Compression>,
+        compression: Option<CompressionAlgorithm>,
         tracing: bool,
+        custom_payload: Option<&HashMap<String, Bytes>>,
     ) -> Result<TaskResponse, InternalRequestError> {
-        let serialized_request = SerializedRequest::make(request, compression, tracing)?;
+        let serialized_request = SerializedRequest::make_with_custom_payload(
+            request,
+            self.protocol_version,
+            compression,
+            tracing,
+            custom_payload,
+        )?;
         let request_id = self.allocate_request_id();
 
         let (response_sender, receiver) = oneshot::channel();
@@ -275,14 +301,16 @@ impl<'id: 'map, 'map> SelfIdentity<'id> {
 pub(crate) struct ConnectionConfig {
     pub(crate) local_ip_address: Option<IpAddr>,
     pub(crate) shard_aware_local_port_range: ShardAwarePortRange,
-    pub(crate) compression: Option<Compression>,
+    pub(crate) compression: Option<CompressionAlgorithm>,
+    pub(crate) zstd_compression_level: Option<i32>,
+    pub(crate) protocol_version: ProtocolVersion,
     pub(crate) tcp_nodelay: bool,
     pub(crate) tcp_keepalive_interval: Option<Duration>,
     pub(crate) timestamp_generator: Option<Arc<dyn TimestampGenerator>>,
     pub(crate) tls_provider: Option<TlsProvider>,
     pub(crate) connect_timeout: std::time::Duration,
     // should be Some only in control connections,
-    pub(crate) event_sender: Option<mpsc::Sender<Event>>,
+    pub(crate) event_sender: Option<mpsc::Sender<ServerEvent>>,
     pub(crate) default_consistency: Consistency,
     pub(crate) authenticator: Option<Arc<dyn AuthenticatorProvider>>,
     pub(crate) address_translator: Option<Arc<dyn AddressTranslator>>,
@@ -291,6 +319,8 @@ pub(crate) struct ConnectionConfig {
     pub(crate) keepalive_interval: Option<Duration>,
     pub(crate) keepalive_timeout: Option<Duration>,
     pub(crate) tablet_sender: Option<mpsc::Sender<(TableSpec<'static>, RawTablet)>>,
+    // Shared by all connections of a session.
+    pub(crate) schema_epochs: Arc<SchemaEpochs>,
 
     pub(crate) identity: SelfIdentity<'static>,
 }
@@ -312,6 +342,8 @@ impl ConnectionConfig {
             local_ip_address: self.local_ip_address,
             shard_aware_local_port_range: self.shard_aware_local_port_range.clone(),
             compression: self.compression,
+            zstd_compression_level: self.zstd_compression_level,
+            protocol_version: self.protocol_version,
             tcp_nodelay: self.tcp_nodelay,
             tcp_keepalive_interval: self.tcp_keepalive_interval,
             timestamp_generator: self.timestamp_generator.clone(),
@@ -325,6 +357,7 @@ impl ConnectionConfig {
             keepalive_interval: self.keepalive_interval,
             keepalive_timeout: self.keepalive_timeout,
             tablet_sender: self.tablet_sender.clone(),
+            schema_epochs: self.schema_epochs.clone(),
             identity: self.identity.clone(),
         }
     }
@@ -337,14 +370,16 @@ impl ConnectionConfig {
 pub(crate) struct HostConnectionConfig {
     pub(crate) local_ip_address: Option<IpAddr>,
     pub(crate) shard_aware_local_port_range: ShardAwarePortRange,
-    pub(crate) compression: Option<Compression>,
+    pub(crate) compression: Option<CompressionAlgorithm>,
+    pub(crate) zstd_compression_level: Option<i32>,
+    pub(crate) protocol_version: ProtocolVersion,
     pub(crate) tcp_nodelay: bool,
     pub(crate) tcp_keepalive_interval: Option<Duration>,
     pub(crate) timestamp_generator: Option<Arc<dyn TimestampGenerator>>,
     pub(crate) tls_config: Option<TlsConfig>,
     pub(crate) connect_timeout: std::time::Duration,
     // should be Some only in control connections,
-    pub(crate) event_sender: Option<mpsc::Sender<Event>>,
+    pub(crate) event_sender: Option<mpsc::Sender<ServerEvent>>,
     pub(crate) default_consistency: Consistency,
     pub(crate) authenticator: Option<Arc<dyn AuthenticatorProvider>>,
     pub(crate) address_translator: Option<Arc<dyn AddressTranslator>>,
@@ -353,6 +388,8 @@ pub(crate) struct HostConnectionConfig {
     pub(crate) keepalive_interval: Option<Duration>,
     pub(crate) keepalive_timeout: Option<Duration>,
     pub(crate) tablet_sender: Option<mpsc::Sender<(TableSpec<'static>, RawTablet)>>,
+    // Shared by all connections of a session.
+    pub(crate) schema_epochs: Arc<SchemaEpochs>,
 
     pub(crate) identity: SelfIdentity<'static>,
 }
@@ -364,6 +401,8 @@ impl Default for HostConnectionConfig {
             local_ip_address: None,
             shard_aware_local_port_range: ShardAwarePortRange::EPHEMERAL_PORT_RANGE,
             compression: None,
+            zstd_compression_level: None,
+            protocol_version: ProtocolVersion::V4,
             tcp_nodelay: true,
             tcp_keepalive_interval: None,
             timestamp_generator: None,
@@ -380,6 +419,7 @@ impl Default for HostConnectionConfig {
             keepalive_timeout: None,
 
             tablet_sender: None,
+            schema_epochs: Arc::new(SchemaEpochs::new()),
 
             identity: SelfIdentity::default(),
         }
@@ -393,6 +433,8 @@ impl Default for ConnectionConfig {
             local_ip_address: None,
             shard_aware_local_port_range: ShardAwarePortRange::EPHEMERAL_PORT_RANGE,
             compression: None,
+            zstd_compression_level: None,
+            protocol_version: ProtocolVersion::V4,
             tcp_nodelay: true,
             tcp_keepalive_interval: None,
             timestamp_generator: None,
@@ -409,6 +451,7 @@ impl Default for ConnectionConfig {
             keepalive_timeout: None,
 
             tablet_sender: None,
+            schema_epochs: Arc::new(SchemaEpochs::new()),
 
             identity: SelfIdentity::default(),
         }
@@ -460,6 +503,8 @@ impl Connection {
             submit_channel: sender,
             request_id_generator: AtomicU64::new(0),
             orphan_notification_sender,
+            protocol_version: config.protocol_version,
+            segment_compression: OnceLock::new(),
         });
 
         let _worker_handle = Self::run_router(
@@ -641,11 +686,16 @@ impl Connection {
         &self,
         statement: &'statement Statement,
     ) -> Result<RawPreparedStatement<'statement>, RequestAttemptError> {
+        let schema_epoch = self.config.schema_epochs.current();
         let query_response = self
             .send_request(
-                &request::Prepare {
-                    query: &statement.contents,
-                },
+                &ExtendedRequest::new(
+                    request::Prepare {
+                        query: &statement.contents,
+                    },
+                    RequestExtensions::default()
+                        .with_keyspace(statement.get_keyspace().map(Cow::Borrowed)),
+                ),
                 true,
                 statement.config.tracing,
                 None,
@@ -666,6 +716,7 @@ impl Connection {
                     p,
                     is_lwt,
                     query_response.tracing_id,
+                    schema_epoch,
                 ))
             }
             _ => Err(RequestAttemptError::UnexpectedResponse(
@@ -690,8 +741,12 @@ impl Connection {
         query: impl Into<Statement>,
         previous_prepared: &PreparedStatement,
     ) -> Result<(), RequestAttemptError> {
-        let reprepare_query: Statement = query.into();
-        let prepared_response = self.prepare_raw(&reprepare_query).await?.prepared_response;
+        let mut reprepare_query: Statement = query.into();
+        // The statement has to be reprepared in the same keyspace.
+        reprepare_query.set_keyspace(previous_prepared.config.keyspace.clone());
+        let raw_prepared = self.prepare_raw(&reprepare_query).await?;
+        let schema_epoch = raw_prepared.schema_epoch;
+        let prepared_response = raw_prepared.prepared_response;
 
         // Reprepared statement should keep its id - it's the md5 sum
         // of statement contents
@@ -702,6 +757,13 @@ impl Connection {
                 expected_id: previous_prepared.get_id().clone().into(),
             })
         } else {
+            // The statement was most likely unprepared due to a schema change,
+            // so the result metadata may have changed as well.
+            previous_prepared.update_result_metadata(ResultMetadataSnapshot {
+                id: prepared_response.result_metadata.id().cloned(),
+                metadata: Arc::new(prepared_response.result_metadata),
+                schema_epoch,
+            });
             Ok(())
         }
     }
@@ -861,18 +923,23 @@ impl Connection {
         };
         let timestamp = statement.get_timestamp().or_else(get_timestamp_from_gen);
 
-        let query_frame = query::Query {
-            contents: Cow::Borrowed(&statement.contents),
-            parameters: query::QueryParameters {
-                consistency,
-                serial_consistency,
-                values: Cow::Borrowed(SerializedValues::EMPTY),
-                page_size: page_size.map(Into::into),
-                paging_state,
-                skip_metadata: false,
-                timestamp,
+        let query_frame = ExtendedRequest::new(
+            query::Query {
+                contents: Cow::Borrowed(&statement.contents),
+                parameters: query::QueryParameters {
+                    consistency,
+                    serial_consistency,
+                    values: Cow::Borrowed(SerializedValues::EMPTY),
+                    page_size: page_size.map(Into::into),
+                    paging_state,
+                    skip_metadata: false,
+                    timestamp,
+                },
             },
-        };
+            RequestExtensions::default()
+                .with_keyspace(statement.get_keyspace().map(Cow::Borrowed))
+                .with_now_in_seconds(statement.get_now_in_seconds()),
+        );
 
         let response = self
             .send_request(&query_frame, true, statement.config.tracing, None)
@@ -920,22 +987,30 @@ impl Connection {
             .get_timestamp()
             .or_else(get_timestamp_from_gen);
 
-        let execute_frame = execute::Execute {
-            id: prepared_statement.get_id().to_owned(),
-            parameters: query::QueryParameters {
-                consistency,
-                serial_consistency,
-                values: Cow::Borrowed(values),
-                page_size: page_size.map(Into::into),
-                timestamp,
-                skip_metadata: prepared_statement.get_use_cached_result_metadata(),
-                paging_state,
+        let schema_epochs = &self.config.schema_epochs;
+        let mut schema_epoch = schema_epochs.current();
+        let mut result_metadata = prepared_statement.get_result_metadata();
+        let mut cached_metadata =
+            Self::up_to_date_cached_metadata(prepared_statement, &result_metadata, schema_epochs);
+
+        let mut execute_frame = ExtendedRequest::new(
+            execute::Execute {
+                id: prepared_statement.get_id().to_owned(),
+                parameters: query::QueryParameters {
+                    consistency,
+                    serial_consistency,
+                    values: Cow::Borrowed(values),
+                    page_size: page_size.map(Into::into),
+                    timestamp,
+                    skip_metadata: cached_metadata.is_some(),
+                    paging_state,
+                },
             },
-        };
-
-        let cached_metadata = prepared_statement
-            .get_use_cached_result_metadata()
-            .then(|| prepared_statement.get_result_metadata());
+            // The keyspace is bound to the statement when it is prepared.
+            RequestExtensions::default()
+                .with_now_in_seconds(prepared_statement.get_now_in_seconds())
+                .with_result_metadata_id(result_metadata.id.clone()),
+        );
 
         let query_response = self
             .send_request(
@@ -964,6 +1039,18 @@ impl Connection {
                 // Repreparation of a statement is needed
                 self.reprepare(prepared_statement.get_statement(), prepared_statement)
                     .await?;
+
+                // Repreparation might have refreshed the result metadata.
+                schema_epoch = schema_epochs.current();
+                result_metadata = prepared_statement.get_result_metadata();
+                cached_metadata = Self::up_to_date_cached_metadata(
+                    prepared_statement,
+                    &result_metadata,
+                    schema_epochs,
+                );
+                execute_frame.request.parameters.skip_metadata = cached_metadata.is_some();
+                execute_frame.extensions.result_metadata_id = result_metadata.id.clone();
+
                 let new_response = self
                     .send_request(
                         &execute_frame,
@@ -982,9 +1069,79 @@ impl Connection {
                     }
                 }
 
+                Self::refresh_cached_metadata_from_response(
+                    prepared_statement,
+                    &result_metadata,
+                    cached_metadata.is_some(),
+                    schema_epoch,
+                    &new_response,
+                );
+
                 Ok(new_response)
             }
-            _ => Ok(query_response),
+            _ => {
+                Self::refresh_cached_metadata_from_response(
+                    prepared_statement,
+                    &result_metadata,
+                    cached_metadata.is_some(),
+                    schema_epoch,
+                    &query_response,
+                );
+
+                Ok(query_response)
+            }
+        }
+    }
+
+    /// Returns the result metadata cached in the statement, if the statement
+    /// is configured to use it and it was received after the last schema change
+    /// that could have affected it.
+    fn up_to_date_cached_metadata<'a>(
+        prepared_statement: &PreparedStatement,
+        result_metadata: &'a ResultMetadataSnapshot,
+        schema_epochs: &SchemaEpochs,
+    ) -> Option<&'a Arc<ResultMetadata<'static>>> {
+        (prepared_statement.get_use_cached_result_metadata()
+            && schema_epochs.is_up_to_date(result_metadata))
+        .then_some(&result_metadata.metadata)
+    }
+
+    /// Stores the result metadata sent by the server in the statement, so that
+    /// following executions can skip it again. This happens if the cached metadata
+    /// could not be trusted due to a schema change, or if the server reported
+    /// (in protocol v5) that the metadata changed.
+    fn refresh_cached_metadata_from_response(
+        prepared_statement: &PreparedStatement,
+        result_metadata: &ResultMetadataSnapshot,
+        used_cached_metadata: bool,
+        schema_epoch: SchemaEpoch,
+        response: &QueryResponse,
+    ) {
+        if !prepared_statement.get_use_cached_result_metadata() {
+            return;
+        }
+        let Response::Result(result::Result::Rows((raw_rows, _))) = &response.response else {
+            return;
+        };
+        let new_metadata_id = raw_rows.new_metadata_id();
+        if used_cached_metadata && new_metadata_id.is_none() {
+            // The cached metadata is up to date.
+            return;
+        }
+
+        match raw_rows.server_metadata_owned() {
+            Ok(Some(metadata)) => {
+                prepared_statement.update_result_metadata(ResultMetadataSnapshot {
+                    metadata: Arc::new(metadata),
+                    id: new_metadata_id.or(result_metadata.id.as_ref()).cloned(),
+                    schema_epoch,
+                })
+            }
+            Ok(None) => (),
+            Err(e) => debug!(
+                "Connection::execute: Failed to refresh cached result metadata: {}",
+                e
+            ),
         }
     }
 
@@ -1053,14 +1210,19 @@ impl Connection {
         };
         let timestamp = batch.get_timestamp().or_else(get_timestamp_from_gen);
 
-        let batch_frame = batch::Batch {
-            statements: Cow::Borrowed(&batch.statements),
-            values,
-            batch_type: batch.get_type(),
-            consistency,
-            serial_consistency,
-            timestamp,
-        };
+        let batch_frame = ExtendedRequest::new(
+            batch::Batch {
+                statements: Cow::Borrowed(&batch.statements),
+                values,
+                batch_type: batch.get_type(),
+                consistency,
+                serial_consistency,
+                timestamp,
+            },
+            RequestExtensions::default()
+                .with_keyspace(batch.get_keyspace().map(Cow::Borrowed))
+                .with_now_in_seconds(batch.get_now_in_seconds()),
+        );
 
         loop {
             let query_response = self
@@ -1241,22 +1403,39 @@ impl Connection {
         Ok(version_id)
     }
 
-    async fn send_request(
+    async fn send_request<R: SerializableRequest>(
         &self,
-        request: &impl SerializableRequest,
+        request: &R,
         compress: bool,
         tracing: bool,
         cached_metadata: Option<&Arc<ResultMetadata<'static>>>,
     ) -> Result<QueryResponse, InternalRequestError> {
-        let compression = if compress {
+        // In protocol v5, compression is applied to whole segments instead of
+        // to individual frames.
+        let compression = if compress && !self.config.protocol_version.uses_segments() {
             self.config.compression
         } else {
             None
         };
 
+        // Propagate the trace context of the current span to the node, so that
+        // server-side traces can be linked with the client-side ones.
+        #[cfg(feature = "opentelemetry")]
+        let custom_payload = match R::OPCODE {
+            RequestOpcode::Query
+            | RequestOpcode::Execute
+            | RequestOpcode::Batch
+            | RequestOpcode::Prepare => {
+                crate::observability::opentelemetry::current_context_payload()
+            }
+            _ => None,
+        };
+        #[cfg(not(feature = "opentelemetry"))]
+        let custom_payload: Option<HashMap<String, Bytes>> = None;
+
         let task_response = self
             .router_handle
-            .send_request(request, compression, tracing)
+            .send_request(request, compression, tracing, custom_payload.as_ref())
             .await?;
 
         let response = Self::parse_response(
@@ -1271,11 +1450,11 @@ impl Connection {
 
     fn parse_response(
         task_response: TaskResponse,
-        compression: Option<Compression>,
+        compression: Option<CompressionAlgorithm>,
         features: &ProtocolFeatures,
         cached_metadata: Option<&Arc<ResultMetadata<'static>>>,
     ) -> Result<QueryResponse, ResponseParseError> {
-        let body_with_ext = frame::parse_response_body_extensions(
+        let body_with_ext = frame::parse_response_body_extensions_with_algorithm(
             task_response.params.flags,
             compression,
             task_response.body,
@@ -1422,9 +1601,10 @@ impl Connection {
         let handler_map = StdMutex::new(ResponseHandlerMap::new());
 
         let write_coalescing_delay = config.write_coalescing_delay;
+        let protocol_version = config.protocol_version;
 
         let k = Self::keepaliver(
-            router_handle,
+            router_handle.clone(),
             config.keepalive_interval,
             config.keepalive_timeout,
             node_address,
@@ -1435,12 +1615,16 @@ impl Connection {
             &handler_map,
             config.event_sender,
             config.compression,
+            protocol_version,
+            &router_handle,
         );
         let w = Self::writer(
             BufWriter::with_capacity(8192, write_half),
             &handler_map,
             receiver,
             write_coalescing_delay,
+            protocol_version,
+            &router_handle,
         );
         let o = Self::orphaner(&handler_map, orphan_notification_receiver);
 
@@ -1467,13 +1651,44 @@ impl Connection {
     async fn reader(
         mut read_half: (impl AsyncRead + Unpin),
         handler_map: &StdMutex<ResponseHandlerMap>,
-        event_sender: Option<mpsc::Sender<Event>>,
-        compression: Option<Compression>,
+        event_sender: Option<mpsc::Sender<ServerEvent>>,
+        compression: Option<CompressionAlgorithm>,
+        protocol_version: ProtocolVersion,
+        router_handle: &RouterHandle,
     ) -> Result<(), BrokenConnectionError> {
+        // Set once the handshake reaches the point after which the server
+        // sends segments instead of bare frames (protocol v5 and newer).
+        let mut segment_decoder: Option<(bool, FrameDecoder)> = None;
         loop {
-            let (params, opcode, body) = frame::read_response_frame(&mut read_half)
-                .await
-                .map_err(BrokenConnectionErrorKind::FrameHeaderParseError)?;
+            let (params, opcode, body) = match segment_decoder.as_mut() {
+                None => frame::read_response_frame(&mut read_half)
+                    .await
+                    .map_err(BrokenConnectionErrorKind::FrameHeaderParseError)?,
+                Some((compressed, decoder)) => loop {
+                    if let Some(frame) = decoder.next_frame() {
+                        break frame.map_err(BrokenConnectionErrorKind::FrameHeaderParseError)?;
+                    }
+                    let segment = segment::read_segment(&mut read_half, *compressed)
+                        .await
+                        .map_err(BrokenConnectionErrorKind::SegmentParseError)?;
+                    decoder
+                        .push_segment(segment)
+                        .map_err(BrokenConnectionErrorKind::SegmentParseError)?;
+                },
+            };
+
+            if segment_decoder.is_none()
+                && protocol_version.uses_segments()
+                && matches!(opcode, ResponseOpcode::Ready | ResponseOpcode::Authenticate)
+            {
+                let compressed = router_handle
+                    .segment_compression
+                    .get()
+                    .copied()
+                    .unwrap_or(false);
+                segment_decoder = Some((compressed, FrameDecoder::new()));
+            }
+
             let response = TaskResponse {
                 params,
                 opcode,
@@ -1554,7 +1769,14 @@ impl Connection {
         handler_map: &StdMutex<ResponseHandlerMap>,
         mut task_receiver: mpsc::Receiver<Task>,
         write_coalescing_delay: Option<WriteCoalescingDelay>,
+        protocol_version: ProtocolVersion,
+        router_handle: &RouterHandle,
     ) -> Result<(), BrokenConnectionError> {
+        // Set after STARTUP is sent, if the protocol version requires frames
+        // to be wrapped in segments.
+        let mut segment_encoder: Option<SegmentEncoder> = None;
+        let mut segment_buf = Vec::new();
+
         // When the Connection object is dropped, the sender half
         // of the channel will be dropped, this task will return an error
         // and the whole worker will be stopped
@@ -1567,10 +1789,31 @@ impl Connection {
                 let req_data: &[u8] = req.get_data();
                 total_sent += req_data.len();
                 num_requests += 1;
-                write_half
-                    .write_all(req_data)
-                    .await
-                    .map_err(BrokenConnectionErrorKind::WriteError)?;
+                match segment_encoder.as_mut() {
+                    Some(encoder) => {
+                        encoder.push_frame(req_data, &mut segment_buf);
+                        write_half
+                            .write_all(&segment_buf)
+                            .await
+                            .map_err(BrokenConnectionErrorKind::WriteError)?;
+                        segment_buf.clear();
+                    }
+                    None => write_half
+                        .write_all(req_data)
+                        .await
+                        .map_err(BrokenConnectionErrorKind::WriteError)?,
+                }
+                if segment_encoder.is_none()
+                    && protocol_version.uses_segments()
+                    && req.opcode() == RequestOpcode::Startup
+                {
+                    let compressed = router_handle
+                        .segment_compression
+                        .get()
+                        .copied()
+                        .unwrap_or(false);
+                    segment_encoder = Some(SegmentEncoder::new(compressed));
+                }
                 task = match task_receiver.try_recv() {
                     Ok(t) => t,
                     Err(_) => match write_coalescing_delay {
@@ -1596,6 +1839,14 @@ impl Connection {
                 }
             }
             trace!("Sending {} requests; {} bytes", num_requests, total_sent);
+            if let Some(encoder) = segment_encoder.as_mut() {
+                encoder.flush(&mut segment_buf);
+                write_half
+                    .write_all(&segment_buf)
+                    .await
+                    .map_err(BrokenConnectionErrorKind::WriteError)?;
+                segment_buf.clear();
+            }
             write_half
                 .flush()
                 .await
@@ -1654,7 +1905,7 @@ impl Connection {
             router_handle: &RouterHandle,
         ) -> Result<(), BrokenConnectionError> {
             router_handle
-                .send_request(&Options, None, false)
+                .send_request(&Options, None, false, None)
                 .await
                 .map(|_| ())
                 .map_err(|req_err| {
@@ -1710,8 +1961,8 @@ impl Connection {
 
     async fn handle_event(
         task_response: TaskResponse,
-        compression: Option<Compression>,
-        event_sender: &mpsc::Sender<Event>,
+        compression: Option<CompressionAlgorithm>,
+        event_sender: &mpsc::Sender<ServerEvent>,
     ) -> Result<(), CqlEventHandlingError> {
         // Protocol features are negotiated during connection handshake.
         // However, the router is already created and sent to a different tokio
@@ -1724,9 +1975,13 @@ impl Connection {
         // future implementers.
         let features = ProtocolFeatures::default(); // TODO: Use the right features
 
+        // Kept in case the event has to be deserialized again, see below.
+        let flags = task_response.params.flags;
+        let body = task_response.body.clone();
+
         let event = match Self::parse_response(task_response, compression, &features, None) {
             Ok(r) => match r.response {
-                Response::Event(event) => event,
+                Response::Event(event) => ServerEvent::Cql(event),
                 _ => {
                     error!("Expected to receive Event response, got {:?}", r.response);
                     return Err(CqlEventHandlingError::UnexpectedResponse(
@@ -1737,6 +1992,19 @@ impl Connection {
             Err(e) => match e {
                 ResponseParseError::BodyExtensionsParseError(e) => return Err(e.into()),
                 ResponseParseError::CqlResponseParseError(e) => match e {
+                    // scylla-cql does not represent MOVED_NODE events, so they are deserialized here.
+                    CqlResponseParseError::CqlEventParseError(
+                        CqlEventParseError::TopologyChangeEventParseError(
+                            ClusterChangeEventParseError::UnknownTypeOfChange(ref type_of_change),
+                        ),
+                    ) if type_of_change == "MOVED_NODE" => {
+                        let body_with_ext = frame::parse_response_body_extensions_with_algorithm(
+                            flags,
+                            compression,
+                            body,
+                        )?;
+                        ServerEvent::NodeMoved(Self::parse_moved_node_event(&body_with_ext.body)?)
+                    }
                     CqlResponseParseError::CqlEventParseError(e) => return Err(e.into()),
                     // Received a response other than EVENT, but failed to deserialize it.
                     _ => {
@@ -1754,6 +2022,21 @@ impl Connection {
             .map_err(|_| CqlEventHandlingError::SendError)
     }
 
+    fn parse_moved_node_event(mut buf: &[u8]) -> Result<SocketAddr, CqlEventParseError> {
+        // Event type and type of change were already validated by scylla-cql.
+        types::read_string(&mut buf).map_err(CqlEventParseError::EventTypeParseError)?;
+        types::read_string(&mut buf).map_err(|e| {
+            CqlEventParseError::TopologyChangeEventParseError(
+                ClusterChangeEventParseError::TypeOfChangeParseError(e),
+            )
+        })?;
+        types::read_inet(&mut buf).map_err(|e| {
+            CqlEventParseError::TopologyChangeEventParseError(
+                ClusterChangeEventParseError::NodeAddressParseError(e),
+            )
+        })
+    }
+
     pub(crate) fn get_shard_info(&self) -> &Option<ShardInfo> {
         &self.features.shard_info
     }
@@ -1845,6 +2128,35 @@ pub(crate) async fn open_connection(
     endpoint: &UntranslatedEndpoint,
     source_port: Option<u16>,
     config: &HostConnectionConfig,
+) -> Result<(Connection, ErrorReceiver), ConnectionError> {
+    match open_connection_with_version(endpoint, source_port, config).await {
+        // Nodes that do not support the requested protocol version respond
+        // to the first request with a protocol error. Retry with v4,
+        // which is supported by all nodes that the driver works with.
+        Err(ConnectionError::ConnectionSetupRequestError(ConnectionSetupRequestError {
+            error: ConnectionSetupRequestErrorKind::DbError(DbError::ProtocolError, msg),
+            ..
+        })) if config.protocol_version > ProtocolVersion::V4 => {
+            debug!(
+                "Protocol {} was rejected by the node ({}). Falling back to protocol {}",
+                config.protocol_version,
+                msg,
+                ProtocolVersion::V4
+            );
+            let config = HostConnectionConfig {
+                protocol_version: ProtocolVersion::V4,
+                ..config.clone()
+            };
+            open_connection_with_version(endpoint, source_port, &config).await
+        }
+        result => result,
+    }
+}
+
+async fn open_connection_with_version(
+    endpoint: &UntranslatedEndpoint,
+    source_port: Option<u16>,
+    config: &HostConnectionConfig,
 ) -> Result<(Connection, ErrorReceiver), ConnectionError> {
     /* Translate the address, if applicable. */
     let addr = maybe_translated_addr(endpoint, config.address_translator.as_deref()).await?;
@@ -1893,7 +2205,8 @@ pub(crate) async fn open_connection(
         .and_then(|p| p.parse::<u16>().ok());
 
     // Parse nonstandard protocol extensions.
-    let protocol_features = ProtocolFeatures::parse_from_supported(&supported.options);
+    let mut protocol_features = ProtocolFeatures::parse_from_supported(&supported.options);
+    protocol_features.protocol_version = config.protocol_version;
 
     // At the beginning, Connection assumes no sharding and no protocol extensions;
     // now that we know them, let's turn them on in the driver.
@@ -1918,23 +2231,56 @@ pub(crate) async fn open_connection(
     config.identity.add_startup_options(&mut options);
 
     // Optional compression.
-    if let Some(compression) = &config.compression {
-        let compression_str = compression.as_str();
-        if supported_compression.iter().any(|c| c == compression_str) {
-            // Compression is reported to be supported by the server,
-            // request it from the server
-            options.insert(
-                Cow::Borrowed(options::COMPRESSION),
-                Cow::Borrowed(compression_str),
-            );
+    let preferred_compression = config
+        .zstd_compression_level
+        .map(|level| CompressionAlgorithm::Zstd { level })
+        .or(config.compression);
+    if let Some(preferred) = preferred_compression {
+        let negotiated = if config.protocol_version.uses_segments() {
+            // Segments can only be compressed with lz4.
+            negotiate_compression(
+                None,
+                Some(CompressionAlgorithm::Lz4),
+                &supported_compression,
+            )
         } else {
-            // Fall back to no compression
-            tracing::warn!(
-                "Requested compression <{}> is not supported by the cluster. Falling back to no compression",
-                compression_str
-            );
-            connection.config.compression = None;
+            negotiate_compression(
+                config.zstd_compression_level,
+                config.compression,
+                &supported_compression,
+            )
+        };
+        match negotiated {
+            Some(negotiated) => {
+                if negotiated != preferred {
+                    tracing::warn!(
+                        "Requested compression <{}> is not supported by the cluster. Falling back to <{}>",
+                        preferred,
+                        negotiated
+                    );
+                }
+                // Compression is reported to be supported by the server,
+                // request it from the server
+                options.insert(
+                    Cow::Borrowed(options::COMPRESSION),
+                    Cow::Borrowed(negotiated.as_str()),
+                );
+            }
+            None => {
+                // Fall back to no compression
+                tracing::warn!(
+                    "Requested compression <{}> is not supported by the cluster. Falling back to no compression",
+                    preferred
+                );
+            }
         }
+        connection.config.compression = negotiated;
+        // The router switches to segment framing right after STARTUP,
+        // so it has to know about compression before it is sent.
+        let _ = connection
+            .router_handle
+            .segment_compression
+            .set(negotiated.is_some());
     }
 
     /* Send the STARTUP frame with all the requested options. */
@@ -1959,6 +2305,33 @@ pub(crate) async fn open_connection(
     Ok((connection, error_receiver))
 }
 
+/// Picks the compression to request in STARTUP, given the configured ones
+/// and the algorithms listed by the server in SUPPORTED.
+///
+/// Zstd with the given level is preferred, if set. If it is not supported,
+/// the configured compression is used, provided that the server supports it.
+fn negotiate_compression(
+    zstd_level: Option<i32>,
+    compression: Option<CompressionAlgorithm>,
+    supported_compression: &[String],
+) -> Option<CompressionAlgorithm> {
+    let is_supported = |compression: &CompressionAlgorithm| {
+        // Without the `zstd` feature, zstd compression would fail on every frame.
+        let is_enabled =
+            !matches!(compression, CompressionAlgorithm::Zstd { .. }) || cfg!(feature = "zstd");
+        is_enabled
+            && supported_compression
+                .iter()
+                .any(|c| c == compression.as_str())
+    };
+
+    zstd_level
+        .map(|level| CompressionAlgorithm::Zstd { level })
+        .into_iter()
+        .chain(compression)
+        .find(is_supported)
+}
+
 pub(super) async fn open_connection_to_shard_aware_port(
     endpoint: &UntranslatedEndpoint,
     shard: Shard,
@@ -2139,7 +2512,7 @@ struct StreamIdSet {
 
 impl StreamIdSet {
     fn new() -> Self {
-        const BITMAP_SIZE: usize = (i16::MAX as usize + 1) / 64;
+        const BITMAP_SIZE: usize = MAX_STREAMS_PER_CONNECTION / 64;
         Self {
             used_bitmap: vec![0; BITMAP_SIZE].into_boxed_slice(),
         }
@@ -2576,6 +2949,73 @@ mod tests {
         )
     }
 
+    #[cfg(feature = "opentelemetry")]
+    #[tokio::test]
+    async fn trace_context_is_sent_in_custom_payload() {
+        use crate::observability::opentelemetry::tests::{otel_subscriber, traceparent};
+        use scylla_cql::frame::flag;
+        use tracing::Instrument as _;
+
+        let _subscriber = tracing::subscriber::set_default(otel_subscriber());
+
+        let proxy_addr = SocketAddr::new(scylla_proxy::get_exclusive_local_address(), 9042);
+        let (query_tx, mut query_rx) = mpsc::unbounded_channel();
+        let rules = vec![
+            RequestRule(
+                Condition::RequestOpcode(RequestOpcode::Options),
+                RequestReaction::forge_response(Arc::new(|frame: RequestFrame| {
+                    ResponseFrame::forged_supported(frame.params, &HashMap::new()).unwrap()
+                })),
+            ),
+            RequestRule(
+                Condition::RequestOpcode(RequestOpcode::Startup),
+                RequestReaction::forge_response(Arc::new(|frame: RequestFrame| {
+                    ResponseFrame::forged_ready(frame.params)
+                })),
+            ),
+            RequestRule(
+                Condition::RequestOpcode(RequestOpcode::Query),
+                RequestReaction::drop_frame().with_feedback_when_performed(query_tx),
+            ),
+        ];
+        let proxy = Proxy::builder()
+            .with_node(
+                Node::builder()
+                    .proxy_address(proxy_addr)
+                    .request_rules(rules)
+                    .build_dry_mode(),
+            )
+            .build()
+            .run()
+            .await
+            .unwrap();
+
+        let endpoint = UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
+            address: proxy_addr,
+            datacenter: None,
+        });
+        let (conn, _error_receiver) =
+            open_connection(&endpoint, None, &HostConnectionConfig::default())
+                .await
+                .unwrap();
+
+        // The query is never answered, so it is interrupted once the proxy receives it.
+        let span = tracing::info_span!("request");
+        let (query, _shard) = select! {
+            _ = conn.query_unpaged("SELECT 1").instrument(span.clone()) => unreachable!(),
+            query = query_rx.recv() => query.unwrap(),
+        };
+
+        let _ = proxy.finish().await;
+
+        assert_ne!(query.params.flags & flag::CUSTOM_PAYLOAD, 0);
+        let payload = types::read_bytes_map(&mut &*query.body).unwrap();
+        assert_eq!(
+            payload.get("traceparent").map(|value| value.as_ref()),
+            Some(traceparent(&span).as_bytes())
+        );
+    }
+
     #[tokio::test]
     #[ntest::timeout(20000)]
     #[cfg_attr(scylla_cloud_tests, ignore)]
@@ -2657,4 +3097,142 @@ mod tests {
 
         let _ = proxy.finish().await;
     }
+
+    #[tokio::test]
+    async fn protocol_v5_falls_back_to_v4_on_protocol_error() {
+        use crate::client::ProtocolVersion;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        setup_tracing();
+
+        let listener = TcpListener::bind((scylla_proxy::get_exclusive_local_address(), 0))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Records the protocol version of the first request on each connection.
+        // Requests in v5 are rejected with a protocol error, as a node
+        // that does not support v5 would do. Connections in v4 are left hanging.
+        let (version_tx, mut version_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut header = [0u8; 9];
+                socket.read_exact(&mut header).await.unwrap();
+                let version = header[0];
+                version_tx.send(version).unwrap();
+                if version == 5 {
+                    let message = b"Beta version of the protocol used";
+                    let mut body = Vec::new();
+                    body.extend_from_slice(&0x000A_i32.to_be_bytes());
+                    body.extend_from_slice(&(message.len() as u16).to_be_bytes());
+                    body.extend_from_slice(message);
+                    let mut response = vec![0x84, 0x00, header[2], header[3], 0x00];
+                    response.extend_from_slice(&(body.len() as u32).to_be_bytes());
+                    response.extend_from_slice(&body);
+                    socket.write_all(&response).await.unwrap();
+                }
+                connections.push(socket);
+            }
+        });
+
+        let config = HostConnectionConfig {
+            protocol_version: ProtocolVersion::V5,
+            ..Default::default()
+        };
+        let endpoint = UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
+            address: addr,
+            datacenter: None,
+        });
+
+        // The handshake never finishes, because the server stops responding in v4.
+        select! {
+            _ = open_connection(&endpoint, None, &config) => unreachable!(),
+            _ = async {
+                assert_eq!(version_rx.recv().await, Some(5));
+                assert_eq!(version_rx.recv().await, Some(4));
+            } => {}
+        }
+    }
+
+    #[tokio::test]
+    async fn moved_node_event_is_passed_to_cluster_worker() {
+        use super::{Connection, TaskResponse};
+        use crate::cluster::events::ServerEvent;
+        use crate::frame::response::ResponseOpcode;
+        use crate::frame::FrameParams;
+
+        let addr: SocketAddr = "127.0.0.5:9042".parse().unwrap();
+        let mut body = Vec::new();
+        types::write_string("TOPOLOGY_CHANGE", &mut body).unwrap();
+        types::write_string("MOVED_NODE", &mut body).unwrap();
+        types::write_inet(addr, &mut body);
+
+        let (event_sender, mut event_receiver) = mpsc::channel(1);
+        let response = TaskResponse {
+            params: FrameParams {
+                stream: -1,
+                ..Default::default()
+            },
+            opcode: ResponseOpcode::Event,
+            body: body.into(),
+        };
+        Connection::handle_event(response, None, &event_sender)
+            .await
+            .unwrap();
+
+        assert_matches!(
+            event_receiver.recv().await,
+            Some(ServerEvent::NodeMoved(moved)) if moved == addr
+        );
+    }
+
+    #[test]
+    fn compression_negotiation_falls_back_to_configured_compression() {
+        use super::negotiate_compression;
+        use scylla_cql::frame::CompressionAlgorithm;
+
+        let supported = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let lz4 = Some(CompressionAlgorithm::Lz4);
+
+        assert_eq!(
+            negotiate_compression(None, lz4, &supported(&["lz4", "snappy"])),
+            lz4
+        );
+        // Without zstd, there is nothing to fall back from.
+        assert_eq!(
+            negotiate_compression(None, lz4, &supported(&["snappy"])),
+            None
+        );
+        assert_eq!(negotiate_compression(None, lz4, &[]), None);
+        assert_eq!(
+            negotiate_compression(Some(5), None, &supported(&["lz4"])),
+            None
+        );
+
+        #[cfg(feature = "zstd")]
+        {
+            let zstd = Some(CompressionAlgorithm::Zstd { level: 5 });
+            assert_eq!(
+                negotiate_compression(Some(5), lz4, &supported(&["zstd", "lz4"])),
+                zstd
+            );
+            assert_eq!(
+                negotiate_compression(Some(5), lz4, &supported(&["lz4", "snappy"])),
+                lz4
+            );
+            assert_eq!(
+                negotiate_compression(Some(5), lz4, &supported(&["snappy"])),
+                None
+            );
+        }
+
+        #[cfg(not(feature = "zstd"))]
+        assert_eq!(
+            negotiate_compression(Some(5), lz4, &supported(&["zstd", "lz4"])),
+            lz4
+        );
+    }
 }
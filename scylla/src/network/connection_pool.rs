@@ -394,6 +394,15 @@ impl NodeConnectionPool {
         })
     }
 
+    /// Returns the number of working connections, without cloning them.
+    #[cfg(feature = "tower-service-03")]
+    pub(crate) fn working_connections_count(&self) -> Result<usize, ConnectionPoolError> {
+        self.with_connections(|pool_conns| match pool_conns {
+            PoolConnections::NotSharded(conns) => conns.len(),
+            PoolConnections::Sharded { connections, .. } => connections.iter().map(Vec::len).sum(),
+        })
+    }
+
     fn choose_random_connection_from_slice(v: &[Arc<Connection>]) -> Option<Arc<Connection>> {
         trace!(
             connections = tracing::field::display(
@@ -9,6 +9,8 @@ mod connection;
 #[cfg(test)]
 pub(crate) use connection::open_connection;
 
+#[cfg(feature = "tower-service-03")]
+pub(crate) use connection::MAX_STREAMS_PER_CONNECTION;
 pub(crate) use connection::{Connection, ConnectionConfig, VerifiedKeyspaceName};
 
 mod connection_pool;
@@ -1,10 +1,16 @@
 use crate::cluster::node::Node;
 use crate::network::Connection;
+#[cfg(feature = "metrics")]
+use crate::observability::metrics::RequestKind;
+#[cfg(feature = "opentelemetry")]
+use crate::policies::load_balancing::RoutingInfo;
 use crate::response::query_result::QueryResult;
 use crate::routing::{Shard, Token};
 use itertools::{Either, Itertools};
 use scylla_cql::frame::response::result::ColumnSpec;
 use scylla_cql::frame::response::result::RawMetadataAndRawRows;
+#[cfg(feature = "opentelemetry")]
+use scylla_cql::frame::types::Consistency;
 use scylla_cql::value::deser_cql_value;
 use std::borrow::Borrow;
 use std::fmt::Display;
@@ -12,18 +18,84 @@ use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use tracing::trace_span;
+#[cfg(feature = "opentelemetry")]
+use uuid::Uuid;
+
+/// Creates the span of a whole request, declaring the given fields.
+///
+/// With the `opentelemetry` feature enabled, the span additionally declares
+/// the attributes from the OpenTelemetry database semantic conventions.
+macro_rules! request_span {
+    ($($fields:tt)*) => {{
+        #[cfg(feature = "opentelemetry")]
+        let span = trace_span!(
+            "Request",
+            otel.kind = "client",
+            otel.name = Empty,
+            db.system = "cassandra",
+            db.statement = Empty,
+            db.operation.name = Empty,
+            db.namespace = Empty,
+            db.collection.name = Empty,
+            db.cassandra.consistency_level = Empty,
+            db.cassandra.idempotence = Empty,
+            db.cassandra.speculative_execution_count = Empty,
+            scylla.tracing_id = Empty,
+            $($fields)*
+        );
+        #[cfg(not(feature = "opentelemetry"))]
+        let span = trace_span!("Request", $($fields)*);
+        span
+    }};
+}
+
+/// Creates the span of a single attempt of sending a request to a node.
+///
+/// With the `opentelemetry` feature enabled, the span additionally carries
+/// the coordinator and consistency attributes from the OpenTelemetry database
+/// semantic conventions. Each attempt (including retries and speculative
+/// executions) gets its own span, nested in the span of the request.
+macro_rules! attempt_span {
+    ($(parent: $parent:expr,)? $name:literal, $node:expr, $shard:expr, $consistency:expr) => {{
+        let node: &$crate::cluster::Node = $node;
+        #[cfg(feature = "opentelemetry")]
+        let span = tracing::trace_span!(
+            $(parent: $parent,)?
+            $name,
+            node = %node.address,
+            shard = %$shard,
+            server.address = %node.address.ip(),
+            server.port = node.address.port(),
+            db.cassandra.coordinator.id = %node.host_id,
+            db.cassandra.coordinator.dc = node.datacenter.as_deref(),
+            db.cassandra.consistency_level =
+                $crate::observability::driver_tracing::consistency_attribute($consistency),
+        );
+        #[cfg(not(feature = "opentelemetry"))]
+        let span = {
+            let _ = $consistency;
+            tracing::trace_span!($(parent: $parent,)? $name, node = %node.address, shard = %$shard)
+        };
+        span
+    }};
+}
+pub(crate) use attempt_span;
 
 pub(crate) struct RequestSpan {
     span: tracing::Span,
     speculative_executions: AtomicUsize,
+    #[cfg(feature = "metrics")]
+    kind: RequestKind,
+    // The value of the `db.operation.name` attribute, used to name the span.
+    #[cfg(feature = "opentelemetry")]
+    operation: String,
 }
 
 impl RequestSpan {
     pub(crate) fn new_query(contents: &str) -> Self {
         use tracing::field::Empty;
 
-        let span = trace_span!(
-            "Request",
+        let span = request_span!(
             kind = "unprepared",
             contents = contents,
             //
@@ -36,20 +108,25 @@ impl RequestSpan {
         );
 
         Self {
+            #[cfg(feature = "opentelemetry")]
+            operation: record_statement(&span, contents),
             span,
             speculative_executions: 0.into(),
+            #[cfg(feature = "metrics")]
+            kind: RequestKind::Unprepared,
         }
     }
 
+    #[cfg_attr(not(feature = "opentelemetry"), allow(unused_variables))]
     pub(crate) fn new_prepared<'ps, 'spec: 'ps>(
+        statement: &str,
         partition_key: Option<impl Iterator<Item = (&'ps [u8], &'ps ColumnSpec<'spec>)> + Clone>,
         token: Option<Token>,
         request_size: usize,
     ) -> Self {
         use tracing::field::Empty;
 
-        let span = trace_span!(
-            "Request",
+        let span = request_span!(
             kind = "prepared",
             partition_key = Empty,
             token = Empty,
@@ -75,16 +152,19 @@ impl RequestSpan {
         }
 
         Self {
+            #[cfg(feature = "opentelemetry")]
+            operation: record_statement(&span, statement),
             span,
             speculative_executions: 0.into(),
+            #[cfg(feature = "metrics")]
+            kind: RequestKind::Prepared,
         }
     }
 
     pub(crate) fn new_batch() -> Self {
         use tracing::field::Empty;
 
-        let span = trace_span!(
-            "Request",
+        let span = request_span!(
             kind = "batch",
             //
             request_size = Empty,
@@ -94,10 +174,17 @@ impl RequestSpan {
             shard = Empty,
             speculative_executions = Empty,
         );
+        #[cfg(feature = "opentelemetry")]
+        span.record("db.operation.name", "BATCH")
+            .record("otel.name", "BATCH");
 
         Self {
             span,
             speculative_executions: 0.into(),
+            #[cfg(feature = "metrics")]
+            kind: RequestKind::Batch,
+            #[cfg(feature = "opentelemetry")]
+            operation: "BATCH".to_owned(),
         }
     }
 
@@ -116,6 +203,49 @@ impl RequestSpan {
         if let Some(raw_metadata_and_rows) = query_result.raw_metadata_and_rows() {
             self.record_raw_rows_fields(raw_metadata_and_rows);
         }
+        #[cfg(feature = "opentelemetry")]
+        self.record_tracing_id(query_result.tracing_id());
+    }
+
+    /// Records the attributes that describe the request as routed by the driver:
+    /// the target table, the consistency and the idempotence. Also sets the span
+    /// name as exported to OpenTelemetry, following the semantic conventions.
+    #[cfg(feature = "opentelemetry")]
+    pub(crate) fn record_routing_attributes(
+        &self,
+        routing_info: &RoutingInfo,
+        is_idempotent: bool,
+    ) {
+        use tracing::field::display;
+
+        self.span.record(
+            "db.cassandra.consistency_level",
+            consistency_attribute(routing_info.consistency),
+        );
+        self.span.record("db.cassandra.idempotence", is_idempotent);
+
+        if let Some(table) = routing_info.table {
+            self.span.record("db.namespace", table.ks_name());
+            self.span.record("db.collection.name", table.table_name());
+            self.span.record(
+                "otel.name",
+                display(format_args!(
+                    "{} {}.{}",
+                    self.operation,
+                    table.ks_name(),
+                    table.table_name()
+                )),
+            );
+        }
+    }
+
+    /// Records the id of the server-side tracing session of the request, if tracing was enabled.
+    #[cfg(feature = "opentelemetry")]
+    pub(crate) fn record_tracing_id(&self, tracing_id: Option<Uuid>) {
+        if let Some(tracing_id) = tracing_id {
+            self.span
+                .record("scylla.tracing_id", tracing::field::display(tracing_id));
+        }
     }
 
     pub(crate) fn record_replicas<'a>(
@@ -146,6 +276,11 @@ impl RequestSpan {
         self.span.record("request_size", size);
     }
 
+    #[cfg(feature = "metrics")]
+    pub(crate) fn kind(&self) -> RequestKind {
+        self.kind
+    }
+
     pub(crate) fn inc_speculative_executions(&self) {
         self.speculative_executions.fetch_add(1, Ordering::Relaxed);
     }
@@ -157,13 +292,51 @@ impl RequestSpan {
 
 impl Drop for RequestSpan {
     fn drop(&mut self) {
+        let speculative_executions = self.speculative_executions.load(Ordering::Relaxed);
+        self.span
+            .record("speculative_executions", speculative_executions);
+        #[cfg(feature = "opentelemetry")]
         self.span.record(
-            "speculative_executions",
-            self.speculative_executions.load(Ordering::Relaxed),
+            "db.cassandra.speculative_execution_count",
+            speculative_executions,
         );
     }
 }
 
+/// Records the statement and the operation it performs (its first keyword,
+/// uppercased) in the span, naming the span after the operation.
+/// Returns the operation.
+#[cfg(feature = "opentelemetry")]
+fn record_statement(span: &tracing::Span, statement: &str) -> String {
+    let operation = statement
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    span.record("db.statement", statement)
+        .record("db.operation.name", operation.as_str())
+        .record("otel.name", operation.as_str());
+    operation
+}
+
+/// The value of the `db.cassandra.consistency_level` attribute for the given consistency.
+#[cfg(feature = "opentelemetry")]
+pub(crate) fn consistency_attribute(consistency: Consistency) -> &'static str {
+    match consistency {
+        Consistency::Any => "any",
+        Consistency::One => "one",
+        Consistency::Two => "two",
+        Consistency::Three => "three",
+        Consistency::Quorum => "quorum",
+        Consistency::All => "all",
+        Consistency::LocalQuorum => "local_quorum",
+        Consistency::EachQuorum => "each_quorum",
+        Consistency::LocalOne => "local_one",
+        Consistency::Serial => "serial",
+        Consistency::LocalSerial => "local_serial",
+    }
+}
+
 fn partition_key_displayer<'ps, 'res, 'spec: 'ps>(
     mut pk_values_iter: impl Iterator<Item = (&'ps [u8], &'ps ColumnSpec<'spec>)> + 'res + Clone,
 ) -> impl Display + 'res {
@@ -178,3 +351,156 @@ fn partition_key_displayer<'ps, 'res, 'spec: 'ps>(
     })
     .format(", ")
 }
+
+#[cfg(all(test, feature = "opentelemetry"))]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use scylla_cql::frame::response::result::TableSpec;
+    use scylla_cql::frame::types::Consistency;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, SubscriberExt as _};
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+    use uuid::Uuid;
+
+    use super::RequestSpan;
+    use crate::cluster::{Node, NodeAddr};
+    use crate::policies::load_balancing::RoutingInfo;
+
+    type Fields = HashMap<String, String>;
+
+    /// Captures the fields of the spans, in the order the spans were created.
+    #[derive(Clone, Default)]
+    struct CapturedSpans(Arc<Mutex<Vec<(&'static str, Fields)>>>);
+
+    impl CapturedSpans {
+        /// Returns the fields of the only span with the given name.
+        fn fields(&self, name: &str) -> Fields {
+            let spans = self.0.lock().unwrap();
+            let mut matching = spans.iter().filter(|(span_name, _)| *span_name == name);
+            let (_, fields) = matching.next().expect("no span with the name");
+            assert!(matching.next().is_none(), "many spans with the name");
+            fields.clone()
+        }
+    }
+
+    struct FieldsVisitor<'a>(&'a mut Fields);
+
+    impl Visit for FieldsVisitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_owned(), value.to_owned());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name().to_owned(), format!("{value:?}"));
+        }
+    }
+
+    // The index of the span in the captured spans.
+    struct SpanIndex(usize);
+
+    impl<S> Layer<S> for CapturedSpans
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut fields = Fields::new();
+            attrs.record(&mut FieldsVisitor(&mut fields));
+            let mut spans = self.0.lock().unwrap();
+            spans.push((attrs.metadata().name(), fields));
+            let span = ctx.span(id).unwrap();
+            span.extensions_mut().insert(SpanIndex(spans.len() - 1));
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+            let span = ctx.span(id).unwrap();
+            let extensions = span.extensions();
+            let SpanIndex(index) = extensions.get::<SpanIndex>().unwrap();
+            values.record(&mut FieldsVisitor(&mut self.0.lock().unwrap()[*index].1));
+        }
+    }
+
+    fn capture_spans(f: impl FnOnce()) -> CapturedSpans {
+        let spans = CapturedSpans::default();
+        let subscriber = tracing_subscriber::registry().with(spans.clone());
+        tracing::subscriber::with_default(subscriber, f);
+        spans
+    }
+
+    #[test]
+    fn request_span_records_semantic_convention_attributes() {
+        let tracing_id = Uuid::new_v4();
+        let spans = capture_spans(|| {
+            let span = RequestSpan::new_query("select a from ks.t where b = ?");
+            let table = TableSpec::borrowed("ks", "t");
+            let routing_info = RoutingInfo {
+                consistency: Consistency::LocalQuorum,
+                table: Some(&table),
+                ..Default::default()
+            };
+            span.record_routing_attributes(&routing_info, true);
+            span.record_tracing_id(Some(tracing_id));
+            span.inc_speculative_executions();
+        });
+
+        let fields = spans.fields("Request");
+        for (name, value) in [
+            ("otel.kind", "client"),
+            ("otel.name", "SELECT ks.t"),
+            ("db.system", "cassandra"),
+            ("db.statement", "select a from ks.t where b = ?"),
+            ("db.operation.name", "SELECT"),
+            ("db.namespace", "ks"),
+            ("db.collection.name", "t"),
+            ("db.cassandra.consistency_level", "local_quorum"),
+            ("db.cassandra.idempotence", "true"),
+            ("db.cassandra.speculative_execution_count", "1"),
+            ("scylla.tracing_id", &tracing_id.to_string()),
+        ] {
+            assert_eq!(fields.get(name).map(String::as_str), Some(value), "{name}");
+        }
+    }
+
+    #[test]
+    fn batch_span_is_named_after_the_operation() {
+        let spans = capture_spans(|| {
+            let span = RequestSpan::new_batch();
+            // Without a table, the span keeps the name of the operation.
+            span.record_routing_attributes(&RoutingInfo::default(), false);
+        });
+
+        let fields = spans.fields("Request");
+        assert_eq!(fields["otel.name"], "BATCH");
+        assert_eq!(fields["db.operation.name"], "BATCH");
+        assert_eq!(fields["db.cassandra.idempotence"], "false");
+        assert!(!fields.contains_key("db.namespace"));
+        assert!(!fields.contains_key("db.statement"));
+    }
+
+    #[test]
+    fn attempt_span_records_coordinator_attributes() {
+        let host_id = Uuid::new_v4();
+        let address = NodeAddr::Translatable("10.0.0.1:9042".parse().unwrap());
+        let node = Node::new_for_test(Some(host_id), Some(address), Some("dc1".to_owned()), None);
+        let spans = capture_spans(|| {
+            let _span = attempt_span!("Executing request", &node, 3, Consistency::One);
+        });
+
+        let fields = spans.fields("Executing request");
+        for (name, value) in [
+            ("node", "10.0.0.1:9042"),
+            ("shard", "3"),
+            ("server.address", "10.0.0.1"),
+            ("server.port", "9042"),
+            ("db.cassandra.coordinator.id", &host_id.to_string()),
+            ("db.cassandra.coordinator.dc", "dc1"),
+            ("db.cassandra.consistency_level", "one"),
+        ] {
+            assert_eq!(fields.get(name).map(String::as_str), Some(value), "{name}");
+        }
+    }
+}
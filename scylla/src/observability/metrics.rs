@@ -1,7 +1,16 @@
 use histogram::{AtomicHistogram, Histogram};
+use scylla_cql::frame::response::error::DbError;
+use scylla_cql::frame::response::result::TableSpec;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 use thiserror::Error;
+use uuid::Uuid;
+
+use crate::cluster::node::Node;
+use crate::errors::RequestAttemptError;
 
 const ORDER_TYPE: Ordering = Ordering::Relaxed;
 
@@ -201,17 +210,285 @@ impl Default for RequestRateMeter {
     }
 }
 
+/// Kind of a request, used as a label of request metrics.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RequestKind {
+    /// An unprepared statement.
+    Unprepared,
+    /// A prepared statement.
+    Prepared,
+    /// A batch.
+    Batch,
+}
+
+impl RequestKind {
+    /// Returns the value of the `kind` label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestKind::Unprepared => "unprepared",
+            RequestKind::Prepared => "prepared",
+            RequestKind::Batch => "batch",
+        }
+    }
+}
+
+impl std::fmt::Display for RequestKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns the value of the `error` label for the given error.
+fn error_label(error: &RequestAttemptError) -> &'static str {
+    match error {
+        RequestAttemptError::DbError(db_error, _) => match db_error {
+            DbError::SyntaxError => "syntax_error",
+            DbError::Invalid => "invalid",
+            DbError::AlreadyExists { .. } => "already_exists",
+            DbError::FunctionFailure { .. } => "function_failure",
+            DbError::AuthenticationError => "authentication_error",
+            DbError::Unauthorized => "unauthorized",
+            DbError::ConfigError => "config_error",
+            DbError::Unavailable { .. } => "unavailable",
+            DbError::Overloaded => "overloaded",
+            DbError::IsBootstrapping => "is_bootstrapping",
+            DbError::TruncateError => "truncate_error",
+            DbError::ReadTimeout { .. } => "read_timeout",
+            DbError::WriteTimeout { .. } => "write_timeout",
+            DbError::ReadFailure { .. } => "read_failure",
+            DbError::WriteFailure { .. } => "write_failure",
+            DbError::Unprepared { .. } => "unprepared",
+            DbError::ServerError => "server_error",
+            DbError::ProtocolError => "protocol_error",
+            DbError::RateLimitReached { .. } => "rate_limit_reached",
+            _ => "other_db_error",
+        },
+        RequestAttemptError::SerializationError(_) => "serialization_error",
+        RequestAttemptError::CqlRequestSerialization(_) => "request_serialization_error",
+        RequestAttemptError::UnableToAllocStreamId => "unable_to_alloc_stream_id",
+        RequestAttemptError::BrokenConnectionError(_) => "broken_connection",
+        RequestAttemptError::BodyExtensionsParseError(_)
+        | RequestAttemptError::CqlResultParseError(_)
+        | RequestAttemptError::CqlErrorParseError(_) => "response_parse_error",
+        RequestAttemptError::UnexpectedResponse(_) => "unexpected_response",
+        RequestAttemptError::RepreparedIdChanged { .. }
+        | RequestAttemptError::RepreparedIdMissingInBatch => "reprepare_error",
+        RequestAttemptError::NonfinishedPagingState => "nonfinished_paging_state",
+    }
+}
+
+/// Upper bounds (in milliseconds) of the buckets of latency histograms
+/// kept for labelled request metrics.
+const LATENCY_BUCKETS_MS: [u64; 14] = [
+    1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 30_000,
+];
+
+/// Labels of a single request attempt.
+pub(crate) struct RequestLabels<'a> {
+    node: &'a Node,
+    kind: RequestKind,
+    table: Option<&'a TableSpec<'a>>,
+}
+
+impl<'a> RequestLabels<'a> {
+    pub(crate) fn new(node: &'a Node, kind: RequestKind, table: Option<&'a TableSpec<'a>>) -> Self {
+        Self { node, kind, table }
+    }
+}
+
+/// Counters of requests sharing the same set of labels.
+#[derive(Debug, Default)]
+struct RequestStats {
+    requests: AtomicU64,
+    retries: AtomicU64,
+    /// Non-cumulative counts; the last bucket counts latencies above all bounds.
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+    latency_sum_us: AtomicU64,
+    // Errors are expected to be rare, so a lock is acceptable here.
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl RequestStats {
+    fn log_attempt(&self, latency: Duration, error: Option<&RequestAttemptError>) {
+        self.requests.fetch_add(1, ORDER_TYPE);
+
+        let latency_ms = latency.as_millis();
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound| latency_ms <= bound as u128)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.latency_buckets[bucket].fetch_add(1, ORDER_TYPE);
+        self.latency_sum_us
+            .fetch_add(latency.as_micros() as u64, ORDER_TYPE);
+
+        if let Some(error) = error {
+            *self
+                .errors
+                .lock()
+                .unwrap()
+                .entry(error_label(error))
+                .or_default() += 1;
+        }
+    }
+
+    fn inc_retries(&self) {
+        self.retries.fetch_add(1, ORDER_TYPE);
+    }
+
+    fn snapshot(&self) -> RequestMetricsSnapshot {
+        let mut cumulative_count = 0;
+        let latency_buckets = LATENCY_BUCKETS_MS
+            .iter()
+            .zip(self.latency_buckets.iter())
+            .map(|(&bound, count)| {
+                cumulative_count += count.load(ORDER_TYPE);
+                (Duration::from_millis(bound), cumulative_count)
+            })
+            .collect();
+        let latency_count =
+            cumulative_count + self.latency_buckets[LATENCY_BUCKETS_MS.len()].load(ORDER_TYPE);
+
+        RequestMetricsSnapshot {
+            requests: self.requests.load(ORDER_TYPE),
+            retries: self.retries.load(ORDER_TYPE),
+            errors: self.errors.lock().unwrap().clone(),
+            latency_buckets,
+            latency_count,
+            latency_sum: Duration::from_micros(self.latency_sum_us.load(ORDER_TYPE)),
+        }
+    }
+}
+
+/// Statistics of request attempts sharing the same set of labels,
+/// collected in a certain moment.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct RequestMetricsSnapshot {
+    /// Number of request attempts (including retries and speculative executions).
+    pub requests: u64,
+    /// Number of times a retry policy decided to retry a failed attempt.
+    pub retries: u64,
+    /// Number of failed attempts, by error type.
+    pub errors: BTreeMap<&'static str, u64>,
+    /// Latency histogram of the attempts: pairs of bucket upper bound and the number
+    /// of attempts that took at most that long. Counts are cumulative.
+    pub latency_buckets: Vec<(Duration, u64)>,
+    /// Number of attempts counted in the latency histogram, including those slower
+    /// than the greatest bucket bound.
+    pub latency_count: u64,
+    /// Total latency of all attempts.
+    pub latency_sum: Duration,
+}
+
+/// Request metrics labelled with the node that the requests were sent to.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct NodeRequestMetrics {
+    pub host_id: Uuid,
+    pub address: SocketAddr,
+    pub datacenter: Option<String>,
+    pub kind: RequestKind,
+    pub metrics: RequestMetricsSnapshot,
+}
+
+/// Request metrics labelled with the table that the requests targeted.
+///
+/// The table is only known for prepared statements
+/// (and batches, whose first statement is prepared).
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct TableRequestMetrics {
+    pub keyspace: String,
+    pub table: String,
+    pub kind: RequestKind,
+    pub metrics: RequestMetricsSnapshot,
+}
+
+#[derive(Debug)]
+struct NodeStats {
+    address: SocketAddr,
+    datacenter: Option<String>,
+    stats: RequestStats,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct TableStatsKey {
+    table: TableSpec<'static>,
+    kind: RequestKind,
+}
+
+#[derive(Debug, Default)]
+struct LabelledMetrics {
+    nodes: RwLock<HashMap<(Uuid, RequestKind), Arc<NodeStats>>>,
+    // See the comment on `TabletsInfo::tablets` about why hashbrown is used here.
+    tables: RwLock<hashbrown::HashMap<TableStatsKey, Arc<RequestStats>>>,
+}
+
+impl LabelledMetrics {
+    fn node_stats(&self, node: &Node, kind: RequestKind) -> Arc<NodeStats> {
+        let key = (node.host_id, kind);
+        if let Some(stats) = self.nodes.read().unwrap().get(&key) {
+            return Arc::clone(stats);
+        }
+        Arc::clone(self.nodes.write().unwrap().entry(key).or_insert_with(|| {
+            Arc::new(NodeStats {
+                address: node.address.into_inner(),
+                datacenter: node.datacenter.clone(),
+                stats: RequestStats::default(),
+            })
+        }))
+    }
+
+    fn table_stats(&self, table: &TableSpec<'_>, kind: RequestKind) -> Arc<RequestStats> {
+        // Must hash the same way as `TableStatsKey`.
+        #[derive(Hash)]
+        struct TableStatsQueryKey<'a> {
+            table: &'a TableSpec<'a>,
+            kind: RequestKind,
+        }
+
+        // Disable the lint, if there is more than one lifetime included.
+        // Can be removed once https://github.com/rust-lang/rust-clippy/issues/12495 is fixed.
+        #[allow(clippy::needless_lifetimes)]
+        impl<'query> hashbrown::Equivalent<TableStatsKey> for TableStatsQueryKey<'query> {
+            fn equivalent(&self, key: &TableStatsKey) -> bool {
+                self.table == &key.table && self.kind == key.kind
+            }
+        }
+
+        let query_key = TableStatsQueryKey { table, kind };
+        if let Some(stats) = self.tables.read().unwrap().get(&query_key) {
+            return Arc::clone(stats);
+        }
+        let key = TableStatsKey {
+            table: table.to_owned(),
+            kind,
+        };
+        Arc::clone(self.tables.write().unwrap().entry(key).or_default())
+    }
+
+    fn for_each_stats(&self, labels: &RequestLabels<'_>, f: impl Fn(&RequestStats)) {
+        f(&self.node_stats(labels.node, labels.kind).stats);
+        if let Some(table) = labels.table {
+            f(&self.table_stats(table, labels.kind));
+        }
+    }
+}
+
 pub struct Metrics {
     errors_num: AtomicU64,
     queries_num: AtomicU64,
     errors_iter_num: AtomicU64,
     queries_iter_num: AtomicU64,
     retries_num: AtomicU64,
+    retry_budget_exhausted_num: AtomicU64,
     histogram: Arc<AtomicHistogram>,
     meter: Arc<RequestRateMeter>,
     total_connections: AtomicU64,
     connection_timeouts: AtomicU64,
     request_timeouts: AtomicU64,
+    labelled: LabelledMetrics,
 }
 
 impl Metrics {
@@ -234,11 +511,13 @@ impl Metrics {
             errors_iter_num: AtomicU64::new(0),
             queries_iter_num: AtomicU64::new(0),
             retries_num: AtomicU64::new(0),
+            retry_budget_exhausted_num: AtomicU64::new(0),
             histogram: Arc::new(AtomicHistogram::new(grouping_power, max_value_power).unwrap()),
             meter: Arc::new(RequestRateMeter::new()),
             total_connections: AtomicU64::new(0),
             connection_timeouts: AtomicU64::new(0),
             request_timeouts: AtomicU64::new(0),
+            labelled: LabelledMetrics::default(),
         }
     }
 
@@ -270,6 +549,12 @@ impl Metrics {
         self.retries_num.fetch_add(1, ORDER_TYPE);
     }
 
+    /// Increments counter measuring how many times a decision to retry a query
+    /// was overridden because the retry budget was exhausted
+    pub(crate) fn inc_retry_budget_exhausted_num(&self) {
+        self.retry_budget_exhausted_num.fetch_add(1, ORDER_TYPE);
+    }
+
     /// Increments counter for active number of connections to the cluster.
     /// Should be called when opening new connections, once per connection.
     pub(crate) fn inc_total_connections(&self) {
@@ -292,6 +577,24 @@ impl Metrics {
         self.request_timeouts.fetch_add(1, ORDER_TYPE);
     }
 
+    /// Logs a single request attempt (successful or not) in labelled metrics.
+    pub(crate) fn log_request_attempt(
+        &self,
+        labels: &RequestLabels<'_>,
+        latency: Duration,
+        error: Option<&RequestAttemptError>,
+    ) {
+        self.labelled
+            .for_each_stats(labels, |stats| stats.log_attempt(latency, error));
+    }
+
+    /// Increments labelled counters measuring how many times a retry policy
+    /// has decided to retry a request attempt.
+    pub(crate) fn inc_labelled_retries(&self, labels: &RequestLabels<'_>) {
+        self.labelled
+            .for_each_stats(labels, RequestStats::inc_retries);
+    }
+
     /// Saves to histogram latency of completing single query.
     /// For paged queries it should log latency for every page.
     ///
@@ -387,6 +690,12 @@ impl Metrics {
         self.retries_num.load(ORDER_TYPE)
     }
 
+    /// Returns counter measuring how many times a decision to retry a query
+    /// was overridden because the retry budget was exhausted
+    pub fn get_retry_budget_exhausted_num(&self) -> u64 {
+        self.retry_budget_exhausted_num.load(ORDER_TYPE)
+    }
+
     /// Returns mean rate of queries per second
     pub fn get_mean_rate(&self) -> f64 {
         self.meter.mean_rate()
@@ -422,6 +731,144 @@ impl Metrics {
         self.request_timeouts.load(ORDER_TYPE)
     }
 
+    /// Returns request metrics labelled with the node and the request kind.
+    ///
+    /// Per-datacenter metrics can be obtained by aggregating metrics of nodes
+    /// with the same datacenter.
+    pub fn get_node_request_metrics(&self) -> Vec<NodeRequestMetrics> {
+        let mut metrics: Vec<_> = self
+            .labelled
+            .nodes
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(&(host_id, kind), node_stats)| NodeRequestMetrics {
+                host_id,
+                address: node_stats.address,
+                datacenter: node_stats.datacenter.clone(),
+                kind,
+                metrics: node_stats.stats.snapshot(),
+            })
+            .collect();
+        metrics.sort_unstable_by_key(|m| (m.address, m.kind));
+        metrics
+    }
+
+    /// Returns request metrics labelled with the table and the request kind.
+    pub fn get_table_request_metrics(&self) -> Vec<TableRequestMetrics> {
+        let mut metrics: Vec<_> = self
+            .labelled
+            .tables
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(key, stats)| TableRequestMetrics {
+                keyspace: key.table.ks_name().to_owned(),
+                table: key.table.table_name().to_owned(),
+                kind: key.kind,
+                metrics: stats.snapshot(),
+            })
+            .collect();
+        metrics.sort_unstable_by(|a, b| {
+            (&a.keyspace, &a.table, a.kind).cmp(&(&b.keyspace, &b.table, b.kind))
+        });
+        metrics
+    }
+
+    /// Returns all metrics in the OpenMetrics text format, ready to be served
+    /// to Prometheus (or other compatible scrapers) with [`OPENMETRICS_CONTENT_TYPE`].
+    pub fn export_openmetrics(&self) -> String {
+        let mut out = String::new();
+        // Writing to a String never fails.
+        let _ = self.write_openmetrics(&mut out);
+        out
+    }
+
+    /// Writes all metrics in the OpenMetrics text format into `out`.
+    /// See [`Metrics::export_openmetrics`].
+    pub fn write_openmetrics(&self, out: &mut impl std::fmt::Write) -> std::fmt::Result {
+        let global_counters = [
+            (
+                "scylla_nonpaged_queries",
+                "Number of nonpaged request attempts.",
+                self.get_queries_num(),
+            ),
+            (
+                "scylla_nonpaged_query_errors",
+                "Number of failed nonpaged request attempts.",
+                self.get_errors_num(),
+            ),
+            (
+                "scylla_paged_queries",
+                "Number of page request attempts in paged queries.",
+                self.get_queries_iter_num(),
+            ),
+            (
+                "scylla_paged_query_errors",
+                "Number of failed page request attempts in paged queries.",
+                self.get_errors_iter_num(),
+            ),
+            (
+                "scylla_retries",
+                "Number of times a retry policy decided to retry a request.",
+                self.get_retries_num(),
+            ),
+            (
+                "scylla_retry_budget_exhausted",
+                "Number of retries not performed because the retry budget was exhausted.",
+                self.get_retry_budget_exhausted_num(),
+            ),
+            (
+                "scylla_connection_timeouts",
+                "Number of timeouts when opening connections.",
+                self.get_connection_timeouts(),
+            ),
+            (
+                "scylla_request_timeouts",
+                "Number of client-side request timeouts.",
+                self.get_request_timeouts(),
+            ),
+        ];
+        for (name, help, value) in global_counters {
+            writeln!(out, "# TYPE {name} counter")?;
+            writeln!(out, "# HELP {name} {help}")?;
+            writeln!(out, "{name}_total {value}")?;
+        }
+        writeln!(out, "# TYPE scylla_connections gauge")?;
+        writeln!(out, "# HELP scylla_connections Number of open connections.")?;
+        writeln!(out, "scylla_connections {}", self.get_total_connections())?;
+
+        let node_metrics: Vec<_> = self
+            .get_node_request_metrics()
+            .into_iter()
+            .map(|m| {
+                let labels = vec![
+                    ("node", m.address.to_string()),
+                    ("datacenter", m.datacenter.unwrap_or_default()),
+                    ("kind", m.kind.as_str().to_owned()),
+                ];
+                (labels, m.metrics)
+            })
+            .collect();
+        write_request_metrics(out, "scylla_node", "node", &node_metrics)?;
+
+        let table_metrics: Vec<_> = self
+            .get_table_request_metrics()
+            .into_iter()
+            .map(|m| {
+                let labels = vec![
+                    ("keyspace", m.keyspace),
+                    ("table", m.table),
+                    ("kind", m.kind.as_str().to_owned()),
+                ];
+                (labels, m.metrics)
+            })
+            .collect();
+        write_request_metrics(out, "scylla_table", "table", &table_metrics)?;
+
+        writeln!(out, "# EOF")
+    }
+
     // Metric implementations
 
     // histogram crate used to implement Histogram::mean() method. Why did they remove it?
@@ -510,6 +957,123 @@ impl Metrics {
     }
 }
 
+/// Content type of the OpenMetrics text format, as returned by [`Metrics::export_openmetrics`].
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+type Labels = Vec<(&'static str, String)>;
+
+/// Writes metric families of labelled request metrics.
+fn write_request_metrics(
+    out: &mut impl std::fmt::Write,
+    prefix: &str,
+    label_description: &str,
+    metrics: &[(Labels, RequestMetricsSnapshot)],
+) -> std::fmt::Result {
+    writeln!(out, "# TYPE {prefix}_requests counter")?;
+    writeln!(
+        out,
+        "# HELP {prefix}_requests Number of request attempts, by {label_description}."
+    )?;
+    for (labels, snapshot) in metrics {
+        write_sample(out, prefix, "_requests_total", labels, None)?;
+        writeln!(out, " {}", snapshot.requests)?;
+    }
+
+    writeln!(out, "# TYPE {prefix}_request_errors counter")?;
+    writeln!(
+        out,
+        "# HELP {prefix}_request_errors Number of failed request attempts, by {label_description} and error type."
+    )?;
+    for (labels, snapshot) in metrics {
+        for (error, count) in &snapshot.errors {
+            write_sample(
+                out,
+                prefix,
+                "_request_errors_total",
+                labels,
+                Some(("error", error)),
+            )?;
+            writeln!(out, " {count}")?;
+        }
+    }
+
+    writeln!(out, "# TYPE {prefix}_retries counter")?;
+    writeln!(
+        out,
+        "# HELP {prefix}_retries Number of retried request attempts, by {label_description}."
+    )?;
+    for (labels, snapshot) in metrics {
+        write_sample(out, prefix, "_retries_total", labels, None)?;
+        writeln!(out, " {}", snapshot.retries)?;
+    }
+
+    writeln!(out, "# TYPE {prefix}_request_latency_seconds histogram")?;
+    writeln!(out, "# UNIT {prefix}_request_latency_seconds seconds")?;
+    writeln!(
+        out,
+        "# HELP {prefix}_request_latency_seconds Latency of request attempts, by {label_description}."
+    )?;
+    for (labels, snapshot) in metrics {
+        for (bound, count) in &snapshot.latency_buckets {
+            let le = format!("{:?}", bound.as_secs_f64());
+            write_sample(
+                out,
+                prefix,
+                "_request_latency_seconds_bucket",
+                labels,
+                Some(("le", &le)),
+            )?;
+            writeln!(out, " {count}")?;
+        }
+        write_sample(
+            out,
+            prefix,
+            "_request_latency_seconds_bucket",
+            labels,
+            Some(("le", "+Inf")),
+        )?;
+        writeln!(out, " {}", snapshot.latency_count)?;
+        write_sample(out, prefix, "_request_latency_seconds_count", labels, None)?;
+        writeln!(out, " {}", snapshot.latency_count)?;
+        write_sample(out, prefix, "_request_latency_seconds_sum", labels, None)?;
+        writeln!(out, " {:?}", snapshot.latency_sum.as_secs_f64())?;
+    }
+
+    Ok(())
+}
+
+/// Writes the name and labels of a single sample, without its value.
+fn write_sample(
+    out: &mut impl std::fmt::Write,
+    prefix: &str,
+    suffix: &str,
+    labels: &Labels,
+    extra_label: Option<(&str, &str)>,
+) -> std::fmt::Result {
+    write!(out, "{prefix}{suffix}{{")?;
+    let all_labels = labels
+        .iter()
+        .map(|(name, value)| (*name, value.as_str()))
+        .chain(extra_label);
+    for (i, (name, value)) in all_labels.enumerate() {
+        if i > 0 {
+            out.write_char(',')?;
+        }
+        write!(out, "{name}=\"")?;
+        for c in value.chars() {
+            match c {
+                '\\' => out.write_str("\\\\")?,
+                '"' => out.write_str("\\\"")?,
+                '\n' => out.write_str("\\n")?,
+                c => out.write_char(c)?,
+            }
+        }
+        out.write_char('"')?;
+    }
+    out.write_char('}')
+}
+
 #[cfg(test)]
 impl Default for Metrics {
     fn default() -> Self {
@@ -526,22 +1090,34 @@ impl std::fmt::Debug for Metrics {
             .field("errors_iter_num", &self.errors_iter_num)
             .field("queries_iter_num", &self.queries_iter_num)
             .field("retries_num", &self.retries_num)
+            .field(
+                "retry_budget_exhausted_num",
+                &self.retry_budget_exhausted_num,
+            )
             .field("histogram", &h)
             .field("meter", &self.meter)
             .field("total_connections", &self.total_connections)
             .field("connection_timeouts", &self.connection_timeouts)
             .field("request_timeouts", &self.request_timeouts)
+            .field("labelled", &self.labelled)
             .finish()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
     use rand::{Rng, SeedableRng};
+    use scylla_cql::frame::response::error::DbError;
+    use scylla_cql::frame::response::result::TableSpec;
 
+    use crate::cluster::node::{Node, NodeAddr};
+    use crate::errors::RequestAttemptError;
     use crate::observability::metrics::Snapshot;
 
-    use super::Metrics;
+    use super::{Metrics, RequestKind, RequestLabels};
 
     // A regression test for a bug where we would return
     // the number of observations in the bucket for the given percentile.
@@ -617,4 +1193,101 @@ mod tests {
         test_with_seed(42);
         test_with_seed(0xDEADCAFE);
     }
+
+    #[test]
+    fn test_labelled_request_metrics() {
+        let metrics = Metrics::new();
+        let node = Node::new_for_test(
+            None,
+            Some(NodeAddr::Translatable(SocketAddr::from((
+                [127, 0, 0, 1],
+                9042,
+            )))),
+            Some("dc1".to_owned()),
+            None,
+        );
+        let table = TableSpec::borrowed("ks", "t");
+        let labels = RequestLabels::new(&node, RequestKind::Prepared, Some(&table));
+        let timeout_error = RequestAttemptError::DbError(DbError::Overloaded, String::new());
+
+        metrics.log_request_attempt(&labels, Duration::from_millis(3), None);
+        metrics.log_request_attempt(&labels, Duration::from_secs(60), Some(&timeout_error));
+        metrics.inc_labelled_retries(&labels);
+        metrics.log_request_attempt(
+            &RequestLabels::new(&node, RequestKind::Unprepared, None),
+            Duration::from_millis(1),
+            None,
+        );
+
+        let node_metrics = metrics.get_node_request_metrics();
+        assert_eq!(node_metrics.len(), 2);
+        assert_eq!(node_metrics[0].kind, RequestKind::Unprepared);
+        assert_eq!(node_metrics[0].metrics.requests, 1);
+        let prepared = &node_metrics[1];
+        assert_eq!(prepared.kind, RequestKind::Prepared);
+        assert_eq!(prepared.datacenter.as_deref(), Some("dc1"));
+        assert_eq!(prepared.metrics.requests, 2);
+        assert_eq!(prepared.metrics.retries, 1);
+        assert_eq!(prepared.metrics.errors.get("overloaded"), Some(&1));
+        assert_eq!(prepared.metrics.latency_count, 2);
+        // 3ms falls into the 5ms bucket; 60s exceeds all buckets.
+        assert_eq!(
+            prepared.metrics.latency_buckets[..3],
+            [
+                (Duration::from_millis(1), 0),
+                (Duration::from_millis(2), 0),
+                (Duration::from_millis(5), 1)
+            ]
+        );
+        assert_eq!(prepared.metrics.latency_buckets.last().unwrap().1, 1);
+
+        // Unprepared statements have no table.
+        let table_metrics = metrics.get_table_request_metrics();
+        assert_eq!(table_metrics.len(), 1);
+        assert_eq!(table_metrics[0].keyspace, "ks");
+        assert_eq!(table_metrics[0].table, "t");
+        assert_eq!(table_metrics[0].metrics.requests, 2);
+    }
+
+    #[test]
+    fn test_openmetrics_export() {
+        let metrics = Metrics::new();
+        let node = Node::new_for_test(
+            None,
+            Some(NodeAddr::Translatable(SocketAddr::from((
+                [127, 0, 0, 1],
+                9042,
+            )))),
+            Some("dc\"1".to_owned()),
+            None,
+        );
+        let table = TableSpec::borrowed("ks", "t");
+        let labels = RequestLabels::new(&node, RequestKind::Batch, Some(&table));
+        metrics.inc_total_nonpaged_queries();
+        metrics.log_request_attempt(
+            &labels,
+            Duration::from_millis(1500),
+            Some(&RequestAttemptError::UnableToAllocStreamId),
+        );
+
+        let exported = metrics.export_openmetrics();
+        let lines: Vec<&str> = exported.lines().collect();
+
+        for expected in [
+            "# TYPE scylla_nonpaged_queries counter",
+            "scylla_nonpaged_queries_total 1",
+            "scylla_connections 0",
+            r#"scylla_node_requests_total{node="127.0.0.1:9042",datacenter="dc\"1",kind="batch"} 1"#,
+            r#"scylla_node_request_errors_total{node="127.0.0.1:9042",datacenter="dc\"1",kind="batch",error="unable_to_alloc_stream_id"} 1"#,
+            r#"scylla_node_request_latency_seconds_bucket{node="127.0.0.1:9042",datacenter="dc\"1",kind="batch",le="1.0"} 0"#,
+            r#"scylla_node_request_latency_seconds_bucket{node="127.0.0.1:9042",datacenter="dc\"1",kind="batch",le="2.0"} 1"#,
+            r#"scylla_node_request_latency_seconds_bucket{node="127.0.0.1:9042",datacenter="dc\"1",kind="batch",le="+Inf"} 1"#,
+            r#"scylla_node_request_latency_seconds_sum{node="127.0.0.1:9042",datacenter="dc\"1",kind="batch"} 1.5"#,
+            r#"scylla_table_requests_total{keyspace="ks",table="t",kind="batch"} 1"#,
+            r#"scylla_table_retries_total{keyspace="ks",table="t",kind="batch"} 0"#,
+        ] {
+            assert!(lines.contains(&expected), "missing line: {expected}");
+        }
+        assert_eq!(lines.last(), Some(&"# EOF"));
+    }
 }
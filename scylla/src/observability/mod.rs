@@ -3,10 +3,13 @@
 //! - driver-side tracing,
 //! - cluster-side tracing,
 //! - request execution history,
-//! - driver metrics.
+//! - driver metrics,
+//! - OpenTelemetry trace context propagation.
 
 pub(crate) mod driver_tracing;
 pub mod history;
 #[cfg(feature = "metrics")]
 pub mod metrics;
+#[cfg(feature = "opentelemetry")]
+pub(crate) mod opentelemetry;
 pub mod tracing;
@@ -0,0 +1,83 @@
+//! Propagation of OpenTelemetry trace context to the cluster.
+//!
+//! The context of the current [tracing] span (as seen by the
+//! `tracing-opentelemetry` layer) is injected using the globally configured
+//! text map propagator, and sent to the node in the custom payload of
+//! QUERY, EXECUTE, BATCH and PREPARE requests. With the W3C propagator this
+//! results in a `traceparent` (and possibly `tracestate`) entry.
+
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use opentelemetry::trace::TraceContextExt;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Returns the custom payload carrying the trace context of the current span,
+/// or `None` if there is no valid context to propagate.
+pub(crate) fn current_context_payload() -> Option<HashMap<String, Bytes>> {
+    let cx = tracing::Span::current().context();
+    if !cx.span().span_context().is_valid() {
+        return None;
+    }
+
+    let mut fields = HashMap::new();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&cx, &mut fields)
+    });
+    if fields.is_empty() {
+        return None;
+    }
+
+    Some(
+        fields
+            .into_iter()
+            .map(|(key, value)| (key, Bytes::from(value)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use opentelemetry::trace::{TraceContextExt as _, TracerProvider as _};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing::Subscriber;
+    use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    use super::current_context_payload;
+
+    /// Returns a subscriber exporting the spans to OpenTelemetry, and sets up
+    /// the global propagator of the W3C trace context.
+    pub(crate) fn otel_subscriber() -> impl Subscriber + Send + Sync {
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let tracer = SdkTracerProvider::builder().build().tracer("scylla-test");
+        tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer))
+    }
+
+    /// Returns the `traceparent` header of the span in the W3C trace context format.
+    pub(crate) fn traceparent(span: &tracing::Span) -> String {
+        let cx = span.context();
+        let span_context = cx.span().span_context().clone();
+        format!(
+            "00-{}-{}-01",
+            span_context.trace_id(),
+            span_context.span_id()
+        )
+    }
+
+    #[test]
+    fn payload_carries_context_of_current_span() {
+        tracing::subscriber::with_default(otel_subscriber(), || {
+            // There is no span to propagate.
+            assert_eq!(current_context_payload(), None);
+
+            let span = tracing::info_span!("request");
+            let payload = span.in_scope(current_context_payload).unwrap();
+            assert_eq!(
+                payload.get("traceparent").map(|value| value.as_ref()),
+                Some(traceparent(&span).as_bytes())
+            );
+        });
+    }
+}
@@ -148,6 +148,7 @@ mod tests {
             error,
             is_idempotent,
             consistency: Consistency::One,
+            retry_budget_exhausted: false,
         }
     }
 
@@ -206,6 +206,7 @@ mod tests {
             error,
             is_idempotent,
             consistency: cl,
+            retry_budget_exhausted: false,
         }
     }
 
@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use rand::Rng;
+use scylla_cql::frame::response::error::DbError;
+
+use crate::errors::RequestAttemptError;
+
+use super::{
+    DefaultRetrySession, RequestInfo, RetryDecision, RetryPolicy, RetrySession, RetryTarget,
+};
+
+/// Exponential backoff retry policy - retries in the same cases as [DefaultRetryPolicy](super::DefaultRetryPolicy),
+/// but backs off before retrying when the cluster signals that it is overloaded.
+///
+/// Requests failing with `Overloaded` or `RateLimitReached` are retried after a delay
+/// which grows exponentially with each retry, starting at `base_delay` and capped at `max_delay`.
+/// The delay is randomized ("full jitter"), so that many requests failing at the same time
+/// don't hit the cluster again all at once.
+///
+/// The total number of retries of a single request is limited by `max_retries`.
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoffRetryPolicy {
+    base_delay: Duration,
+    max_delay: Duration,
+    max_retries: usize,
+}
+
+impl ExponentialBackoffRetryPolicy {
+    /// Creates the policy with the default parameters:
+    /// 100ms base delay, 10s max delay and at most 3 retries per request.
+    pub fn new() -> ExponentialBackoffRetryPolicy {
+        ExponentialBackoffRetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            max_retries: 3,
+        }
+    }
+
+    /// Sets the upper bound of the delay before the first retry.
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Sets the upper bound of the delay before any retry.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets the maximum number of retries of a single request.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+}
+
+impl Default for ExponentialBackoffRetryPolicy {
+    fn default() -> ExponentialBackoffRetryPolicy {
+        ExponentialBackoffRetryPolicy::new()
+    }
+}
+
+impl RetryPolicy for ExponentialBackoffRetryPolicy {
+    fn new_session(&self) -> Box<dyn RetrySession> {
+        Box::new(ExponentialBackoffRetrySession::new(self.clone()))
+    }
+}
+
+/// A retry session of [ExponentialBackoffRetryPolicy], created for each request.
+///
+/// Counts the retries of the request, so that the delays grow with each retry
+/// and the request is not retried more than `max_retries` times.
+pub struct ExponentialBackoffRetrySession {
+    policy: ExponentialBackoffRetryPolicy,
+    retries: usize,
+    default_session: DefaultRetrySession,
+}
+
+impl ExponentialBackoffRetrySession {
+    /// Creates a session of the policy, with no retries made yet.
+    pub fn new(policy: ExponentialBackoffRetryPolicy) -> ExponentialBackoffRetrySession {
+        ExponentialBackoffRetrySession {
+            policy,
+            retries: 0,
+            default_session: DefaultRetrySession::new(),
+        }
+    }
+
+    fn backoff_delay(&self) -> Duration {
+        backoff_delay(self.policy.base_delay, self.policy.max_delay, self.retries)
+    }
+}
+
+/// The upper bound of the delay before the next retry, after `retries` retries,
+/// i.e. `min(max_delay, base_delay * 2^retries)`.
+fn delay_bound(base_delay: Duration, max_delay: Duration, retries: usize) -> Duration {
+    let factor = 1u32.checked_shl(retries as u32).unwrap_or(u32::MAX);
+    base_delay.saturating_mul(factor).min(max_delay)
+}
+
+/// A random delay before the next retry, after `retries` retries,
+/// up to [delay_bound] ("full jitter").
+pub(crate) fn backoff_delay(base_delay: Duration, max_delay: Duration, retries: usize) -> Duration {
+    let bound = delay_bound(base_delay, max_delay, retries);
+    if bound.is_zero() {
+        return bound;
+    }
+    rand::rng().random_range(Duration::ZERO..=bound)
+}
+
+impl RetrySession for ExponentialBackoffRetrySession {
+    fn decide_should_retry(&mut self, request_info: RequestInfo) -> RetryDecision {
+        if self.retries >= self.policy.max_retries {
+            return RetryDecision::DontRetry;
+        }
+
+        let decision = match request_info.error {
+            // The coordinator is overloaded - back off and try another one.
+            RequestAttemptError::DbError(DbError::Overloaded, _)
+                if request_info.is_idempotent && !request_info.consistency.is_serial() =>
+            {
+                RetryDecision::RetryAfter(self.backoff_delay(), RetryTarget::NextTarget, None)
+            }
+            // The rate limit of the partition was exceeded. The limit is enforced
+            // by the replicas, so another coordinator won't help - just back off.
+            RequestAttemptError::DbError(DbError::RateLimitReached { .. }, _)
+                if request_info.is_idempotent =>
+            {
+                RetryDecision::RetryAfter(self.backoff_delay(), RetryTarget::SameTarget, None)
+            }
+            _ => self.default_session.decide_should_retry(request_info),
+        };
+
+        if decision != RetryDecision::DontRetry && decision != RetryDecision::IgnoreWriteError {
+            self.retries += 1;
+        }
+        decision
+    }
+
+    fn reset(&mut self) {
+        self.retries = 0;
+        self.default_session.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ExponentialBackoffRetryPolicy, RequestInfo, RetryDecision, RetryPolicy};
+    use crate::errors::{DbError, OperationType, RequestAttemptError};
+    use crate::policies::retry::RetryTarget;
+    use crate::statement::Consistency;
+    use crate::test_utils::setup_tracing;
+
+    fn make_request_info(error: &RequestAttemptError, is_idempotent: bool) -> RequestInfo<'_> {
+        RequestInfo {
+            error,
+            is_idempotent,
+            consistency: Consistency::One,
+            retry_budget_exhausted: false,
+        }
+    }
+
+    fn assert_retry_after(
+        decision: RetryDecision,
+        expected_target: RetryTarget,
+        max_delay: Duration,
+    ) {
+        match decision {
+            RetryDecision::RetryAfter(delay, target, None) => {
+                assert_eq!(target, expected_target);
+                assert!(delay <= max_delay, "{:?} > {:?}", delay, max_delay);
+            }
+            other => panic!("Expected RetryAfter, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn exponential_backoff_overloaded() {
+        setup_tracing();
+        let error = RequestAttemptError::DbError(DbError::Overloaded, String::new());
+        let policy = ExponentialBackoffRetryPolicy::new()
+            .with_base_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(300))
+            .with_max_retries(4);
+
+        let mut session = policy.new_session();
+        for max_delay in [100, 200, 300, 300] {
+            assert_retry_after(
+                session.decide_should_retry(make_request_info(&error, true)),
+                RetryTarget::NextTarget,
+                Duration::from_millis(max_delay),
+            );
+        }
+        // The retry budget is exhausted.
+        assert_eq!(
+            session.decide_should_retry(make_request_info(&error, true)),
+            RetryDecision::DontRetry
+        );
+
+        // Non-idempotent requests are not retried.
+        let mut session = policy.new_session();
+        assert_eq!(
+            session.decide_should_retry(make_request_info(&error, false)),
+            RetryDecision::DontRetry
+        );
+    }
+
+    #[test]
+    fn exponential_backoff_rate_limit_reached() {
+        setup_tracing();
+        let error = RequestAttemptError::DbError(
+            DbError::RateLimitReached {
+                op_type: OperationType::Write,
+                rejected_by_coordinator: false,
+            },
+            String::new(),
+        );
+        let mut session = ExponentialBackoffRetryPolicy::new().new_session();
+        assert_retry_after(
+            session.decide_should_retry(make_request_info(&error, true)),
+            RetryTarget::SameTarget,
+            Duration::from_millis(100),
+        );
+        assert_eq!(
+            ExponentialBackoffRetryPolicy::new()
+                .new_session()
+                .decide_should_retry(make_request_info(&error, false)),
+            RetryDecision::DontRetry
+        );
+    }
+
+    #[test]
+    fn exponential_backoff_falls_back_to_default() {
+        setup_tracing();
+        let error = RequestAttemptError::DbError(DbError::IsBootstrapping, String::new());
+        let mut session = ExponentialBackoffRetryPolicy::new()
+            .with_max_retries(1)
+            .new_session();
+        assert_eq!(
+            session.decide_should_retry(make_request_info(&error, false)),
+            RetryDecision::RetryNextTarget(None)
+        );
+        // Retries decided by the default policy count towards the retry budget.
+        assert_eq!(
+            session.decide_should_retry(make_request_info(&error, false)),
+            RetryDecision::DontRetry
+        );
+
+        session.reset();
+        assert_eq!(
+            session.decide_should_retry(make_request_info(&error, false)),
+            RetryDecision::RetryNextTarget(None)
+        );
+    }
+}
@@ -1,11 +1,17 @@
 mod default;
 mod downgrading_consistency;
+mod exponential_backoff;
 mod fallthrough;
+mod retry_budget;
 mod retry_policy;
 
 pub use default::{DefaultRetryPolicy, DefaultRetrySession};
 pub use downgrading_consistency::{
     DowngradingConsistencyRetryPolicy, DowngradingConsistencyRetrySession,
 };
+pub(crate) use exponential_backoff::backoff_delay;
+pub use exponential_backoff::{ExponentialBackoffRetryPolicy, ExponentialBackoffRetrySession};
 pub use fallthrough::{FallthroughRetryPolicy, FallthroughRetrySession};
-pub use retry_policy::{RequestInfo, RetryDecision, RetryPolicy, RetrySession};
+pub use retry_budget::RetryBudget;
+pub(crate) use retry_budget::RetryBudgetTracker;
+pub use retry_policy::{RequestInfo, RetryDecision, RetryPolicy, RetrySession, RetryTarget};
@@ -0,0 +1,261 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use super::RetryDecision;
+
+/// A session-wide limit on the number of retries, relative to the number of requests.
+///
+/// Retry policies decide about each request separately, so when the cluster becomes
+/// overloaded, every request is retried and the load on the cluster is multiplied.
+/// The retry budget prevents such retry amplification: within a sliding `window`,
+/// the session performs at most `ratio * requests + min_retries_per_second * window`
+/// retries. Once the budget is exhausted, failed requests are not retried,
+/// regardless of the decision of the retry policy.
+///
+/// Whether the budget is exhausted is also passed to the retry policy,
+/// see [RequestInfo::retry_budget_exhausted](super::RequestInfo::retry_budget_exhausted).
+///
+/// The budget is set in [SessionBuilder](crate::client::session_builder::GenericSessionBuilder::retry_budget).
+/// By default, the number of retries is not limited.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryBudget {
+    ratio: f64,
+    min_retries_per_second: u32,
+    window: Duration,
+}
+
+impl RetryBudget {
+    /// Creates a budget allowing `ratio` retries per request (e.g. `0.1` for one retry per ten requests),
+    /// with the default floor of 10 retries per second and the default window of 10 seconds.
+    ///
+    /// The ratio must be a finite, non-negative number. Negative and NaN ratios are treated as `0.0`,
+    /// i.e. only the floor of retries per second is allowed, and an infinite ratio is clamped
+    /// to [f64::MAX].
+    pub fn new(ratio: f64) -> Self {
+        let ratio = if ratio.is_nan() {
+            0.0
+        } else {
+            ratio.clamp(0.0, f64::MAX)
+        };
+        Self {
+            ratio,
+            min_retries_per_second: 10,
+            window: Duration::from_secs(10),
+        }
+    }
+
+    /// Sets the number of retries per second which are allowed regardless of the number of requests,
+    /// so that a session issuing few requests can still retry them.
+    pub fn with_min_retries_per_second(mut self, min_retries_per_second: u32) -> Self {
+        self.min_retries_per_second = min_retries_per_second;
+        self
+    }
+
+    /// Sets the length of the sliding window over which the requests and the retries are counted.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// The number of retries allowed per request.
+    pub fn get_ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// The number of retries per second allowed regardless of the number of requests.
+    pub fn get_min_retries_per_second(&self) -> u32 {
+        self.min_retries_per_second
+    }
+
+    /// The length of the sliding window over which the requests and the retries are counted.
+    pub fn get_window(&self) -> Duration {
+        self.window
+    }
+}
+
+/// The number of buckets the sliding window is divided into.
+const WINDOW_BUCKETS: usize = 10;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    epoch: u64,
+    requests: u64,
+    retries: u64,
+}
+
+/// Tracks the requests and the retries of a session and enforces its [RetryBudget].
+#[derive(Debug)]
+pub(crate) struct RetryBudgetTracker {
+    ratio: f64,
+    min_retries: f64,
+    bucket_len: Duration,
+    start: Instant,
+    buckets: Mutex<[Bucket; WINDOW_BUCKETS]>,
+}
+
+impl RetryBudgetTracker {
+    pub(crate) fn new(budget: &RetryBudget) -> Self {
+        Self {
+            ratio: budget.ratio,
+            min_retries: budget.min_retries_per_second as f64 * budget.window.as_secs_f64(),
+            bucket_len: (budget.window / WINDOW_BUCKETS as u32).max(Duration::from_millis(1)),
+            start: Instant::now(),
+            buckets: Mutex::new([Bucket::default(); WINDOW_BUCKETS]),
+        }
+    }
+
+    /// Records a new request, which increases the budget.
+    pub(crate) fn record_request(&self) {
+        let epoch = self.current_epoch();
+        let mut buckets = self.buckets.lock().unwrap();
+        Self::bucket_mut(&mut buckets, epoch).requests += 1;
+    }
+
+    /// Checks whether there is no budget left for a retry.
+    pub(crate) fn is_exhausted(&self) -> bool {
+        let epoch = self.current_epoch();
+        let buckets = self.buckets.lock().unwrap();
+        !self.has_budget(&buckets, epoch)
+    }
+
+    /// If the decision is to retry, withdraws the retry from the budget.
+    /// Returns false if the decision is to retry, but the budget is exhausted.
+    pub(crate) fn try_withdraw(&self, decision: &RetryDecision) -> bool {
+        match decision {
+            RetryDecision::RetrySameTarget(_)
+            | RetryDecision::RetryNextTarget(_)
+            | RetryDecision::RetryAfter(..) => (),
+            RetryDecision::DontRetry | RetryDecision::IgnoreWriteError => return true,
+        }
+
+        let epoch = self.current_epoch();
+        let mut buckets = self.buckets.lock().unwrap();
+        if !self.has_budget(&buckets, epoch) {
+            return false;
+        }
+        Self::bucket_mut(&mut buckets, epoch).retries += 1;
+        true
+    }
+
+    fn current_epoch(&self) -> u64 {
+        (self.start.elapsed().as_nanos() / self.bucket_len.as_nanos()) as u64
+    }
+
+    fn bucket_mut(buckets: &mut [Bucket; WINDOW_BUCKETS], epoch: u64) -> &mut Bucket {
+        let bucket = &mut buckets[epoch as usize % WINDOW_BUCKETS];
+        if bucket.epoch != epoch {
+            // The bucket is reused after it has fallen out of the window.
+            *bucket = Bucket {
+                epoch,
+                ..Default::default()
+            };
+        }
+        bucket
+    }
+
+    fn has_budget(&self, buckets: &[Bucket; WINDOW_BUCKETS], epoch: u64) -> bool {
+        let (requests, retries) = buckets
+            .iter()
+            .filter(|bucket| epoch - bucket.epoch < WINDOW_BUCKETS as u64)
+            .fold((0, 0), |(requests, retries), bucket| {
+                (requests + bucket.requests, retries + bucket.retries)
+            });
+        (retries as f64) < self.min_retries + self.ratio * requests as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::policies::retry::{RetryDecision, RetryTarget};
+
+    use super::{RetryBudget, RetryBudgetTracker};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_budget() {
+        let budget = RetryBudget::new(0.5)
+            .with_min_retries_per_second(0)
+            .with_window(Duration::from_secs(10));
+        let tracker = RetryBudgetTracker::new(&budget);
+        let retry = RetryDecision::RetryNextTarget(None);
+
+        // No requests - no budget.
+        assert!(tracker.is_exhausted());
+        assert!(!tracker.try_withdraw(&retry));
+        // Decisions not to retry are always allowed.
+        assert!(tracker.try_withdraw(&RetryDecision::DontRetry));
+
+        for _ in 0..4 {
+            tracker.record_request();
+        }
+        assert!(!tracker.is_exhausted());
+        assert!(tracker.try_withdraw(&retry));
+        assert!(tracker.try_withdraw(&RetryDecision::RetryAfter(
+            Duration::from_millis(10),
+            RetryTarget::SameTarget,
+            None
+        )));
+        assert!(tracker.is_exhausted());
+        assert!(!tracker.try_withdraw(&retry));
+
+        // The requests fall out of the window before the retries do.
+        tokio::time::advance(Duration::from_secs(5)).await;
+        for _ in 0..2 {
+            tracker.record_request();
+        }
+        assert!(tracker.try_withdraw(&retry));
+        assert!(tracker.is_exhausted());
+
+        tokio::time::advance(Duration::from_secs(6)).await;
+        // Only the last two requests and one retry are left in the window.
+        assert!(tracker.is_exhausted());
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        // Nothing is left in the window.
+        tracker.record_request();
+        tracker.record_request();
+        assert!(tracker.try_withdraw(&retry));
+        assert!(tracker.is_exhausted());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_budget_min_retries() {
+        let budget = RetryBudget::new(0.0)
+            .with_min_retries_per_second(1)
+            .with_window(Duration::from_secs(2));
+        let tracker = RetryBudgetTracker::new(&budget);
+        let retry = RetryDecision::RetrySameTarget(None);
+
+        assert!(tracker.try_withdraw(&retry));
+        assert!(tracker.try_withdraw(&retry));
+        assert!(!tracker.try_withdraw(&retry));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_budget_invalid_ratio() {
+        assert_eq!(RetryBudget::new(-1.0).get_ratio(), 0.0);
+        assert_eq!(RetryBudget::new(f64::NAN).get_ratio(), 0.0);
+        assert_eq!(RetryBudget::new(f64::NEG_INFINITY).get_ratio(), 0.0);
+        assert_eq!(RetryBudget::new(f64::INFINITY).get_ratio(), f64::MAX);
+
+        let retry = RetryDecision::RetrySameTarget(None);
+        let budget = RetryBudget::new(f64::NAN).with_min_retries_per_second(0);
+        let tracker = RetryBudgetTracker::new(&budget);
+        tracker.record_request();
+        assert!(!tracker.try_withdraw(&retry));
+
+        let budget = RetryBudget::new(f64::INFINITY).with_min_retries_per_second(0);
+        let tracker = RetryBudgetTracker::new(&budget);
+        // No requests - no budget, even with an unbounded ratio.
+        assert!(tracker.is_exhausted());
+        for _ in 0..2 {
+            tracker.record_request();
+        }
+        for _ in 0..100 {
+            assert!(tracker.try_withdraw(&retry));
+        }
+    }
+}
@@ -2,6 +2,8 @@
 //! To decide when to retry a request the `Session` can use any object which implements
 //! the `RetryPolicy` trait
 
+use std::time::Duration;
+
 use crate::errors::RequestAttemptError;
 use crate::frame::types::Consistency;
 
@@ -16,6 +18,9 @@ pub struct RequestInfo<'a> {
     pub is_idempotent: bool,
     /// Consistency with which the request failed
     pub consistency: Consistency,
+    /// Whether the session-wide [RetryBudget](super::RetryBudget) is exhausted.\
+    /// If set to `true`, a decision to retry will be overridden with [RetryDecision::DontRetry].
+    pub retry_budget_exhausted: bool,
 }
 
 /// Returned by implementations of RetryPolicy. Instructs the driver on what
@@ -27,12 +32,25 @@ pub enum RetryDecision {
     RetrySameTarget(Option<Consistency>), // None means that the same consistency should be used as before
     /// Request will be sent to the next target generated by load balancing policy.
     RetryNextTarget(Option<Consistency>), // ditto
+    /// Request will be sent to the given target after the given delay.
+    /// Allows backing off from a node (or the whole cluster) that is overloaded.
+    RetryAfter(Duration, RetryTarget, Option<Consistency>), // ditto
     /// Fails the whole request.
     DontRetry,
     /// Will cause the driver to return an empty successful response.
     IgnoreWriteError,
 }
 
+/// The target of a delayed retry, see [RetryDecision::RetryAfter].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RetryTarget {
+    /// The same shard on the same host.
+    SameTarget,
+    /// The next target generated by load balancing policy.
+    NextTarget,
+}
+
 /// Specifies a policy used to decide when to retry a request
 pub trait RetryPolicy: std::fmt::Debug + Send + Sync {
     /// Called for each new request, starts a session of deciding about retries
@@ -110,6 +110,9 @@ fn can_be_ignored<ResT>(result: &Result<ResT, RequestError>) -> bool {
             // Can try on another node.
             RequestError::ConnectionPoolError { .. } => true,
 
+            // The node is saturated by requests of this session, can try on another node.
+            RequestError::RequestQueueTimeout(_) => true,
+
             RequestError::LastAttemptError(e) => {
                 // Do not remove this lint!
                 // It's there for a reason - we don't want new variants
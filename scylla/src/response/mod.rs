@@ -16,4 +16,6 @@ pub(crate) use request_response::{
     NonErrorAuthResponse, NonErrorQueryResponse, NonErrorStartupResponse, QueryResponse,
     RawPreparedStatement,
 };
+#[cfg(feature = "paging-cursor")]
+pub use scylla_cql::frame::request::query::{PagingCursor, PagingCursorCodec, PagingCursorError};
 pub use scylla_cql::frame::request::query::{PagingState, PagingStateResponse};
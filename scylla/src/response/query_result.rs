@@ -3,7 +3,11 @@ use std::fmt::Debug;
 use thiserror::Error;
 use uuid::Uuid;
 
+#[cfg(feature = "arrow-54")]
+use scylla_cql::deserialize::arrow::RecordBatchBuilder;
 use scylla_cql::deserialize::result::TypedRowIterator;
+#[cfg(feature = "arrow-54")]
+use scylla_cql::deserialize::row::ColumnIterator;
 use scylla_cql::deserialize::row::DeserializeRow;
 use scylla_cql::deserialize::{DeserializationError, TypeCheckError};
 use scylla_cql::frame::frame_errors::ResultMetadataAndRowsCountParseError;
@@ -378,6 +382,24 @@ impl QueryRowsResult {
         }
     }
 
+    /// Deserializes all the received rows into an Arrow
+    /// [RecordBatch](arrow_array_54::RecordBatch).
+    ///
+    /// The schema of the batch is derived from the [column specs](Self::column_specs),
+    /// see [crate::deserialize::arrow] for the type mapping.
+    /// The rows are deserialized directly from the response frame.
+    #[cfg(feature = "arrow-54")]
+    pub fn record_batch(&self) -> Result<arrow_array_54::RecordBatch, DeserializationError> {
+        let mut builder = RecordBatchBuilder::new(self.column_specs().as_slice());
+        let rows = self
+            .rows::<ColumnIterator>()
+            .expect("ColumnIterator accepts rows of any type");
+        for row in rows {
+            builder.append_row(row?)?;
+        }
+        Ok(builder.finish())
+    }
+
     #[cfg(cpp_rust_unstable)]
     pub fn into_inner(
         self,
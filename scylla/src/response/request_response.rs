@@ -11,7 +11,7 @@ use crate::errors::RequestAttemptError;
 use crate::frame::response::{self, result};
 use crate::response::query_result::QueryResult;
 use crate::response::Coordinator;
-use crate::statement::prepared::PreparedStatement;
+use crate::statement::prepared::{PreparedStatement, ResultMetadataSnapshot, SchemaEpoch};
 use crate::statement::Statement;
 
 pub(crate) struct QueryResponse {
@@ -155,6 +155,8 @@ pub(crate) struct RawPreparedStatement<'statement> {
     pub(crate) prepared_response: result::Prepared,
     pub(crate) is_lwt: bool,
     pub(crate) tracing_id: Option<Uuid>,
+    /// Schema epoch read before sending the PREPARE request.
+    pub(crate) schema_epoch: SchemaEpoch,
 }
 
 impl<'statement> RawPreparedStatement<'statement> {
@@ -163,12 +165,14 @@ impl<'statement> RawPreparedStatement<'statement> {
         prepared_response: result::Prepared,
         is_lwt: bool,
         tracing_id: Option<Uuid>,
+        schema_epoch: SchemaEpoch,
     ) -> Self {
         Self {
             statement,
             prepared_response,
             is_lwt,
             tracing_id,
+            schema_epoch,
         }
     }
 }
@@ -183,12 +187,17 @@ impl RawPreparedStatement<'_> {
             prepared_response,
             is_lwt,
             tracing_id,
+            schema_epoch,
         } = self;
         let mut prepared_statement = PreparedStatement::new(
             prepared_response.id,
             is_lwt,
             prepared_response.prepared_metadata,
-            Arc::new(prepared_response.result_metadata),
+            ResultMetadataSnapshot {
+                id: prepared_response.result_metadata.id().cloned(),
+                metadata: Arc::new(prepared_response.result_metadata),
+                schema_epoch,
+            },
             statement.contents.clone(),
             statement.get_validated_page_size(),
             statement.config.clone(),
@@ -235,9 +235,12 @@ mod tests {
                 strategy: Strategy::SimpleStrategy {
                     replication_factor: 2,
                 },
+                durable_writes: true,
                 tables: HashMap::new(),
                 views: HashMap::new(),
                 user_defined_types: HashMap::new(),
+                user_defined_functions: HashMap::new(),
+                user_defined_aggregates: HashMap::new(),
             }),
         )]
         .iter()
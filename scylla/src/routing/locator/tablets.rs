@@ -356,6 +356,11 @@ impl TableTablets {
         tablet.filter(|t| t.first_token <= token)
     }
 
+    /// Returns the `(first_token, last_token)` ranges of the known tablets, in the ring order.
+    pub(crate) fn tablet_ranges(&self) -> impl Iterator<Item = (Token, Token)> + '_ {
+        self.tablet_list.iter().map(Tablet::range)
+    }
+
     pub(crate) fn replicas_for_token(&self, token: Token) -> Option<&[(Arc<Node>, Shard)]> {
         self.tablet_for_token(token)
             .map(|tablet| tablet.replicas.all.as_ref())
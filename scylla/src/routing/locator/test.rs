@@ -122,9 +122,12 @@ pub(crate) fn mock_metadata_for_token_aware_tests() -> Metadata {
                 strategy: Strategy::SimpleStrategy {
                     replication_factor: 2,
                 },
+                durable_writes: true,
                 tables: HashMap::new(),
                 views: HashMap::new(),
                 user_defined_types: HashMap::new(),
+                user_defined_functions: HashMap::new(),
+                user_defined_aggregates: HashMap::new(),
             }),
         ),
         (
@@ -135,9 +138,12 @@ pub(crate) fn mock_metadata_for_token_aware_tests() -> Metadata {
                         .into_iter()
                         .collect(),
                 },
+                durable_writes: true,
                 tables: HashMap::new(),
                 views: HashMap::new(),
                 user_defined_types: HashMap::new(),
+                user_defined_functions: HashMap::new(),
+                user_defined_aggregates: HashMap::new(),
             }),
         ),
         (
@@ -148,9 +154,12 @@ pub(crate) fn mock_metadata_for_token_aware_tests() -> Metadata {
                         .into_iter()
                         .collect(),
                 },
+                durable_writes: true,
                 tables: HashMap::new(),
                 views: HashMap::new(),
                 user_defined_types: HashMap::new(),
+                user_defined_functions: HashMap::new(),
+                user_defined_aggregates: HashMap::new(),
             }),
         ),
     ]
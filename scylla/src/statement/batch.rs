@@ -123,6 +123,35 @@ impl Batch {
         self.config.timestamp
     }
 
+    /// Sets the keyspace in which the statements of this batch are executed.
+    /// If not None, it overrides the keyspace set with `USE` for this batch only.
+    ///
+    /// Requires protocol v5 or newer; executing the batch
+    /// over an older protocol version fails.
+    pub fn set_keyspace(&mut self, keyspace: Option<String>) {
+        self.config.keyspace = keyspace
+    }
+
+    /// Gets the keyspace in which the statements of this batch are executed.
+    pub fn get_keyspace(&self) -> Option<&str> {
+        self.config.keyspace.as_deref()
+    }
+
+    /// Sets the current time (in seconds since the Unix epoch) that the server
+    /// should use when executing this batch, e.g. when computing TTL expiration.
+    /// If None, the server uses its own clock.
+    ///
+    /// Requires protocol v5 or newer; executing the batch
+    /// over an older protocol version fails.
+    pub fn set_now_in_seconds(&mut self, now_in_seconds: Option<i32>) {
+        self.config.now_in_seconds = now_in_seconds
+    }
+
+    /// Gets the current time in seconds that the server uses when executing this batch.
+    pub fn get_now_in_seconds(&self) -> Option<i32> {
+        self.config.now_in_seconds
+    }
+
     /// Set the retry policy for this batch, overriding the one from execution profile if not None.
     #[inline]
     pub fn set_retry_policy(&mut self, retry_policy: Option<Arc<dyn RetryPolicy>>) {
@@ -36,6 +36,10 @@ pub(crate) struct StatementConfig {
     pub(crate) timestamp: Option<i64>,
     pub(crate) request_timeout: Option<Duration>,
 
+    // Protocol v5 request features.
+    pub(crate) keyspace: Option<String>,
+    pub(crate) now_in_seconds: Option<i32>,
+
     pub(crate) history_listener: Option<Arc<dyn HistoryListener>>,
 
     pub(crate) execution_profile_handle: Option<ExecutionProfileHandle>,
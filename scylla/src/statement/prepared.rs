@@ -1,4 +1,6 @@
+use arc_swap::ArcSwap;
 use bytes::{Bytes, BytesMut};
+use scylla_cql::frame::response::event::{SchemaChangeEvent, SchemaChangeType};
 use scylla_cql::frame::response::result::{
     ColumnSpec, PartitionKeyIndex, ResultMetadata, TableSpec,
 };
@@ -6,8 +8,10 @@ use scylla_cql::frame::types::RawValue;
 use scylla_cql::serialize::row::{RowSerializationContext, SerializeRow, SerializedValues};
 use scylla_cql::serialize::SerializationError;
 use smallvec::{smallvec, SmallVec};
+use std::collections::HashMap;
 use std::convert::TryInto;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use thiserror::Error;
 use uuid::Uuid;
@@ -65,30 +69,23 @@ use crate::routing::Token;
 /// # Altering schema
 /// If for some reason you decided to alter the part of schema that corresponds to given prepared
 /// statement, then the corresponding statement (and its copies obtained via [`PreparedStatement::clone`]) should
-/// be dropped. The statement should be prepared again.
+/// be dropped, if the bound values of the statement are affected. The statement should be prepared again.
 ///
-/// There are two reasons for this:
+/// The reason for this is that [`PreparedMetadata`] (describing the bound values) is immutable
+/// on the client side, so it is not updated during statement repreparation. This may result
+/// in bound values serialization errors.
 ///
-/// ### CQL v4 protocol limitations
-/// The driver only supports CQL version 4.
+/// On the other hand, the [`ResultMetadata`] cached in the statement (see
+/// [`PreparedStatement::set_use_cached_result_metadata`]) is refreshed automatically:
+/// * In protocol v5, the server detects that the client's result metadata is outdated
+///   (by comparing result metadata ids) and sends the new metadata along with the results.
+/// * In protocol v4, the driver stops trusting the cached result metadata whenever it learns
+///   that the table the statement reads from (or its keyspace) was altered or dropped (from
+///   a schema change event or a response to a schema-altering statement), and requests
+///   the metadata with the next execution. Schema changes are tracked per session.
+///   Note that this relies on events, which may be delayed or lost if the control connection breaks.
 ///
-/// In multi-client scenario, only the first client which reprepares the statement
-/// will receive the updated metadata from the server.
-/// The rest of the clients will still hold on the outdated metadata.
-/// In version 4 of CQL protocol there is currently no way for the server to notify other
-/// clients about prepared statement's metadata update.
-///
-/// ### Client-side metadata immutability
-/// The decision was made to keep client-side metadata immutable.
-/// Mainly because of the CQLv4 limitations mentioned above. This means
-/// that metadata is not updated during statement repreparation.
-/// This raises two issues:
-/// * bound values serialization errors - since [`PreparedMetadata`] is not updated
-/// * result deserialization errors - when [`PreparedStatement::set_use_cached_result_metadata`] is enabled,
-///   since [`ResultMetadata`] is not updated
-///
-/// So, to mitigate those issues, drop the outdated [`PreparedStatement`] manually
-/// and prepare it again against the new schema.
+/// In both cases, the new metadata is stored in the statement and shared by all its copies.
 #[derive(Debug)]
 pub struct PreparedStatement {
     pub(crate) config: StatementConfig,
@@ -104,10 +101,201 @@ pub struct PreparedStatement {
 #[derive(Debug)]
 struct PreparedStatementSharedData {
     metadata: PreparedMetadata,
-    result_metadata: Arc<ResultMetadata<'static>>,
+    // Result metadata returned when the statement was prepared.
+    initial_result_metadata: Arc<ResultMetadata<'static>>,
+    // Result metadata as last seen by the driver.
+    result_metadata: ArcSwap<ResultMetadataSnapshot>,
     statement: String,
 }
 
+/// Tracks schema changes in the cluster of a session, so that the result metadata
+/// cached in prepared statements is only distrusted when the table it describes
+/// (or its keyspace) could have been altered.
+///
+/// Epochs are values of a counter which is incremented on every schema change.
+/// For each table, keyspace and for the whole cluster the tracker remembers
+/// the epoch of the last change affecting it. Result metadata is trusted
+/// if it was received in an epoch not older than the last change of its table.
+#[derive(Debug)]
+pub(crate) struct SchemaEpochs {
+    // Distinguishes trackers of different sessions, because prepared statements
+    // are not bound to a session and may be executed in several of them.
+    tracker_id: u64,
+    state: RwLock<SchemaEpochsState>,
+}
+
+#[derive(Debug, Default)]
+struct SchemaEpochsState {
+    // Epoch of the most recent change of any kind.
+    current: u64,
+    // Epoch of the most recent change which could have affected every table.
+    cluster: u64,
+    keyspaces: HashMap<String, u64>,
+    tables: HashMap<(String, String), u64>,
+}
+
+impl SchemaEpochsState {
+    fn next_epoch(&mut self) -> u64 {
+        self.current += 1;
+        self.current
+    }
+
+    /// Epoch of the last change which could have affected the given table.
+    /// If the table is unknown, any change is taken into account.
+    fn last_change_of(&self, table: Option<&TableSpec<'_>>) -> u64 {
+        let Some(table) = table else {
+            return self.current;
+        };
+        let keyspace_change = self.keyspaces.get(table.ks_name()).copied();
+        let table_change = self
+            .tables
+            .get(&(table.ks_name().to_owned(), table.table_name().to_owned()))
+            .copied();
+        self.cluster
+            .max(keyspace_change.unwrap_or(0))
+            .max(table_change.unwrap_or(0))
+    }
+}
+
+/// Point in time of a [SchemaEpochs] tracker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SchemaEpoch {
+    tracker_id: u64,
+    value: u64,
+}
+
+impl SchemaEpoch {
+    /// Returns true if this epoch is known to be later than the other one.
+    fn is_later_than(&self, other: &SchemaEpoch) -> bool {
+        self.tracker_id == other.tracker_id && self.value > other.value
+    }
+}
+
+impl SchemaEpochs {
+    pub(crate) fn new() -> Self {
+        static NEXT_TRACKER_ID: AtomicU64 = AtomicU64::new(0);
+
+        Self {
+            tracker_id: NEXT_TRACKER_ID.fetch_add(1, Ordering::Relaxed),
+            state: RwLock::new(SchemaEpochsState::default()),
+        }
+    }
+
+    /// Returns the current schema epoch. It should be read before sending a request
+    /// whose response carries result metadata, so that a schema change which happens
+    /// in the meantime invalidates that metadata.
+    pub(crate) fn current(&self) -> SchemaEpoch {
+        SchemaEpoch {
+            tracker_id: self.tracker_id,
+            value: self.state.read().unwrap().current,
+        }
+    }
+
+    /// Returns true if no schema change which could have affected the given
+    /// result metadata happened since it was received.
+    pub(crate) fn is_up_to_date(&self, snapshot: &ResultMetadataSnapshot) -> bool {
+        if snapshot.schema_epoch.tracker_id != self.tracker_id {
+            // The metadata was received by another session, which
+            // tracks schema changes independently.
+            return false;
+        }
+        let table = snapshot
+            .metadata
+            .col_specs()
+            .first()
+            .map(|spec| spec.table_spec());
+        self.state.read().unwrap().last_change_of(table) <= snapshot.schema_epoch.value
+    }
+
+    /// Marks result metadata cached in all prepared statements as possibly outdated.
+    pub(crate) fn invalidate_all(&self) {
+        let mut state = self.state.write().unwrap();
+        state.cluster = state.next_epoch();
+    }
+
+    /// Invalidates cached result metadata if the given schema change could have affected it.
+    /// Creating new schema objects does not change the result metadata of statements
+    /// prepared earlier, so such changes are ignored.
+    pub(crate) fn invalidate_on(&self, event: &SchemaChangeEvent) {
+        let mut state = self.state.write().unwrap();
+        match event {
+            SchemaChangeEvent::TableChange {
+                change_type: SchemaChangeType::Created,
+                ..
+            }
+            | SchemaChangeEvent::KeyspaceChange {
+                change_type: SchemaChangeType::Created,
+                ..
+            }
+            | SchemaChangeEvent::TypeChange {
+                change_type: SchemaChangeType::Created,
+                ..
+            }
+            | SchemaChangeEvent::FunctionChange {
+                change_type: SchemaChangeType::Created,
+                ..
+            }
+            | SchemaChangeEvent::AggregateChange {
+                change_type: SchemaChangeType::Created,
+                ..
+            } => (),
+            SchemaChangeEvent::TableChange {
+                keyspace_name,
+                object_name,
+                ..
+            } => {
+                let epoch = state.next_epoch();
+                state
+                    .tables
+                    .insert((keyspace_name.clone(), object_name.clone()), epoch);
+            }
+            // Altering a user defined type changes the metadata of every table using it.
+            SchemaChangeEvent::KeyspaceChange { keyspace_name, .. }
+            | SchemaChangeEvent::TypeChange { keyspace_name, .. } => {
+                let epoch = state.next_epoch();
+                state.keyspaces.insert(keyspace_name.clone(), epoch);
+            }
+            // Functions from any keyspace can be used in a statement.
+            SchemaChangeEvent::FunctionChange { .. }
+            | SchemaChangeEvent::AggregateChange { .. } => {
+                state.cluster = state.next_epoch();
+            }
+        }
+    }
+}
+
+/// Column specifications of the result set of a prepared statement, as last seen by the driver.
+///
+/// Returned by [`PreparedStatement::get_current_result_set_col_specs`]. Holds a snapshot
+/// of the result metadata, so it is not affected by later refreshes.
+#[derive(Debug, Clone)]
+pub struct ResultSetColumnSpecs {
+    metadata: Arc<ResultMetadata<'static>>,
+}
+
+impl ResultSetColumnSpecs {
+    /// Returns the column specifications wrapped in [`ColumnSpecs`].
+    pub fn column_specs(&self) -> ColumnSpecs<'_, 'static> {
+        ColumnSpecs::new(self.metadata.col_specs())
+    }
+
+    /// Returns a slice of the column specifications.
+    pub fn as_slice(&self) -> &[ColumnSpec<'static>] {
+        self.metadata.col_specs()
+    }
+}
+
+/// Result metadata of a prepared statement, together with the information
+/// needed to tell whether it is up to date.
+#[derive(Debug)]
+pub(crate) struct ResultMetadataSnapshot {
+    pub(crate) metadata: Arc<ResultMetadata<'static>>,
+    /// Id of the metadata, present in protocol v5 and later.
+    pub(crate) id: Option<Bytes>,
+    /// Schema epoch from before the request which returned the metadata.
+    pub(crate) schema_epoch: SchemaEpoch,
+}
+
 impl Clone for PreparedStatement {
     fn clone(&self) -> Self {
         Self {
@@ -127,7 +315,7 @@ impl PreparedStatement {
         id: Bytes,
         is_lwt: bool,
         metadata: PreparedMetadata,
-        result_metadata: Arc<ResultMetadata<'static>>,
+        result_metadata: ResultMetadataSnapshot,
         statement: String,
         page_size: PageSize,
         config: StatementConfig,
@@ -136,7 +324,8 @@ impl PreparedStatement {
             id,
             shared: Arc::new(PreparedStatementSharedData {
                 metadata,
-                result_metadata,
+                initial_result_metadata: result_metadata.metadata.clone(),
+                result_metadata: ArcSwap::from_pointee(result_metadata),
                 statement,
             }),
             prepare_tracing_ids: Vec::new(),
@@ -164,6 +353,11 @@ impl PreparedStatement {
             .unwrap_or_else(|err| panic!("PreparedStatement::set_page_size: {err}"));
     }
 
+    /// Sets the already validated page size for this CQL query.
+    pub(crate) fn set_validated_page_size(&mut self, page_size: PageSize) {
+        self.page_size = page_size;
+    }
+
     /// Returns the page size for this CQL query.
     pub(crate) fn get_validated_page_size(&self) -> PageSize {
         self.page_size
@@ -351,6 +545,8 @@ impl PreparedStatement {
     /// The driver will cache the result metadata received from the server
     /// after statement preparation and will use it
     /// to deserialize the results of statement execution.
+    /// The cached metadata is refreshed when the schema changes - see
+    /// the "Altering schema" section of [`PreparedStatement`] docs.
     ///
     /// This option is false by default.
     pub fn set_use_cached_result_metadata(&mut self, use_cached_metadata: bool) {
@@ -376,6 +572,21 @@ impl PreparedStatement {
         self.config.timestamp
     }
 
+    /// Sets the current time (in seconds since the Unix epoch) that the server
+    /// should use when executing this statement, e.g. when computing TTL expiration.
+    /// If None, the server uses its own clock.
+    ///
+    /// Requires protocol v5 or newer; executing the statement
+    /// over an older protocol version fails.
+    pub fn set_now_in_seconds(&mut self, now_in_seconds: Option<i32>) {
+        self.config.now_in_seconds = now_in_seconds
+    }
+
+    /// Gets the current time in seconds that the server uses when executing this statement.
+    pub fn get_now_in_seconds(&self) -> Option<i32> {
+        self.config.now_in_seconds
+    }
+
     /// Sets the client-side timeout for this statement.
     /// If not None, the driver will stop waiting for the request
     /// to finish after `timeout` passed.
@@ -409,14 +620,51 @@ impl PreparedStatement {
         &self.shared.metadata.pk_indexes
     }
 
-    /// Access metadata about the result of prepared statement returned by the database
-    pub(crate) fn get_result_metadata(&self) -> &Arc<ResultMetadata<'static>> {
-        &self.shared.result_metadata
+    /// Access the most recent metadata about the result of prepared statement,
+    /// along with its id and the schema epoch it was received in.
+    pub(crate) fn get_result_metadata(&self) -> Arc<ResultMetadataSnapshot> {
+        self.shared.result_metadata.load_full()
+    }
+
+    /// Replaces the result metadata of this statement (and all its copies).
+    pub(crate) fn update_result_metadata(&self, snapshot: ResultMetadataSnapshot) {
+        // If another request updated the metadata concurrently,
+        // keep the snapshot that was received later.
+        self.shared.result_metadata.rcu(|current| {
+            if current.schema_epoch.is_later_than(&snapshot.schema_epoch) {
+                current.clone()
+            } else {
+                Arc::new(ResultMetadataSnapshot {
+                    metadata: snapshot.metadata.clone(),
+                    id: snapshot.id.clone(),
+                    schema_epoch: snapshot.schema_epoch,
+                })
+            }
+        });
+    }
+
+    /// Access the id of the most recent result metadata of this statement, as returned
+    /// by the database in protocol v5. `None` for older protocol versions.
+    pub fn get_result_metadata_id(&self) -> Option<Bytes> {
+        self.shared.result_metadata.load().id.clone()
     }
 
     /// Access column specifications of the result set returned after the execution of this statement
     pub fn get_result_set_col_specs(&self) -> ColumnSpecs<'_, 'static> {
-        ColumnSpecs::new(self.shared.result_metadata.col_specs())
+        ColumnSpecs::new(self.shared.initial_result_metadata.col_specs())
+    }
+
+    /// Access column specifications of the result set returned after the execution of this statement,
+    /// as last seen by the driver.
+    ///
+    /// Unlike [`PreparedStatement::get_result_set_col_specs`], which returns the specifications
+    /// returned when the statement was prepared, these are refreshed when the driver learns that
+    /// the result metadata changed (see [`PreparedStatement`] docs), so the returned snapshot
+    /// may differ from the one returned by a previous call.
+    pub fn get_current_result_set_col_specs(&self) -> ResultSetColumnSpecs {
+        ResultSetColumnSpecs {
+            metadata: self.shared.result_metadata.load().metadata.clone(),
+        }
     }
 
     /// Get the name of the partitioner used for this statement.
@@ -629,12 +877,16 @@ impl<'ps> PartitionKey<'ps> {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use scylla_cql::frame::response::event::{SchemaChangeEvent, SchemaChangeType};
     use scylla_cql::frame::response::result::{
-        ColumnSpec, ColumnType, NativeType, PartitionKeyIndex, PreparedMetadata, TableSpec,
+        ColumnSpec, ColumnType, NativeType, PartitionKeyIndex, PreparedMetadata, ResultMetadata,
+        TableSpec,
     };
     use scylla_cql::serialize::row::SerializedValues;
 
-    use crate::statement::prepared::PartitionKey;
+    use crate::statement::prepared::{PartitionKey, ResultMetadataSnapshot, SchemaEpochs};
     use crate::test_utils::setup_tracing;
 
     fn make_meta(
@@ -705,4 +957,75 @@ mod tests {
             ]
         );
     }
+
+    fn make_result_metadata_snapshot(
+        schema_epochs: &SchemaEpochs,
+        ks: &str,
+        table: &str,
+    ) -> ResultMetadataSnapshot {
+        let table_spec = TableSpec::owned(ks.to_owned(), table.to_owned());
+        let col_specs = vec![ColumnSpec::owned(
+            "col".to_owned(),
+            ColumnType::Native(NativeType::Int),
+            table_spec,
+        )];
+        ResultMetadataSnapshot {
+            metadata: Arc::new(ResultMetadata::new_for_test(1, col_specs)),
+            id: None,
+            schema_epoch: schema_epochs.current(),
+        }
+    }
+
+    fn table_change(change_type: SchemaChangeType, ks: &str, table: &str) -> SchemaChangeEvent {
+        SchemaChangeEvent::TableChange {
+            change_type,
+            keyspace_name: ks.to_owned(),
+            object_name: table.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_schema_epochs_invalidate_only_affected_tables() {
+        setup_tracing();
+        let schema_epochs = SchemaEpochs::new();
+        let t1 = make_result_metadata_snapshot(&schema_epochs, "ks", "t1");
+        let t2 = make_result_metadata_snapshot(&schema_epochs, "ks", "t2");
+        let other_ks = make_result_metadata_snapshot(&schema_epochs, "other_ks", "t1");
+        assert!(schema_epochs.is_up_to_date(&t1));
+
+        // Creating schema objects does not affect existing statements.
+        schema_epochs.invalidate_on(&table_change(SchemaChangeType::Created, "ks", "t1"));
+        assert!(schema_epochs.is_up_to_date(&t1));
+
+        schema_epochs.invalidate_on(&table_change(SchemaChangeType::Updated, "ks", "t1"));
+        assert!(!schema_epochs.is_up_to_date(&t1));
+        assert!(schema_epochs.is_up_to_date(&t2));
+        assert!(schema_epochs.is_up_to_date(&other_ks));
+
+        // Metadata received after the change is trusted again.
+        let t1 = make_result_metadata_snapshot(&schema_epochs, "ks", "t1");
+        assert!(schema_epochs.is_up_to_date(&t1));
+
+        schema_epochs.invalidate_on(&SchemaChangeEvent::TypeChange {
+            change_type: SchemaChangeType::Updated,
+            keyspace_name: "ks".to_owned(),
+            type_name: "udt".to_owned(),
+        });
+        assert!(!schema_epochs.is_up_to_date(&t1));
+        assert!(!schema_epochs.is_up_to_date(&t2));
+        assert!(schema_epochs.is_up_to_date(&other_ks));
+
+        schema_epochs.invalidate_all();
+        assert!(!schema_epochs.is_up_to_date(&other_ks));
+    }
+
+    #[test]
+    fn test_schema_epochs_are_not_shared_between_trackers() {
+        setup_tracing();
+        let first = SchemaEpochs::new();
+        let second = SchemaEpochs::new();
+        let snapshot = make_result_metadata_snapshot(&first, "ks", "t");
+        assert!(first.is_up_to_date(&snapshot));
+        assert!(!second.is_up_to_date(&snapshot));
+    }
 }
@@ -117,6 +117,37 @@ impl Statement {
         self.config.timestamp
     }
 
+    /// Sets the keyspace in which this statement is executed, or prepared
+    /// when passed to [`Session::prepare`](crate::client::session::Session::prepare).
+    /// If not None, it overrides the keyspace set with `USE` for this statement only,
+    /// which lets a single session serve many keyspaces at once.
+    ///
+    /// Requires protocol v5 or newer; executing the statement
+    /// over an older protocol version fails.
+    pub fn set_keyspace(&mut self, keyspace: Option<String>) {
+        self.config.keyspace = keyspace
+    }
+
+    /// Gets the keyspace in which this statement is executed.
+    pub fn get_keyspace(&self) -> Option<&str> {
+        self.config.keyspace.as_deref()
+    }
+
+    /// Sets the current time (in seconds since the Unix epoch) that the server
+    /// should use when executing this statement, e.g. when computing TTL expiration.
+    /// If None, the server uses its own clock.
+    ///
+    /// Requires protocol v5 or newer; executing the statement
+    /// over an older protocol version fails.
+    pub fn set_now_in_seconds(&mut self, now_in_seconds: Option<i32>) {
+        self.config.now_in_seconds = now_in_seconds
+    }
+
+    /// Gets the current time in seconds that the server uses when executing this statement.
+    pub fn get_now_in_seconds(&self) -> Option<i32> {
+        self.config.now_in_seconds
+    }
+
     /// Sets the client-side timeout for this statement.
     /// If not None, the driver will stop waiting for the request
     /// to finish after `timeout` passed.
@@ -0,0 +1,71 @@
+//! Tests that CQL statements generated from schema metadata recreate the same schema.
+
+use crate::utils::{
+    create_new_session_builder, scylla_supports_tablets, setup_tracing, unique_keyspace_name,
+    PerformDDL,
+};
+
+#[tokio::test]
+async fn test_describe_keyspace_roundtrip() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    let mut create_ks = format!("CREATE KEYSPACE {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks);
+    // Materialized views + tablets are not supported in Scylla 2025.1.
+    // Tablets options are not a part of the metadata, so they have to be added
+    // to the generated statement as well.
+    let tablets_option = if scylla_supports_tablets(&session).await {
+        " AND TABLETS = { 'enabled': false}"
+    } else {
+        ""
+    };
+    create_ks += tablets_option;
+    session.ddl(create_ks).await.unwrap();
+    session.use_keyspace(ks.clone(), false).await.unwrap();
+
+    for statement in [
+        "CREATE TYPE type_b (a int, b text)",
+        "CREATE TYPE type_a (a map<frozen<set<text>>, frozen<type_b>>)",
+        "CREATE TABLE t (p1 int, p2 text, \"Ck\" bigint, s int static, v frozen<type_a>, \
+            PRIMARY KEY ((p1, p2), \"Ck\")) \
+            WITH CLUSTERING ORDER BY (\"Ck\" DESC) AND comment = 'It''s a table' \
+            AND gc_grace_seconds = 3600",
+        "CREATE TABLE t2 (id int PRIMARY KEY, v int, l list<int>)",
+        "CREATE INDEX t2_v_idx ON t2(v)",
+        "CREATE MATERIALIZED VIEW mv AS SELECT * FROM t2 WHERE v IS NOT NULL PRIMARY KEY (v, id)",
+    ] {
+        session.ddl(statement).await.unwrap();
+    }
+
+    session.await_schema_agreement().await.unwrap();
+    session.refresh_metadata().await.unwrap();
+    let original = session
+        .get_cluster_state()
+        .get_keyspace(&ks)
+        .unwrap()
+        .clone();
+    let description = original.describe(&ks).unwrap();
+
+    session.ddl(format!("DROP KEYSPACE {}", ks)).await.unwrap();
+    let mut statements = description.split(";\n\n").map(|s| s.trim_end_matches(';'));
+    let create_ks = format!("{}{}", statements.next().unwrap(), tablets_option);
+    session.ddl(create_ks).await.unwrap();
+    for statement in statements {
+        session.ddl(statement).await.unwrap();
+    }
+
+    session.await_schema_agreement().await.unwrap();
+    session.refresh_metadata().await.unwrap();
+    let recreated = session
+        .get_cluster_state()
+        .get_keyspace(&ks)
+        .unwrap()
+        .clone();
+
+    assert_eq!(recreated.describe(&ks).unwrap(), description);
+    assert_eq!(recreated.tables, original.tables);
+    assert_eq!(recreated.user_defined_types, original.user_defined_types);
+
+    session.ddl(format!("DROP KEYSPACE {}", ks)).await.unwrap();
+}
@@ -1 +1,2 @@
+mod describe;
 mod metadata_custom_timeouts;
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use assert_matches::assert_matches;
+use async_trait::async_trait;
+use scylla::cdc::{
+    CdcChange, CdcCheckpoint, CdcCheckpointSaver, CdcConsumer, CdcConsumerFactory, CdcError,
+    CdcHookError, CdcReader, ChangeKind, OperationType, StreamId,
+};
+use scylla::value::{CqlTimestamp, CqlValue};
+
+use crate::utils::{
+    create_new_session_builder, scylla_supports_tablets, setup_tracing, unique_keyspace_name,
+    PerformDDL,
+};
+
+#[derive(Default)]
+struct Collector {
+    changes: Arc<Mutex<Vec<CdcChange>>>,
+}
+
+#[async_trait]
+impl CdcConsumer for Collector {
+    async fn consume(&mut self, change: CdcChange) -> Result<(), CdcHookError> {
+        self.changes.lock().unwrap().push(change);
+        Ok(())
+    }
+}
+
+impl CdcConsumerFactory for Collector {
+    fn new_consumer(&self, _streams: &[StreamId]) -> Box<dyn CdcConsumer> {
+        Box::new(Collector {
+            changes: self.changes.clone(),
+        })
+    }
+}
+
+#[derive(Default)]
+struct InMemorySaver {
+    checkpoints: Mutex<HashMap<Vec<StreamId>, CdcCheckpoint>>,
+    generation: Mutex<Option<CqlTimestamp>>,
+}
+
+#[async_trait]
+impl CdcCheckpointSaver for InMemorySaver {
+    async fn save_checkpoint(&self, checkpoint: &CdcCheckpoint) -> Result<(), CdcHookError> {
+        self.checkpoints
+            .lock()
+            .unwrap()
+            .insert(checkpoint.streams.clone(), checkpoint.clone());
+        Ok(())
+    }
+
+    async fn load_checkpoint(
+        &self,
+        generation: CqlTimestamp,
+        streams: &[StreamId],
+    ) -> Result<Option<CqlTimestamp>, CdcHookError> {
+        Ok(self
+            .checkpoints
+            .lock()
+            .unwrap()
+            .get(streams)
+            .filter(|checkpoint| checkpoint.generation == generation)
+            .map(|checkpoint| checkpoint.time))
+    }
+
+    async fn save_generation(&self, generation: CqlTimestamp) -> Result<(), CdcHookError> {
+        *self.generation.lock().unwrap() = Some(generation);
+        Ok(())
+    }
+
+    async fn load_generation(&self) -> Result<Option<CqlTimestamp>, CdcHookError> {
+        Ok(*self.generation.lock().unwrap())
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+#[tokio::test]
+#[ntest::timeout(60000)]
+async fn test_cdc_reader() {
+    setup_tracing();
+    if option_env!("CDC") == Some("disabled") {
+        return;
+    }
+
+    let session = Arc::new(create_new_session_builder().build().await.unwrap());
+    let ks = unique_keyspace_name();
+
+    // This test uses CDC which is not yet compatible with Scylla's tablets.
+    let mut create_ks = format!(
+        "CREATE KEYSPACE {ks} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}"
+    );
+    if scylla_supports_tablets(&session).await {
+        create_ks += " AND TABLETS = {'enabled': false}";
+    }
+    session.ddl(create_ks).await.unwrap();
+    session.use_keyspace(&ks, false).await.unwrap();
+    session
+        .ddl("CREATE TABLE t (pk int, ck int, v int, PRIMARY KEY (pk, ck)) WITH cdc = {'enabled': true}")
+        .await
+        .unwrap();
+    session
+        .ddl("CREATE TABLE no_cdc (pk int PRIMARY KEY)")
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+    session.refresh_metadata().await.unwrap();
+
+    let factory = Arc::new(Collector::default());
+    let err = CdcReader::new(session.clone(), ks.clone(), "no_cdc", factory.clone())
+        .start()
+        .await
+        .unwrap_err();
+    assert_matches!(err, CdcError::CdcNotEnabled { .. });
+
+    let start_time = now() - 1000;
+    for pk in 0..10 {
+        session
+            .query_unpaged("INSERT INTO t (pk, ck, v) VALUES (?, 0, ?)", (pk, pk * 10))
+            .await
+            .unwrap();
+    }
+    session
+        .query_unpaged("UPDATE t SET v = 100 WHERE pk = 1 AND ck = 0", ())
+        .await
+        .unwrap();
+    session
+        .query_unpaged("DELETE v FROM t WHERE pk = 2 AND ck = 0", ())
+        .await
+        .unwrap();
+    let end_time = now() + 1000;
+
+    let saver = Arc::new(InMemorySaver::default());
+    let handle = CdcReader::new(session.clone(), ks.clone(), "t", factory.clone())
+        .with_checkpoint_saver(saver.clone())
+        .with_start_time(CqlTimestamp(start_time))
+        .with_end_time(CqlTimestamp(end_time))
+        .with_safety_interval(Duration::ZERO)
+        .with_sleep_interval(Duration::from_millis(100))
+        .start()
+        .await
+        .unwrap();
+    handle.join().await.unwrap();
+
+    let generation = {
+        let changes = factory.changes.lock().unwrap();
+        assert!(changes
+            .iter()
+            .all(|change| change.kind() == ChangeKind::Delta));
+        let mut inserted: Vec<i32> = changes
+            .iter()
+            .filter(|change| change.operation == OperationType::RowInsert)
+            .map(|change| change.value("pk").and_then(CqlValue::as_int).unwrap())
+            .collect();
+        inserted.sort_unstable();
+        assert_eq!(inserted, (0..10).collect::<Vec<_>>());
+
+        let update = changes
+            .iter()
+            .find(|change| change.operation == OperationType::RowUpdate && !change.is_deleted("v"))
+            .unwrap();
+        assert_eq!(update.value("pk"), Some(&CqlValue::Int(1)));
+        assert_eq!(update.value("v"), Some(&CqlValue::Int(100)));
+
+        // Deleting a single column is recorded as an update marking it as deleted.
+        let delete = changes
+            .iter()
+            .find(|change| change.is_deleted("v"))
+            .unwrap();
+        assert_eq!(delete.value("pk"), Some(&CqlValue::Int(2)));
+        assert_eq!(delete.value("v"), None);
+
+        let checkpoints = saver.checkpoints.lock().unwrap();
+        assert!(!checkpoints.is_empty());
+        assert!(checkpoints
+            .values()
+            .all(|checkpoint| checkpoint.time == CqlTimestamp(end_time)));
+        checkpoints.values().next().unwrap().generation
+    };
+
+    // When resuming from a saved generation, the groups of streams without
+    // a checkpoint are read from the start of the generation, not from now.
+    let resumed_saver = Arc::new(InMemorySaver {
+        generation: Mutex::new(Some(generation)),
+        ..Default::default()
+    });
+    let resumed_factory = Arc::new(Collector::default());
+    let handle = CdcReader::new(session.clone(), ks.clone(), "t", resumed_factory.clone())
+        .with_checkpoint_saver(resumed_saver)
+        .with_end_time(CqlTimestamp(end_time))
+        .with_safety_interval(Duration::ZERO)
+        .with_sleep_interval(Duration::from_millis(100))
+        .start()
+        .await
+        .unwrap();
+    handle.join().await.unwrap();
+
+    let resumed_inserts = resumed_factory
+        .changes
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|change| change.operation == OperationType::RowInsert)
+        .count();
+    assert_eq!(resumed_inserts, 10);
+}
@@ -0,0 +1,194 @@
+use std::net::SocketAddr;
+
+use futures::TryStreamExt as _;
+use scylla::client::session_builder::SessionBuilder;
+use scylla::errors::{DbError, ExecutionError, RequestAttemptError};
+use scylla::frame::response::result::{ColumnType, NativeType};
+use scylla::statement::unprepared::Statement;
+use scylla::statement::Consistency;
+use scylla::value::CqlValue;
+use scylla_proxy::{
+    Condition, MockCluster, MockNode, MockResponse, MockRows, MockRule, RequestReaction,
+    RequestRule,
+};
+
+use crate::utils::setup_tracing;
+
+// Runs the driver against a simulated cluster, without any real node.
+#[tokio::test]
+#[ntest::timeout(60000)]
+async fn test_session_against_mock_cluster() {
+    setup_tracing();
+
+    // The driver connects to the peers on the port of the initial contact point,
+    // so all the simulated nodes must listen on the same port.
+    let addresses: Vec<SocketAddr> = (0..3)
+        .map(|_| SocketAddr::new(scylla_proxy::get_exclusive_local_address(), 9042))
+        .collect();
+
+    let mut rows = MockRows::new(
+        "ks",
+        "t",
+        [
+            ("pk", ColumnType::Native(NativeType::Int)),
+            ("v", ColumnType::Native(NativeType::Text)),
+        ],
+    );
+    for pk in 0..10 {
+        rows = rows.with_row([
+            Some(CqlValue::Int(pk)),
+            Some(CqlValue::Text(format!("value {pk}"))),
+        ]);
+    }
+
+    let proxy = MockCluster::new(addresses.iter().map(|&address| MockNode::new(address)))
+        .with_rule(MockRule::exact(
+            "SELECT pk, v FROM ks.t",
+            MockResponse::Rows(rows.clone()),
+        ))
+        .with_rule(
+            MockRule::exact(
+                "SELECT pk, v FROM ks.t WHERE pk = ?",
+                MockResponse::Rows(rows),
+            )
+            .with_variables([("pk", ColumnType::Native(NativeType::Int))])
+            .with_partition_key_indexes(vec![0]),
+        )
+        .with_rule(MockRule::contains(
+            "INSERT INTO ks.t",
+            MockResponse::error(DbError::Overloaded),
+        ))
+        .into_proxy();
+    let running_proxy = proxy.run().await.unwrap();
+
+    let session = SessionBuilder::new()
+        .known_node_addr(addresses[0])
+        .build()
+        .await
+        .unwrap();
+
+    let cluster_state = session.get_cluster_state();
+    let mut node_addresses: Vec<SocketAddr> = cluster_state
+        .get_nodes_info()
+        .iter()
+        .map(|node| SocketAddr::new(node.address.ip(), node.address.port()))
+        .collect();
+    node_addresses.sort_unstable();
+    let mut expected_addresses = addresses.clone();
+    expected_addresses.sort_unstable();
+    assert_eq!(node_addresses, expected_addresses);
+
+    let result = session
+        .query_unpaged("SELECT pk, v FROM ks.t", ())
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap();
+    assert_eq!(result.rows_num(), 10);
+
+    let mut statement = Statement::new("SELECT pk, v FROM ks.t");
+    statement.set_page_size(3);
+    let paged: Vec<(i32, String)> = session
+        .query_iter(statement, ())
+        .await
+        .unwrap()
+        .rows_stream::<(i32, String)>()
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(paged.len(), 10);
+    assert_eq!(paged[7], (7, "value 7".to_owned()));
+
+    let prepared = session
+        .prepare("SELECT pk, v FROM ks.t WHERE pk = ?")
+        .await
+        .unwrap();
+    assert_eq!(prepared.get_variable_pk_indexes().len(), 1);
+    let (pk, v) = session
+        .execute_unpaged(&prepared, (3,))
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap()
+        .first_row::<(i32, String)>()
+        .unwrap();
+    assert_eq!((pk, v.as_str()), (0, "value 0"));
+
+    let err = session
+        .query_unpaged("INSERT INTO ks.t (pk, v) VALUES (1, 'a')", ())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ExecutionError::LastAttemptError(RequestAttemptError::DbError(DbError::Overloaded, _))
+    ));
+
+    running_proxy.finish().await.unwrap();
+}
+
+// Injects failures only into the writes to one table at QUORUM,
+// whether sent as unprepared or as prepared statements.
+#[tokio::test]
+#[ntest::timeout(60000)]
+async fn test_statement_aware_rules_on_mock_cluster() {
+    setup_tracing();
+
+    let addresses: Vec<SocketAddr> = (0..3)
+        .map(|_| SocketAddr::new(scylla_proxy::get_exclusive_local_address(), 9042))
+        .collect();
+    let writes_to_t_at_quorum = || {
+        vec![RequestRule(
+            Condition::table(Some("ks"), "t")
+                .and(Condition::StatementContains("INSERT".to_owned()))
+                .and(Condition::Consistency(Consistency::Quorum)),
+            RequestReaction::forge_with_error(DbError::Overloaded),
+        )]
+    };
+
+    let proxy = MockCluster::new(
+        addresses
+            .iter()
+            .map(|&address| MockNode::new(address).request_rules(writes_to_t_at_quorum())),
+    )
+    .with_rule(
+        MockRule::contains("INSERT INTO", MockResponse::Void)
+            .with_variables([("pk", ColumnType::Native(NativeType::Int))]),
+    )
+    .into_proxy();
+    let running_proxy = proxy.run().await.unwrap();
+
+    let session = SessionBuilder::new()
+        .known_node_addr(addresses[0])
+        .build()
+        .await
+        .unwrap();
+
+    let insert_t = session
+        .prepare("INSERT INTO ks.t (pk) VALUES (?)")
+        .await
+        .unwrap();
+    let insert_other = session
+        .prepare("INSERT INTO ks.other (pk) VALUES (?)")
+        .await
+        .unwrap();
+    for (mut prepared, consistency, should_fail) in [
+        (insert_t.clone(), Consistency::Quorum, true),
+        (insert_t, Consistency::One, false),
+        (insert_other, Consistency::Quorum, false),
+    ] {
+        prepared.set_consistency(consistency);
+        let result = session.execute_unpaged(&prepared, (1,)).await;
+        assert_eq!(result.is_err(), should_fail, "{:?}", result);
+    }
+
+    let mut statement = Statement::new("INSERT INTO ks.t (pk) VALUES (1)");
+    statement.set_consistency(Consistency::Quorum);
+    let err = session.query_unpaged(statement, ()).await.unwrap_err();
+    assert!(matches!(
+        err,
+        ExecutionError::LastAttemptError(RequestAttemptError::DbError(DbError::Overloaded, _))
+    ));
+
+    running_proxy.finish().await.unwrap();
+}
@@ -1,8 +1,14 @@
 mod authenticate;
 mod caching_session;
+mod cdc;
 mod history;
+mod mock_cluster;
+mod network_faults;
 mod new_session;
+mod record_replay;
 mod retries;
 mod self_identity;
 #[allow(clippy::module_inception)]
 mod session;
+#[cfg(any(feature = "openssl-010", feature = "rustls-023"))]
+mod tls;
@@ -0,0 +1,124 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use scylla::client::session::Session;
+use scylla::client::session_builder::SessionBuilder;
+use scylla::errors::{BrokenConnectionErrorKind, ExecutionError, RequestAttemptError};
+use scylla::statement::unprepared::Statement;
+use scylla_proxy::{MockCluster, MockNode, MockResponse, MockRule, RunningProxy};
+
+use crate::utils::setup_tracing;
+
+const INSERT: &str = "INSERT INTO ks.t (pk) VALUES (1)";
+
+async fn run_mock_cluster() -> (RunningProxy, Session) {
+    run_mock_cluster_with(|builder| builder).await
+}
+
+async fn run_mock_cluster_with(
+    configure: impl FnOnce(SessionBuilder) -> SessionBuilder,
+) -> (RunningProxy, Session) {
+    let address = SocketAddr::new(scylla_proxy::get_exclusive_local_address(), 9042);
+    let proxy = MockCluster::new([MockNode::new(address)])
+        .with_rule(MockRule::exact(INSERT, MockResponse::Void))
+        .into_proxy();
+    let running_proxy = proxy.run().await.unwrap();
+
+    let session = configure(SessionBuilder::new().known_node_addr(address))
+        .build()
+        .await
+        .unwrap();
+    session.query_unpaged(INSERT, ()).await.unwrap();
+
+    (running_proxy, session)
+}
+
+// The connections broken by resets are reopened by the pool once the node stops resetting them.
+#[tokio::test]
+#[ntest::timeout(60000)]
+async fn test_pool_reconnects_after_connection_resets() {
+    setup_tracing();
+    let (mut running_proxy, session) = run_mock_cluster().await;
+
+    running_proxy.running_nodes[0].set_reset_mid_frame(true);
+    session.query_unpaged(INSERT, ()).await.unwrap_err();
+
+    running_proxy.clear_network_faults();
+    while let Err(err) = session.query_unpaged(INSERT, ()).await {
+        tracing::info!("Waiting for the pool to reconnect: {}", err);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    running_proxy.finish().await.unwrap();
+}
+
+// Requests sent during a partition time out, but the connections keep working after it heals.
+#[tokio::test]
+#[ntest::timeout(60000)]
+async fn test_requests_time_out_during_partition() {
+    setup_tracing();
+    let (mut running_proxy, session) = run_mock_cluster().await;
+
+    let mut statement = Statement::new(INSERT);
+    statement.set_request_timeout(Some(Duration::from_millis(500)));
+
+    running_proxy.partition(&[0]);
+    let err = session
+        .query_unpaged(statement.clone(), ())
+        .await
+        .unwrap_err();
+    assert_matches!(err, ExecutionError::RequestTimeout(_));
+
+    running_proxy.heal_partition();
+    session.query_unpaged(statement, ()).await.unwrap();
+
+    running_proxy.finish().await.unwrap();
+}
+
+// Half-open connections, on which the node never responds, are detected by the keepalives
+// and closed, instead of making all the requests sent through them time out.
+#[tokio::test]
+#[ntest::timeout(60000)]
+async fn test_keepalives_detect_half_open_connections() {
+    setup_tracing();
+    let (mut running_proxy, session) = run_mock_cluster_with(|builder| {
+        builder
+            .keepalive_interval(Duration::from_millis(200))
+            .keepalive_timeout(Duration::from_millis(500))
+    })
+    .await;
+
+    let mut statement = Statement::new(INSERT);
+    statement.set_request_timeout(Some(Duration::from_millis(500)));
+
+    running_proxy.running_nodes[0].set_half_open(true);
+    loop {
+        match session.query_unpaged(statement.clone(), ()).await {
+            // The connection is not yet known to be broken.
+            Err(ExecutionError::RequestTimeout(_)) => continue,
+            // The keepalives timed out and the connection was closed, either while
+            // the request was in flight or before it was sent.
+            Err(ExecutionError::LastAttemptError(RequestAttemptError::BrokenConnectionError(
+                err,
+            ))) => {
+                assert_matches!(
+                    err.downcast_ref::<BrokenConnectionErrorKind>(),
+                    Some(BrokenConnectionErrorKind::KeepaliveTimeout(_))
+                );
+                break;
+            }
+            Err(ExecutionError::ConnectionPoolError(_)) => break,
+            other => panic!("Unexpected result of a request on a half-open connection: {other:?}"),
+        }
+    }
+
+    running_proxy.clear_network_faults();
+    while let Err(err) = session.query_unpaged(INSERT, ()).await {
+        tracing::info!("Waiting for the pool to reconnect: {}", err);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // The proxy reports the connections closed by the driver on keepalive timeouts as errors.
+    let _ = running_proxy.finish().await;
+}
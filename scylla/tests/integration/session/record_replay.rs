@@ -0,0 +1,125 @@
+use std::net::SocketAddr;
+
+use futures::TryStreamExt as _;
+use scylla::client::session::Session;
+use scylla::client::session_builder::SessionBuilder;
+use scylla::frame::response::result::{ColumnType, NativeType};
+use scylla::statement::unprepared::Statement;
+use scylla::value::CqlValue;
+use scylla_proxy::{MockCluster, MockNode, MockResponse, MockRows, MockRule, Recording};
+
+use crate::utils::setup_tracing;
+
+async fn run_workload(session: &Session) -> Vec<(i32, String)> {
+    let mut statement = Statement::new("SELECT pk, v FROM ks.t");
+    statement.set_page_size(2);
+    let mut rows: Vec<(i32, String)> = session
+        .query_iter(statement, ())
+        .await
+        .unwrap()
+        .rows_stream::<(i32, String)>()
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    let prepared = session
+        .prepare("SELECT pk, v FROM ks.t WHERE pk = ?")
+        .await
+        .unwrap();
+    let row = session
+        .execute_unpaged(&prepared, (1,))
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap()
+        .first_row::<(i32, String)>()
+        .unwrap();
+    rows.push(row);
+    rows
+}
+
+// Records the traffic between the driver and a simulated cluster, and replays it
+// to another session after the cluster is gone.
+#[tokio::test]
+#[ntest::timeout(60000)]
+async fn test_record_and_replay() {
+    setup_tracing();
+
+    let addresses: Vec<SocketAddr> = (0..3)
+        .map(|_| SocketAddr::new(scylla_proxy::get_exclusive_local_address(), 9042))
+        .collect();
+
+    let mut rows = MockRows::new(
+        "ks",
+        "t",
+        [
+            ("pk", ColumnType::Native(NativeType::Int)),
+            ("v", ColumnType::Native(NativeType::Text)),
+        ],
+    );
+    for pk in 0..5 {
+        rows = rows.with_row([
+            Some(CqlValue::Int(pk)),
+            Some(CqlValue::Text(format!("value {pk}"))),
+        ]);
+    }
+    let proxy = MockCluster::new(addresses.iter().map(|&address| MockNode::new(address)))
+        .with_rule(MockRule::exact(
+            "SELECT pk, v FROM ks.t",
+            MockResponse::Rows(rows.clone()),
+        ))
+        .with_rule(
+            MockRule::exact(
+                "SELECT pk, v FROM ks.t WHERE pk = ?",
+                MockResponse::Rows(rows),
+            )
+            .with_variables([("pk", ColumnType::Native(NativeType::Int))])
+            .with_partition_key_indexes(vec![0]),
+        )
+        .into_proxy();
+
+    let mut running_proxy = proxy.run().await.unwrap();
+    running_proxy.start_recording();
+    let session = SessionBuilder::new()
+        .known_node_addr(addresses[0])
+        .build()
+        .await
+        .unwrap();
+    let recorded_rows = run_workload(&session).await;
+    let recording = running_proxy.stop_recording();
+    drop(session);
+    running_proxy.finish().await.unwrap();
+
+    assert_eq!(recorded_rows.len(), 6);
+    let mut recorded_addresses: Vec<SocketAddr> = recording
+        .connections
+        .iter()
+        .map(|connection| connection.proxy_addr)
+        .collect();
+    recorded_addresses.sort_unstable();
+    recorded_addresses.dedup();
+    let mut expected_addresses = addresses.clone();
+    expected_addresses.sort_unstable();
+    assert_eq!(recorded_addresses, expected_addresses);
+
+    let path =
+        std::env::temp_dir().join(format!("scylla-proxy-recording-{}.rec", addresses[0].ip()));
+    recording.save(&path).unwrap();
+    let loaded = Recording::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, recording);
+
+    // No node is running anymore, the responses come from the recording.
+    let running_proxy = loaded.into_proxy().run().await.unwrap();
+    let session = SessionBuilder::new()
+        .known_node_addr(addresses[0])
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(session.get_cluster_state().get_nodes_info().len(), 3);
+    let replayed_rows = run_workload(&session).await;
+    assert_eq!(replayed_rows, recorded_rows);
+    drop(session);
+    running_proxy.finish().await.unwrap();
+}
@@ -9,7 +9,7 @@ use scylla::client::session::Session;
 use scylla::client::session_builder::SessionBuilder;
 use scylla::cluster::metadata::Strategy::NetworkTopologyStrategy;
 use scylla::cluster::metadata::{
-    CollectionType, ColumnKind, ColumnType, NativeType, UserDefinedType,
+    CollectionType, ColumnKind, ColumnType, IndexKind, NativeType, UserDefinedType,
 };
 use scylla::deserialize::value::DeserializeValue;
 use scylla::errors::OperationType;
@@ -2284,6 +2284,64 @@ async fn test_unprepared_reprepare_in_execute() {
     assert_eq!(all_rows, vec![(1, 2, 3), (1, 3, 2)]);
 }
 
+// Checks that cached result metadata of a prepared statement is refreshed after the table
+// is altered, so that results of `SELECT *` are deserialized using the new set of columns.
+#[tokio::test]
+async fn test_cached_result_metadata_refreshed_after_alter() {
+    setup_tracing();
+
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+    session.use_keyspace(ks, false).await.unwrap();
+
+    session
+        .ddl("CREATE TABLE IF NOT EXISTS tab (a int primary key, b int)")
+        .await
+        .unwrap();
+    session
+        .query_unpaged("INSERT INTO tab (a, b) VALUES (1, 2)", ())
+        .await
+        .unwrap();
+
+    let mut select_all = session.prepare("SELECT * FROM tab").await.unwrap();
+    select_all.set_use_cached_result_metadata(true);
+
+    let rows = session
+        .execute_unpaged(&select_all, ())
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap();
+    assert_eq!(rows.column_specs().len(), 2);
+
+    session.ddl("ALTER TABLE tab ADD c text").await.unwrap();
+    session
+        .query_unpaged("INSERT INTO tab (a, b, c) VALUES (1, 2, 'foo')", ())
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        let row = session
+            .execute_unpaged(&select_all, ())
+            .await
+            .unwrap()
+            .into_rows_result()
+            .unwrap()
+            .single_row::<(i32, i32, String)>()
+            .unwrap();
+        assert_eq!(row, (1, 2, "foo".to_owned()));
+    }
+    assert_eq!(
+        select_all
+            .get_current_result_set_col_specs()
+            .as_slice()
+            .len(),
+        3
+    );
+}
+
 #[tokio::test]
 async fn test_unusual_valuelists() {
     let _ = tracing_subscriber::fmt::try_init();
@@ -2531,6 +2589,48 @@ async fn test_views_in_schema_info() {
     )
 }
 
+#[tokio::test]
+async fn test_indexes_and_table_options_in_metadata() {
+    setup_tracing();
+
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    let mut create_ks = format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks);
+    // Secondary indexes are backed by materialized views, which are not supported with tablets in Scylla 2025.1.
+    if scylla_supports_tablets(&session).await {
+        create_ks += " and TABLETS = { 'enabled': false}";
+    }
+    session.ddl(create_ks).await.unwrap();
+    session.use_keyspace(ks.clone(), false).await.unwrap();
+
+    session
+        .ddl(
+            "CREATE TABLE t(id int PRIMARY KEY, v int) WITH comment = 'some comment' \
+            AND default_time_to_live = 3600 AND gc_grace_seconds = 7200 \
+            AND compaction = {'class': 'LeveledCompactionStrategy'}",
+        )
+        .await
+        .unwrap();
+    session.ddl("CREATE INDEX t_v_idx ON t(v)").await.unwrap();
+
+    session.await_schema_agreement().await.unwrap();
+    session.refresh_metadata().await.unwrap();
+
+    let cluster_state = session.get_cluster_state();
+    let table = &cluster_state.get_keyspace(&ks).unwrap().tables["t"];
+
+    assert_eq!(table.options.comment, "some comment");
+    assert_eq!(table.options.default_time_to_live, 3600);
+    assert_eq!(table.options.gc_grace_seconds, 7200);
+    assert!(table.options.compaction["class"].ends_with("LeveledCompactionStrategy"));
+
+    assert_eq!(table.indexes.keys().collect::<Vec<_>>(), vec!["t_v_idx"]);
+    let index = &table.indexes["t_v_idx"];
+    assert_eq!(index.kind, IndexKind::Composites);
+    assert_eq!(index.target(), Some("v"));
+}
+
 async fn assert_test_batch_table_rows_contain(sess: &Session, expected_rows: &[(i32, i32)]) {
     let selected_rows: BTreeSet<(i32, i32)> = sess
         .query_unpaged("SELECT a, b FROM test_batch_table", ())
@@ -3023,6 +3123,77 @@ async fn test_iter_methods_with_modification_statements() {
         .unwrap_err(); // assert empty
 }
 
+#[cfg(feature = "paging-cursor")]
+#[tokio::test]
+async fn test_iter_resumed_from_paging_cursor() {
+    use scylla::response::{PagingCursorCodec, PagingCursorError};
+
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+    session
+        .ddl(format!(
+            "CREATE TABLE IF NOT EXISTS {}.t (a int, b int, primary key (a, b))",
+            ks
+        ))
+        .await
+        .unwrap();
+    for b in 0..30 {
+        session
+            .query_unpaged(format!("INSERT INTO {}.t (a, b) VALUES (1, ?)", ks), (b,))
+            .await
+            .unwrap();
+    }
+
+    let mut prepared = session
+        .prepare(format!("SELECT b FROM {}.t WHERE a = ?", ks))
+        .await
+        .unwrap();
+    prepared.set_page_size(10);
+    let (result, paging_state_response) = session
+        .execute_single_page(&prepared, (1,), PagingState::start())
+        .await
+        .unwrap();
+    let first_page: Vec<(i32,)> = result
+        .into_rows_result()
+        .unwrap()
+        .rows()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(first_page, (0..10).map(|b| (b,)).collect::<Vec<_>>());
+    let PagingStateResponse::HasMorePages { state } = paging_state_response else {
+        panic!("Expected more pages");
+    };
+
+    let codec = PagingCursorCodec::new();
+    let cursor = codec.encode(&state, Some(prepared.get_id()));
+    let decoded = codec.decode(&cursor, Some(prepared.get_id())).unwrap();
+    assert_eq!(decoded.paging_state(), &state);
+
+    let remaining: Vec<(i32,)> = session
+        .execute_iter_from(prepared.clone(), (1,), decoded)
+        .await
+        .unwrap()
+        .rows_stream::<(i32,)>()
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(remaining, (10..30).map(|b| (b,)).collect::<Vec<_>>());
+
+    let other = session
+        .prepare(format!("SELECT a, b FROM {}.t WHERE a = ?", ks))
+        .await
+        .unwrap();
+    assert_matches!(
+        codec.decode(&cursor, Some(other.get_id())),
+        Err(PagingCursorError::StatementMismatch)
+    );
+}
+
 #[tokio::test]
 async fn test_get_keyspace_name() {
     let ks = unique_keyspace_name();
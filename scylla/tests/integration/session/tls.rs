@@ -0,0 +1,175 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use scylla::client::session::TlsContext;
+use scylla::client::session_builder::SessionBuilder;
+use scylla::errors::{DbError, ExecutionError, RequestAttemptError};
+use scylla_proxy::{
+    Condition, DoorkeeperError, MockCluster, MockNode, MockResponse, MockRule, ProxyError,
+    RequestReaction, RequestRule, RunningProxy,
+};
+
+use crate::utils::setup_tracing;
+
+const INSERT: &str = "INSERT INTO ks.t (pk) VALUES (1)";
+const FAULTY_INSERT: &str = "INSERT INTO ks.faulty (pk) VALUES (1)";
+
+const CA_CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/tls/ca.crt");
+// A self-signed certificate, not signed by the CA that signed the proxy's certificate.
+const UNTRUSTED_CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/tls/untrusted.crt");
+
+// The test certificates are valid for this address, so each test uses its own port.
+fn address(port: u16) -> SocketAddr {
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3)), port)
+}
+
+async fn run_mock_node(address: SocketAddr, tls: bool) -> RunningProxy {
+    let mut node = MockNode::new(address).request_rules(vec![RequestRule(
+        Condition::table(Some("ks"), "faulty"),
+        RequestReaction::forge_with_error(DbError::Overloaded),
+    )]);
+    if tls {
+        let config = scylla_proxy::server_tls_config(
+            include_bytes!("../../../../test/tls/proxy.crt"),
+            include_bytes!("../../../../test/tls/proxy.key"),
+        )
+        .unwrap();
+        node = node.driver_tls(config);
+    }
+    MockCluster::new([node])
+        .with_rule(MockRule::contains("INSERT INTO", MockResponse::Void))
+        .into_proxy()
+        .run()
+        .await
+        .unwrap()
+}
+
+// Errors injected by the proxy reach the driver over TLS, and the pool reestablishes
+// TLS connections reset by the proxy.
+async fn check_faults_over_tls(address: SocketAddr, tls_context: TlsContext) {
+    let mut running_proxy = run_mock_node(address, true).await;
+    let session = SessionBuilder::new()
+        .known_node_addr(address)
+        .tls_context(Some(tls_context))
+        .build()
+        .await
+        .unwrap();
+
+    session.query_unpaged(INSERT, ()).await.unwrap();
+    let err = session.query_unpaged(FAULTY_INSERT, ()).await.unwrap_err();
+    assert_matches!(
+        err,
+        ExecutionError::LastAttemptError(RequestAttemptError::DbError(DbError::Overloaded, _))
+    );
+
+    running_proxy.running_nodes[0].set_reset_mid_frame(true);
+    session.query_unpaged(INSERT, ()).await.unwrap_err();
+    running_proxy.clear_network_faults();
+    while let Err(err) = session.query_unpaged(INSERT, ()).await {
+        tracing::info!("Waiting for the pool to reconnect: {}", err);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    running_proxy.finish().await.unwrap();
+}
+
+// The driver refuses a certificate not signed by the CA it trusts.
+async fn check_untrusted_certificate(address: SocketAddr, tls_context: TlsContext) {
+    let mut running_proxy = run_mock_node(address, true).await;
+    SessionBuilder::new()
+        .known_node_addr(address)
+        .tls_context(Some(tls_context))
+        .build()
+        .await
+        .unwrap_err();
+
+    assert_matches!(
+        running_proxy.wait_for_error().await,
+        Some(ProxyError::Doorkeeper(DoorkeeperError::DriverTlsHandshake(
+            ..
+        )))
+    );
+    let _ = running_proxy.finish().await;
+}
+
+// The TLS handshake fails if the node does not speak TLS.
+async fn check_handshake_with_plaintext_node(address: SocketAddr, tls_context: TlsContext) {
+    let running_proxy = run_mock_node(address, false).await;
+    SessionBuilder::new()
+        .known_node_addr(address)
+        .tls_context(Some(tls_context))
+        .connection_timeout(Duration::from_secs(1))
+        .build()
+        .await
+        .unwrap_err();
+
+    // The proxy fails to parse the handshake as a CQL frame.
+    let _ = running_proxy.finish().await;
+}
+
+#[cfg(feature = "rustls-023")]
+mod rustls_backend {
+    use super::*;
+
+    fn tls_context(ca_cert: &str) -> TlsContext {
+        scylla_proxy::client_tls_config(&std::fs::read(ca_cert).unwrap())
+            .unwrap()
+            .into()
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(60000)]
+    async fn test_rustls_faults_over_tls() {
+        setup_tracing();
+        check_faults_over_tls(address(9911), tls_context(CA_CERT)).await;
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(60000)]
+    async fn test_rustls_untrusted_certificate() {
+        setup_tracing();
+        check_untrusted_certificate(address(9912), tls_context(UNTRUSTED_CERT)).await;
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(60000)]
+    async fn test_rustls_handshake_with_plaintext_node() {
+        setup_tracing();
+        check_handshake_with_plaintext_node(address(9913), tls_context(CA_CERT)).await;
+    }
+}
+
+#[cfg(feature = "openssl-010")]
+mod openssl_backend {
+    use super::*;
+    use openssl::ssl::{SslContextBuilder, SslMethod, SslVerifyMode};
+
+    fn tls_context(ca_cert: &str) -> TlsContext {
+        let mut context_builder = SslContextBuilder::new(SslMethod::tls()).unwrap();
+        context_builder.set_ca_file(ca_cert).unwrap();
+        context_builder.set_verify(SslVerifyMode::PEER);
+        context_builder.build().into()
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(60000)]
+    async fn test_openssl_faults_over_tls() {
+        setup_tracing();
+        check_faults_over_tls(address(9921), tls_context(CA_CERT)).await;
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(60000)]
+    async fn test_openssl_untrusted_certificate() {
+        setup_tracing();
+        check_untrusted_certificate(address(9922), tls_context(UNTRUSTED_CERT)).await;
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(60000)]
+    async fn test_openssl_handshake_with_plaintext_node() {
+        setup_tracing();
+        check_handshake_with_plaintext_node(address(9923), tls_context(CA_CERT)).await;
+    }
+}
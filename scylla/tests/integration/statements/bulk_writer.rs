@@ -0,0 +1,53 @@
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use scylla::client::bulk_writer::{BulkWriteError, BulkWriter};
+
+use crate::utils::{create_new_session_builder, setup_tracing, unique_keyspace_name, PerformDDL};
+
+#[tokio::test]
+#[ntest::timeout(60000)]
+async fn test_bulk_writer() {
+    setup_tracing();
+    let session = Arc::new(create_new_session_builder().build().await.unwrap());
+    let ks = unique_keyspace_name();
+    session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+    session.use_keyspace(ks, false).await.unwrap();
+    session
+        .ddl("CREATE TABLE IF NOT EXISTS bulk_writer (a int, b int, c text, PRIMARY KEY (a, b))")
+        .await
+        .unwrap();
+
+    let statement = session
+        .prepare("INSERT INTO bulk_writer (a, b, c) VALUES (?, ?, ?)")
+        .await
+        .unwrap();
+    let rows = (0..1000).map(|i| (Some(i % 10), i, "abc".to_owned()));
+    // The row with a null partition key is rejected by the database.
+    let rows = rows.chain([(None, 1000, "abc".to_owned())]);
+
+    let report = BulkWriter::new(session.clone(), statement)
+        .with_max_batch_size(NonZeroUsize::new(16).unwrap())
+        .with_max_buffered_rows(NonZeroUsize::new(100).unwrap())
+        .with_max_in_flight(NonZeroUsize::new(8).unwrap())
+        .write_all(rows)
+        .await;
+
+    assert_eq!(report.written, 1000);
+    assert_eq!(report.failures.len(), 1);
+    let failure = &report.failures[0];
+    assert_eq!(failure.index, 1000);
+    assert_eq!(failure.row.1, 1000);
+    assert_matches!(failure.error, BulkWriteError::ExecutionError(_));
+
+    let (count,): (i64,) = session
+        .query_unpaged("SELECT COUNT(*) FROM bulk_writer", ())
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap()
+        .single_row()
+        .unwrap();
+    assert_eq!(count, 1000);
+}
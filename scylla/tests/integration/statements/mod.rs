@@ -1,6 +1,8 @@
 mod batch;
+mod bulk_writer;
 mod consistency;
 mod execution_profiles;
 mod silent_prepare_query;
 mod skip_metadata_optimization;
 mod statement;
+mod token_range_scanner;
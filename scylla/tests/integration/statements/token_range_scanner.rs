@@ -0,0 +1,80 @@
+use std::collections::BTreeSet;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use futures::TryStreamExt;
+use scylla::client::token_range_scanner::{RangeProgress, TokenRangeScanError, TokenRangeScanner};
+
+use crate::utils::{create_new_session_builder, setup_tracing, unique_keyspace_name, PerformDDL};
+
+#[tokio::test]
+#[ntest::timeout(60000)]
+async fn test_token_range_scanner() {
+    setup_tracing();
+    let session = Arc::new(create_new_session_builder().build().await.unwrap());
+    let ks = unique_keyspace_name();
+    session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+    session.use_keyspace(&ks, false).await.unwrap();
+    session
+        .ddl("CREATE TABLE IF NOT EXISTS token_range_scanner (a int, b int, c text, PRIMARY KEY ((a, b), c))")
+        .await
+        .unwrap();
+    session.refresh_metadata().await.unwrap();
+
+    let insert = session
+        .prepare("INSERT INTO token_range_scanner (a, b, c) VALUES (?, ?, ?)")
+        .await
+        .unwrap();
+    let mut expected = BTreeSet::new();
+    for i in 0..300 {
+        let row = (i % 100, i / 100, format!("c{}", i % 3));
+        session.execute_unpaged(&insert, &row).await.unwrap();
+        expected.insert(row);
+    }
+
+    let scanner = || {
+        TokenRangeScanner::new(session.clone(), ks.clone(), "token_range_scanner")
+            .with_columns(["a", "b", "c"])
+            .with_parallelism(NonZeroUsize::new(4).unwrap())
+            .with_page_size(7)
+    };
+
+    let rows: BTreeSet<(i32, i32, String)> =
+        scanner().scan().await.unwrap().try_collect().await.unwrap();
+    assert_eq!(rows, expected);
+
+    // Interrupt a scan, then resume it from the checkpoint.
+    let mut scan = scanner().scan::<(i32, i32, String)>().await.unwrap();
+    let mut rows = BTreeSet::new();
+    for _ in 0..100 {
+        rows.insert(scan.try_next().await.unwrap().unwrap());
+    }
+    let checkpoint = scan.checkpoint();
+    assert!(!checkpoint.is_finished());
+    assert!(checkpoint
+        .ranges()
+        .iter()
+        .any(|(_, progress)| *progress == RangeProgress::Done));
+    drop(scan);
+
+    let mut scan = scanner()
+        .with_checkpoint(checkpoint)
+        .scan::<(i32, i32, String)>()
+        .await
+        .unwrap();
+    while let Some(row) = scan.try_next().await.unwrap() {
+        rows.insert(row);
+    }
+    assert!(scan.checkpoint().is_finished());
+    assert_eq!(rows, expected);
+
+    let err = TokenRangeScanner::new(session.clone(), ks.clone(), "no_such_table")
+        .scan::<(i32,)>()
+        .await
+        .unwrap_err();
+    assert_matches!(err, TokenRangeScanError::TableNotFound { .. });
+
+    let err = scanner().scan::<(String,)>().await.unwrap_err();
+    assert_matches!(err, TokenRangeScanError::TypeCheckError(_));
+}